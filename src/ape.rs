@@ -0,0 +1,187 @@
+use crate::error::{Error, ErrorKind, Result};
+use crate::id3v1::Id3v1;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+/// The length of an APEv2 header or footer in bytes.
+const FOOTER_LEN: u64 = 32;
+
+/// The APE keys whose vorbis comment key is not simply the upper case key.
+const KEYS: [(&str, &str); 4] = [
+    ("YEAR", "DATE"),
+    ("TRACK", "TRACKNUMBER"),
+    ("DISC", "DISCNUMBER"),
+    ("ALBUM ARTIST", "ALBUMARTIST"),
+];
+
+/// The type of the value of an APEv2 item.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ApeItemKind {
+    /// UTF-8 text, with multiple values separated by null bytes.
+    Text,
+    /// Binary data.
+    Binary,
+    /// A UTF-8 link to an external resource.
+    Locator,
+}
+
+/// An item of an APEv2 tag.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApeItem {
+    /// The key of the item. Keys are compared case insensitively.
+    pub key: String,
+    /// The type of the value.
+    pub kind: ApeItemKind,
+    /// The raw value.
+    pub value: Vec<u8>,
+}
+
+impl ApeItem {
+    /// Returns the values of a text item, or `None` if the item is not a text item or is not
+    /// valid UTF-8.
+    pub fn text_values(&self) -> Option<Vec<&str>> {
+        if self.kind != ApeItemKind::Text {
+            return None;
+        }
+        std::str::from_utf8(&self.value)
+            .ok()
+            .map(|text| text.split('\0').collect())
+    }
+}
+
+/// An APEv2 tag appended to the end of a file.
+///
+/// APEv2 tags are not part of the FLAC format, but some old tools append them to FLAC files. The
+/// tag may be followed by an ID3v1 tag.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ApeTag {
+    /// The items of the tag, in the order in which they were stored.
+    pub items: Vec<ApeItem>,
+}
+
+impl ApeTag {
+    /// Returns a new `ApeTag` without items.
+    pub fn new() -> ApeTag {
+        ApeTag::default()
+    }
+
+    /// Reads the APEv2 tag at the end of the reader, or before an ID3v1 tag at the end of the
+    /// reader. Returns the tag and its byte range including any header, or `None` if there is no
+    /// APEv2 tag. The reader position is left at an unspecified position.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Option<(ApeTag, Range<u64>)>> {
+        let mut end = reader.seek(SeekFrom::End(0))?;
+        if Id3v1::read_from(reader)?.is_some() {
+            end -= Id3v1::LEN;
+        }
+        if end < FOOTER_LEN {
+            return Ok(None);
+        }
+
+        let mut footer = [0; FOOTER_LEN as usize];
+        reader.seek(SeekFrom::Start(end - FOOTER_LEN))?;
+        reader.read_exact(&mut footer)?;
+        if &footer[0..8] != b"APETAGEX" {
+            return Ok(None);
+        }
+
+        let invalid = || Error::new(ErrorKind::InvalidInput, "invalid apev2 tag");
+
+        // the size covers the items and the footer, but not the header
+        let size = LittleEndian::read_u32(&footer[12..16]) as u64;
+        let count = LittleEndian::read_u32(&footer[16..20]);
+        let has_header = LittleEndian::read_u32(&footer[20..24]) & 0x8000_0000 != 0;
+        let header_len = if has_header { FOOTER_LEN } else { 0 };
+        if size < FOOTER_LEN || size + header_len > end {
+            return Err(invalid());
+        }
+        let start = end - size - header_len;
+
+        let mut bytes = vec![0; (size - FOOTER_LEN) as usize];
+        reader.seek(SeekFrom::Start(end - size))?;
+        reader.read_exact(&mut bytes)?;
+
+        let mut tag = ApeTag::new();
+        let mut i = 0;
+        for _ in 0..count {
+            if bytes.len() < i + 8 {
+                return Err(invalid());
+            }
+            let value_len = LittleEndian::read_u32(&bytes[i..i + 4]) as usize;
+            let flags = LittleEndian::read_u32(&bytes[i + 4..i + 8]);
+            i += 8;
+
+            let key_len = bytes[i..]
+                .iter()
+                .position(|&b| b == 0)
+                .ok_or_else(invalid)?;
+            let key = String::from_utf8_lossy(&bytes[i..i + key_len]).into_owned();
+            i += key_len + 1;
+
+            if bytes.len() - i < value_len {
+                return Err(invalid());
+            }
+            let value = bytes[i..i + value_len].to_vec();
+            i += value_len;
+
+            let kind = match (flags >> 1) & 0x3 {
+                0 => ApeItemKind::Text,
+                2 => ApeItemKind::Locator,
+                _ => ApeItemKind::Binary,
+            };
+            tag.items.push(ApeItem { key, kind, value });
+        }
+
+        Ok(Some((tag, start..end)))
+    }
+
+    /// Returns the item with the key, compared case insensitively.
+    pub fn get(&self, key: &str) -> Option<&ApeItem> {
+        self.items
+            .iter()
+            .find(|item| item.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Returns the values of the text items as vorbis comment keys and values. Items whose key is
+    /// not a valid vorbis comment key are skipped.
+    pub(crate) fn comments(&self) -> Vec<(String, String)> {
+        let mut comments = Vec::new();
+        for item in self.items.iter() {
+            let key = item.key.to_ascii_uppercase();
+            let key = match KEYS.iter().find(|&&(ape_key, _)| ape_key == key) {
+                Some(&(_, vorbis_key)) => vorbis_key.to_string(),
+                None => key,
+            };
+            if !crate::block::VorbisComment::is_valid_key(&key) {
+                continue;
+            }
+            for value in item.text_values().unwrap_or_default() {
+                if !value.is_empty() {
+                    comments.push((key.clone(), value.to_string()));
+                }
+            }
+        }
+        comments
+    }
+}
+
+/// Removes the APEv2 tag from the end of the file, keeping any ID3v1 tag which follows it.
+/// Returns whether a tag was removed.
+pub(crate) fn strip(mut file: &File) -> Result<bool> {
+    let range = match ApeTag::read_from(&mut file)? {
+        Some((_, range)) => range,
+        None => return Ok(false),
+    };
+
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(range.end))?;
+    file.read_to_end(&mut tail)?;
+
+    file.set_len(range.start)?;
+    file.seek(SeekFrom::Start(range.start))?;
+    file.write_all(&tail)?;
+    Ok(true)
+}
@@ -0,0 +1,262 @@
+use crate::block::ident_error;
+use crate::error::{Error, ErrorKind, Result};
+use crate::options::{ReadOptions, WriteOptions};
+use crate::raw::not_flac_error;
+use crate::tag::Tag;
+
+use byteorder::{BigEndian, ByteOrder};
+use tokio::io::{sink, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use std::path::Path;
+
+impl Tag {
+    /// Attempts to read a FLAC tag from the async reader without blocking the runtime. Only the
+    /// metadata is read, leaving the reader positioned at the start of the audio.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// let bytes = tag.write_to_vec().unwrap();
+    ///
+    /// let tag = Tag::read_from_async(&mut &bytes[..]).await.unwrap();
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+    /// # });
+    /// ```
+    pub async fn read_from_async<R: AsyncRead + Unpin + ?Sized>(reader: &mut R) -> Result<Tag> {
+        Tag::read_from_async_with(reader, &ReadOptions::new()).await
+    }
+
+    /// Attempts to read a FLAC tag from the async reader using the specified options. See
+    /// `Tag::read_from_with`.
+    ///
+    /// The metadata is read into memory before it is parsed, so `ParseMode::Recover` cannot
+    /// search the audio for block headers.
+    pub async fn read_from_async_with<R: AsyncRead + Unpin + ?Sized>(
+        reader: &mut R,
+        options: &ReadOptions,
+    ) -> Result<Tag> {
+        let (id3_len, junk_len, bytes) = read_metadata(reader, options).await?;
+        Tag::read_blocks(
+            &mut &bytes[..],
+            id3_len,
+            junk_len,
+            options,
+            crate::tag::skip_by_reading,
+        )
+    }
+
+    /// Attempts to read a FLAC tag from the file at the specified path on the blocking thread
+    /// pool of the tokio runtime. See `Tag::read_from_path`.
+    pub async fn read_from_path_async<P: AsRef<Path>>(path: P) -> Result<Tag> {
+        let path = path.as_ref().to_path_buf();
+        blocking(move || Tag::read_from_path(path)).await
+    }
+
+    /// Attempts to write the FLAC tag to the async writer. See `Tag::write_to`.
+    pub async fn write_to_async<W: AsyncWrite + Unpin + ?Sized>(
+        &mut self,
+        writer: &mut W,
+    ) -> Result<()> {
+        let bytes = self.write_to_vec()?;
+        writer.write_all(&bytes).await?;
+        Ok(())
+    }
+
+    /// Attempts to save the tag back to the file which it was read from on the blocking thread
+    /// pool of the tokio runtime. See `Tag::save`.
+    pub async fn save_async(&mut self) -> Result<()> {
+        self.save_with_async(&WriteOptions::new()).await
+    }
+
+    /// Attempts to save the tag back to the file which it was read from using the specified
+    /// options on the blocking thread pool of the tokio runtime. See `Tag::save_with`.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::{Block, StreamInfo};
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let name = format!("metaflac-doc-save-async-{}.flac", std::process::id());
+    /// let path = std::env::temp_dir().join(name);
+    /// let mut streaminfo = StreamInfo::new();
+    /// streaminfo.num_channels = 2;
+    /// streaminfo.bits_per_sample = 16;
+    /// let mut tag = Tag::new();
+    /// tag.push_block(Block::StreamInfo(streaminfo));
+    /// tag.write_to_path(&path).unwrap();
+    ///
+    /// let mut tag = Tag::read_from_path_async(&path).await.unwrap();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// tag.save_async().await.unwrap();
+    ///
+    /// let tag = Tag::read_from_path_async(&path).await.unwrap();
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// # });
+    /// ```
+    pub async fn save_with_async(&mut self, options: &WriteOptions) -> Result<()> {
+        let mut tag = self.clone();
+        let options = options.clone();
+        *self = blocking(move || tag.save_with(&options).map(|_| tag)).await?;
+        Ok(())
+    }
+
+    /// Attempts to write the tag to the file at the specified path on the blocking thread pool of
+    /// the tokio runtime. See `Tag::write_to_path_with`.
+    pub async fn write_to_path_async<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        let mut tag = self.clone();
+        let path = path.as_ref().to_path_buf();
+        let options = options.clone();
+        *self = blocking(move || tag.write_to_path_with(path, &options).map(|_| tag)).await?;
+        Ok(())
+    }
+}
+
+/// Runs the blocking operation on the blocking thread pool of the tokio runtime.
+async fn blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(std::io::Error::from)?
+}
+
+/// Reads the metadata blocks of the stream, returning the lengths of the ID3v2 tag and the junk
+/// before the `fLaC` marker, which are skipped without being kept, and the bytes of the blocks.
+/// Reading stops early at a block which exceeds the limits of the options or is truncated, leaving
+/// the block for the parser to reject or record as it would for a blocking reader.
+async fn read_metadata<R: AsyncRead + Unpin + ?Sized>(
+    reader: &mut R,
+    options: &ReadOptions,
+) -> Result<(u64, u64, Vec<u8>)> {
+    let mut ident = [0; 4];
+    reader.read_exact(&mut ident).await.map_err(ident_error)?;
+
+    let mut id3_len = 0;
+    if &ident[0..3] == b"ID3" && [0x02, 0x03, 0x04].contains(&ident[3]) {
+        let mut header = [0; 10];
+        header[..4].copy_from_slice(&ident);
+        reader
+            .read_exact(&mut header[4..])
+            .await
+            .map_err(ident_error)?;
+        id3_len = crate::raw::id3v2_len(&header).unwrap_or(10);
+        // discard the tag without keeping it in memory
+        tokio::io::copy(&mut (&mut *reader).take(id3_len - 10), &mut sink()).await?;
+        reader.read_exact(&mut ident).await.map_err(ident_error)?;
+    }
+
+    // slide over junk one byte at a time until the identifier lines up
+    let mut junk_len = 0;
+    while &ident[..] != b"fLaC" && junk_len < options.max_junk_len {
+        ident.rotate_left(1);
+        match reader.read_exact(&mut ident[3..]).await {
+            Ok(_) => junk_len += 1,
+            Err(ref err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    if &ident[..] != b"fLaC" {
+        return Err(not_flac_error());
+    }
+
+    let mut bytes = Vec::new();
+    loop {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header).await?;
+        bytes.extend(&header);
+        let is_last = header[0] & 0x80 != 0;
+        let length = BigEndian::read_u24(&header[1..]);
+        // leave a block which is too long for the parser to reject before reading it
+        if options.max_block_len.is_some_and(|max| length > max) {
+            break;
+        }
+        if options
+            .max_metadata_len
+            .is_some_and(|max| bytes.len() as u64 + length as u64 > max)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "metadata exceeds the maximum metadata length",
+            ));
+        }
+        let read = (&mut *reader)
+            .take(length as u64)
+            .read_to_end(&mut bytes)
+            .await?;
+        // a truncated block ends the metadata, as when it is read
+        if is_last || read < length as usize {
+            break;
+        }
+    }
+
+    Ok((id3_len, junk_len, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+    use crate::test_util::test_streaminfo;
+
+    #[tokio::test]
+    async fn read_leaves_audio() {
+        let mut tag = Tag::new();
+        tag.push_block(Block::StreamInfo(test_streaminfo()));
+        tag.set_vorbis("ARTIST", vec!["artist"]);
+        let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x02\x00\x00junk".to_vec();
+        bytes.extend(tag.write_to_vec().unwrap());
+        bytes.extend(b"audio");
+
+        let mut reader = &bytes[..];
+        assert!(Tag::read_from_async(&mut reader).await.is_err());
+
+        let mut options = ReadOptions::new();
+        options.max_junk_len = 4;
+        let mut reader = &bytes[..];
+        let tag = Tag::read_from_async_with(&mut reader, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(),
+            &["artist"]
+        );
+        assert_eq!(reader, b"audio");
+
+        options.max_block_len = Some(4);
+        let mut reader = &bytes[..];
+        assert!(Tag::read_from_async_with(&mut reader, &options)
+            .await
+            .is_err());
+
+        options.max_block_len = None;
+        options.max_metadata_len = Some(40);
+        let mut reader = &bytes[..];
+        assert!(Tag::read_from_async_with(&mut reader, &options)
+            .await
+            .is_err());
+
+        // a truncated last block is recorded as it is by a blocking reader
+        options.max_metadata_len = None;
+        let truncated = &bytes[..bytes.len() - 8];
+        let tag = Tag::read_from_async_with(&mut &truncated[..], &options)
+            .await
+            .unwrap();
+        let blocking = Tag::read_from_with(&mut &truncated[..], &options).unwrap();
+        assert!(!tag.warnings().is_empty());
+        assert_eq!(tag.warnings(), blocking.warnings());
+        assert_eq!(tag.block_layout(), blocking.block_layout());
+        assert_eq!(tag.junk(), Some(12..16));
+    }
+}
@@ -0,0 +1,477 @@
+//! A command line tool for editing FLAC metadata, compatible with a subset of the options of the
+//! reference `metaflac` tool.
+//!
+//! Operations are applied to every file in the order in which they are given. The file is only
+//! written if one of the operations modified it.
+//!
+//! Exit codes:
+//!
+//! * 0: success
+//! * 1: invalid command line usage
+//! * 2: a file is not a FLAC file
+//! * 3: the metadata of a file could not be read
+//! * 4: a file could not be written
+//! * 5: an operation failed validation, e.g. a malformed tag or picture specification
+//! * 6: a file could not be opened or read, e.g. because it does not exist
+
+use metaflac::block::{Block, BlockType, Picture, PictureType};
+use metaflac::{ErrorKind, PaddingPolicy, Tag, WriteOptions};
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "\
+Usage: metaflac-rs [options] [operations] file...
+
+Operations:
+  --list                         list the contents of every metadata block
+  --show-tag=NAME                show the values of the tag NAME
+  --set-tag=NAME=VALUE           add a value to the tag NAME
+  --remove-tag=NAME              remove every value of the tag NAME
+  --remove-all-tags              remove every tag
+  --remove-all                   remove every metadata block except STREAMINFO
+  --remove                       remove the blocks selected by --block-number
+  --import-tags-from=FILE        add tags from NAME=VALUE lines in FILE, or - for stdin
+  --import-picture-from=SPEC     import a picture, where SPEC is either a file name or
+                                 [TYPE]|[MIME-TYPE]|[DESCRIPTION]|[WIDTHxHEIGHTxDEPTH[/COLORS]]|FILE
+  --export-picture-to=FILE       write the data of the first picture to FILE, or - for stdout
+  --import-cuesheet-from=FILE    replace the CUESHEET block with one parsed from the cue sheet
+                                 FILE, or - for stdin
+  --export-cuesheet-to=FILE      write the CUESHEET block as a cue sheet to FILE, or - for stdout
+
+Options:
+  --block-number=N[,N...]        select blocks by their position in the file, where the
+                                 STREAMINFO block is 0
+  --dont-use-padding             always rewrite the file instead of reusing padding
+  --preserve-modtime             keep the access and modification times of the file
+  --add-padding=LENGTH           write a padding block of exactly LENGTH bytes
+  --errors=json                  report errors as JSON objects on stderr
+  --help                         show this message
+
+Exit codes:
+  0  success
+  1  invalid command line usage
+  2  a file is not a FLAC file
+  3  the metadata of a file could not be read
+  4  a file could not be written
+  5  an operation failed validation
+  6  a file could not be opened or read";
+
+const EXIT_USAGE: i32 = 1;
+const EXIT_NOT_FLAC: i32 = 2;
+const EXIT_PARSE: i32 = 3;
+const EXIT_WRITE: i32 = 4;
+const EXIT_VALIDATION: i32 = 5;
+const EXIT_IO: i32 = 6;
+
+enum Operation {
+    List,
+    ShowTag(String),
+    SetTag(String),
+    RemoveTag(String),
+    RemoveAllTags,
+    RemoveAll,
+    Remove,
+    ImportTags(String),
+    ImportPicture(String),
+    ExportPicture(String),
+    ImportCuesheet(String),
+    ExportCuesheet(String),
+}
+
+struct Failure {
+    code: i32,
+    message: String,
+}
+
+impl Failure {
+    fn new<M: Into<String>>(code: i32, message: M) -> Failure {
+        Failure {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+fn main() {
+    let mut operations = Vec::new();
+    let mut options = WriteOptions::new();
+    let mut json_errors = false;
+    let mut block_numbers = Vec::new();
+    let mut paths = Vec::new();
+
+    for arg in std::env::args().skip(1) {
+        let (name, value) = match arg.find('=') {
+            Some(i) => (&arg[..i], Some(arg[i + 1..].to_string())),
+            None => (&arg[..], None),
+        };
+
+        let operation = match (name, value) {
+            ("--list", None) => Operation::List,
+            ("--show-tag", Some(value)) => Operation::ShowTag(value),
+            ("--set-tag", Some(value)) => Operation::SetTag(value),
+            ("--remove-tag", Some(value)) => Operation::RemoveTag(value),
+            ("--remove-all-tags", None) => Operation::RemoveAllTags,
+            ("--remove-all", None) => Operation::RemoveAll,
+            ("--remove", None) => Operation::Remove,
+            ("--import-tags-from", Some(value)) => Operation::ImportTags(value),
+            ("--import-picture-from", Some(value)) => Operation::ImportPicture(value),
+            ("--export-picture-to", Some(value)) => Operation::ExportPicture(value),
+            ("--import-cuesheet-from", Some(value)) => Operation::ImportCuesheet(value),
+            ("--export-cuesheet-to", Some(value)) => Operation::ExportCuesheet(value),
+            ("--dont-use-padding", None) => {
+                options.use_padding = false;
+                continue;
+            }
+            ("--preserve-modtime", None) => {
+                options.preserve_modtime = true;
+                continue;
+            }
+            ("--add-padding", Some(value)) => match value.parse() {
+                Ok(padding) if padding < 1 << 24 => {
+                    options.padding = PaddingPolicy::Fixed(padding);
+                    continue;
+                }
+                _ => usage_error(&format!("invalid padding length: {}", value)),
+            },
+            ("--block-number", Some(value)) => {
+                for number in value.split(',') {
+                    match number.trim().parse() {
+                        Ok(number) => block_numbers.push(number),
+                        Err(_) => usage_error(&format!("invalid block number: {}", number)),
+                    }
+                }
+                continue;
+            }
+            ("--errors", Some(ref value)) if value == "json" => {
+                json_errors = true;
+                continue;
+            }
+            ("--help", None) => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("--") => usage_error(&format!("unknown option: {}", arg)),
+            _ => {
+                paths.push(arg);
+                continue;
+            }
+        };
+        operations.push(operation);
+    }
+
+    if paths.is_empty() {
+        usage_error("no files specified");
+    }
+    let removes = operations
+        .iter()
+        .any(|operation| matches!(operation, Operation::Remove));
+    if removes && block_numbers.is_empty() {
+        usage_error("--remove requires --block-number");
+    }
+
+    let mut exit_code = 0;
+    for path in paths.iter() {
+        let prefix = if paths.len() > 1 {
+            format!("{}:", path)
+        } else {
+            String::new()
+        };
+
+        if let Err(failure) = process_file(path, &prefix, &operations, &block_numbers, &options) {
+            report(path, &failure, json_errors);
+            exit_code = failure.code;
+        }
+    }
+
+    process::exit(exit_code);
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("metaflac-rs: {}\n\n{}", message, USAGE);
+    process::exit(EXIT_USAGE);
+}
+
+fn report(path: &str, failure: &Failure, json: bool) {
+    if json {
+        let kind = match failure.code {
+            EXIT_NOT_FLAC => "not_flac",
+            EXIT_PARSE => "parse_error",
+            EXIT_WRITE => "write_error",
+            EXIT_IO => "io_error",
+            _ => "validation_failure",
+        };
+        eprintln!(
+            "{{\"file\":{},\"error\":\"{}\",\"exit_code\":{},\"message\":{}}}",
+            json_string(path),
+            kind,
+            failure.code,
+            json_string(&failure.message)
+        );
+    } else {
+        eprintln!("{}: {}", path, failure.message);
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn process_file(
+    path: &str,
+    prefix: &str,
+    operations: &[Operation],
+    block_numbers: &[usize],
+    options: &WriteOptions,
+) -> Result<(), Failure> {
+    let mut tag = Tag::read_from_path(path).map_err(|err| match err.kind {
+        ErrorKind::NotFlac => Failure::new(EXIT_NOT_FLAC, "not a FLAC file"),
+        // running out of input while reading the metadata means it is damaged
+        ErrorKind::Io(ref io_err) if io_err.kind() != io::ErrorKind::UnexpectedEof => {
+            Failure::new(EXIT_IO, format!("unable to read file: {}", io_err))
+        }
+        _ => Failure::new(EXIT_PARSE, format!("unable to read metadata: {}", err)),
+    })?;
+
+    let mut modified = false;
+    for operation in operations {
+        match *operation {
+            Operation::List => list(&tag, prefix),
+            Operation::ShowTag(ref name) => {
+                if let Some(values) = tag.get_vorbis(name) {
+                    for value in values {
+                        println!("{}{}={}", prefix, name, value);
+                    }
+                }
+            }
+            Operation::SetTag(ref field) => {
+                let (name, value) = match field.find('=') {
+                    Some(i) => (&field[..i], &field[i + 1..]),
+                    _ => {
+                        return Err(Failure::new(
+                            EXIT_VALIDATION,
+                            format!("malformed tag, expected NAME=VALUE: {}", field),
+                        ))
+                    }
+                };
+                let mut values = tag.get_vorbis_values(name).unwrap_or_default();
+                values.push(value.to_string());
+                tag.try_set_vorbis(name, values).map_err(|_| {
+                    Failure::new(EXIT_VALIDATION, format!("invalid tag name: {}", name))
+                })?;
+                modified = true;
+            }
+            Operation::RemoveTag(ref name) => {
+                tag.remove_vorbis(name);
+                modified = true;
+            }
+            Operation::RemoveAllTags => {
+                let keys: Vec<String> = tag
+                    .vorbis_comments()
+                    .map(|comments| comments.keys())
+                    .unwrap_or_default();
+                for key in keys {
+                    tag.remove_vorbis(&key);
+                }
+                // comments without a separator have no key
+                if tag.vorbis_comments().is_some() {
+                    tag.vorbis_comments_mut().unseparated.clear();
+                }
+                modified = true;
+            }
+            Operation::RemoveAll => {
+                tag.strip(&[]);
+                modified = true;
+            }
+            Operation::Remove => {
+                let mut numbers = block_numbers.to_vec();
+                numbers.sort_unstable();
+                numbers.dedup();
+                for &number in numbers.iter() {
+                    match tag.blocks().nth(number).map(Block::block_type) {
+                        None => {
+                            return Err(Failure::new(
+                                EXIT_VALIDATION,
+                                format!("no block number {}", number),
+                            ))
+                        }
+                        Some(BlockType::StreamInfo) => {
+                            return Err(Failure::new(
+                                EXIT_VALIDATION,
+                                "the STREAMINFO block cannot be removed",
+                            ))
+                        }
+                        Some(_) => {}
+                    }
+                }
+                for &number in numbers.iter().rev() {
+                    tag.remove_block_at(number).map_err(|err| {
+                        Failure::new(EXIT_VALIDATION, format!("block {}: {}", number, err))
+                    })?;
+                }
+                modified = true;
+            }
+            Operation::ImportTags(ref source) => {
+                let text = read_text(source)?;
+                tag.import_comments_from_str(&text)
+                    .map_err(|err| Failure::new(EXIT_VALIDATION, format!("{}: {}", source, err)))?;
+                modified = true;
+            }
+            Operation::ImportPicture(ref spec) => {
+                let picture = read_picture(spec)?;
+                // there may only be one picture of each of the icon types
+                if picture.picture_type == PictureType::Icon
+                    || picture.picture_type == PictureType::OtherIcon
+                {
+                    tag.remove_picture_type(picture.picture_type);
+                }
+                tag.push_block(Block::Picture(picture));
+                modified = true;
+            }
+            Operation::ExportPicture(ref dest) => {
+                let picture = tag
+                    .pictures()
+                    .next()
+                    .ok_or_else(|| Failure::new(EXIT_VALIDATION, "file has no pictures"))?;
+                let result = if dest == "-" {
+                    io::stdout().write_all(&picture.data)
+                } else {
+                    fs::write(dest, &picture.data)
+                };
+                result.map_err(|err| {
+                    Failure::new(EXIT_WRITE, format!("unable to write {}: {}", dest, err))
+                })?;
+            }
+            Operation::ImportCuesheet(ref source) => {
+                let text = read_text(source)?;
+                tag.import_cuesheet(&text)
+                    .map_err(|err| Failure::new(EXIT_VALIDATION, format!("{}: {}", source, err)))?;
+                modified = true;
+            }
+            Operation::ExportCuesheet(ref dest) => {
+                let cuesheet = tag
+                    .cuesheet()
+                    .ok_or_else(|| Failure::new(EXIT_VALIDATION, "file has no cuesheet"))?;
+                let file_name = Path::new(path)
+                    .file_name()
+                    .map_or_else(|| path.into(), |name| name.to_string_lossy());
+                let text = cuesheet.to_cue(&file_name);
+                let result = if dest == "-" {
+                    io::stdout().write_all(text.as_bytes())
+                } else {
+                    fs::write(dest, text)
+                };
+                result.map_err(|err| {
+                    Failure::new(EXIT_WRITE, format!("unable to write {}: {}", dest, err))
+                })?;
+            }
+        }
+    }
+
+    if modified {
+        tag.save_with(options)
+            .map_err(|err| Failure::new(EXIT_WRITE, format!("unable to write file: {}", err)))?;
+    }
+
+    Ok(())
+}
+
+/// Reads the text of a file, or of stdin if the file name is `-`.
+fn read_text(source: &str) -> Result<String, Failure> {
+    let text = if source == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map(|_| text)
+    } else {
+        fs::read_to_string(source)
+    };
+    text.map_err(|err| Failure::new(EXIT_IO, format!("unable to read {}: {}", source, err)))
+}
+
+/// Reads a picture from a file name or a specification of the form
+/// `[TYPE]|[MIME-TYPE]|[DESCRIPTION]|[WIDTHxHEIGHTxDEPTH[/COLORS]]|FILE`.
+fn read_picture(spec: &str) -> Result<Picture, Failure> {
+    let invalid = || Failure::new(EXIT_VALIDATION, format!("invalid picture: {}", spec));
+
+    let fields: Vec<&str> = spec.split('|').collect();
+    let (fields, file) = match fields.len() {
+        1 => (["", "", "", ""], fields[0]),
+        5 => ([fields[0], fields[1], fields[2], fields[3]], fields[4]),
+        _ => return Err(invalid()),
+    };
+
+    let mut picture = Picture::new();
+    picture.picture_type = if fields[0].is_empty() {
+        PictureType::CoverFront
+    } else {
+        fields[0]
+            .parse()
+            .ok()
+            .and_then(PictureType::from_u32)
+            .ok_or_else(invalid)?
+    };
+    picture.description = fields[2].to_string();
+
+    if !fields[3].is_empty() {
+        let (dimensions, colors) = match fields[3].find('/') {
+            Some(i) => (&fields[3][..i], Some(&fields[3][i + 1..])),
+            None => (fields[3], None),
+        };
+        let dimensions = dimensions
+            .split('x')
+            .map(|n| n.parse::<u32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if dimensions.len() != 3 {
+            return Err(invalid());
+        }
+        picture.width = dimensions[0];
+        picture.height = dimensions[1];
+        picture.depth = dimensions[2];
+        if let Some(colors) = colors {
+            picture.num_colors = colors.parse().map_err(|_| invalid())?;
+        }
+    }
+
+    picture.data = fs::read(file)
+        .map_err(|err| Failure::new(EXIT_IO, format!("unable to read {}: {}", file, err)))?;
+
+    picture.mime_type = if fields[1].is_empty() {
+        Picture::detect_mime_type(&picture.data)
+            .ok_or_else(|| {
+                Failure::new(
+                    EXIT_VALIDATION,
+                    format!("unable to detect the MIME type of {}", file),
+                )
+            })?
+            .to_string()
+    } else {
+        fields[1].to_string()
+    };
+
+    #[cfg(feature = "image")]
+    if fields[3].is_empty() {
+        picture.read_dimensions();
+    }
+
+    Ok(picture)
+}
+
+fn list(tag: &Tag, prefix: &str) {
+    for line in tag.to_string().lines() {
+        println!("{}{}", prefix, line);
+    }
+}
@@ -1,59 +1,25 @@
-use crate::error::{Error, ErrorKind, Result};
-
-use byteorder::{ReadBytesExt, WriteBytesExt, BE};
-
-use std::collections::HashMap;
-use std::convert::TryInto;
-use std::io::{Read, Write};
+use crate::error::{Error, ErrorKind, ParseWarning, Result};
+use crate::options::ReadOptions;
+use crate::raw::{self, id3v2_len, not_flac_error, MAX_CONTENT_LEN};
+use crate::replaygain;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use byteorder::{ReadBytesExt, BE};
+
+use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::iter::repeat;
+use std::str::FromStr;
+use std::time::Duration;
 
-// BlockType {{{
-/// Types of blocks. Used primarily to map blocks to block identifiers when reading and writing.
-#[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub enum BlockType {
-    StreamInfo,
-    Padding,
-    Application,
-    SeekTable,
-    VorbisComment,
-    CueSheet,
-    Picture,
-    Unknown(u8),
-}
-
-#[allow(missing_docs)]
-impl BlockType {
-    fn to_u8(&self) -> u8 {
-        match *self {
-            BlockType::StreamInfo => 0,
-            BlockType::Padding => 1,
-            BlockType::Application => 2,
-            BlockType::SeekTable => 3,
-            BlockType::VorbisComment => 4,
-            BlockType::CueSheet => 5,
-            BlockType::Picture => 6,
-            BlockType::Unknown(n) => n as u8,
-        }
-    }
-
-    fn from_u8(n: u8) -> BlockType {
-        match n {
-            0 => BlockType::StreamInfo,
-            1 => BlockType::Padding,
-            2 => BlockType::Application,
-            3 => BlockType::SeekTable,
-            4 => BlockType::VorbisComment,
-            5 => BlockType::CueSheet,
-            6 => BlockType::Picture,
-            n => BlockType::Unknown(n),
-        }
-    }
-}
-// }}}
+pub use crate::raw::BlockType;
 
 /// The parsed content of a metadata block.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Block {
     /// A value containing a parsed streaminfo block.
     StreamInfo(StreamInfo),
@@ -76,20 +42,193 @@ pub enum Block {
 impl Block {
     /// Attempts to read a block from the reader. Returns a tuple containing a boolean indicating
     /// if the block was the last block, the length of the block in bytes, and the new `Block`.
-    pub fn read_from(reader: &mut dyn Read) -> Result<(bool, u32, Block)> {
-        let byte = reader.read_u8()?;
-        let is_last = (byte & 0x80) != 0;
-        let blocktype_byte = byte & 0x7F;
+    pub fn read_from<R: Read + ?Sized>(reader: &mut R) -> Result<(bool, u32, Block)> {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        let (is_last, blocktype_byte, length) = raw::parse_header(header);
         let blocktype = BlockType::from_u8(blocktype_byte);
-        let length = reader.read_uint::<BE>(3)? as u32;
 
         debug!("Reading block {:?} with {} bytes", blocktype, length);
 
+        let block = Block::read_body(reader, blocktype_byte, length)?;
+        Ok((is_last, length + 4, block))
+    }
+
+    /// Attempts to read a block from the reader, checking it against the FLAC specification
+    /// according to the options. Violations which the options tolerate are recorded in
+    /// `warnings`. `offset` is the position of the block header within the file. A truncated
+    /// block is kept as an unknown block containing the bytes which could be read, and is treated
+    /// as the last block.
+    pub(crate) fn read_with<R: Read + ?Sized>(
+        reader: &mut R,
+        offset: u64,
+        options: &ReadOptions,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<(bool, u32, Block)> {
+        let header = Block::read_header(reader, offset)?;
+        Block::read_contents(reader, offset, options, warnings, header)
+    }
+
+    /// Reads a block header at the offset, returning a tuple containing a boolean indicating if
+    /// the block is the last block, the block type byte and the length of the contents.
+    pub(crate) fn read_header<R: Read + ?Sized>(
+        reader: &mut R,
+        offset: u64,
+    ) -> Result<(bool, u8, u32)> {
+        let mut header = [0; 4];
+        reader
+            .read_exact(&mut header)
+            .map_err(|err| Error::from(err).in_block(None, offset))?;
+        Ok(raw::parse_header(header))
+    }
+
+    /// Reads the contents of a block following the header returned by `Block::read_header`. See
+    /// `Block::read_with`.
+    pub(crate) fn read_contents<R: Read + ?Sized>(
+        reader: &mut R,
+        offset: u64,
+        options: &ReadOptions,
+        warnings: &mut Vec<ParseWarning>,
+        (is_last, blocktype_byte, length): (bool, u8, u32),
+    ) -> Result<(bool, u32, Block)> {
+        Block::read_contents_with(
+            reader,
+            offset,
+            options,
+            warnings,
+            is_last,
+            blocktype_byte,
+            length,
+        )
+        .map_err(|err| err.in_block(Some(BlockType::from_u8(blocktype_byte)), offset))
+    }
+
+    /// Reads the contents of a block for `Block::read_contents`, given the fields of its header.
+    fn read_contents_with<R: Read + ?Sized>(
+        reader: &mut R,
+        offset: u64,
+        options: &ReadOptions,
+        warnings: &mut Vec<ParseWarning>,
+        is_last: bool,
+        blocktype_byte: u8,
+        length: u32,
+    ) -> Result<(bool, u32, Block)> {
+        let blocktype = BlockType::from_u8(blocktype_byte);
+        if blocktype == BlockType::Picture && options.skip_picture_data {
+            debug!(
+                "Reading picture block with {} bytes, deferring data",
+                length
+            );
+
+            let mut reader = reader.take(length as u64);
+            let fields = Picture::read_field_bytes(&mut reader)?;
+            let (picture, data_length, _) = raw::Picture::read_fields(&fields)?;
+            let mut picture = Picture::try_from(picture)?;
+            picture.deferred = Some(DeferredData {
+                offset: offset + 4 + fields.len() as u64,
+                len: data_length,
+            });
+
+            // skip the data and anything following it in the block
+            io::copy(&mut reader, &mut io::sink())?;
+
+            return Ok((is_last, length + 4, Block::Picture(picture)));
+        }
+
+        debug!("Reading block {:?} with {} bytes", blocktype, length);
+
+        if options
+            .max_block_len
+            .is_some_and(|max_block_len| length > max_block_len)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "block exceeds the maximum block length",
+            ));
+        }
+
+        let mut data = Vec::new();
+        reader.take(length as u64).read_to_end(&mut data)?;
+        if data.len() < length as usize {
+            options.parse_mode.violation(
+                offset,
+                ErrorKind::Truncated,
+                "block is truncated",
+                warnings,
+            )?;
+            let length = data.len() as u32;
+            return Ok((true, length + 4, Block::Unknown((blocktype_byte, data))));
+        }
+
+        let valid_length = match blocktype {
+            BlockType::StreamInfo => length == 34,
+            BlockType::Application => length >= 4,
+            BlockType::SeekTable => length % 18 == 0,
+            _ => true,
+        };
+        if !valid_length {
+            options.parse_mode.violation(
+                offset,
+                ErrorKind::InvalidLength,
+                "block has an invalid length",
+                warnings,
+            )?;
+        }
+
+        // blocks which are too short to be parsed are kept as they are
+        let block = match blocktype {
+            BlockType::StreamInfo if length < 34 => Block::Unknown((blocktype_byte, data)),
+            BlockType::Application if length < 4 => Block::Unknown((blocktype_byte, data)),
+            BlockType::VorbisComment => {
+                let mut invalid = Vec::new();
+                let lossy = if options.lossy_utf8 {
+                    Some(&mut invalid)
+                } else {
+                    None
+                };
+                let vorbis = VorbisComment::parse(&data[..], lossy, options.max_comments)?;
+                for field in invalid {
+                    warnings.push(ParseWarning {
+                        offset,
+                        message: format!("invalid UTF-8 in {}", field),
+                    });
+                }
+                Block::VorbisComment(vorbis)
+            }
+            _ => Block::parse_body(blocktype_byte, length, data)?,
+        };
+
+        Ok((is_last, length + 4, block))
+    }
+
+    /// Reads the contents of a block with the specified type and length.
+    fn read_body<R: Read + ?Sized>(
+        reader: &mut R,
+        blocktype_byte: u8,
+        length: u32,
+    ) -> Result<Block> {
         let mut data = Vec::new();
-        reader.take(length as u64).read_to_end(&mut data).unwrap();
+        reader.take(length as u64).read_to_end(&mut data)?;
+        Block::parse_body(blocktype_byte, length, data)
+    }
 
+    /// Parses the contents of a block with the specified type and length.
+    fn parse_body(blocktype_byte: u8, length: u32, data: Vec<u8>) -> Result<Block> {
+        let blocktype = BlockType::from_u8(blocktype_byte);
         let block = match blocktype {
-            BlockType::StreamInfo => Block::StreamInfo(StreamInfo::from_bytes(&data[..])),
+            BlockType::StreamInfo if data.len() < 34 => {
+                return Err(Error::new(
+                    ErrorKind::InvalidLength,
+                    "streaminfo block is shorter than 34 bytes",
+                ));
+            }
+            BlockType::Application if data.len() < 4 => {
+                return Err(Error::new(
+                    ErrorKind::InvalidLength,
+                    "application block is shorter than 4 bytes",
+                ));
+            }
+            BlockType::StreamInfo => Block::StreamInfo(StreamInfo::from_bytes(&data[..])?),
             BlockType::Padding => Block::Padding(length),
             BlockType::Application => Block::Application(Application::from_bytes(&data[..])),
             BlockType::SeekTable => Block::SeekTable(SeekTable::from_bytes(&data[..])),
@@ -101,39 +240,61 @@ impl Block {
 
         debug!("{:?}", block);
 
-        Ok((is_last, length + 4, block))
+        Ok(block)
+    }
+
+    /// Attempts to parse a single serialized block, including its header. The slice must contain
+    /// exactly one block.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Block;
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set_title(vec!["title"]);
+    ///
+    /// let bytes = Block::VorbisComment(vorbis).to_bytes(true).unwrap();
+    /// match Block::from_bytes(&bytes).unwrap() {
+    ///     Block::VorbisComment(vorbis) => assert_eq!(vorbis.title().unwrap(), &["title"]),
+    ///     _ => panic!("unexpected block type"),
+    /// }
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Block> {
+        let mut reader = bytes;
+        let header = reader.read_u32::<BE>()?;
+        if (header & 0xFF_FF_FF) as usize != reader.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "block length does not match the length of the data",
+            ));
+        }
+
+        let (_, _, block) = Block::read_from(&mut &bytes[..])?;
+        Ok(block)
+    }
+
+    /// Attempts to serialize the block, including its header. `is_last` sets the flag which marks
+    /// the last metadata block of a stream.
+    pub fn to_bytes(&self, is_last: bool) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write_to(is_last, &mut bytes)?;
+        Ok(bytes)
     }
 
     /// Attemps to write the block to the writer. Returns the length of the block in bytes.
-    pub fn write_to(&self, is_last: bool, writer: &mut dyn Write) -> Result<u32> {
-        let (content_len, contents) = match *self {
-            Block::StreamInfo(ref streaminfo) => {
-                let bytes = streaminfo.to_bytes();
-                (bytes.len() as u32, Some(bytes))
-            }
-            Block::Application(ref application) => {
-                let bytes = application.to_bytes();
-                (bytes.len() as u32, Some(bytes))
-            }
-            Block::CueSheet(ref cuesheet) => {
-                let bytes = cuesheet.to_bytes();
-                (bytes.len() as u32, Some(bytes))
-            }
-            Block::Padding(size) => (size, None),
-            Block::Picture(ref picture) => {
-                let bytes = picture.to_bytes();
-                (bytes.len() as u32, Some(bytes))
-            }
-            Block::SeekTable(ref seektable) => {
-                let bytes = seektable.to_bytes();
-                (bytes.len() as u32, Some(bytes))
-            }
-            Block::VorbisComment(ref vorbis) => {
-                let bytes = vorbis.to_bytes();
-                (bytes.len() as u32, Some(bytes))
-            }
-            Block::Unknown((_, ref bytes)) => (bytes.len() as u32, Some(bytes.clone())),
+    ///
+    /// Returns an `InvalidLength` error without writing anything if the contents of the block do
+    /// not fit in the 24 bit length of the block header.
+    pub fn write_to<W: Write + ?Sized>(&self, is_last: bool, writer: &mut W) -> Result<u32> {
+        let contents = self.contents()?;
+        let content_len = match (self, contents.as_ref()) {
+            (&Block::Padding(size), _) => size as usize,
+            (_, Some(bytes)) => bytes.len(),
+            (_, None) => 0,
         };
+        let header = raw::header(self.block_type(), is_last, content_len)?;
+        let content_len = content_len as u32;
 
         debug!(
             "Writing block {:?} with {} bytes",
@@ -141,14 +302,7 @@ impl Block {
             content_len
         );
 
-        let mut byte: u8 = 0;
-        if is_last {
-            byte |= 0x80;
-        }
-
-        byte |= self.block_type().to_u8() & 0x7F;
-        writer.write_u8(byte)?;
-        writer.write_all(&content_len.to_be_bytes()[1..])?;
+        writer.write_all(&header)?;
 
         match contents {
             Some(bytes) => writer.write_all(&bytes[..])?,
@@ -170,6 +324,71 @@ impl Block {
         Ok(content_len + 4)
     }
 
+    /// Returns the serialized contents of the block without the block header, or `None` for a
+    /// padding block.
+    fn contents(&self) -> Result<Option<Vec<u8>>> {
+        let bytes = match *self {
+            Block::StreamInfo(ref streaminfo) => streaminfo.to_bytes()?,
+            Block::Application(ref application) => application.to_bytes(),
+            Block::CueSheet(ref cuesheet) => {
+                cuesheet.check_fields()?;
+                cuesheet.to_bytes()
+            }
+            Block::Padding(_) => return Ok(None),
+            Block::Picture(ref picture) => {
+                if picture.deferred.is_some() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "picture data has not been loaded",
+                    ));
+                }
+                picture.to_bytes()
+            }
+            Block::SeekTable(ref seektable) => seektable.to_bytes(),
+            Block::VorbisComment(ref vorbis) => vorbis.to_bytes(),
+            Block::Unknown((_, ref bytes)) => bytes.clone(),
+        };
+        Ok(Some(bytes))
+    }
+
+    /// Returns the length of the contents of the block in bytes as it would be written, not
+    /// counting the block header. The length may exceed what fits in a block header.
+    pub(crate) fn content_len(&self) -> u64 {
+        match *self {
+            // fixed length, even for a streaminfo block whose fields cannot be written
+            Block::StreamInfo(_) => 34,
+            Block::Padding(size) => size as u64,
+            Block::Picture(ref picture) => picture.content_len(),
+            ref block => block
+                .contents()
+                .ok()
+                .flatten()
+                .map_or(0, |bytes| bytes.len() as u64),
+        }
+    }
+
+    /// Returns true if the contents of the block fit in the 24 bit length of a block header.
+    pub fn fits_block_header(&self) -> bool {
+        self.content_len() <= MAX_CONTENT_LEN as u64
+    }
+
+    /// Returns true if the block has the type byte of a block with contents, but was kept as an
+    /// `Unknown` block because it is truncated or too short to be parsed, as lenient reads do.
+    pub fn is_damaged(&self) -> bool {
+        match *self {
+            Block::Unknown((blocktype, _)) => !matches!(
+                BlockType::from_u8(blocktype),
+                BlockType::Padding | BlockType::Unknown(_)
+            ),
+            _ => false,
+        }
+    }
+
+    /// Returns the error for a block which does not fit in the 24 bit length of a block header.
+    pub(crate) fn too_long_error(&self) -> Error {
+        raw::too_long_error(self.block_type())
+    }
+
     /// Returns the corresponding block type byte for the block.
     pub fn block_type(&self) -> BlockType {
         match *self {
@@ -185,9 +404,23 @@ impl Block {
     }
 }
 
+// BlockLocation {{{
+/// The location of a metadata block within a file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BlockLocation {
+    /// The type of the block.
+    pub block_type: BlockType,
+    /// The offset of the block header from the start of the file.
+    pub offset: u64,
+    /// The length of the block in bytes, including the 4 byte header.
+    pub length: u32,
+}
+//}}}
+
 // StreamInfo {{{
 /// A structure representing a STREAMINFO block.
 #[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StreamInfo {
     /// The minimum block size (in samples) used in the stream.
     pub min_block_size: u16,
@@ -231,82 +464,84 @@ impl StreamInfo {
         }
     }
 
-    /// Parses the bytes as a StreamInfo block.
-    pub fn from_bytes(bytes: &[u8]) -> StreamInfo {
-        let mut streaminfo = StreamInfo::new();
-        let mut i = 0;
-
-        streaminfo.min_block_size =
-            u16::from_be_bytes((&bytes[i..i + 2]).try_into().unwrap()) as u16;
-        i += 2;
-
-        streaminfo.max_block_size =
-            u16::from_be_bytes((&bytes[i..i + 2]).try_into().unwrap()) as u16;
-        i += 2;
-
-        streaminfo.min_frame_size = (&bytes[i..i + 3]).read_uint::<BE>(3).unwrap() as u32;
-        i += 3;
-
-        streaminfo.max_frame_size = (&bytes[i..i + 3]).read_uint::<BE>(3).unwrap() as u32;
-        i += 3;
-
-        // first 16 bits of sample rate
-        let sample_first = u16::from_be_bytes((&bytes[i..i + 2]).try_into().unwrap()) as u16;
-        i += 2;
-
-        // last 4 bits of sample rate, 3 bits of channel, first bit of bits/sample
-        let sample_channel_bps = bytes[i];
-        i += 1;
-
-        streaminfo.sample_rate = (sample_first as u32) << 4 | (sample_channel_bps as u32) >> 4;
-        streaminfo.num_channels = ((sample_channel_bps >> 1) & 0x7) + 1;
-
-        // last 4 bits of bits/sample, 36 bits of total samples
-        let bps_total = (&bytes[i..i + 5]).read_uint::<BE>(5).unwrap();
-        i += 5;
-
-        streaminfo.bits_per_sample =
-            ((sample_channel_bps & 0x1) << 4 | (bps_total >> 36) as u8) + 1;
-        streaminfo.total_samples = bps_total & 0xF_FF_FF_FF_FF;
-
-        streaminfo.md5 = bytes[i..i + 16].to_vec();
-
-        streaminfo
+    /// Attempts to parse the bytes as a StreamInfo block. Returns an `InvalidLength` error if
+    /// there are fewer than 34 bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<StreamInfo> {
+        Ok(raw::StreamInfo::from_bytes(bytes)?.into())
     }
 
-    /// Returns a vector representation of the streaminfo block suitable for writing to a file.
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+    /// Attempts to return a vector representation of the streaminfo block suitable for writing to
+    /// a file. An empty MD5 signature is written as zeroes, which marks the signature as unknown.
+    ///
+    /// Returns an `InvalidInput` error if a field does not fit in the block, such as the number of
+    /// channels and bits per sample of `StreamInfo::new`, which are zero, or an MD5 signature
+    /// which is neither empty nor 16 bytes long.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::StreamInfo;
+    ///
+    /// let mut streaminfo = StreamInfo::new();
+    /// assert!(streaminfo.to_bytes().is_err());
+    ///
+    /// streaminfo.num_channels = 2;
+    /// streaminfo.bits_per_sample = 16;
+    /// let bytes = streaminfo.to_bytes().unwrap();
+    /// assert_eq!(StreamInfo::from_bytes(&bytes).unwrap().md5, &[0; 16]);
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut md5 = [0; 16];
+        match self.md5.len() {
+            0 => {}
+            16 => md5.copy_from_slice(&self.md5),
+            _ => {
+                let mut err = Error::new(
+                    ErrorKind::InvalidInput,
+                    "streaminfo MD5 signature is not 16 bytes long",
+                );
+                err.block_type = Some(BlockType::StreamInfo);
+                return Err(err);
+            }
+        }
 
-        bytes.extend(self.min_block_size.to_be_bytes().iter());
-        bytes.extend(self.max_block_size.to_be_bytes().iter());
-        bytes.extend(self.min_frame_size.to_be_bytes()[1..].iter());
-        bytes.extend(self.max_frame_size.to_be_bytes()[1..].iter());
-
-        // first 16 bits of sample rate
-        bytes.extend(((self.sample_rate >> 4) as u16).to_be_bytes().iter());
-
-        // last 4 bits of sample rate, 3 bits of channel, first bit of bits/sample
-        let byte = ((self.sample_rate & 0xF) << 4) as u8
-            | (((self.num_channels - 1) & 0x7) << 1) as u8
-            | (((self.bits_per_sample - 1) >> 4) & 0x1) as u8;
-        bytes.push(byte);
-
-        // last 4 bits of bits/sample, first 4 bits of sample count
-        let byte = (((self.bits_per_sample - 1) & 0xF) << 4) as u8
-            | ((self.total_samples >> 32) & 0xF) as u8;
-        bytes.push(byte);
-
-        // last 32 bits of sample count
-        bytes.extend(
-            ((self.total_samples & 0xFF_FF_FF_FF) as u32)
-                .to_be_bytes()
-                .iter(),
-        );
+        raw::StreamInfo {
+            min_block_size: self.min_block_size,
+            max_block_size: self.max_block_size,
+            min_frame_size: self.min_frame_size,
+            max_frame_size: self.max_frame_size,
+            sample_rate: self.sample_rate,
+            num_channels: self.num_channels,
+            bits_per_sample: self.bits_per_sample,
+            total_samples: self.total_samples,
+            md5,
+        }
+        .to_bytes()
+    }
 
-        bytes.extend(self.md5.iter().cloned());
+    /// Returns the duration of the stream computed from the total samples and the sample rate.
+    /// Returns `None` if either is unknown.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::StreamInfo;
+    /// use std::time::Duration;
+    ///
+    /// let mut streaminfo = StreamInfo::new();
+    /// assert!(streaminfo.duration().is_none());
+    ///
+    /// streaminfo.sample_rate = 44100;
+    /// streaminfo.total_samples = 44100 * 90 + 22050;
+    /// assert_eq!(streaminfo.duration(), Some(Duration::from_millis(90_500)));
+    /// ```
+    pub fn duration(&self) -> Option<Duration> {
+        if self.sample_rate == 0 || self.total_samples == 0 {
+            return None;
+        }
 
-        bytes
+        let sample_rate = self.sample_rate as u64;
+        let secs = self.total_samples / sample_rate;
+        let nanos = (self.total_samples % sample_rate) * 1_000_000_000 / sample_rate;
+        Some(Duration::new(secs, nanos as u32))
     }
 }
 
@@ -315,11 +550,28 @@ impl Default for StreamInfo {
         Self::new()
     }
 }
+
+impl From<raw::StreamInfo> for StreamInfo {
+    fn from(streaminfo: raw::StreamInfo) -> StreamInfo {
+        StreamInfo {
+            min_block_size: streaminfo.min_block_size,
+            max_block_size: streaminfo.max_block_size,
+            min_frame_size: streaminfo.min_frame_size,
+            max_frame_size: streaminfo.max_frame_size,
+            sample_rate: streaminfo.sample_rate,
+            num_channels: streaminfo.num_channels,
+            bits_per_sample: streaminfo.bits_per_sample,
+            total_samples: streaminfo.total_samples,
+            md5: streaminfo.md5.to_vec(),
+        }
+    }
+}
 //}}}
 
 // Application {{{
 /// A structure representing an APPLICATION block.
 #[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Application {
     /// Registered application ID.
     pub id: Vec<u8>,
@@ -329,12 +581,21 @@ pub struct Application {
 
 impl ::std::fmt::Debug for Application {
     fn fmt(&self, out: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        write!(
-            out,
-            "Application {{ id: {}, data: {:?} }}",
-            hex::encode(&self.id[..]),
-            self.data
-        )
+        match self.known_id() {
+            Some(known) => write!(
+                out,
+                "Application {{ id: {} ({}), data: {:?} }}",
+                hex::encode(&self.id[..]),
+                known.name(),
+                self.data
+            ),
+            None => write!(
+                out,
+                "Application {{ id: {}, data: {:?} }}",
+                hex::encode(&self.id[..]),
+                self.data
+            ),
+        }
     }
 }
 
@@ -369,6 +630,11 @@ impl Application {
 
         bytes
     }
+
+    /// Returns the registered application which owns the block, if the ID is a known one.
+    pub fn known_id(&self) -> Option<ApplicationId> {
+        ApplicationId::from_id(&self.id)
+    }
 }
 
 impl Default for Application {
@@ -376,11 +642,183 @@ impl Default for Application {
         Self::new()
     }
 }
+
+/// An application ID registered with the FLAC project.
+///
+/// # Example
+/// ```
+/// use metaflac::block::{Application, ApplicationId};
+///
+/// assert_eq!(ApplicationId::from_id(b"riff"), Some(ApplicationId::Riff));
+/// assert_eq!(ApplicationId::from_name("GoldWave cue points"), Some(ApplicationId::Cues));
+/// assert_eq!(ApplicationId::Cues.id(), b"Cues");
+///
+/// let application = Application { id: b"aiff".to_vec(), data: Vec::new() };
+/// assert_eq!(application.known_id(), Some(ApplicationId::Aiff));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ApplicationId {
+    /// `ATCH`: FlacFile.
+    FlacFile,
+    /// `BSOL`: beSolo.
+    BeSolo,
+    /// `BUGS`: Bugs Player.
+    BugsPlayer,
+    /// `Cues`: GoldWave cue points.
+    Cues,
+    /// `Fica`: CUE Splitter.
+    CueSplitter,
+    /// `Ftol`: flac-tools.
+    FlacTools,
+    /// `MOTB`: MOTB MetaCzar.
+    MetaCzar,
+    /// `MPSE`: MP3 Stream Editor.
+    Mp3StreamEditor,
+    /// `MuML`: MusicML.
+    MusicMl,
+    /// `RIFF`: Sound Devices RIFF chunk storage.
+    SoundDevicesRiff,
+    /// `SFFL`: Sound Font FLAC.
+    SoundFont,
+    /// `SONY`: Sony Creative Software.
+    Sony,
+    /// `SQEZ`: flacsqueeze.
+    FlacSqueeze,
+    /// `TtWv`: TwistedWave.
+    TwistedWave,
+    /// `UITS`: UITS Embedding tools.
+    Uits,
+    /// `aiff`: FLAC AIFF chunk storage.
+    Aiff,
+    /// `imag`: flac-image.
+    FlacImage,
+    /// `peem`: Parseable Embedded Extensible Metadata.
+    Peem,
+    /// `qfst`: QFLAC Studio.
+    QflacStudio,
+    /// `riff`: FLAC RIFF chunk storage.
+    Riff,
+    /// `tune`: TagTuner.
+    TagTuner,
+    /// `w64 `: FLAC Wave64 chunk storage.
+    Wave64,
+    /// `xbat`: XBAT.
+    Xbat,
+    /// `xmcd`: xmcd.
+    Xmcd,
+}
+
+impl ApplicationId {
+    /// All registered application IDs.
+    pub const ALL: [ApplicationId; 24] = [
+        ApplicationId::FlacFile,
+        ApplicationId::BeSolo,
+        ApplicationId::BugsPlayer,
+        ApplicationId::Cues,
+        ApplicationId::CueSplitter,
+        ApplicationId::FlacTools,
+        ApplicationId::MetaCzar,
+        ApplicationId::Mp3StreamEditor,
+        ApplicationId::MusicMl,
+        ApplicationId::SoundDevicesRiff,
+        ApplicationId::SoundFont,
+        ApplicationId::Sony,
+        ApplicationId::FlacSqueeze,
+        ApplicationId::TwistedWave,
+        ApplicationId::Uits,
+        ApplicationId::Aiff,
+        ApplicationId::FlacImage,
+        ApplicationId::Peem,
+        ApplicationId::QflacStudio,
+        ApplicationId::Riff,
+        ApplicationId::TagTuner,
+        ApplicationId::Wave64,
+        ApplicationId::Xbat,
+        ApplicationId::Xmcd,
+    ];
+
+    /// Returns the four byte ID stored in the application block.
+    pub fn id(self) -> &'static [u8; 4] {
+        match self {
+            ApplicationId::FlacFile => b"ATCH",
+            ApplicationId::BeSolo => b"BSOL",
+            ApplicationId::BugsPlayer => b"BUGS",
+            ApplicationId::Cues => b"Cues",
+            ApplicationId::CueSplitter => b"Fica",
+            ApplicationId::FlacTools => b"Ftol",
+            ApplicationId::MetaCzar => b"MOTB",
+            ApplicationId::Mp3StreamEditor => b"MPSE",
+            ApplicationId::MusicMl => b"MuML",
+            ApplicationId::SoundDevicesRiff => b"RIFF",
+            ApplicationId::SoundFont => b"SFFL",
+            ApplicationId::Sony => b"SONY",
+            ApplicationId::FlacSqueeze => b"SQEZ",
+            ApplicationId::TwistedWave => b"TtWv",
+            ApplicationId::Uits => b"UITS",
+            ApplicationId::Aiff => b"aiff",
+            ApplicationId::FlacImage => b"imag",
+            ApplicationId::Peem => b"peem",
+            ApplicationId::QflacStudio => b"qfst",
+            ApplicationId::Riff => b"riff",
+            ApplicationId::TagTuner => b"tune",
+            ApplicationId::Wave64 => b"w64 ",
+            ApplicationId::Xbat => b"xbat",
+            ApplicationId::Xmcd => b"xmcd",
+        }
+    }
+
+    /// Returns the name of the application as listed in the registry.
+    pub fn name(self) -> &'static str {
+        match self {
+            ApplicationId::FlacFile => "FlacFile",
+            ApplicationId::BeSolo => "beSolo",
+            ApplicationId::BugsPlayer => "Bugs Player",
+            ApplicationId::Cues => "GoldWave cue points",
+            ApplicationId::CueSplitter => "CUE Splitter",
+            ApplicationId::FlacTools => "flac-tools",
+            ApplicationId::MetaCzar => "MOTB MetaCzar",
+            ApplicationId::Mp3StreamEditor => "MP3 Stream Editor",
+            ApplicationId::MusicMl => "MusicML",
+            ApplicationId::SoundDevicesRiff => "Sound Devices RIFF chunk storage",
+            ApplicationId::SoundFont => "Sound Font FLAC",
+            ApplicationId::Sony => "Sony Creative Software",
+            ApplicationId::FlacSqueeze => "flacsqueeze",
+            ApplicationId::TwistedWave => "TwistedWave",
+            ApplicationId::Uits => "UITS Embedding tools",
+            ApplicationId::Aiff => "FLAC AIFF chunk storage",
+            ApplicationId::FlacImage => "flac-image",
+            ApplicationId::Peem => "Parseable Embedded Extensible Metadata",
+            ApplicationId::QflacStudio => "QFLAC Studio",
+            ApplicationId::Riff => "FLAC RIFF chunk storage",
+            ApplicationId::TagTuner => "TagTuner",
+            ApplicationId::Wave64 => "FLAC Wave64 chunk storage",
+            ApplicationId::Xbat => "XBAT",
+            ApplicationId::Xmcd => "xmcd",
+        }
+    }
+
+    /// Returns the registered application with the four byte ID.
+    pub fn from_id(id: &[u8]) -> Option<ApplicationId> {
+        ApplicationId::ALL
+            .iter()
+            .cloned()
+            .find(|known| &known.id()[..] == id)
+    }
+
+    /// Returns the registered application with the name, ignoring ASCII case.
+    pub fn from_name(name: &str) -> Option<ApplicationId> {
+        ApplicationId::ALL
+            .iter()
+            .cloned()
+            .find(|known| known.name().eq_ignore_ascii_case(name))
+    }
+}
 //}}}
 
 // CueSheet {{{
 /// A structure representing a cuesheet track index.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CueSheetTrackIndex {
     /// Offset in samples, relative to the track offset, of the index point.
     pub offset: u64,
@@ -406,6 +844,7 @@ impl Default for CueSheetTrackIndex {
 
 /// A structure representing a cuesheet track.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CueSheetTrack {
     /// Track offset in samples, relative to the beginning of the FLAC audio stream. It is the
     /// offset to the first index point of the track.
@@ -445,6 +884,7 @@ impl Default for CueSheetTrack {
 
 /// A structure representing a CUESHEET block.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CueSheet {
     /// Media catalog number.
     pub catalog_num: String,
@@ -467,61 +907,55 @@ impl CueSheet {
         }
     }
 
-    /// Parses the bytes as a cuesheet block.
+    /// Parses the bytes as a cuesheet block. Returns a `Truncated` error if the bytes end before
+    /// the last track.
     pub fn from_bytes(bytes: &[u8]) -> Result<CueSheet> {
         let mut cuesheet = CueSheet::new();
         let mut i = 0;
 
-        cuesheet.catalog_num = String::from_utf8(bytes[i..i + 128].to_vec())?;
-        i += 128;
+        cuesheet.catalog_num =
+            String::from_utf8(trim_nul(cuesheet_field(bytes, &mut i, 128)?).to_vec())?;
 
-        cuesheet.num_leadin = u64::from_be_bytes((&bytes[i..i + 8]).try_into().unwrap());
-        i += 8;
+        cuesheet.num_leadin =
+            u64::from_be_bytes(cuesheet_field(bytes, &mut i, 8)?.try_into().unwrap());
 
-        let flags = bytes[i];
-        i += 1;
+        let flags = cuesheet_field(bytes, &mut i, 1)?[0];
 
         cuesheet.is_cd = (flags & 0x80) != 0;
 
-        i += 258;
+        cuesheet_field(bytes, &mut i, 258)?;
 
-        let num_tracks = bytes[i];
-        i += 1;
+        let num_tracks = cuesheet_field(bytes, &mut i, 1)?[0];
 
         for _ in 0..num_tracks {
             let mut track = CueSheetTrack::new();
 
-            track.offset = u64::from_be_bytes((&bytes[i..i + 8]).try_into().unwrap());
-            i += 8;
+            track.offset =
+                u64::from_be_bytes(cuesheet_field(bytes, &mut i, 8)?.try_into().unwrap());
 
-            track.number = bytes[i];
-            i += 1;
+            track.number = cuesheet_field(bytes, &mut i, 1)?[0];
 
-            track.isrc = String::from_utf8(bytes[i..i + 12].to_vec())?;
-            i += 12;
+            track.isrc = String::from_utf8(trim_nul(cuesheet_field(bytes, &mut i, 12)?).to_vec())?;
 
-            let flags = bytes[i];
-            i += 1;
+            let flags = cuesheet_field(bytes, &mut i, 1)?[0];
 
             track.is_audio = (flags & 0x80) == 0;
 
             track.pre_emphasis = (flags & 0x40) != 0;
 
-            i += 13;
+            cuesheet_field(bytes, &mut i, 13)?;
 
-            let num_indices = bytes[i];
-            i += 1;
+            let num_indices = cuesheet_field(bytes, &mut i, 1)?[0];
 
             for _ in 0..num_indices {
                 let mut index = CueSheetTrackIndex::new();
 
-                index.offset = u64::from_be_bytes((&bytes[i..i + 8]).try_into().unwrap());
-                i += 8;
+                index.offset =
+                    u64::from_be_bytes(cuesheet_field(bytes, &mut i, 8)?.try_into().unwrap());
 
-                index.point_num = bytes[i];
-                i += 1;
+                index.point_num = cuesheet_field(bytes, &mut i, 1)?[0];
 
-                i += 3;
+                cuesheet_field(bytes, &mut i, 3)?;
 
                 track.indices.push(index);
             }
@@ -532,19 +966,39 @@ impl CueSheet {
         Ok(cuesheet)
     }
 
+    /// Returns an `InvalidInput` error if a field of the cuesheet does not fit in the cuesheet
+    /// block: a catalog number longer than 128 bytes, an ISRC longer than 12 bytes, or more than
+    /// 255 tracks or indices of a track.
+    pub(crate) fn check_fields(&self) -> Result<()> {
+        let message = if self.catalog_num.len() > 128 {
+            "cuesheet catalog number is longer than 128 bytes"
+        } else if self.tracks.len() > 255 {
+            "cuesheet has more than 255 tracks"
+        } else if self.tracks.iter().any(|track| track.isrc.len() > 12) {
+            "cuesheet track ISRC is longer than 12 bytes"
+        } else if self.tracks.iter().any(|track| track.indices.len() > 255) {
+            "cuesheet track has more than 255 indices"
+        } else {
+            return Ok(());
+        };
+        let mut err = Error::new(ErrorKind::InvalidInput, message);
+        err.block_type = Some(BlockType::CueSheet);
+        Err(err)
+    }
+
     /// Returns a vector representation of the cuesheet block suitable for writing to a file.
+    ///
+    /// # Panics
+    /// Panics if a field does not fit in the cuesheet block. `Block::to_bytes` returns an error
+    /// instead.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
 
         assert!(self.catalog_num.len() <= 128);
+        assert!(self.tracks.len() <= 255);
 
-        bytes.extend(self.catalog_num.clone().into_bytes().into_iter());
-        bytes.extend(
-            repeat(0)
-                .take(128 - self.catalog_num.len())
-                .collect::<Vec<u8>>()
-                .into_iter(),
-        );
+        bytes.extend(self.catalog_num.clone().into_bytes());
+        bytes.extend(repeat(0).take(128 - self.catalog_num.len()));
         bytes.extend(self.num_leadin.to_be_bytes().iter());
 
         let mut flags = 0;
@@ -559,16 +1013,12 @@ impl CueSheet {
 
         for track in self.tracks.iter() {
             assert!(track.isrc.len() <= 12);
+            assert!(track.indices.len() <= 255);
 
             bytes.extend(track.offset.to_be_bytes().iter());
             bytes.push(track.number);
-            bytes.extend(track.isrc.clone().into_bytes().into_iter());
-            bytes.extend(
-                repeat(0)
-                    .take(12 - track.isrc.len())
-                    .collect::<Vec<u8>>()
-                    .into_iter(),
-            );
+            bytes.extend(track.isrc.clone().into_bytes());
+            bytes.extend(repeat(0).take(12 - track.isrc.len()));
 
             let mut flags = 0;
             if !track.is_audio {
@@ -599,11 +1049,47 @@ impl Default for CueSheet {
         Self::new()
     }
 }
+
+/// Returns the field of the length at the position within the bytes of a cuesheet block,
+/// advancing the position past it. Returns a `Truncated` error if the bytes end first.
+fn cuesheet_field<'b>(bytes: &'b [u8], i: &mut usize, len: usize) -> Result<&'b [u8]> {
+    let field = bytes
+        .get(*i..*i + len)
+        .ok_or_else(|| Error::new(ErrorKind::Truncated, "cuesheet block is truncated"))?;
+    *i += len;
+    Ok(field)
+}
+
+/// A problem found by `CueSheet::validate`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CueSheetIssue {
+    /// The position of the track in `CueSheet::tracks` to which the problem applies, or `None` if
+    /// it applies to the cuesheet as a whole.
+    pub track: Option<usize>,
+    /// A human readable string describing the problem.
+    pub message: &'static str,
+}
+
+impl fmt::Display for CueSheetIssue {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self.track {
+            Some(track) => write!(out, "track[{}]: {}", track, self.message),
+            None => write!(out, "{}", self.message),
+        }
+    }
+}
+
+/// Returns the bytes without the NUL padding of a fixed-size string field.
+fn trim_nul(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    &bytes[..len]
+}
 //}}}
 
 // Picture {{{
 /// Types of pictures that can be used in the picture block.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum PictureType {
     Other,
@@ -630,7 +1116,9 @@ pub enum PictureType {
 }
 
 impl PictureType {
-    fn from_u32(n: u32) -> Option<PictureType> {
+    /// Returns the picture type with the specified numeric value, or `None` if the value is not a
+    /// known picture type.
+    pub fn from_u32(n: u32) -> Option<PictureType> {
         match n {
             0 => Some(PictureType::Other),
             1 => Some(PictureType::Icon),
@@ -658,8 +1146,12 @@ impl PictureType {
     }
 }
 
+/// The vorbis comment key under which pictures are stored in Ogg Vorbis and Opus files.
+pub const METADATA_BLOCK_PICTURE: &str = "METADATA_BLOCK_PICTURE";
+
 /// A structure representing a PICTURE block.
 #[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Picture {
     /// The picture type.
     pub picture_type: PictureType,
@@ -676,17 +1168,35 @@ pub struct Picture {
     /// For indexed-color pictures (e.g. GIF), the number of colors used, or 0 for non-indexed
     /// pictures.
     pub num_colors: u32,
-    /// The binary picture data.
+    /// The binary picture data. It is serialized as a base64 string in human readable formats.
+    #[cfg_attr(feature = "serde", serde(with = "crate::json::base64_bytes"))]
     pub data: Vec<u8>,
+    /// The location of the picture data within the file the picture was read from, if the data
+    /// was skipped while reading. `data` is empty until the data is loaded.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub deferred: Option<DeferredData>,
+}
+
+/// The location of picture data which has not been loaded yet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeferredData {
+    /// The offset of the data from the start of the file in bytes.
+    pub offset: u64,
+    /// The length of the data in bytes.
+    pub len: u32,
 }
 
 impl ::std::fmt::Debug for Picture {
     fn fmt(&self, out: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        write!(out, "Picture {{ picture_type: {:?}, mime_type: {}, description: {}, width: {}, height: {}, depth: {}, num_colors: {}, data: Vec<u8> ({}) }}", self.picture_type, self.mime_type, self.description, self.width, self.height, self.depth, self.num_colors, self.data.len())
+        write!(out, "Picture {{ picture_type: {:?}, mime_type: {}, description: {}, width: {}, height: {}, depth: {}, num_colors: {}, data: Vec<u8> ({}), deferred: {:?} }}", self.picture_type, self.mime_type, self.description, self.width, self.height, self.depth, self.num_colors, self.data.len(), self.deferred)
     }
 }
 
 impl Picture {
+    /// The MIME type which marks the picture data as a URL linking to the image.
+    pub const URL_MIME_TYPE: &'static str = "-->";
+
     /// Returns a new `Picture` with zero/empty values.
     pub fn new() -> Picture {
         Picture {
@@ -698,81 +1208,208 @@ impl Picture {
             depth: 0,
             num_colors: 0,
             data: Vec::new(),
+            deferred: None,
         }
     }
 
-    /// Attempts to parse the bytes as a `Picture` block. Returns a `Picture` on success.
-    pub fn from_bytes(bytes: &[u8]) -> Result<Picture> {
-        let mut picture = Picture::new();
-        let mut i = 0;
+    /// Detects the MIME type of JPEG, PNG, GIF and WebP image data from its magic bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::Picture;
+    ///
+    /// assert_eq!(Picture::detect_mime_type(b"\x89PNG\r\n\x1a\n"), Some("image/png"));
+    /// assert_eq!(Picture::detect_mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+    /// assert_eq!(Picture::detect_mime_type(b"text"), None);
+    /// ```
+    pub fn detect_mime_type(data: &[u8]) -> Option<&'static str> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some("image/png")
+        } else if data.starts_with(b"\xFF\xD8\xFF") {
+            Some("image/jpeg")
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some("image/gif")
+        } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            Some("image/webp")
+        } else {
+            None
+        }
+    }
 
-        let picture_type_u32 = u32::from_be_bytes((&bytes[i..i + 4]).try_into().unwrap());
-        picture.picture_type = match PictureType::from_u32(picture_type_u32) {
-            Some(picture_type) => picture_type,
-            None => {
-                debug!("Encountered invalid picture type: {}", picture_type_u32);
-                return Err(Error::new(ErrorKind::InvalidInput, "invalid picture type"));
-            }
-        };
-        i += 4;
+    /// Returns true if the picture data has been loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.deferred.is_none()
+    }
 
-        let mime_length = u32::from_be_bytes((&bytes[i..i + 4]).try_into().unwrap()) as usize;
-        i += 4;
+    /// Attempts to load deferred picture data from a reader over the file the picture was read
+    /// from. Does nothing if the data is already loaded.
+    pub fn load_data<R: Read + Seek>(&mut self, reader: &mut R) -> Result<()> {
+        if let Some(deferred) = self.deferred {
+            reader.seek(SeekFrom::Start(deferred.offset))?;
+            let mut data = vec![0; deferred.len as usize];
+            reader.read_exact(&mut data)?;
+            self.data = data;
+            self.deferred = None;
+        }
+        Ok(())
+    }
 
-        picture.mime_type = String::from_utf8(bytes[i..i + mime_length].to_vec())?;
-        i += mime_length;
+    /// Returns a new `Picture` of the specified type which links to an image at the URL instead
+    /// of containing the image data. An error is returned if the URL is empty or contains
+    /// characters other than printable ASCII without spaces.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{Picture, PictureType};
+    ///
+    /// let picture = Picture::from_url(PictureType::CoverFront, "https://example.com/a.jpg").unwrap();
+    /// assert_eq!(picture.mime_type, Picture::URL_MIME_TYPE);
+    /// assert_eq!(picture.url(), Some("https://example.com/a.jpg"));
+    ///
+    /// assert!(Picture::from_url(PictureType::CoverFront, "not a url").is_err());
+    /// ```
+    pub fn from_url(picture_type: PictureType, url: &str) -> Result<Picture> {
+        if !is_url_text(url.as_bytes()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid picture url"));
+        }
+        let mut picture = Picture::new();
+        picture.picture_type = picture_type;
+        picture.mime_type = Picture::URL_MIME_TYPE.to_owned();
+        picture.data = url.as_bytes().to_vec();
+        Ok(picture)
+    }
 
-        let description_length =
-            u32::from_be_bytes((&bytes[i..i + 4]).try_into().unwrap()) as usize;
-        i += 4;
+    /// Returns true if the MIME type marks the picture data as a URL.
+    pub fn is_url(&self) -> bool {
+        self.mime_type == Picture::URL_MIME_TYPE
+    }
 
-        picture.description = String::from_utf8(bytes[i..i + description_length].to_vec())?;
-        i += description_length;
+    /// Returns the URL of a picture which links to its image, or `None` if the picture contains
+    /// image data, its data is not loaded or the data is not valid URL text.
+    pub fn url(&self) -> Option<&str> {
+        if !self.is_url() || !is_url_text(&self.data) {
+            return None;
+        }
+        std::str::from_utf8(&self.data).ok()
+    }
 
-        picture.width = u32::from_be_bytes((&bytes[i..i + 4]).try_into().unwrap());
-        i += 4;
+    /// Replaces the URL of a picture which links to its image with the image itself. The fetch
+    /// function is called with the URL and returns the MIME type and data of the image; this
+    /// crate does not fetch anything itself. Returns false without calling the function if the
+    /// picture does not link to an image.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{Picture, PictureType};
+    ///
+    /// let mut picture = Picture::from_url(PictureType::CoverFront, "file:cover.png").unwrap();
+    /// let resolved = picture
+    ///     .resolve_url(|url| {
+    ///         assert_eq!(url, "file:cover.png");
+    ///         Ok(("image/png".to_owned(), b"\x89PNG\r\n\x1a\n".to_vec()))
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert!(resolved);
+    /// assert_eq!(picture.mime_type, "image/png");
+    /// assert!(picture.url().is_none());
+    /// ```
+    pub fn resolve_url<F>(&mut self, fetch: F) -> Result<bool>
+    where
+        F: FnOnce(&str) -> Result<(String, Vec<u8>)>,
+    {
+        let (mime_type, data) = match self.url() {
+            Some(url) => fetch(url)?,
+            None => return Ok(false),
+        };
+        self.mime_type = mime_type;
+        self.data = data;
+        #[cfg(feature = "image")]
+        self.read_dimensions();
+        Ok(true)
+    }
 
-        picture.height = u32::from_be_bytes((&bytes[i..i + 4]).try_into().unwrap());
-        i += 4;
+    /// Returns the length of the picture block in bytes, not counting the block header. The data
+    /// of a deferred picture counts with the length it has in the file.
+    pub(crate) fn content_len(&self) -> u64 {
+        let data_len = self
+            .deferred
+            .map_or(self.data.len() as u64, |deferred| deferred.len as u64);
+        32 + self.mime_type.len() as u64 + self.description.len() as u64 + data_len
+    }
 
-        picture.depth = u32::from_be_bytes((&bytes[i..i + 4]).try_into().unwrap());
-        i += 4;
+    /// Returns true if the picture block fits in the 24 bit length of a block header.
+    pub fn fits_block_header(&self) -> bool {
+        self.content_len() <= MAX_CONTENT_LEN as u64
+    }
 
-        picture.num_colors = u32::from_be_bytes((&bytes[i..i + 4]).try_into().unwrap());
-        i += 4;
+    /// Attempts to parse the bytes as a `Picture` block. Returns a `Picture` on success.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Picture> {
+        Picture::try_from(raw::Picture::from_bytes(bytes)?)
+    }
 
-        let data_length = u32::from_be_bytes((&bytes[i..i + 4]).try_into().unwrap()) as usize;
-        i += 4;
+    /// Attempts to decode a picture from the base64 encoded value of a `METADATA_BLOCK_PICTURE`
+    /// vorbis comment, as used to store pictures in Ogg Vorbis and Opus files.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{Picture, PictureType};
+    ///
+    /// let mut picture = Picture::new();
+    /// picture.picture_type = PictureType::CoverFront;
+    /// picture.mime_type = "image/png".to_owned();
+    /// picture.data = vec![1, 2, 3];
+    ///
+    /// let value = picture.to_base64();
+    /// assert!(Picture::from_base64(&value).unwrap() == picture);
+    /// assert!(Picture::from_base64("not base64").is_err());
+    /// ```
+    pub fn from_base64(value: &str) -> Result<Picture> {
+        let bytes = BASE64
+            .decode(value.trim())
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid base64 picture"))?;
+        Picture::from_bytes(&bytes)
+    }
 
-        picture.data = bytes[i..i + data_length].to_vec();
+    /// Returns the picture encoded as the base64 value of a `METADATA_BLOCK_PICTURE` vorbis
+    /// comment.
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.to_bytes())
+    }
 
-        Ok(picture)
+    /// Reads the bytes of the fields of a picture block which precede the picture data. Reading
+    /// stops early if the block ends, leaving the parser to report the truncated field.
+    fn read_field_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        // the picture type and the length of the MIME type, then the MIME type and the length of
+        // the description, then the description and the fields which follow it
+        let mut len = 8;
+        for &following in [4, 20].iter() {
+            let end = bytes.len() + len as usize;
+            reader.by_ref().take(len).read_to_end(&mut bytes)?;
+            if bytes.len() < end {
+                return Ok(bytes);
+            }
+            let field_len = u32::from_be_bytes(bytes[end - 4..].try_into().unwrap());
+            len = field_len as u64 + following;
+        }
+        reader.take(len).read_to_end(&mut bytes)?;
+        Ok(bytes)
     }
 
     /// Returns a vector representation of the picture block suitable for writing to a file.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-
-        bytes.extend((self.picture_type as u32).to_be_bytes().iter());
-
-        let mime_type = self.mime_type.clone().into_bytes();
-        bytes.extend((mime_type.len() as u32).to_be_bytes().iter());
-        bytes.extend(mime_type.into_iter());
-
-        let description = self.description.clone().into_bytes();
-        bytes.extend((description.len() as u32).to_be_bytes().iter());
-        bytes.extend(description.into_iter());
-
-        bytes.extend(self.width.to_be_bytes().iter());
-        bytes.extend(self.height.to_be_bytes().iter());
-        bytes.extend(self.depth.to_be_bytes().iter());
-        bytes.extend(self.num_colors.to_be_bytes().iter());
-
-        let data = self.data.clone();
-        bytes.extend((data.len() as u32).to_be_bytes().iter());
-        bytes.extend(data.into_iter());
-
-        bytes
+        raw::Picture {
+            picture_type: self.picture_type as u32,
+            mime_type: &self.mime_type,
+            description: &self.description,
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+            num_colors: self.num_colors,
+            data: &self.data,
+        }
+        .to_bytes()
     }
 }
 
@@ -781,24 +1418,74 @@ impl Default for Picture {
         Self::new()
     }
 }
+
+impl<'a> TryFrom<raw::Picture<'a>> for Picture {
+    type Error = Error;
+
+    fn try_from(picture: raw::Picture<'a>) -> Result<Picture> {
+        let picture_type = match PictureType::from_u32(picture.picture_type) {
+            Some(picture_type) => picture_type,
+            None => {
+                debug!("Encountered invalid picture type: {}", picture.picture_type);
+                return Err(Error::new(ErrorKind::InvalidInput, "invalid picture type"));
+            }
+        };
+
+        Ok(Picture {
+            picture_type,
+            mime_type: picture.mime_type.to_owned(),
+            description: picture.description.to_owned(),
+            width: picture.width,
+            height: picture.height,
+            depth: picture.depth,
+            num_colors: picture.num_colors,
+            data: picture.data.to_vec(),
+            deferred: None,
+        })
+    }
+}
+
+/// Returns true if the bytes are non-empty printable ASCII without spaces, as required of the URL
+/// of a picture which links to its image.
+fn is_url_text(bytes: &[u8]) -> bool {
+    !bytes.is_empty() && bytes.iter().all(|&b| (0x21..0x7F).contains(&b))
+}
 //}}}
 
 // SeekTable {{{
 // SeekPoint {{{
 /// A structure representing a seektable seek point.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SeekPoint {
     /// Sample number of first sample in the target frame, or 0xFFFFFFFFFFFFFFFF for a placeholder
     /// point.
-    sample_number: u64,
+    pub sample_number: u64,
     /// Offset (in bytes) from the first byte of the first frame header to the first byte of the
     /// target frame's header.
-    offset: u64,
+    pub offset: u64,
     /// Number of samples in the target frame.
-    num_samples: u16,
+    pub num_samples: u16,
 }
 
 impl SeekPoint {
+    /// The sample number of a placeholder point.
+    pub const PLACEHOLDER: u64 = 0xFF_FF_FF_FF_FF_FF_FF_FF;
+
+    /// Returns a new placeholder `SeekPoint`.
+    pub fn placeholder() -> SeekPoint {
+        SeekPoint {
+            sample_number: SeekPoint::PLACEHOLDER,
+            offset: 0,
+            num_samples: 0,
+        }
+    }
+
+    /// Returns true if the seekpoint is a placeholder point.
+    pub fn is_placeholder(&self) -> bool {
+        self.sample_number == SeekPoint::PLACEHOLDER
+    }
+
     /// Returns a new `SeekPoint` with all zero values.
     pub fn new() -> SeekPoint {
         SeekPoint {
@@ -845,6 +1532,7 @@ impl Default for SeekPoint {
 
 /// A structure representing a SEEKTABLE block.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SeekTable {
     /// One or more seek points.
     pub seekpoints: Vec<SeekPoint>,
@@ -878,28 +1566,621 @@ impl SeekTable {
         let mut bytes = Vec::new();
 
         for seekpoint in self.seekpoints.iter() {
-            bytes.extend(seekpoint.to_bytes().into_iter());
+            bytes.extend(seekpoint.to_bytes());
         }
 
         bytes
     }
-}
 
-impl Default for SeekTable {
-    fn default() -> Self {
-        Self::new()
+    /// Inserts a seekpoint, keeping the seekpoints sorted by sample number with placeholder points
+    /// last. A seekpoint with the same sample number as an existing non-placeholder point
+    /// replaces it.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{SeekPoint, SeekTable};
+    ///
+    /// let mut seektable = SeekTable::new();
+    /// seektable.insert(SeekPoint::placeholder());
+    /// seektable.insert(SeekPoint { sample_number: 4096, offset: 100, num_samples: 4096 });
+    /// seektable.insert(SeekPoint { sample_number: 0, offset: 0, num_samples: 4096 });
+    ///
+    /// let samples: Vec<_> = seektable.seekpoints.iter().map(|p| p.sample_number).collect();
+    /// assert_eq!(samples, &[0, 4096, SeekPoint::PLACEHOLDER]);
+    /// ```
+    pub fn insert(&mut self, seekpoint: SeekPoint) {
+        let sample_number = seekpoint.sample_number;
+        match self
+            .seekpoints
+            .binary_search_by_key(&sample_number, |p| p.sample_number)
+        {
+            Ok(i) if !seekpoint.is_placeholder() => self.seekpoints[i] = seekpoint,
+            Ok(i) | Err(i) => self.seekpoints.insert(i, seekpoint),
+        }
     }
-}
-//}}}
 
-// VorbisComment {{{
-/// A structure representing a VORBIS_COMMENT block.
-#[derive(Clone, Debug, Eq, PartialEq)]
+    /// Removes the seekpoint with the specified sample number. Returns the removed seekpoint, or
+    /// `None` if there is no such seekpoint.
+    pub fn remove(&mut self, sample_number: u64) -> Option<SeekPoint> {
+        let i = self
+            .seekpoints
+            .iter()
+            .position(|p| p.sample_number == sample_number)?;
+        Some(self.seekpoints.remove(i))
+    }
+
+    /// Returns the last seekpoint at or before the sample, which is where decoding has to start to
+    /// reach the sample. Placeholder points are ignored. Returns `None` if every seekpoint is after
+    /// the sample.
+    ///
+    /// The lookup is a binary search, so the seekpoints must be sorted as the FLAC format
+    /// requires and as `SeekTable::insert` keeps them.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{SeekPoint, SeekTable};
+    ///
+    /// let mut seektable = SeekTable::new();
+    /// seektable.insert(SeekPoint { sample_number: 4096, offset: 100, num_samples: 4096 });
+    /// seektable.insert(SeekPoint { sample_number: 8192, offset: 200, num_samples: 4096 });
+    /// seektable.insert(SeekPoint::placeholder());
+    ///
+    /// assert_eq!(seektable.seek_point_before(5000).unwrap().offset, 100);
+    /// assert_eq!(seektable.seek_point_before(8192).unwrap().offset, 200);
+    /// assert_eq!(seektable.seek_point_before(u64::MAX).unwrap().offset, 200);
+    /// assert!(seektable.seek_point_before(4095).is_none());
+    ///
+    /// assert_eq!(seektable.seek_point_after(5000).unwrap().offset, 200);
+    /// assert!(seektable.seek_point_after(8193).is_none());
+    /// ```
+    pub fn seek_point_before(&self, sample: u64) -> Option<&SeekPoint> {
+        let points = self.sorted_points();
+        let i = points.partition_point(|p| p.sample_number <= sample);
+        i.checked_sub(1).map(|i| &points[i])
+    }
+
+    /// Returns the first seekpoint at or after the sample, ignoring placeholder points. Returns
+    /// `None` if every seekpoint is before the sample. The seekpoints must be sorted as for
+    /// `SeekTable::seek_point_before`.
+    pub fn seek_point_after(&self, sample: u64) -> Option<&SeekPoint> {
+        let points = self.sorted_points();
+        points.get(points.partition_point(|p| p.sample_number < sample))
+    }
+
+    /// Returns the seekpoint to start decoding from to reach the time into the stream with the
+    /// specified sample rate, as for `SeekTable::seek_point_before`.
+    pub fn seek_point_before_time(&self, time: Duration, sample_rate: u32) -> Option<&SeekPoint> {
+        let sample = time.as_nanos() * sample_rate as u128 / 1_000_000_000;
+        self.seek_point_before(sample.try_into().unwrap_or(u64::MAX))
+    }
+
+    /// Returns the seekpoints before the placeholder points, which sort last.
+    fn sorted_points(&self) -> &[SeekPoint] {
+        let end = self.seekpoints.partition_point(|p| !p.is_placeholder());
+        &self.seekpoints[..end]
+    }
+
+    /// Removes all placeholder points.
+    pub fn remove_placeholders(&mut self) {
+        self.seekpoints.retain(|p| !p.is_placeholder());
+    }
+
+    /// Returns a seektable of `n` placeholder points, reserving room for points to be filled in
+    /// later. This is what `metaflac --add-seekpoint=#` adds.
+    pub fn template_n_points(n: u32) -> SeekTable {
+        SeekTable {
+            seekpoints: vec![SeekPoint::placeholder(); n as usize],
+        }
+    }
+
+    /// Returns a seektable template with a point every `secs` seconds of the stream, starting at
+    /// the first sample. This is what `metaflac --add-seekpoint=<secs>s` adds. The template is
+    /// empty if the stream has an unknown length or sample rate or `secs` is not positive.
+    ///
+    /// The points of a template only have sample numbers. Their offsets are filled in with
+    /// `SeekTable::fill_from_frames` once the frame positions are known.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{SeekTable, StreamInfo};
+    ///
+    /// let mut streaminfo = StreamInfo::new();
+    /// streaminfo.sample_rate = 44100;
+    /// streaminfo.total_samples = 44100 * 25;
+    ///
+    /// let seektable = SeekTable::template_every_seconds(&streaminfo, 10.0);
+    /// let samples: Vec<_> = seektable.seekpoints.iter().map(|p| p.sample_number).collect();
+    /// assert_eq!(samples, &[0, 441000, 882000]);
+    /// ```
+    pub fn template_every_seconds(streaminfo: &StreamInfo, secs: f64) -> SeekTable {
+        let interval = (secs * streaminfo.sample_rate as f64) as u64;
+        let total_samples = streaminfo.total_samples;
+        if interval == 0 || total_samples == 0 {
+            return SeekTable::new();
+        }
+
+        SeekTable {
+            seekpoints: (0..total_samples.div_ceil(interval))
+                .map(|i| SeekPoint {
+                    sample_number: i * interval,
+                    offset: 0,
+                    num_samples: 0,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns a seektable template with `n` points spaced evenly over the stream, starting at the
+    /// first sample. This is what `metaflac --add-seekpoint=<n>x` adds. The template is empty if
+    /// the stream has an unknown length.
+    pub fn template_spaced_points(streaminfo: &StreamInfo, n: u32) -> SeekTable {
+        let total_samples = streaminfo.total_samples;
+        let mut seektable = SeekTable::new();
+        if total_samples == 0 {
+            return seektable;
+        }
+
+        for i in 0..n as u64 {
+            let sample_number = (total_samples as u128 * i as u128 / n as u128) as u64;
+            // a stream shorter than the number of points would repeat sample numbers
+            if seektable
+                .seekpoints
+                .last()
+                .map_or(true, |p| p.sample_number != sample_number)
+            {
+                seektable.seekpoints.push(SeekPoint {
+                    sample_number,
+                    offset: 0,
+                    num_samples: 0,
+                });
+            }
+        }
+        seektable
+    }
+
+    /// Fills in the points of a template from the positions of the frames of the stream, given as
+    /// seekpoints with the first sample number, offset and number of samples of each frame in
+    /// stream order. Each point moves to the start of the frame which contains its sample. Points
+    /// which end up in the same frame as an earlier point become placeholders, and the points are
+    /// sorted as in `SeekTable::insert`. Returns the number of distinct points which were filled
+    /// in.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{SeekPoint, SeekTable};
+    ///
+    /// let mut seektable = SeekTable::new();
+    /// seektable.insert(SeekPoint { sample_number: 5000, offset: 0, num_samples: 0 });
+    /// seektable.insert(SeekPoint { sample_number: 6000, offset: 0, num_samples: 0 });
+    ///
+    /// let frames = (0..4u64).map(|i| SeekPoint {
+    ///     sample_number: i * 4096,
+    ///     offset: i * 1000,
+    ///     num_samples: 4096,
+    /// });
+    /// assert_eq!(seektable.fill_from_frames(frames), 1);
+    ///
+    /// assert_eq!(seektable.seekpoints[0].sample_number, 4096);
+    /// assert_eq!(seektable.seekpoints[0].offset, 1000);
+    /// assert!(seektable.seekpoints[1].is_placeholder());
+    /// ```
+    pub fn fill_from_frames<I: IntoIterator<Item = SeekPoint>>(&mut self, frames: I) -> usize {
+        self.seekpoints
+            .sort_by_key(|p| (p.is_placeholder(), p.sample_number));
+
+        let mut filled = vec![false; self.seekpoints.len()];
+        let mut i = 0;
+        for frame in frames {
+            let end = frame.sample_number + frame.num_samples as u64;
+            while i < self.seekpoints.len() && !self.seekpoints[i].is_placeholder() {
+                let sample_number = self.seekpoints[i].sample_number;
+                if sample_number >= end {
+                    break;
+                }
+                if sample_number >= frame.sample_number {
+                    self.seekpoints[i] = frame;
+                    filled[i] = true;
+                }
+                i += 1;
+            }
+        }
+
+        // points moved to the start of a shared frame are duplicates
+        let mut last = None;
+        for (seekpoint, filled) in self.seekpoints.iter_mut().zip(filled.iter_mut()) {
+            if seekpoint.is_placeholder() {
+                continue;
+            }
+            if last == Some(seekpoint.sample_number) {
+                *seekpoint = SeekPoint::placeholder();
+                *filled = false;
+            } else {
+                last = Some(seekpoint.sample_number);
+            }
+        }
+        self.seekpoints
+            .sort_by_key(|p| (p.is_placeholder(), p.sample_number));
+        filled.iter().filter(|&&filled| filled).count()
+    }
+}
+
+impl Default for SeekTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+//}}}
+
+// Date {{{
+/// A date stored in a vorbis comment, such as the DATE comment. The month and day are optional,
+/// matching the `YYYY`, `YYYY-MM` and `YYYY-MM-DD` formats.
+///
+/// # Example
+/// ```
+/// use metaflac::block::Date;
+///
+/// let date: Date = "2004-07".parse().unwrap();
+/// assert_eq!(date.year, 2004);
+/// assert_eq!(date.month, Some(7));
+/// assert_eq!(date.day, None);
+/// assert_eq!(date.to_string(), "2004-07");
+///
+/// assert!("2004-13-01".parse::<Date>().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Date {
+    /// The year.
+    pub year: u16,
+    /// The month, from 1 to 12.
+    pub month: Option<u8>,
+    /// The day of the month, from 1 to 31. Only present if the month is present.
+    pub day: Option<u8>,
+}
+
+impl Date {
+    /// Returns a new `Date` with only a year.
+    pub fn new(year: u16) -> Date {
+        Date {
+            year,
+            month: None,
+            day: None,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Date> {
+        let invalid = || Error::new(ErrorKind::InvalidInput, "invalid date");
+        let parse = |part: &str, len: usize| {
+            if part.len() == len && part.bytes().all(|b| b.is_ascii_digit()) {
+                part.parse::<u16>().map_err(|_| invalid())
+            } else {
+                Err(invalid())
+            }
+        };
+
+        let mut parts = s.trim().split('-');
+        let mut date = Date::new(parse(parts.next().unwrap_or(""), 4)?);
+        if let Some(month) = parts.next() {
+            let month = parse(month, 2)?;
+            if !(1..=12).contains(&month) {
+                return Err(invalid());
+            }
+            date.month = Some(month as u8);
+        }
+        if let Some(day) = parts.next() {
+            let day = parse(day, 2)?;
+            if !(1..=31).contains(&day) {
+                return Err(invalid());
+            }
+            date.day = Some(day as u8);
+        }
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        write!(out, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(out, "-{:02}", month)?;
+            if let Some(day) = self.day {
+                write!(out, "-{:02}", day)?;
+            }
+        }
+        Ok(())
+    }
+}
+//}}}
+
+// KeyAliases {{{
+/// Groups of vorbis comment keys which different taggers use for the same logical field. The
+/// first key of each group is its canonical form.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyAliases {
+    /// The groups of keys, each starting with the canonical key. Keys are upper case.
+    pub groups: Vec<Vec<String>>,
+}
+
+impl KeyAliases {
+    /// Returns a new `KeyAliases` with the common aliases for the total number of tracks and
+    /// discs and the album artist.
+    pub fn new() -> KeyAliases {
+        let groups = [
+            ["TOTALTRACKS", "TRACKTOTAL"],
+            ["TOTALDISCS", "DISCTOTAL"],
+            ["ALBUMARTIST", "ALBUM ARTIST"],
+        ];
+        KeyAliases {
+            groups: groups
+                .iter()
+                .map(|group| group.iter().map(|key| key.to_string()).collect())
+                .collect(),
+        }
+    }
+
+    /// Returns the group of keys which contains the key, if any.
+    pub fn group(&self, key: &str) -> Option<&[String]> {
+        let key = key.to_ascii_uppercase();
+        self.groups
+            .iter()
+            .find(|group| group.contains(&key))
+            .map(|group| &group[..])
+    }
+
+    /// Returns the canonical form of the key, which is the key itself if it has no aliases.
+    pub fn canonical(&self, key: &str) -> String {
+        match self.group(key) {
+            Some(group) if !group.is_empty() => group[0].clone(),
+            _ => key.to_ascii_uppercase(),
+        }
+    }
+}
+
+impl Default for KeyAliases {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+//}}}
+
+// TrackNumberStyle {{{
+/// The conventions for storing the total number of tracks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TrackNumberStyle {
+    /// The track number in TRACKNUMBER and the total in TOTALTRACKS, e.g. `TRACKNUMBER=3`.
+    Separate,
+    /// Both numbers in TRACKNUMBER, e.g. `TRACKNUMBER=3/12`.
+    Combined,
+    /// Both numbers in TRACKNUMBER, with the total repeated in TOTALTRACKS.
+    Both,
+}
+//}}}
+
+// RatingPolicy {{{
+/// Controls how ratings are mapped to the RATING comment, which players interpret differently.
+/// The FMPS_RATING comment always holds the rating from 0.0 to 1.0.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RatingPolicy {
+    /// Writes the rating to the RATING comment as well as the FMPS_RATING comment.
+    pub mirror: bool,
+    /// The value of the RATING comment which corresponds to a rating of 1.0, e.g. 100 for a
+    /// percentage or 5 for a number of stars.
+    pub scale: u32,
+}
+
+impl RatingPolicy {
+    /// Returns a new `RatingPolicy` which mirrors ratings to RATING as a percentage.
+    pub fn new() -> RatingPolicy {
+        RatingPolicy {
+            mirror: true,
+            scale: 100,
+        }
+    }
+}
+
+impl Default for RatingPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+//}}}
+
+// Chapter {{{
+/// A chapter of an audiobook or podcast, stored in `CHAPTERxxx`, `CHAPTERxxxNAME` and
+/// `CHAPTERxxxURL` vorbis comments.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Chapter {
+    /// The time from the start of the stream at which the chapter starts.
+    pub start: Duration,
+    /// The title of the chapter.
+    pub title: String,
+    /// A link to more information about the chapter.
+    pub url: Option<String>,
+}
+
+impl Chapter {
+    /// Returns a new `Chapter` starting at the start of the stream without a title.
+    pub fn new() -> Chapter {
+        Chapter {
+            start: Duration::from_secs(0),
+            title: String::new(),
+            url: None,
+        }
+    }
+}
+
+impl Default for Chapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits a chapter comment key into the chapter number and the suffix after it, which is empty
+/// for the start time, `NAME` or `URL`.
+fn chapter_key(key: &str) -> Option<(u32, &str)> {
+    let rest = key
+        .get(..7)?
+        .eq_ignore_ascii_case("CHAPTER")
+        .then(|| &key[7..])?;
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let suffix = &rest[digits..];
+    let is_suffix = ["", "NAME", "URL"]
+        .iter()
+        .any(|other| suffix.eq_ignore_ascii_case(other));
+    if digits < 3 || !is_suffix {
+        return None;
+    }
+    Some((rest[..digits].parse().ok()?, suffix))
+}
+
+/// Parses a chapter start time of the form `HH:MM:SS` or `HH:MM:SS.fff`.
+fn parse_chapter_time(s: &str) -> Option<Duration> {
+    let mut parts = s.trim().splitn(3, ':');
+    let hours = parts.next()?;
+    let minutes = parts.next()?;
+    let seconds = parts.next()?;
+    let (seconds, fraction) = match seconds.find('.') {
+        Some(i) => (&seconds[..i], &seconds[i + 1..]),
+        None => (seconds, ""),
+    };
+
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(hours) || minutes.len() != 2 || seconds.len() != 2 {
+        return None;
+    }
+    if !all_digits(minutes) || !all_digits(seconds) {
+        return None;
+    }
+    let nanos = match fraction.len() {
+        0 => 0,
+        1..=9 if all_digits(fraction) => {
+            fraction.parse::<u32>().ok()? * 10u32.pow(9 - fraction.len() as u32)
+        }
+        _ => return None,
+    };
+    let (minutes, seconds) = (minutes.parse::<u64>().ok()?, seconds.parse::<u64>().ok()?);
+    if minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+
+    let seconds = hours.parse::<u64>().ok()? * 3600 + minutes * 60 + seconds;
+    Some(Duration::new(seconds, nanos))
+}
+
+/// Returns the chapter comments for the chapters, numbered from 0 in order of their start time.
+pub(crate) fn chapter_comments(chapters: &[Chapter]) -> Vec<(String, String)> {
+    let mut chapters: Vec<&Chapter> = chapters.iter().collect();
+    chapters.sort_by_key(|chapter| chapter.start);
+
+    let mut comments = Vec::new();
+    for (i, chapter) in chapters.into_iter().enumerate() {
+        let millis = chapter.start.as_millis();
+        let start = format!(
+            "{:02}:{:02}:{:02}.{:03}",
+            millis / 3_600_000,
+            millis / 60_000 % 60,
+            millis / 1000 % 60,
+            millis % 1000
+        );
+        comments.push((format!("CHAPTER{:03}", i), start));
+        comments.push((format!("CHAPTER{:03}NAME", i), chapter.title.clone()));
+        if let Some(ref url) = chapter.url {
+            comments.push((format!("CHAPTER{:03}URL", i), url.clone()));
+        }
+    }
+    comments
+}
+//}}}
+
+// VorbisComment {{{
+/// Parses lyrics in the LRC format into lines sorted by time. Lines without a timestamp are
+/// skipped.
+fn parse_lrc(lrc: &str) -> Vec<(Duration, String)> {
+    let mut lyrics = Vec::new();
+    for line in lrc.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while rest.starts_with('[') {
+            let end = match rest.find(']') {
+                Some(end) => end,
+                None => break,
+            };
+            match parse_lrc_time(&rest[1..end]) {
+                Some(time) => times.push(time),
+                None => break,
+            }
+            rest = &rest[end + 1..];
+        }
+
+        for time in times {
+            lyrics.push((time, rest.trim().to_owned()));
+        }
+    }
+
+    lyrics.sort_by_key(|(time, _)| *time);
+    lyrics
+}
+
+/// Parses an LRC timestamp of the form `mm:ss`, `mm:ss.xx` or `mm:ss.xxx`.
+fn parse_lrc_time(s: &str) -> Option<Duration> {
+    let (minutes, seconds) = s.split_at(s.find(':')?);
+    let seconds = &seconds[1..];
+    let (seconds, fraction) = match seconds.find('.') {
+        Some(i) => (&seconds[..i], &seconds[i + 1..]),
+        None => (seconds, ""),
+    };
+
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(minutes) || !all_digits(seconds) || seconds.len() != 2 {
+        return None;
+    }
+    let millis = match fraction.len() {
+        0 => 0,
+        1..=3 if all_digits(fraction) => {
+            fraction.parse::<u64>().ok()? * 10u64.pow(3 - fraction.len() as u32)
+        }
+        _ => return None,
+    };
+
+    let seconds = minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds) + Duration::from_millis(millis))
+}
+
+/// Parses the four digit year at the start of a date.
+fn parse_year(s: &str) -> Option<u16> {
+    let s = s.trim();
+    match s.get(..4) {
+        Some(year) if year.bytes().all(|b| b.is_ascii_digit()) => year.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Returns true if the byte is allowed in a vorbis comment field name.
+fn is_valid_key_byte(b: u8) -> bool {
+    (0x20..=0x7D).contains(&b) && b != b'='
+}
+
+/// A structure representing a VORBIS_COMMENT block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VorbisComment {
     /// The vendor string.
     pub vendor_string: String,
-    /// A map of keys to a list of their values.
-    pub comments: HashMap<String, Vec<String>>,
+    /// The comments as key and value pairs, in the order they are written. Keys are compared
+    /// ignoring case and are written as they are stored.
+    pub comments: Vec<(String, String)>,
+    /// The comments without a `=` separator, which have no key or value, each with the number of
+    /// comments in `comments` which are written before it. They are kept so that they are written
+    /// back in place, and are removed by `sanitize`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unseparated: Vec<(usize, String)>,
 }
 
 impl VorbisComment {
@@ -907,114 +2188,395 @@ impl VorbisComment {
     pub fn new() -> VorbisComment {
         VorbisComment {
             vendor_string: String::new(),
-            comments: HashMap::new(),
+            comments: Vec::new(),
+            unseparated: Vec::new(),
         }
     }
 
     /// Attempts to parse the bytes as a vorbis comment block. Returns a `VorbisComment` on
     /// success.
     pub fn from_bytes(bytes: &[u8]) -> Result<VorbisComment> {
-        let mut vorbis = VorbisComment::new();
-        let mut i = 0;
+        VorbisComment::parse(bytes, None, None)
+    }
 
-        let vendor_length = u32::from_le_bytes((&bytes[i..i + 4]).try_into().unwrap()) as usize;
-        i += 4;
+    /// Attempts to parse the bytes as a vorbis comment block, replacing invalid UTF-8 sequences
+    /// with the replacement character instead of failing. Returns the `VorbisComment` along with
+    /// the keys of the comments which contained invalid UTF-8, or `VENDOR` for the vendor string.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut bytes = vec![0, 0, 0, 0, 1, 0, 0, 0];
+    /// let comment = b"TITLE=caf\xe9";
+    /// bytes.extend(&(comment.len() as u32).to_le_bytes());
+    /// bytes.extend(&comment[..]);
+    ///
+    /// assert!(VorbisComment::from_bytes(&bytes).is_err());
+    ///
+    /// let (vorbis, invalid) = VorbisComment::from_bytes_lossy(&bytes).unwrap();
+    /// assert_eq!(vorbis.title().unwrap(), &["caf\u{fffd}"]);
+    /// assert_eq!(invalid, &["TITLE"]);
+    /// ```
+    pub fn from_bytes_lossy(bytes: &[u8]) -> Result<(VorbisComment, Vec<String>)> {
+        let mut invalid = Vec::new();
+        let vorbis = VorbisComment::parse(bytes, Some(&mut invalid), None)?;
+        Ok((vorbis, invalid))
+    }
+
+    /// Parses the bytes as a vorbis comment block. Invalid UTF-8 is an error unless `invalid` is
+    /// given, in which case it is replaced and the affected fields are added to `invalid`.
+    /// Returns an error if the block contains more than `max_comments` comments.
+    fn parse(
+        bytes: &[u8],
+        mut invalid: Option<&mut Vec<String>>,
+        max_comments: Option<u32>,
+    ) -> Result<VorbisComment> {
+        let decode = |bytes: &[u8], is_vendor: bool| -> Result<String> {
+            match (String::from_utf8(bytes.to_vec()), invalid.as_mut()) {
+                (Ok(string), _) => Ok(string),
+                (Err(_), Some(invalid)) => {
+                    let string = String::from_utf8_lossy(bytes).into_owned();
+                    invalid.push(if is_vendor {
+                        "VENDOR".to_owned()
+                    } else {
+                        string.split('=').next().unwrap().to_ascii_uppercase()
+                    });
+                    Ok(string)
+                }
+                (Err(err), None) => Err(err.into()),
+            }
+        };
 
-        vorbis.vendor_string = String::from_utf8(bytes[i..i + vendor_length].to_vec())?;
-        i += vendor_length;
+        let (vendor_string, comments) = raw::read_comments(bytes, max_comments, decode)?;
+        let comments = comments.iter().map(String::as_str);
+        Ok(raw::VorbisComment::from_comments(&vendor_string, comments).into())
+    }
 
-        let num_comments = u32::from_le_bytes((&bytes[i..i + 4]).try_into().unwrap());
-        i += 4;
+    /// Returns every comment as a key and value pair in the order the comments are written.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("TITLE", vec!["title"]);
+    /// vorbis.set("ARTIST", vec!["a", "b"]);
+    /// assert_eq!(
+    ///     vorbis.entries(),
+    ///     &[("TITLE", "title"), ("ARTIST", "a"), ("ARTIST", "b")]
+    /// );
+    /// ```
+    pub fn entries(&self) -> Vec<(&str, &str)> {
+        self.comments
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
 
-        for _ in 0..num_comments {
-            let comment_length =
-                u32::from_le_bytes((&bytes[i..i + 4]).try_into().unwrap()) as usize;
-            i += 4;
+    /// Returns a vector representation of the vorbis comment suitable for writing to a file.
+    /// Comments are written in the order they were read, with their original keys, and comments
+    /// without a separator are written back in place, so an unmodified block is reproduced
+    /// exactly.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut bytes = vec![0, 0, 0, 0, 3, 0, 0, 0];
+    /// for comment in ["title=a", "ARTIST=b", "TITLE=c"].iter() {
+    ///     bytes.extend(&(comment.len() as u32).to_le_bytes());
+    ///     bytes.extend(comment.as_bytes());
+    /// }
+    ///
+    /// let mut vorbis = VorbisComment::from_bytes(&bytes).unwrap();
+    /// assert_eq!(vorbis.get("TITLE").unwrap(), &["a", "c"]);
+    /// assert_eq!(vorbis.to_bytes(), bytes);
+    ///
+    /// vorbis.set("TITLE", vec!["d", "e"]);
+    /// assert_eq!(VorbisComment::from_bytes(&vorbis.to_bytes()).unwrap(), vorbis);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        raw::VorbisComment {
+            vendor_string: &self.vendor_string,
+            comments: self.entries(),
+            unseparated: self
+                .unseparated
+                .iter()
+                .map(|(i, comment)| (*i, comment.as_str()))
+                .collect(),
+        }
+        .to_bytes()
+    }
 
-            let comments = String::from_utf8(bytes[i..i + comment_length].to_vec())?;
-            i += comment_length;
+    /// Returns true if the key is a valid field name. The specification requires field names to
+    /// be non-empty and to consist of the characters 0x20 through 0x7D, excluding `=`.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// assert!(VorbisComment::is_valid_key("MUSICBRAINZ_ALBUMID"));
+    /// assert!(!VorbisComment::is_valid_key("KEY=VALUE"));
+    /// assert!(!VorbisComment::is_valid_key("CAF\u{c9}"));
+    /// assert!(!VorbisComment::is_valid_key(""));
+    /// ```
+    pub fn is_valid_key(key: &str) -> bool {
+        !key.is_empty() && key.bytes().all(is_valid_key_byte)
+    }
 
-            let comments_split: Vec<&str> = comments.splitn(2, '=').collect();
-            let key = comments_split[0].to_ascii_uppercase();
-            let value = comments_split[1].to_owned();
+    /// Returns the key with any characters which are not allowed in field names removed.
+    pub(crate) fn sanitize_key(key: &str) -> String {
+        key.chars()
+            .filter(|c| c.is_ascii() && is_valid_key_byte(*c as u8))
+            .collect()
+    }
 
-            vorbis
-                .comments
-                .entry(key)
-                .or_insert_with(|| Vec::with_capacity(1))
-                .push(value);
+    /// Returns the values of the comments with the specified key, ignoring case, in order.
+    /// Returns `None` if there are no comments with the key.
+    pub fn get(&self, key: &str) -> Option<Vec<&str>> {
+        let values: Vec<&str> = self
+            .comments
+            .iter()
+            .filter(|(other, _)| other.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+            .collect();
+        if values.is_empty() {
+            None
+        } else {
+            Some(values)
         }
-
-        Ok(vorbis)
     }
 
-    /// Returns a vector representation of the vorbis comment suitable for writing to a file.
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+    /// Returns the values of the key and of every alias of the key, starting with the canonical
+    /// key. Returns `None` if none of the keys have values.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{KeyAliases, VorbisComment};
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("TRACKTOTAL", vec!["12"]);
+    ///
+    /// let aliases = KeyAliases::new();
+    /// assert_eq!(vorbis.get_aliased("TOTALTRACKS", &aliases).unwrap(), &["12"]);
+    ///
+    /// vorbis.normalize_keys(&aliases);
+    /// assert!(vorbis.get("TRACKTOTAL").is_none());
+    /// assert_eq!(vorbis.get("TOTALTRACKS").unwrap(), &["12"]);
+    /// ```
+    pub fn get_aliased(&self, key: &str, aliases: &KeyAliases) -> Option<Vec<&str>> {
+        let key = key.to_ascii_uppercase();
+        let keys = match aliases.group(&key) {
+            Some(group) => group.to_vec(),
+            None => vec![key],
+        };
 
-        let vendor_string = self.vendor_string.clone().into_bytes();
+        let values: Vec<&str> = keys
+            .iter()
+            .filter_map(|key| self.get(key))
+            .flatten()
+            .collect();
+        if values.is_empty() {
+            None
+        } else {
+            Some(values)
+        }
+    }
 
-        bytes.extend((vendor_string.len() as u32).to_le_bytes().iter());
-        bytes.extend(vendor_string.into_iter());
+    /// Rewrites every aliased key to its canonical form, appending the values of aliases to the
+    /// values of the canonical key.
+    pub fn normalize_keys(&mut self, aliases: &KeyAliases) {
+        for group in aliases.groups.iter() {
+            let (canonical, others) = match group.split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+
+            let mut values = self.values(canonical);
+            let mut changed = false;
+            for alias in others {
+                let alias_values = self.values(alias);
+                if !alias_values.is_empty() {
+                    values.extend(alias_values);
+                    self.remove(alias);
+                    changed = true;
+                }
+            }
+            if changed {
+                self.set(canonical.clone(), values);
+            }
+        }
+    }
 
-        bytes.extend(
-            (self
-                .comments
-                .values()
-                .fold(0u32, |acc, l| acc + l.len() as u32))
-            .to_le_bytes()
-            .iter(),
-        );
+    /// Sets the comments for the specified key. Any previous values under the key will be removed.
+    /// The new values take the positions of the previous values, and any additional values are
+    /// written after all other comments.
+    pub fn set<K: Into<String>, V: Into<String>>(&mut self, key: K, values: Vec<V>) {
+        let key = key.into();
+        let mut values = values.into_iter().map(|s| s.into());
+
+        let mut comments = Vec::with_capacity(self.comments.len());
+        for (other, value) in self.comments.drain(..) {
+            if !other.eq_ignore_ascii_case(&key) {
+                comments.push((other, value));
+            } else if let Some(value) = values.next() {
+                comments.push((other, value));
+            }
+        }
+        comments.extend(values.map(|value| (key.clone(), value)));
+        self.comments = comments;
+    }
 
-        for (key, list) in self.comments.iter() {
-            for value in list.iter() {
-                let comment_string = format!("{}={}", key, value);
-                debug!("Writing comment: {}", comment_string);
-                let comment = comment_string.into_bytes();
-                bytes.extend((comment.len() as u32).to_le_bytes().iter());
-                bytes.extend(comment.into_iter());
+    /// Returns the distinct keys of the comments in upper case, in the order in which they first
+    /// appear.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("title", vec!["title"]);
+    /// vorbis.set("ARTIST", vec!["a", "b"]);
+    /// assert_eq!(vorbis.keys(), &["TITLE", "ARTIST"]);
+    /// ```
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        for (key, _) in self.comments.iter() {
+            let key = key.to_ascii_uppercase();
+            if !keys.contains(&key) {
+                keys.push(key);
             }
         }
+        keys
+    }
 
-        bytes
+    /// Returns copies of the values of the comments with the specified key.
+    fn values(&self, key: &str) -> Vec<String> {
+        self.get(key)
+            .into_iter()
+            .flatten()
+            .map(String::from)
+            .collect()
     }
 
-    /// Returns a reference to the vector of comments for the specified key.
-    pub fn get(&self, key: &str) -> Option<&Vec<String>> {
-        self.comments.get(key)
+    /// Appends the comments of the other block after the comments of this block, in the order in
+    /// which they are written. The vendor string of this block is kept unless it is empty.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("ARTIST", vec!["a"]);
+    /// let mut other = VorbisComment::new();
+    /// other.set("TITLE", vec!["title"]);
+    /// other.set("ARTIST", vec!["b"]);
+    ///
+    /// vorbis.merge(&other);
+    /// assert_eq!(
+    ///     vorbis.entries(),
+    ///     &[("ARTIST", "a"), ("TITLE", "title"), ("ARTIST", "b")]
+    /// );
+    /// ```
+    pub fn merge(&mut self, other: &VorbisComment) {
+        if self.vendor_string.is_empty() {
+            self.vendor_string = other.vendor_string.clone();
+        }
+        let offset = self.comments.len();
+        self.unseparated.extend(
+            other
+                .unseparated
+                .iter()
+                .map(|(i, comment)| (offset + (*i).min(other.comments.len()), comment.clone())),
+        );
+        self.comments.extend(other.comments.iter().cloned());
     }
 
-    /// Sets the comments for the specified key. Any previous values under the key will be removed.
-    pub fn set<K: Into<String>, V: Into<String>>(&mut self, key: K, values: Vec<V>) {
-        let key_owned = key.into();
-        self.remove(&key_owned[..]);
-        self.comments
-            .insert(key_owned, values.into_iter().map(|s| s.into()).collect());
+    /// Sorts the comments by key, keeping the order of the values of each key. The keys are
+    /// converted to upper case, so comments with the same values are always written the same way
+    /// regardless of the order in which they were read or added.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("TITLE", vec!["title"]);
+    /// vorbis.set("ARTIST", vec!["b", "a"]);
+    ///
+    /// vorbis.sort_comments();
+    /// assert_eq!(
+    ///     vorbis.entries(),
+    ///     &[("ARTIST", "b"), ("ARTIST", "a"), ("TITLE", "title")]
+    /// );
+    /// ```
+    pub fn sort_comments(&mut self) {
+        for (key, _) in self.comments.iter_mut() {
+            key.make_ascii_uppercase();
+        }
+        self.comments.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+
+    /// Cleans up the comments, keeping the order of the remaining comments. NUL characters are
+    /// stripped from values, and comments without a separator, with an empty key, or with a value
+    /// which is empty or only whitespace are removed. If `dedupe` is set, repeated comments with
+    /// the same key, ignoring case, and the same value are removed. Returns whether any comments
+    /// changed.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("ARTIST", vec!["a\0", " ", "a", "b"]);
+    /// vorbis.set("", vec!["value"]);
+    /// vorbis.set("GENRE", Vec::<String>::new());
+    ///
+    /// assert!(vorbis.sanitize(true));
+    /// assert_eq!(vorbis.entries(), &[("ARTIST", "a"), ("ARTIST", "b")]);
+    /// assert!(vorbis.get("GENRE").is_none());
+    /// assert!(!vorbis.sanitize(true));
+    /// ```
+    pub fn sanitize(&mut self, dedupe: bool) -> bool {
+        let mut changed = !self.unseparated.is_empty();
+        self.unseparated.clear();
+        let mut seen = HashSet::new();
+        let mut kept = Vec::with_capacity(self.comments.len());
+        for (key, value) in self.comments.iter() {
+            let clean = value.replace('\0', "");
+            if key.trim().is_empty()
+                || clean.trim().is_empty()
+                || (dedupe && !seen.insert((key.to_ascii_uppercase(), clean.clone())))
+            {
+                changed = true;
+                continue;
+            }
+            changed |= clean.len() != value.len();
+            kept.push((key.clone(), clean));
+        }
+        if changed {
+            self.comments = kept;
+        }
+        changed
     }
 
     /// Removes the comments for the specified key.
     pub fn remove(&mut self, key: &str) {
-        self.comments.remove(key);
+        self.comments
+            .retain(|(other, _)| !other.eq_ignore_ascii_case(key));
     }
 
     /// Removes any matching key/value pairs.
     pub fn remove_pair(&mut self, key: &str, value: &str) {
-        if let Some(list) = self.comments.get_mut(key) {
-            list.retain(|s| &s[..] != value);
-        }
-
-        let mut num_values = 0;
-        if let Some(values) = self.get(key) {
-            num_values = values.len();
-        }
-        if num_values == 0 {
-            self.remove(key)
-        }
+        self.comments.retain(|(other, other_value)| {
+            !other.eq_ignore_ascii_case(key) || other_value != value
+        });
     }
 
     // Getters/Setters {{{
     /// Returns a reference to the vector of values with the ARTIST key.
-    pub fn artist(&self) -> Option<&Vec<String>> {
+    pub fn artist(&self) -> Option<Vec<&str>> {
         self.get("ARTIST")
     }
 
@@ -1033,7 +2595,7 @@ impl VorbisComment {
     }
 
     /// Returns a reference to the vector of values with the ALBUM key.
-    pub fn album(&self) -> Option<&Vec<String>> {
+    pub fn album(&self) -> Option<Vec<&str>> {
         self.get("ALBUM")
     }
 
@@ -1052,7 +2614,7 @@ impl VorbisComment {
     }
 
     /// Returns a reference to the vector of values with the GENRE key.
-    pub fn genre(&self) -> Option<&Vec<String>> {
+    pub fn genre(&self) -> Option<Vec<&str>> {
         self.get("GENRE")
     }
 
@@ -1067,7 +2629,7 @@ impl VorbisComment {
     }
 
     /// Returns reference to the vector of values with the TITLE key.
-    pub fn title(&self) -> Option<&Vec<String>> {
+    pub fn title(&self) -> Option<Vec<&str>> {
         self.get("TITLE")
     }
 
@@ -1085,11 +2647,22 @@ impl VorbisComment {
         self.remove("TITLE");
     }
 
-    /// Attempts to convert the first TRACKNUMBER comment to a `u32`.
+    /// Attempts to convert the first TRACKNUMBER comment to a `u32`. For values of the form
+    /// `track/total` the track is returned.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("TRACKNUMBER", vec!["3/12"]);
+    /// assert_eq!(vorbis.track(), Some(3));
+    /// assert_eq!(vorbis.total_tracks(), Some(12));
+    /// ```
     pub fn track(&self) -> Option<u32> {
         self.get("TRACKNUMBER").and_then(|s| {
             if !s.is_empty() {
-                s[0].parse::<u32>().ok()
+                s[0].split('/').next()?.trim().parse::<u32>().ok()
             } else {
                 None
             }
@@ -1106,15 +2679,18 @@ impl VorbisComment {
         self.remove("TRACKNUMBER");
     }
 
-    /// Attempts to convert the first TOTALTRACKS comment to a `u32`.
+    /// Attempts to convert the first TOTALTRACKS or TRACKTOTAL comment to a `u32`. If there are
+    /// no such comments, the total of a TRACKNUMBER comment of the form `track/total` is
+    /// returned.
     pub fn total_tracks(&self) -> Option<u32> {
-        self.get("TOTALTRACKS").and_then(|s| {
-            if !s.is_empty() {
-                s[0].parse::<u32>().ok()
-            } else {
-                None
-            }
-        })
+        self.get_aliased("TOTALTRACKS", &KeyAliases::new())
+            .and_then(|s| s[0].parse::<u32>().ok())
+            .or_else(|| {
+                self.get("TRACKNUMBER")
+                    .and_then(|s| s.first().copied())
+                    .and_then(|s| s.split('/').nth(1))
+                    .and_then(|total| total.trim().parse::<u32>().ok())
+            })
     }
 
     /// Sets the TOTALTRACKS comment.
@@ -1122,14 +2698,51 @@ impl VorbisComment {
         self.set("TOTALTRACKS", vec![format!("{}", total_tracks)]);
     }
 
-    /// Removes all values with the TOTALTRACKS key.
+    /// Removes all values with the TOTALTRACKS key or its TRACKTOTAL alias.
     pub fn remove_total_tracks(&mut self) {
-        self.remove("TOTALTRACKS");
+        if let Some(group) = KeyAliases::new().group("TOTALTRACKS") {
+            for key in group {
+                self.remove(key);
+            }
+        }
     }
 
-    /// Returns a reference to the vector of values with the ALBUMARTIST key.
-    pub fn album_artist(&self) -> Option<&Vec<String>> {
-        self.get("ALBUMARTIST")
+    /// Sets the track number and the total number of tracks using the specified style.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{TrackNumberStyle, VorbisComment};
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set_track_pair(3, 12, TrackNumberStyle::Combined);
+    /// assert_eq!(vorbis.get("TRACKNUMBER").unwrap(), &["3/12"]);
+    /// assert!(vorbis.get("TOTALTRACKS").is_none());
+    ///
+    /// vorbis.set_track_pair(3, 12, TrackNumberStyle::Separate);
+    /// assert_eq!(vorbis.get("TRACKNUMBER").unwrap(), &["3"]);
+    /// assert_eq!(vorbis.get("TOTALTRACKS").unwrap(), &["12"]);
+    /// ```
+    pub fn set_track_pair(&mut self, track: u32, total: u32, style: TrackNumberStyle) {
+        match style {
+            TrackNumberStyle::Separate => {
+                self.set_track(track);
+                self.set_total_tracks(total);
+            }
+            TrackNumberStyle::Combined => {
+                self.set("TRACKNUMBER", vec![format!("{}/{}", track, total)]);
+                self.remove_total_tracks();
+            }
+            TrackNumberStyle::Both => {
+                self.set("TRACKNUMBER", vec![format!("{}/{}", track, total)]);
+                self.set_total_tracks(total);
+            }
+        }
+    }
+
+    /// Returns a reference to the vector of values with the ALBUMARTIST key, or with the
+    /// ALBUM ARTIST key if there are no ALBUMARTIST comments.
+    pub fn album_artist(&self) -> Option<Vec<&str>> {
+        self.get("ALBUMARTIST").or_else(|| self.get("ALBUM ARTIST"))
     }
 
     /// Sets the values for the ALBUMARTIST key. This will result in any ALBUMARTISTSORT comments
@@ -1147,7 +2760,7 @@ impl VorbisComment {
     }
 
     /// Returns a reference to the vector of values with the LYRICS key.
-    pub fn lyrics(&self) -> Option<&Vec<String>> {
+    pub fn lyrics(&self) -> Option<Vec<&str>> {
         self.get("LYRICS")
     }
 
@@ -1160,27 +2773,612 @@ impl VorbisComment {
     pub fn remove_lyrics(&mut self) {
         self.remove("LYRICS");
     }
-    // }}}
-}
 
-impl Default for VorbisComment {
-    fn default() -> Self {
-        Self::new()
+    /// Attempts to parse the first DATE comment as a `Date`.
+    pub fn date(&self) -> Option<Date> {
+        self.get("DATE")
+            .and_then(|s| s.first().copied())
+            .and_then(|s| s.parse().ok())
     }
-}
-//}}}
 
-/// Iterator over FLAC stream's blocks
-pub struct Blocks<R> {
-    ident_read: bool,
-    finished: bool,
-    reader: R,
-}
+    /// Sets the DATE comment.
+    pub fn set_date(&mut self, date: Date) {
+        self.set("DATE", vec![date.to_string()]);
+    }
 
-impl<R> Blocks<R>
-where
-    R: Read,
-{
+    /// Removes all values with the DATE key.
+    pub fn remove_date(&mut self) {
+        self.remove("DATE");
+    }
+
+    /// Returns the year of the first DATE comment. Dates which do not match one of the supported
+    /// formats are accepted as long as they start with a four digit year.
+    pub fn year(&self) -> Option<u16> {
+        self.get("DATE")
+            .and_then(|s| s.first().copied())
+            .and_then(parse_year)
+    }
+
+    /// Attempts to parse the first ORIGINALDATE comment as a `Date`.
+    pub fn original_date(&self) -> Option<Date> {
+        self.get("ORIGINALDATE")
+            .and_then(|s| s.first().copied())
+            .and_then(|s| s.parse().ok())
+    }
+
+    /// Sets the ORIGINALDATE comment.
+    pub fn set_original_date(&mut self, date: Date) {
+        self.set("ORIGINALDATE", vec![date.to_string()]);
+    }
+
+    /// Removes all values with the ORIGINALDATE key.
+    pub fn remove_original_date(&mut self) {
+        self.remove("ORIGINALDATE");
+    }
+
+    /// Returns the year of the first ORIGINALDATE comment, accepting any value which starts with
+    /// a four digit year.
+    pub fn original_year(&self) -> Option<u16> {
+        self.get("ORIGINALDATE")
+            .and_then(|s| s.first().copied())
+            .and_then(parse_year)
+    }
+
+    /// Returns a reference to the vector of values with the COMPOSER key.
+    pub fn composer(&self) -> Option<Vec<&str>> {
+        self.get("COMPOSER")
+    }
+
+    /// Sets the values for the COMPOSER key.
+    pub fn set_composer<T: Into<String>>(&mut self, composers: Vec<T>) {
+        self.set("COMPOSER", composers);
+    }
+
+    /// Removes all values with the COMPOSER key.
+    pub fn remove_composer(&mut self) {
+        self.remove("COMPOSER");
+    }
+
+    /// Returns a reference to the vector of values with the CONDUCTOR key.
+    pub fn conductor(&self) -> Option<Vec<&str>> {
+        self.get("CONDUCTOR")
+    }
+
+    /// Sets the values for the CONDUCTOR key.
+    pub fn set_conductor<T: Into<String>>(&mut self, conductors: Vec<T>) {
+        self.set("CONDUCTOR", conductors);
+    }
+
+    /// Removes all values with the CONDUCTOR key.
+    pub fn remove_conductor(&mut self) {
+        self.remove("CONDUCTOR");
+    }
+
+    /// Returns a reference to the vector of values with the PERFORMER key.
+    pub fn performer(&self) -> Option<Vec<&str>> {
+        self.get("PERFORMER")
+    }
+
+    /// Sets the values for the PERFORMER key.
+    pub fn set_performer<T: Into<String>>(&mut self, performers: Vec<T>) {
+        self.set("PERFORMER", performers);
+    }
+
+    /// Removes all values with the PERFORMER key.
+    pub fn remove_performer(&mut self) {
+        self.remove("PERFORMER");
+    }
+
+    /// Returns a reference to the vector of values with the LYRICIST key.
+    pub fn lyricist(&self) -> Option<Vec<&str>> {
+        self.get("LYRICIST")
+    }
+
+    /// Sets the values for the LYRICIST key.
+    pub fn set_lyricist<T: Into<String>>(&mut self, lyricists: Vec<T>) {
+        self.set("LYRICIST", lyricists);
+    }
+
+    /// Removes all values with the LYRICIST key.
+    pub fn remove_lyricist(&mut self) {
+        self.remove("LYRICIST");
+    }
+
+    /// Returns a reference to the vector of values with the ARRANGER key.
+    pub fn arranger(&self) -> Option<Vec<&str>> {
+        self.get("ARRANGER")
+    }
+
+    /// Sets the values for the ARRANGER key.
+    pub fn set_arranger<T: Into<String>>(&mut self, arrangers: Vec<T>) {
+        self.set("ARRANGER", arrangers);
+    }
+
+    /// Removes all values with the ARRANGER key.
+    pub fn remove_arranger(&mut self) {
+        self.remove("ARRANGER");
+    }
+
+    /// Returns a reference to the vector of values with the REMIXER key.
+    pub fn remixer(&self) -> Option<Vec<&str>> {
+        self.get("REMIXER")
+    }
+
+    /// Sets the values for the REMIXER key.
+    pub fn set_remixer<T: Into<String>>(&mut self, remixers: Vec<T>) {
+        self.set("REMIXER", remixers);
+    }
+
+    /// Removes all values with the REMIXER key.
+    pub fn remove_remixer(&mut self) {
+        self.remove("REMIXER");
+    }
+
+    /// Attempts to parse the first REPLAYGAIN_TRACK_GAIN comment as a gain in dB.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("REPLAYGAIN_TRACK_GAIN", vec!["-7.5 dB"]);
+    /// assert_eq!(vorbis.replaygain_track_gain(), Some(-7.5));
+    ///
+    /// vorbis.set_replaygain_track_gain(3.0);
+    /// assert_eq!(vorbis.get("REPLAYGAIN_TRACK_GAIN").unwrap(), &["+3.00 dB"]);
+    /// ```
+    pub fn replaygain_track_gain(&self) -> Option<f64> {
+        self.get("REPLAYGAIN_TRACK_GAIN")
+            .and_then(|s| s.first().copied())
+            .and_then(replaygain::parse_value)
+    }
+
+    /// Sets the REPLAYGAIN_TRACK_GAIN comment.
+    pub fn set_replaygain_track_gain(&mut self, gain: f64) {
+        self.set("REPLAYGAIN_TRACK_GAIN", vec![replaygain::format_gain(gain)]);
+    }
+
+    /// Removes all values with the REPLAYGAIN_TRACK_GAIN key.
+    pub fn remove_replaygain_track_gain(&mut self) {
+        self.remove("REPLAYGAIN_TRACK_GAIN");
+    }
+
+    /// Attempts to parse the first REPLAYGAIN_TRACK_PEAK comment as a peak, where 1.0 is full
+    /// scale.
+    pub fn replaygain_track_peak(&self) -> Option<f64> {
+        self.get("REPLAYGAIN_TRACK_PEAK")
+            .and_then(|s| s.first().copied())
+            .and_then(replaygain::parse_value)
+    }
+
+    /// Sets the REPLAYGAIN_TRACK_PEAK comment.
+    pub fn set_replaygain_track_peak(&mut self, peak: f64) {
+        self.set("REPLAYGAIN_TRACK_PEAK", vec![replaygain::format_peak(peak)]);
+    }
+
+    /// Removes all values with the REPLAYGAIN_TRACK_PEAK key.
+    pub fn remove_replaygain_track_peak(&mut self) {
+        self.remove("REPLAYGAIN_TRACK_PEAK");
+    }
+
+    /// Attempts to parse the first REPLAYGAIN_ALBUM_GAIN comment as a gain in dB.
+    pub fn replaygain_album_gain(&self) -> Option<f64> {
+        self.get("REPLAYGAIN_ALBUM_GAIN")
+            .and_then(|s| s.first().copied())
+            .and_then(replaygain::parse_value)
+    }
+
+    /// Sets the REPLAYGAIN_ALBUM_GAIN comment.
+    pub fn set_replaygain_album_gain(&mut self, gain: f64) {
+        self.set("REPLAYGAIN_ALBUM_GAIN", vec![replaygain::format_gain(gain)]);
+    }
+
+    /// Removes all values with the REPLAYGAIN_ALBUM_GAIN key.
+    pub fn remove_replaygain_album_gain(&mut self) {
+        self.remove("REPLAYGAIN_ALBUM_GAIN");
+    }
+
+    /// Attempts to parse the first REPLAYGAIN_ALBUM_PEAK comment as a peak, where 1.0 is full
+    /// scale.
+    pub fn replaygain_album_peak(&self) -> Option<f64> {
+        self.get("REPLAYGAIN_ALBUM_PEAK")
+            .and_then(|s| s.first().copied())
+            .and_then(replaygain::parse_value)
+    }
+
+    /// Sets the REPLAYGAIN_ALBUM_PEAK comment.
+    pub fn set_replaygain_album_peak(&mut self, peak: f64) {
+        self.set("REPLAYGAIN_ALBUM_PEAK", vec![replaygain::format_peak(peak)]);
+    }
+
+    /// Removes all values with the REPLAYGAIN_ALBUM_PEAK key.
+    pub fn remove_replaygain_album_peak(&mut self) {
+        self.remove("REPLAYGAIN_ALBUM_PEAK");
+    }
+
+    /// Returns a reference to the vector of values with the ISRC key.
+    pub fn isrc(&self) -> Option<Vec<&str>> {
+        self.get("ISRC")
+    }
+
+    /// Sets the values for the ISRC key. Returns an error without changing the comments if any
+    /// value is not a valid ISRC.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// assert!(vorbis.set_isrc(vec!["USRC17607839"]).is_ok());
+    /// assert!(vorbis.set_isrc(vec!["not an isrc"]).is_err());
+    /// assert_eq!(vorbis.isrc().unwrap(), &["USRC17607839"]);
+    /// ```
+    pub fn set_isrc<T: Into<String>>(&mut self, isrcs: Vec<T>) -> Result<()> {
+        let isrcs: Vec<String> = isrcs.into_iter().map(|s| s.into()).collect();
+        if !isrcs.iter().all(|isrc| VorbisComment::is_valid_isrc(isrc)) {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid ISRC"));
+        }
+        self.set("ISRC", isrcs);
+        Ok(())
+    }
+
+    /// Removes all values with the ISRC key.
+    pub fn remove_isrc(&mut self) {
+        self.remove("ISRC");
+    }
+
+    /// Returns true if the value is a valid International Standard Recording Code: a two letter
+    /// country code, a three character alphanumeric registrant code, a two digit year and a five
+    /// digit designation code, optionally separated by hyphens.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// assert!(VorbisComment::is_valid_isrc("USRC17607839"));
+    /// assert!(VorbisComment::is_valid_isrc("US-RC1-76-07839"));
+    /// assert!(!VorbisComment::is_valid_isrc("US-RC17607839"));
+    /// assert!(!VorbisComment::is_valid_isrc("usrc17607839"));
+    /// ```
+    pub fn is_valid_isrc(isrc: &str) -> bool {
+        let code: Vec<u8> = isrc.bytes().filter(|b| *b != b'-').collect();
+        let hyphens = isrc.len() - code.len();
+        if code.len() != 12 || (hyphens != 0 && isrc.split('-').map(str::len).ne([2, 3, 2, 5])) {
+            return false;
+        }
+
+        code[..2].iter().all(u8::is_ascii_uppercase)
+            && code[2..5]
+                .iter()
+                .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+            && code[5..].iter().all(u8::is_ascii_digit)
+    }
+
+    /// Returns a reference to the vector of values with the CATALOGNUMBER key.
+    pub fn catalog_number(&self) -> Option<Vec<&str>> {
+        self.get("CATALOGNUMBER")
+    }
+
+    /// Sets the values for the CATALOGNUMBER key.
+    pub fn set_catalog_number<T: Into<String>>(&mut self, catalog_numbers: Vec<T>) {
+        self.set("CATALOGNUMBER", catalog_numbers);
+    }
+
+    /// Removes all values with the CATALOGNUMBER key.
+    pub fn remove_catalog_number(&mut self) {
+        self.remove("CATALOGNUMBER");
+    }
+
+    /// Returns a reference to the vector of values with the BARCODE key.
+    pub fn barcode(&self) -> Option<Vec<&str>> {
+        self.get("BARCODE")
+    }
+
+    /// Sets the values for the BARCODE key.
+    pub fn set_barcode<T: Into<String>>(&mut self, barcodes: Vec<T>) {
+        self.set("BARCODE", barcodes);
+    }
+
+    /// Removes all values with the BARCODE key.
+    pub fn remove_barcode(&mut self) {
+        self.remove("BARCODE");
+    }
+
+    /// Returns a reference to the vector of values with the LABEL key.
+    pub fn label(&self) -> Option<Vec<&str>> {
+        self.get("LABEL")
+    }
+
+    /// Sets the values for the LABEL key.
+    pub fn set_label<T: Into<String>>(&mut self, labels: Vec<T>) {
+        self.set("LABEL", labels);
+    }
+
+    /// Removes all values with the LABEL key.
+    pub fn remove_label(&mut self) {
+        self.remove("LABEL");
+    }
+
+    /// Attempts to convert the first BPM comment to a number of beats per minute, which may be
+    /// fractional.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set_bpm(127.5);
+    /// assert_eq!(vorbis.bpm(), Some(127.5));
+    ///
+    /// vorbis.set_bpm(128.0);
+    /// assert_eq!(vorbis.get("BPM").unwrap(), &["128"]);
+    /// ```
+    pub fn bpm(&self) -> Option<f64> {
+        self.get("BPM")
+            .and_then(|s| s.first().copied())
+            .and_then(|s| s.trim().parse().ok())
+    }
+
+    /// Sets the BPM comment. Whole numbers are written without a fractional part.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.set("BPM", vec![format!("{}", bpm)]);
+    }
+
+    /// Removes all values with the BPM key.
+    pub fn remove_bpm(&mut self) {
+        self.remove("BPM");
+    }
+
+    /// Returns a reference to the vector of values with the INITIALKEY key.
+    pub fn initial_key(&self) -> Option<Vec<&str>> {
+        self.get("INITIALKEY")
+    }
+
+    /// Sets the values for the INITIALKEY key.
+    pub fn set_initial_key<T: Into<String>>(&mut self, keys: Vec<T>) {
+        self.set("INITIALKEY", keys);
+    }
+
+    /// Removes all values with the INITIALKEY key.
+    pub fn remove_initial_key(&mut self) {
+        self.remove("INITIALKEY");
+    }
+
+    /// Returns the rating from 0.0 to 1.0, read from the FMPS_RATING comment, or from a RATING
+    /// percentage if there is no FMPS_RATING comment.
+    pub fn rating(&self) -> Option<f64> {
+        self.rating_with(&RatingPolicy::new())
+    }
+
+    /// Returns the rating from 0.0 to 1.0, read from the FMPS_RATING comment, or from the RATING
+    /// comment using the scale of the policy if there is no FMPS_RATING comment.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{RatingPolicy, VorbisComment};
+    ///
+    /// let mut policy = RatingPolicy::new();
+    /// policy.scale = 5;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("RATING", vec!["4"]);
+    /// assert_eq!(vorbis.rating_with(&policy), Some(0.8));
+    ///
+    /// vorbis.set_rating_with(0.6, &policy);
+    /// assert_eq!(vorbis.get("FMPS_RATING").unwrap(), &["0.6"]);
+    /// assert_eq!(vorbis.get("RATING").unwrap(), &["3"]);
+    /// ```
+    pub fn rating_with(&self, policy: &RatingPolicy) -> Option<f64> {
+        let fmps = self
+            .get("FMPS_RATING")
+            .and_then(|s| s.first().copied())
+            .and_then(|s| s.trim().parse::<f64>().ok())
+            .filter(|rating| (0.0..=1.0).contains(rating));
+        if fmps.is_some() || policy.scale == 0 {
+            return fmps;
+        }
+
+        self.get("RATING")
+            .and_then(|s| s.first().copied())
+            .and_then(|s| s.trim().parse::<f64>().ok())
+            .map(|rating| rating / policy.scale as f64)
+            .filter(|rating| (0.0..=1.0).contains(rating))
+    }
+
+    /// Sets the FMPS_RATING comment to the rating, clamped to 0.0 to 1.0, and the RATING comment
+    /// to the rating as a percentage.
+    pub fn set_rating(&mut self, rating: f64) {
+        self.set_rating_with(rating, &RatingPolicy::new());
+    }
+
+    /// Sets the FMPS_RATING comment to the rating, clamped to 0.0 to 1.0, and the RATING comment
+    /// according to the policy.
+    pub fn set_rating_with(&mut self, rating: f64, policy: &RatingPolicy) {
+        let rating = rating.clamp(0.0, 1.0);
+        self.set("FMPS_RATING", vec![format!("{}", rating)]);
+        if policy.mirror {
+            let scaled = (rating * policy.scale as f64).round() as u32;
+            self.set("RATING", vec![format!("{}", scaled)]);
+        }
+    }
+
+    /// Removes all values with the FMPS_RATING and RATING keys.
+    pub fn remove_rating(&mut self) {
+        self.remove("FMPS_RATING");
+        self.remove("RATING");
+    }
+
+    /// Attempts to parse timed lyrics in the LRC format from the first SYNCEDLYRICS comment, or
+    /// from the first LYRICS comment if it contains timed lyrics. Returns the lines sorted by
+    /// time. Lines with several timestamps are returned once for each timestamp, and LRC
+    /// metadata such as `[ar:Artist]` is ignored.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    /// use std::time::Duration;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("LYRICS", vec!["[ar:Artist]\n[00:12.50]first\n[01:02.00]second"]);
+    ///
+    /// let lyrics = vorbis.synced_lyrics().unwrap();
+    /// assert_eq!(lyrics[0], (Duration::from_millis(12_500), "first".to_owned()));
+    /// assert_eq!(lyrics[1], (Duration::from_secs(62), "second".to_owned()));
+    ///
+    /// vorbis.set_synced_lyrics(&lyrics);
+    /// assert_eq!(
+    ///     vorbis.get("SYNCEDLYRICS").unwrap(),
+    ///     &["[00:12.50]first\n[01:02.00]second"]
+    /// );
+    /// ```
+    pub fn synced_lyrics(&self) -> Option<Vec<(Duration, String)>> {
+        self.get("SYNCEDLYRICS")
+            .into_iter()
+            .chain(self.get("LYRICS"))
+            .filter_map(|values| values.first().copied())
+            .map(parse_lrc)
+            .find(|lyrics| !lyrics.is_empty())
+    }
+
+    /// Sets the SYNCEDLYRICS comment to the timed lyrics in the LRC format.
+    pub fn set_synced_lyrics(&mut self, lyrics: &[(Duration, String)]) {
+        let lines: Vec<String> = lyrics
+            .iter()
+            .map(|(time, text)| {
+                let centis = time.as_millis() / 10;
+                format!(
+                    "[{:02}:{:02}.{:02}]{}",
+                    centis / 6000,
+                    centis / 100 % 60,
+                    centis % 100,
+                    text
+                )
+            })
+            .collect();
+        self.set("SYNCEDLYRICS", vec![lines.join("\n")]);
+    }
+
+    /// Removes all values with the SYNCEDLYRICS key.
+    pub fn remove_synced_lyrics(&mut self) {
+        self.remove("SYNCEDLYRICS");
+    }
+
+    /// Returns the chapters stored in `CHAPTERxxx` comments, which hold the start time in the
+    /// `HH:MM:SS.fff` format, together with the `CHAPTERxxxNAME` title and `CHAPTERxxxURL` link
+    /// of each chapter. The chapters are sorted by start time. Chapters with an invalid start
+    /// time are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{Chapter, VorbisComment};
+    /// use std::time::Duration;
+    ///
+    /// let mut vorbis = VorbisComment::new();
+    /// vorbis.set("CHAPTER001", vec!["00:01:30.500"]);
+    /// vorbis.set("CHAPTER001NAME", vec!["Second"]);
+    /// vorbis.set("CHAPTER000", vec!["00:00:00.000"]);
+    /// vorbis.set("CHAPTER000NAME", vec!["First"]);
+    ///
+    /// let chapters = vorbis.chapters();
+    /// assert_eq!(chapters[0].title, "First");
+    /// assert_eq!(chapters[1].start, Duration::from_millis(90_500));
+    ///
+    /// vorbis.set_chapters(&[Chapter {
+    ///     start: Duration::from_secs(3600),
+    ///     title: "Epilogue".to_owned(),
+    ///     url: Some("https://example.com".to_owned()),
+    /// }]);
+    /// assert_eq!(vorbis.get("CHAPTER000").unwrap(), &["01:00:00.000"]);
+    /// assert_eq!(vorbis.get("CHAPTER000URL").unwrap(), &["https://example.com"]);
+    /// assert!(vorbis.get("CHAPTER001").is_none());
+    /// ```
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let value = |key: &str| self.get(key).and_then(|values| values.first().copied());
+
+        let mut chapters = Vec::new();
+        for key in self.keys().iter() {
+            match chapter_key(key) {
+                Some((_, "")) => {}
+                _ => continue,
+            }
+            let start = match value(key).and_then(parse_chapter_time) {
+                Some(start) => start,
+                None => continue,
+            };
+            chapters.push(Chapter {
+                start,
+                title: value(&format!("{}NAME", key))
+                    .map(String::from)
+                    .unwrap_or_default(),
+                url: value(&format!("{}URL", key)).map(String::from),
+            });
+        }
+        chapters.sort_by_key(|chapter| chapter.start);
+        chapters
+    }
+
+    /// Replaces the chapter comments with comments for the chapters, numbered from `CHAPTER000`
+    /// in order of their start time.
+    pub fn set_chapters(&mut self, chapters: &[Chapter]) {
+        self.remove_chapters();
+        for (key, value) in chapter_comments(chapters) {
+            self.set(key, vec![value]);
+        }
+    }
+
+    /// Removes all chapter comments.
+    pub fn remove_chapters(&mut self) {
+        for key in self.chapter_keys() {
+            self.remove(&key);
+        }
+    }
+
+    /// Returns the keys of the chapter comments.
+    pub(crate) fn chapter_keys(&self) -> Vec<String> {
+        self.keys()
+            .into_iter()
+            .filter(|key| chapter_key(key).is_some())
+            .collect()
+    }
+    // }}}
+}
+
+impl Default for VorbisComment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> From<raw::VorbisComment<'a>> for VorbisComment {
+    fn from(vorbis: raw::VorbisComment<'a>) -> VorbisComment {
+        VorbisComment {
+            vendor_string: vorbis.vendor_string.to_owned(),
+            comments: vorbis
+                .comments
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+            unseparated: vorbis
+                .unseparated
+                .into_iter()
+                .map(|(i, comment)| (i, comment.to_owned()))
+                .collect(),
+        }
+    }
+}
+//}}}
+
+/// Iterator over FLAC stream's blocks
+pub struct Blocks<R> {
+    ident_read: bool,
+    finished: bool,
+    reader: R,
+}
+
+impl<R> Blocks<R>
+where
+    R: Read,
+{
     /// Create new iterator over FLAC stream's blocks
     pub fn new(reader: R) -> Self {
         Blocks {
@@ -1224,45 +3422,922 @@ where
     }
 }
 
+/// Iterator over the blocks of a FLAC stream, yielding whether each block is the last metadata
+/// block along with the block. Blocks are read one at a time as the iterator advances, so
+/// processing can stop early without reading the rest of the metadata. The iterator ends after
+/// the last block or the first error.
+///
+/// Blocks are checked against the specification according to the `ReadOptions`, except that
+/// `ParseMode::Recover` does not search for block headers and behaves like `ParseMode::Lenient`.
+///
+/// # Example
+/// ```
+/// use metaflac::block::{BlockReader, PictureType};
+/// use metaflac::{Block, Tag};
+///
+/// let mut tag = Tag::new();
+/// tag.set_vorbis("TITLE", vec!["title"]);
+/// tag.add_picture("image/png", PictureType::CoverFront, vec![0; 1024]);
+/// let bytes = tag.write_to_vec().unwrap();
+///
+/// // stops before the picture is read
+/// let comments = BlockReader::new(&bytes[..])
+///     .find_map(|block| match block {
+///         Ok((_, Block::VorbisComment(comments))) => Some(comments),
+///         _ => None,
+///     })
+///     .unwrap();
+/// assert_eq!(comments.title().unwrap(), &["title"]);
+///
+/// let pictures = BlockReader::new(&bytes[..])
+///     .filter(|block| matches!(block, Ok((_, Block::Picture(_)))))
+///     .count();
+/// assert_eq!(pictures, 1);
+/// ```
+pub struct BlockReader<R> {
+    reader: R,
+    options: ReadOptions,
+    offset: Option<u64>,
+    finished: bool,
+    warnings: Vec<ParseWarning>,
+}
+
+impl<R> BlockReader<R>
+where
+    R: Read,
+{
+    /// Returns a new `BlockReader` which reads the stream with the default options.
+    pub fn new(reader: R) -> Self {
+        BlockReader::with_options(reader, &ReadOptions::new())
+    }
+
+    /// Returns a new `BlockReader` which reads the stream using the specified options. Blocks of
+    /// the types which `ReadOptions::block_types` leaves out are skipped.
+    pub fn with_options(reader: R, options: &ReadOptions) -> Self {
+        BlockReader {
+            reader,
+            options: options.clone(),
+            offset: None,
+            finished: false,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Returns the violations of the specification tolerated in the blocks read so far.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    /// Returns the reader, positioned after the last block which was read. Once the last block
+    /// has been read this is the start of the audio.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> Iterator for BlockReader<R>
+where
+    R: Read,
+{
+    /// Whether the block is the last metadata block, and the block.
+    type Item = Result<(bool, Block)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let mut offset = match self.offset {
+            Some(offset) => offset,
+            None => match read_ident_with(&mut self.reader, self.options.max_junk_len) {
+                Ok((id3_len, junk_len)) => id3_len + junk_len + 4,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            },
+        };
+
+        // blocks of the types which the options leave out are skipped
+        let header = loop {
+            let header = match Block::read_header(&mut self.reader, offset) {
+                Ok(header) => header,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            };
+            let (is_last, blocktype_byte, length) = header;
+            if self.options.reads(BlockType::from_u8(blocktype_byte)) {
+                break header;
+            }
+            let skipped =
+                match io::copy(&mut (&mut self.reader).take(length as u64), &mut io::sink()) {
+                    Ok(skipped) => skipped,
+                    Err(err) => {
+                        self.finished = true;
+                        return Some(Err(err.into()));
+                    }
+                };
+            offset += skipped + 4;
+            self.offset = Some(offset);
+            // a truncated block ends the metadata, as when it is read
+            if is_last || skipped < length as u64 {
+                self.finished = true;
+                return None;
+            }
+        };
+
+        match Block::read_contents(
+            &mut self.reader,
+            offset,
+            &self.options,
+            &mut self.warnings,
+            header,
+        ) {
+            Ok((is_last, length, block)) => {
+                self.offset = Some(offset + length as u64);
+                self.finished = is_last;
+                Some(Ok((is_last, block)))
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// What `parse_with` does with a block once its header has been read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VisitAction {
+    /// Reads and parses the contents of the block, passing them to the visitor.
+    Read,
+    /// Skips the contents of the block without parsing them.
+    Skip,
+    /// Stops parsing, leaving the reader positioned before the contents of the block.
+    Stop,
+}
+
+/// Callbacks for the blocks of a FLAC stream parsed by `parse_with`. Every method has a default
+/// implementation, so a visitor only implements those for the blocks it needs.
+pub trait BlockVisitor {
+    /// Called with the header of each block before its contents are read, returning what to do
+    /// with the contents. Reads every block by default.
+    fn visit_header(&mut self, block_type: BlockType, length: u32, is_last: bool) -> VisitAction {
+        let _ = (block_type, length, is_last);
+        VisitAction::Read
+    }
+
+    /// Called with a parsed streaminfo block.
+    fn visit_streaminfo(&mut self, streaminfo: StreamInfo) {
+        let _ = streaminfo;
+    }
+
+    /// Called with a parsed application block.
+    fn visit_application(&mut self, application: Application) {
+        let _ = application;
+    }
+
+    /// Called with a parsed cuesheet block.
+    fn visit_cuesheet(&mut self, cuesheet: CueSheet) {
+        let _ = cuesheet;
+    }
+
+    /// Called with the number of bytes of a padding block.
+    fn visit_padding(&mut self, length: u32) {
+        let _ = length;
+    }
+
+    /// Called with a parsed picture block.
+    fn visit_picture(&mut self, picture: Picture) {
+        let _ = picture;
+    }
+
+    /// Called with a parsed seektable block.
+    fn visit_seektable(&mut self, seektable: SeekTable) {
+        let _ = seektable;
+    }
+
+    /// Called with a parsed vorbis comment block.
+    fn visit_vorbis_comment(&mut self, vorbis_comment: VorbisComment) {
+        let _ = vorbis_comment;
+    }
+
+    /// Called with the type and the bytes of a block which could not be parsed, or whose type is
+    /// unknown.
+    fn visit_unknown(&mut self, block_type: u8, data: Vec<u8>) {
+        let _ = (block_type, data);
+    }
+}
+
+/// Parses the blocks of the FLAC stream in the reader, passing each one to the visitor. The
+/// visitor decides from the header of each block whether its contents are parsed, skipped or
+/// parsing stops, so only the blocks which are needed are parsed.
+///
+/// # Example
+/// ```
+/// use metaflac::block::{self, BlockType, BlockVisitor, PictureType, VisitAction, VorbisComment};
+/// use metaflac::Tag;
+///
+/// #[derive(Default)]
+/// struct Summary {
+///     title: Option<String>,
+///     picture_bytes: u32,
+/// }
+///
+/// impl BlockVisitor for Summary {
+///     fn visit_header(&mut self, block_type: BlockType, length: u32, _: bool) -> VisitAction {
+///         match block_type {
+///             BlockType::VorbisComment => VisitAction::Read,
+///             BlockType::Picture => {
+///                 self.picture_bytes += length;
+///                 VisitAction::Skip
+///             }
+///             _ => VisitAction::Skip,
+///         }
+///     }
+///
+///     fn visit_vorbis_comment(&mut self, vorbis_comment: VorbisComment) {
+///         self.title = vorbis_comment.title().map(|title| title[0].to_owned());
+///     }
+/// }
+///
+/// let mut tag = Tag::new();
+/// tag.set_vorbis("TITLE", vec!["title"]);
+/// tag.add_picture("image/png", PictureType::CoverFront, vec![0; 1024]);
+/// let bytes = tag.write_to_vec().unwrap();
+///
+/// let mut summary = Summary::default();
+/// block::parse_with(&mut &bytes[..], &mut summary).unwrap();
+/// assert_eq!(summary.title.as_deref(), Some("title"));
+/// assert!(summary.picture_bytes > 1024);
+/// ```
+pub fn parse_with<R, V>(reader: &mut R, visitor: &mut V) -> Result<()>
+where
+    R: Read + ?Sized,
+    V: BlockVisitor + ?Sized,
+{
+    let options = ReadOptions::new();
+    let mut warnings = Vec::new();
+
+    let (id3_len, junk_len) = read_ident_with(&mut *reader, options.max_junk_len)?;
+    let mut offset = id3_len + junk_len + 4;
+    loop {
+        let header = reader
+            .read_u32::<BE>()
+            .map_err(|err| Error::from(err).in_block(None, offset))?;
+        let is_last = (header >> 24) & 0x80 != 0;
+        let blocktype_byte = (header >> 24) as u8 & 0x7F;
+        let blocktype = BlockType::from_u8(blocktype_byte);
+        let length = header & 0xFF_FF_FF;
+
+        let is_last = match visitor.visit_header(blocktype, length, is_last) {
+            VisitAction::Stop => return Ok(()),
+            VisitAction::Skip => {
+                let skipped = io::copy(&mut reader.take(length as u64), &mut io::sink())?;
+                // a truncated block ends the metadata, as when it is read
+                is_last || skipped < length as u64
+            }
+            VisitAction::Read => {
+                let (is_last, _, block) = Block::read_contents_with(
+                    reader,
+                    offset,
+                    &options,
+                    &mut warnings,
+                    is_last,
+                    blocktype_byte,
+                    length,
+                )
+                .map_err(|err| err.in_block(Some(blocktype), offset))?;
+                match block {
+                    Block::StreamInfo(streaminfo) => visitor.visit_streaminfo(streaminfo),
+                    Block::Application(application) => visitor.visit_application(application),
+                    Block::CueSheet(cuesheet) => visitor.visit_cuesheet(cuesheet),
+                    Block::Padding(length) => visitor.visit_padding(length),
+                    Block::Picture(picture) => visitor.visit_picture(picture),
+                    Block::SeekTable(seektable) => visitor.visit_seektable(seektable),
+                    Block::VorbisComment(vorbis_comment) => {
+                        visitor.visit_vorbis_comment(vorbis_comment)
+                    }
+                    Block::Unknown((blocktype_byte, data)) => {
+                        visitor.visit_unknown(blocktype_byte, data)
+                    }
+                }
+                is_last
+            }
+        };
+
+        offset += length as u64 + 4;
+        if is_last {
+            return Ok(());
+        }
+    }
+}
+
 /// Read from a reader until a flac file identifier is found. Returns an error if no flac identifier
 /// could be found.
-pub(crate) fn read_ident<R: Read>(mut reader: R) -> Result<()> {
+pub(crate) fn read_ident<R: Read>(reader: R) -> Result<()> {
+    read_ident_with(reader, 0).map(|_| ())
+}
+
+/// Read from a reader until a flac file identifier is found, scanning past at most `max_junk_len`
+/// bytes of junk after any id3v2 tag. Returns the number of id3v2 bytes and junk bytes which were
+/// skipped. Returns an error if no flac identifier could be found.
+pub(crate) fn read_ident_with<R: Read>(mut reader: R, max_junk_len: u64) -> Result<(u64, u64)> {
     use std::io;
 
     let mut ident = [0; 4];
-    reader.read_exact(&mut ident)?;
+    reader.read_exact(&mut ident).map_err(ident_error)?;
+
+    let mut id3_len = 0;
 
     // skip id3 v2.2, v2.3 and v2.4
-    if &ident[0..3] == b"ID3" && vec![0x02, 0x03, 0x04].contains(&ident[3]) {
-        let mut header_tail = [0; 6];
-        reader.read_exact(&mut header_tail)?;
-        // Header layout from the id3v2 tag spec:
-        // 3 Bytes: "ID3"
-        // 2 Bytes: Maj/Min version
-        // 1 Byte: Flags, bit 0x10 indicates a 10-Byte footer
-        // 4 Bytes: size of the Tag, excluding header and footer, taking 7 bits per byte.
-        let has_footer = header_tail[1] & 0x10 > 0;
-        let size = (header_tail[2] as u32 & 0b_0111_1111) << 21
-            | (header_tail[3] as u32 & 0b_0111_1111) << 14
-            | (header_tail[4] as u32 & 0b_0111_1111) << 7
-            | (header_tail[5] as u32 & 0b_0111_1111);
-        // Discard `size` bytes without allocating. See https://stackoverflow.com/questions/42243355/how-to-advance-through-data-from-the-stdioread-trait-when-seek-isnt-impleme
-        if has_footer {
-            io::copy(&mut (&mut reader).take(size as u64 + 10), &mut io::sink())?;
-        } else {
-            io::copy(&mut (&mut reader).take(size as u64), &mut io::sink())?;
-        }
+    if &ident[0..3] == b"ID3" && [0x02, 0x03, 0x04].contains(&ident[3]) {
+        let mut header = [0; 10];
+        header[..4].copy_from_slice(&ident);
+        reader.read_exact(&mut header[4..]).map_err(ident_error)?;
+        id3_len = id3v2_len(&header).unwrap_or(10);
+        // Discard the tag without allocating. See https://stackoverflow.com/questions/42243355/how-to-advance-through-data-from-the-stdioread-trait-when-seek-isnt-impleme
+        io::copy(&mut (&mut reader).take(id3_len - 10), &mut io::sink())?;
 
         //try to read fLaC again.
-        reader.read_exact(&mut ident)?;
+        reader.read_exact(&mut ident).map_err(ident_error)?;
+    }
+
+    // slide over junk one byte at a time until the identifier lines up
+    let mut junk_len = 0;
+    while &ident[..] != b"fLaC" && junk_len < max_junk_len {
+        ident.rotate_left(1);
+        match reader.read_exact(&mut ident[3..]) {
+            Ok(()) => junk_len += 1,
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
     }
 
     if &ident[..] == b"fLaC" {
-        Ok(())
+        if junk_len > 0 {
+            debug!(
+                "Skipped {} bytes of junk before the stream marker",
+                junk_len
+            );
+        }
+        Ok((id3_len, junk_len))
     } else {
-        Err(Error::new(
-            ErrorKind::InvalidInput,
-            "reader does not contain flac metadata",
-        ))
+        Err(not_flac_error())
+    }
+}
+
+/// Converts an error reading the bytes before the `fLaC` marker. A stream which ends before the
+/// marker is not a FLAC stream.
+pub(crate) fn ident_error(err: io::Error) -> Error {
+    if err.kind() == io::ErrorKind::UnexpectedEof {
+        not_flac_error()
+    } else {
+        err.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the bytes of a vorbis comment block with the comments.
+    fn comment_bytes(comments: &[&str]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0, 0];
+        bytes.extend(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            bytes.extend(&(comment.len() as u32).to_le_bytes());
+            bytes.extend(comment.as_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn streaminfo_round_trip() {
+        let mut streaminfo = StreamInfo::new();
+        streaminfo.min_block_size = 4096;
+        streaminfo.max_block_size = 4096;
+        streaminfo.min_frame_size = 14;
+        streaminfo.max_frame_size = (1 << 24) - 1;
+        streaminfo.sample_rate = 96000;
+        streaminfo.num_channels = 8;
+        streaminfo.bits_per_sample = 32;
+        streaminfo.total_samples = (1 << 36) - 1;
+        streaminfo.md5 = (0..16).collect();
+        let bytes = streaminfo.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 34);
+        assert_eq!(StreamInfo::from_bytes(&bytes).unwrap(), streaminfo);
+
+        let err = StreamInfo::from_bytes(&bytes[..33]).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidLength));
+    }
+
+    #[test]
+    fn invalid_streaminfo() {
+        let mut valid = StreamInfo::new();
+        valid.num_channels = 1;
+        valid.bits_per_sample = 4;
+        assert!(valid.to_bytes().is_ok());
+
+        let invalid = [
+            StreamInfo::new(),
+            StreamInfo {
+                num_channels: 9,
+                ..valid.clone()
+            },
+            StreamInfo {
+                bits_per_sample: 0,
+                ..valid.clone()
+            },
+            StreamInfo {
+                bits_per_sample: 33,
+                ..valid.clone()
+            },
+            StreamInfo {
+                sample_rate: 1 << 20,
+                ..valid.clone()
+            },
+            StreamInfo {
+                total_samples: 1 << 36,
+                ..valid.clone()
+            },
+            StreamInfo {
+                md5: vec![0; 15],
+                ..valid.clone()
+            },
+        ];
+        for streaminfo in invalid.iter() {
+            let err = streaminfo.to_bytes().unwrap_err();
+            assert!(matches!(err.kind, ErrorKind::InvalidInput));
+            assert_eq!(err.block_type, Some(BlockType::StreamInfo));
+        }
+
+        // an invalid streaminfo block is an error when the block is written
+        let block = Block::StreamInfo(StreamInfo::new());
+        assert_eq!(block.content_len(), 34);
+        assert!(block.to_bytes(true).is_err());
+    }
+
+    #[test]
+    fn invalid_cuesheet_fields() {
+        let mut valid = CueSheet::new();
+        valid.catalog_num = "1".repeat(128);
+        let mut track = CueSheetTrack::new();
+        track.isrc = "ABCDE1234567".to_owned();
+        track.indices = vec![CueSheetTrackIndex::new(); 255];
+        valid.tracks = vec![track; 255];
+        assert!(Block::CueSheet(valid.clone()).to_bytes(true).is_ok());
+
+        let mut catalog_num = valid.clone();
+        catalog_num.catalog_num.push('1');
+        let mut tracks = valid.clone();
+        tracks.tracks.push(CueSheetTrack::new());
+        let mut isrc = valid.clone();
+        isrc.tracks[3].isrc.push('8');
+        let mut indices = valid.clone();
+        indices.tracks[3].indices.push(CueSheetTrackIndex::new());
+        for cuesheet in [catalog_num, tracks, isrc, indices] {
+            let err = Block::CueSheet(cuesheet.clone())
+                .to_bytes(true)
+                .unwrap_err();
+            assert!(matches!(err.kind, ErrorKind::InvalidInput));
+            assert_eq!(err.block_type, Some(BlockType::CueSheet));
+
+            let mut tag = crate::Tag::new();
+            tag.push_block(Block::CueSheet(cuesheet));
+            assert!(tag.write_to_vec().is_err());
+        }
+    }
+
+    #[test]
+    fn vorbis_comment_order() {
+        let bytes = comment_bytes(&["artist=a", "TITLE=t", "ARTIST=b", "ARTIST=c"]);
+        let mut vorbis = VorbisComment::from_bytes(&bytes).unwrap();
+        assert_eq!(vorbis.get("Artist").unwrap(), &["a", "b", "c"]);
+        assert_eq!(vorbis.keys(), &["ARTIST", "TITLE"]);
+        assert_eq!(vorbis.to_bytes(), bytes);
+
+        vorbis.remove_pair("ARTIST", "b");
+        assert_eq!(
+            vorbis.to_bytes(),
+            comment_bytes(&["artist=a", "TITLE=t", "ARTIST=c"])
+        );
+
+        // the new values take the places of the old ones
+        vorbis.set("ARTIST", vec!["d", "e", "f"]);
+        assert_eq!(
+            vorbis.to_bytes(),
+            comment_bytes(&["artist=d", "TITLE=t", "ARTIST=e", "ARTIST=f"])
+        );
+        vorbis.set("ARTIST", vec!["g"]);
+        assert_eq!(vorbis.entries(), &[("artist", "g"), ("TITLE", "t")]);
+
+        // comments constructed directly are written as they are
+        let vorbis = VorbisComment {
+            vendor_string: String::new(),
+            comments: vec![
+                ("TITLE".to_owned(), "t".to_owned()),
+                ("ARTIST".to_owned(), "a".to_owned()),
+            ],
+            unseparated: Vec::new(),
+        };
+        assert_eq!(vorbis.to_bytes(), comment_bytes(&["TITLE=t", "ARTIST=a"]));
+        assert_eq!(
+            VorbisComment::from_bytes(&vorbis.to_bytes()).unwrap(),
+            vorbis
+        );
+    }
+
+    #[test]
+    fn unseparated_comments() {
+        let bytes = comment_bytes(&["junk", "TITLE=t", "more junk", "ARTIST=a", "end"]);
+        let mut vorbis = VorbisComment::from_bytes(&bytes).unwrap();
+        assert_eq!(vorbis.entries(), &[("TITLE", "t"), ("ARTIST", "a")]);
+        assert_eq!(
+            vorbis.unseparated,
+            &[
+                (0, "junk".to_owned()),
+                (1, "more junk".to_owned()),
+                (2, "end".to_owned())
+            ]
+        );
+        assert_eq!(vorbis.to_bytes(), bytes);
+        let raw = raw::VorbisComment::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            raw.unseparated,
+            &[(0, "junk"), (1, "more junk"), (2, "end")]
+        );
+        assert_eq!(raw.to_bytes(), bytes);
+
+        // the comments keep their positions among the comments before them
+        vorbis.remove("ARTIST");
+        assert_eq!(
+            vorbis.to_bytes(),
+            comment_bytes(&["junk", "TITLE=t", "more junk", "end"])
+        );
+        let mut other = VorbisComment::from_bytes(&bytes).unwrap();
+        other.merge(&vorbis);
+        assert_eq!(
+            other.to_bytes(),
+            comment_bytes(&[
+                "junk",
+                "TITLE=t",
+                "more junk",
+                "ARTIST=a",
+                "end",
+                "junk",
+                "TITLE=t",
+                "more junk",
+                "end"
+            ])
+        );
+
+        // a tag keeps them when it is edited and written
+        let mut tag = crate::Tag::new();
+        tag.push_block(Block::VorbisComment(
+            VorbisComment::from_bytes(&bytes).unwrap(),
+        ));
+        tag.set_vorbis("TITLE", vec!["u"]);
+        let tag = crate::Tag::read_from_bytes(&tag.write_to_vec().unwrap()).unwrap();
+        assert_eq!(
+            tag.vorbis_comments().unwrap().to_bytes(),
+            comment_bytes(&["junk", "TITLE=u", "more junk", "ARTIST=a", "end"])
+        );
+
+        assert!(vorbis.sanitize(false));
+        assert_eq!(vorbis.to_bytes(), comment_bytes(&["TITLE=t"]));
+    }
+
+    #[test]
+    fn dates() {
+        let date = |s: &str| s.parse::<Date>().ok();
+        assert_eq!(date("2004"), Some(Date::new(2004)));
+        assert_eq!(
+            date(" 2004-07-31 "),
+            Some(Date {
+                year: 2004,
+                month: Some(7),
+                day: Some(31)
+            })
+        );
+        for invalid in [
+            "",
+            "04",
+            "2004-7",
+            "2004-00",
+            "2004-07-32",
+            "2004-07-31-1",
+            "year",
+        ] {
+            assert_eq!(date(invalid), None, "{}", invalid);
+        }
+        assert_eq!(date("0999-01").unwrap().to_string(), "0999-01");
+
+        let mut vorbis = VorbisComment::new();
+        assert_eq!(vorbis.date(), None);
+        vorbis.set_date("2004-07".parse().unwrap());
+        assert_eq!(vorbis.get("DATE").unwrap(), &["2004-07"]);
+        assert_eq!(vorbis.year(), Some(2004));
+
+        // the year is read from dates in other formats
+        vorbis.set("DATE", vec!["2004-07-31T12:00:00", "1999"]);
+        assert_eq!(vorbis.date(), None);
+        assert_eq!(vorbis.year(), Some(2004));
+        vorbis.remove_date();
+        assert_eq!(vorbis.year(), None);
+
+        vorbis.set_original_date(Date::new(1969));
+        assert_eq!(vorbis.get("ORIGINALDATE").unwrap(), &["1969"]);
+        assert_eq!(vorbis.original_date(), Some(Date::new(1969)));
+        vorbis.set("ORIGINALDATE", vec!["1969 (remastered)"]);
+        assert_eq!(vorbis.original_date(), None);
+        assert_eq!(vorbis.original_year(), Some(1969));
+        vorbis.remove_original_date();
+        assert_eq!(vorbis.original_year(), None);
+    }
+
+    #[test]
+    fn replaygain() {
+        let mut vorbis = VorbisComment::new();
+        assert_eq!(vorbis.replaygain_track_gain(), None);
+
+        vorbis.set("REPLAYGAIN_TRACK_GAIN", vec![" -7.5dB "]);
+        assert_eq!(vorbis.replaygain_track_gain(), Some(-7.5));
+        vorbis.set("replaygain_album_gain", vec!["+1.25 db"]);
+        assert_eq!(vorbis.replaygain_album_gain(), Some(1.25));
+        vorbis.set("REPLAYGAIN_TRACK_PEAK", vec!["0.98765432"]);
+        assert_eq!(vorbis.replaygain_track_peak(), Some(0.98765432));
+        vorbis.set("REPLAYGAIN_ALBUM_PEAK", vec!["loud"]);
+        assert_eq!(vorbis.replaygain_album_peak(), None);
+
+        vorbis.set_replaygain_track_gain(-0.5);
+        vorbis.set_replaygain_album_gain(12.345);
+        vorbis.set_replaygain_track_peak(1.0);
+        vorbis.set_replaygain_album_peak(0.5);
+        assert_eq!(vorbis.get("REPLAYGAIN_TRACK_GAIN").unwrap(), &["-0.50 dB"]);
+        assert_eq!(vorbis.get("REPLAYGAIN_ALBUM_GAIN").unwrap(), &["+12.35 dB"]);
+        assert_eq!(
+            vorbis.get("REPLAYGAIN_TRACK_PEAK").unwrap(),
+            &["1.00000000"]
+        );
+        assert_eq!(
+            vorbis.get("REPLAYGAIN_ALBUM_PEAK").unwrap(),
+            &["0.50000000"]
+        );
+        assert_eq!(vorbis.replaygain_album_peak(), Some(0.5));
+
+        vorbis.remove_replaygain_track_gain();
+        vorbis.remove_replaygain_album_gain();
+        vorbis.remove_replaygain_track_peak();
+        vorbis.remove_replaygain_album_peak();
+        assert!(vorbis.comments.is_empty());
+    }
+
+    #[test]
+    fn ratings() {
+        let mut vorbis = VorbisComment::new();
+        assert_eq!(vorbis.rating(), None);
+        vorbis.set("RATING", vec!["80"]);
+        assert_eq!(vorbis.rating(), Some(0.8));
+        // FMPS_RATING takes precedence unless it is out of range
+        vorbis.set("FMPS_RATING", vec![" 0.25 "]);
+        assert_eq!(vorbis.rating(), Some(0.25));
+        vorbis.set("FMPS_RATING", vec!["1.5"]);
+        assert_eq!(vorbis.rating(), Some(0.8));
+        vorbis.set("RATING", vec!["101"]);
+        assert_eq!(vorbis.rating(), None);
+
+        vorbis.set_rating(2.0);
+        assert_eq!(vorbis.get("FMPS_RATING").unwrap(), &["1"]);
+        assert_eq!(vorbis.get("RATING").unwrap(), &["100"]);
+        vorbis.set_rating(0.456);
+        assert_eq!(vorbis.get("RATING").unwrap(), &["46"]);
+
+        // without mirroring the RATING comment is left alone and not read
+        let policy = RatingPolicy {
+            mirror: false,
+            scale: 0,
+        };
+        vorbis.set_rating_with(0.5, &policy);
+        assert_eq!(vorbis.get("FMPS_RATING").unwrap(), &["0.5"]);
+        assert_eq!(vorbis.get("RATING").unwrap(), &["46"]);
+        vorbis.remove("FMPS_RATING");
+        assert_eq!(vorbis.rating_with(&policy), None);
+        assert_eq!(vorbis.rating(), Some(0.46));
+
+        vorbis.remove_rating();
+        assert!(vorbis.comments.is_empty());
+    }
+
+    #[test]
+    fn synced_lyrics() {
+        let ms = Duration::from_millis;
+        let mut vorbis = VorbisComment::new();
+        vorbis.set("LYRICS", vec!["plain lyrics\n[no time]"]);
+        assert_eq!(vorbis.synced_lyrics(), None);
+
+        // SYNCEDLYRICS is read before LYRICS, and lines are sorted by time
+        vorbis.set(
+            "SYNCEDLYRICS",
+            vec!["[00:10.5][01:00.123] chorus \r\n[00:05]verse\n[1:00]short\n[00:07.1234]long"],
+        );
+        assert_eq!(
+            vorbis.synced_lyrics().unwrap(),
+            &[
+                (ms(5_000), "verse".to_owned()),
+                (ms(10_500), "chorus".to_owned()),
+                (ms(60_000), "short".to_owned()),
+                (ms(60_123), "chorus".to_owned()),
+            ]
+        );
+
+        let lyrics = [
+            (ms(1_239), "a".to_owned()),
+            (Duration::from_secs(6_000), "b".to_owned()),
+        ];
+        vorbis.set_synced_lyrics(&lyrics);
+        assert_eq!(
+            vorbis.get("SYNCEDLYRICS").unwrap(),
+            &["[00:01.23]a\n[100:00.00]b"]
+        );
+        assert_eq!(
+            vorbis.synced_lyrics().unwrap(),
+            &[(ms(1_230), "a".to_owned()), lyrics[1].clone()]
+        );
+
+        vorbis.remove_synced_lyrics();
+        assert!(vorbis.get("SYNCEDLYRICS").is_none());
+        assert!(vorbis.get("LYRICS").is_some());
+    }
+
+    #[test]
+    fn track_numbers() {
+        let mut vorbis = VorbisComment::new();
+        assert_eq!((vorbis.track(), vorbis.total_tracks()), (None, None));
+        vorbis.set("TRACKNUMBER", vec![" 3 / 12 "]);
+        assert_eq!((vorbis.track(), vorbis.total_tracks()), (Some(3), Some(12)));
+        vorbis.set("TRACKNUMBER", vec!["3/", "4/13"]);
+        assert_eq!((vorbis.track(), vorbis.total_tracks()), (Some(3), None));
+        vorbis.set("TRACKNUMBER", vec!["/12"]);
+        assert_eq!((vorbis.track(), vorbis.total_tracks()), (None, Some(12)));
+
+        // a separate total takes precedence over the combined one
+        vorbis.set("TRACKNUMBER", vec!["3/12"]);
+        vorbis.set("TRACKTOTAL", vec!["14"]);
+        assert_eq!(vorbis.total_tracks(), Some(14));
+        vorbis.set("TRACKTOTAL", vec!["x"]);
+        assert_eq!(vorbis.total_tracks(), Some(12));
+        vorbis.set("TRACKTOTAL", vec!["14"]);
+
+        vorbis.set_track_pair(5, 10, TrackNumberStyle::Both);
+        assert_eq!(vorbis.get("TRACKNUMBER").unwrap(), &["5/10"]);
+        assert_eq!(vorbis.get("TOTALTRACKS").unwrap(), &["10"]);
+        assert_eq!(vorbis.total_tracks(), Some(10));
+        // the combined style removes the total under either key
+        vorbis.set_track_pair(6, 11, TrackNumberStyle::Combined);
+        assert_eq!(vorbis.entries(), &[("TRACKNUMBER", "6/11")]);
+        assert_eq!(vorbis.total_tracks(), Some(11));
+        vorbis.set("TRACKTOTAL", vec!["14"]);
+        vorbis.remove_total_tracks();
+        assert_eq!(vorbis.total_tracks(), Some(11));
+        vorbis.set_track_pair(7, 12, TrackNumberStyle::Separate);
+        assert_eq!(
+            vorbis.entries(),
+            &[("TRACKNUMBER", "7"), ("TOTALTRACKS", "12")]
+        );
+        assert_eq!((vorbis.track(), vorbis.total_tracks()), (Some(7), Some(12)));
+    }
+
+    #[test]
+    fn seektable_templates() {
+        let samples = |seektable: &SeekTable| -> Vec<u64> {
+            seektable
+                .seekpoints
+                .iter()
+                .map(|p| p.sample_number)
+                .collect()
+        };
+        let mut streaminfo = StreamInfo::new();
+        streaminfo.sample_rate = 100;
+        streaminfo.total_samples = 1000;
+
+        let template = SeekTable::template_n_points(3);
+        assert_eq!(template.seekpoints.len(), 3);
+        assert!(template.seekpoints.iter().all(SeekPoint::is_placeholder));
+
+        assert_eq!(
+            samples(&SeekTable::template_every_seconds(&streaminfo, 4.0)),
+            &[0, 400, 800]
+        );
+        assert_eq!(
+            samples(&SeekTable::template_every_seconds(&streaminfo, 5.0)),
+            &[0, 500]
+        );
+        for secs in [0.0, -1.0, 0.001] {
+            assert!(SeekTable::template_every_seconds(&streaminfo, secs)
+                .seekpoints
+                .is_empty());
+        }
+
+        assert_eq!(
+            samples(&SeekTable::template_spaced_points(&streaminfo, 4)),
+            &[0, 250, 500, 750]
+        );
+        // a stream shorter than the number of points has a point for each sample
+        streaminfo.total_samples = 3;
+        assert_eq!(
+            samples(&SeekTable::template_spaced_points(&streaminfo, 5)),
+            &[0, 1, 2]
+        );
+        streaminfo.total_samples = 0;
+        assert!(SeekTable::template_every_seconds(&streaminfo, 1.0)
+            .seekpoints
+            .is_empty());
+        assert!(SeekTable::template_spaced_points(&streaminfo, 5)
+            .seekpoints
+            .is_empty());
+    }
+
+    #[test]
+    fn seektable_fill_from_frames() {
+        let mut seektable = SeekTable::new();
+        for sample_number in [0, 100, 150, 250, 1000] {
+            seektable.insert(SeekPoint {
+                sample_number,
+                offset: 0,
+                num_samples: 0,
+            });
+        }
+        seektable.insert(SeekPoint::placeholder());
+
+        let frames = (0..3u64).map(|i| SeekPoint {
+            sample_number: i * 100,
+            offset: 10 + i * 50,
+            num_samples: 100,
+        });
+        assert_eq!(seektable.fill_from_frames(frames), 3);
+
+        let points: Vec<_> = seektable
+            .seekpoints
+            .iter()
+            .map(|p| (p.sample_number, p.offset, p.num_samples))
+            .collect();
+        let placeholder = (SeekPoint::PLACEHOLDER, 0, 0);
+        // the point after the last frame is left as it was
+        assert_eq!(
+            points,
+            &[
+                (0, 10, 100),
+                (100, 60, 100),
+                (200, 110, 100),
+                (1000, 0, 0),
+                placeholder,
+                placeholder
+            ]
+        );
+    }
+
+    #[test]
+    fn seek_point_lookups() {
+        let mut seektable = SeekTable::new();
+        assert!(seektable.seek_point_before(0).is_none());
+        assert!(seektable.seek_point_after(0).is_none());
+        seektable.insert(SeekPoint::placeholder());
+        assert!(seektable.seek_point_before(u64::MAX).is_none());
+        for (sample_number, offset) in [(44100, 2), (0, 0), (22050, 1)] {
+            seektable.insert(SeekPoint {
+                sample_number,
+                offset,
+                num_samples: 4096,
+            });
+        }
+
+        let before = |sample| seektable.seek_point_before(sample).map(|p| p.offset);
+        let after = |sample| seektable.seek_point_after(sample).map(|p| p.offset);
+        assert_eq!(
+            [0, 22049, 22050, 44099, 44100].map(before),
+            [Some(0), Some(0), Some(1), Some(1), Some(2)]
+        );
+        assert_eq!(
+            [0, 1, 22050, 44100, 44101].map(after),
+            [Some(0), Some(1), Some(1), Some(2), None]
+        );
+
+        let time = |millis| {
+            seektable
+                .seek_point_before_time(Duration::from_millis(millis), 44100)
+                .map(|p| p.offset)
+        };
+        assert_eq!(time(499), Some(0));
+        assert_eq!(time(500), Some(1));
+        assert_eq!(time(1000), Some(2));
+        assert_eq!(
+            seektable
+                .seek_point_before_time(Duration::MAX, u32::MAX)
+                .map(|p| p.offset),
+            Some(2)
+        );
     }
 }
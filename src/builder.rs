@@ -0,0 +1,170 @@
+use crate::block::{Block, Picture, PictureType, StreamInfo, VorbisComment};
+use crate::error::{Error, ErrorKind, Result};
+use crate::tag::Tag;
+
+/// A builder for constructing a tag from scratch, e.g. in an encoder or a ripper.
+///
+/// The values are only validated when the tag is built.
+///
+/// # Example
+/// ```
+/// use metaflac::TagBuilder;
+/// use metaflac::block::{Picture, PictureType, StreamInfo};
+///
+/// let mut picture = Picture::new();
+/// picture.picture_type = PictureType::CoverFront;
+/// picture.mime_type = "image/png".to_owned();
+/// picture.data = vec![0x89, 0x50, 0x4E, 0x47];
+///
+/// let tag = TagBuilder::new()
+///     .title("title")
+///     .artist("artist")
+///     .comment("GENRE", "rock")
+///     .picture(picture)
+///     .padding(1024)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+/// assert_eq!(tag.pictures().count(), 1);
+///
+/// assert!(TagBuilder::new().comment("TITLE=", "title").build().is_err());
+/// assert!(TagBuilder::new().streaminfo(StreamInfo::new()).build().is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TagBuilder {
+    streaminfo: Option<StreamInfo>,
+    vendor_string: Option<String>,
+    comments: Vec<(String, String)>,
+    pictures: Vec<Picture>,
+    padding: Option<u32>,
+}
+
+impl TagBuilder {
+    /// Creates a builder for an empty tag.
+    pub fn new() -> TagBuilder {
+        TagBuilder::default()
+    }
+
+    /// Sets the streaminfo block of the tag.
+    pub fn streaminfo(mut self, streaminfo: StreamInfo) -> TagBuilder {
+        self.streaminfo = Some(streaminfo);
+        self
+    }
+
+    /// Sets the vendor string of the vorbis comment block.
+    pub fn vendor_string<T: Into<String>>(mut self, vendor_string: T) -> TagBuilder {
+        self.vendor_string = Some(vendor_string.into());
+        self
+    }
+
+    /// Adds a value for the vorbis comment key. Values for the same key are kept in the order in
+    /// which they are added.
+    pub fn comment<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> TagBuilder {
+        self.comments.push((key.into(), value.into()));
+        self
+    }
+
+    /// Adds a TITLE comment.
+    pub fn title<T: Into<String>>(self, title: T) -> TagBuilder {
+        self.comment("TITLE", title)
+    }
+
+    /// Adds an ARTIST comment.
+    pub fn artist<T: Into<String>>(self, artist: T) -> TagBuilder {
+        self.comment("ARTIST", artist)
+    }
+
+    /// Adds an ALBUM comment.
+    pub fn album<T: Into<String>>(self, album: T) -> TagBuilder {
+        self.comment("ALBUM", album)
+    }
+
+    /// Adds a picture block.
+    pub fn picture(mut self, picture: Picture) -> TagBuilder {
+        self.pictures.push(picture);
+        self
+    }
+
+    /// Sets the length of the padding block written after the other blocks.
+    pub fn padding(mut self, padding: u32) -> TagBuilder {
+        self.padding = Some(padding);
+        self
+    }
+
+    /// Validates the values and builds the tag.
+    ///
+    /// Returns an `InvalidInput` error if a comment key is not a valid field name, a picture has
+    /// a MIME type which is not printable ASCII, a picture which links to its image has an invalid
+    /// URL, there is more than one picture of one of the icon types, the streaminfo block cannot
+    /// be written as described for `StreamInfo::to_bytes`, or a block does not fit in the 24 bit
+    /// length of a block header.
+    pub fn build(self) -> Result<Tag> {
+        let mut tag = Tag::new();
+
+        if let Some(streaminfo) = self.streaminfo {
+            streaminfo.to_bytes()?;
+            tag.push_block(Block::StreamInfo(streaminfo));
+        }
+
+        if let Some(vendor_string) = self.vendor_string {
+            tag.set_vendor_string(vendor_string);
+        }
+
+        for (key, value) in self.comments {
+            if !VorbisComment::is_valid_key(&key) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "invalid vorbis comment key",
+                ));
+            }
+            let mut values = tag.get_vorbis_values(&key).unwrap_or_default();
+            values.push(value);
+            tag.set_vorbis(key, values);
+        }
+
+        for picture in self.pictures {
+            if !picture.mime_type.bytes().all(|b| (0x20..0x7F).contains(&b)) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "picture mime type is not printable ascii",
+                ));
+            }
+            if picture.is_url() && picture.url().is_none() {
+                return Err(Error::new(ErrorKind::InvalidInput, "invalid picture url"));
+            }
+            let is_icon = |picture_type| {
+                picture_type == PictureType::Icon || picture_type == PictureType::OtherIcon
+            };
+            if is_icon(picture.picture_type)
+                && tag
+                    .pictures()
+                    .any(|other| other.picture_type == picture.picture_type)
+            {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "more than one picture of an icon type",
+                ));
+            }
+            tag.push_block(Block::Picture(picture));
+        }
+
+        if let Some(padding) = self.padding {
+            tag.push_block(Block::Padding(padding));
+        }
+
+        // the streaminfo block has a fixed length
+        if tag
+            .blocks()
+            .filter(|block| !matches!(block, Block::StreamInfo(_)))
+            .any(|block| !block.fits_block_header())
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "block is too long for a metadata block",
+            ));
+        }
+
+        Ok(tag)
+    }
+}
@@ -0,0 +1,789 @@
+use crate::block::{CueSheet, CueSheetIssue, CueSheetTrack, CueSheetTrackIndex, StreamInfo};
+use crate::error::{Error, ErrorKind, Result};
+use crate::tag::Tag;
+
+/// The number of CD frames per second, the unit of the frame part of an MM:SS:FF timestamp.
+const FRAMES_PER_SECOND: u64 = 75;
+/// The number of samples in a CD frame at the 44.1 kHz sample rate of a CD.
+const CD_SAMPLES_PER_FRAME: u64 = 44100 / FRAMES_PER_SECOND;
+
+/// The number of the lead-out track of a CD.
+const CD_LEAD_OUT: u8 = 170;
+/// The number of the lead-out track of other media.
+const LEAD_OUT: u8 = 255;
+
+impl CueSheet {
+    /// Parses the text of a cue sheet file into a cuesheet block for the stream described by the
+    /// streaminfo block, as `metaflac --import-cuesheet-from` does.
+    ///
+    /// The `CATALOG`, `FILE`, `TRACK`, `FLAGS`, `ISRC` and `INDEX` commands are used, and other
+    /// commands such as `TITLE` and `PERFORMER` are ignored. Only a single `FILE` is supported.
+    /// The stream is treated as a CD if it has the CD-DA format of 16 bit samples at 44.1 kHz, in
+    /// which case the CD-DA constraints on catalog numbers and track numbers apply and the index
+    /// points must be `MM:SS:FF` timestamps. Other streams may also use sample numbers. A lead-out
+    /// track is added at the end of the stream.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{CueSheet, StreamInfo};
+    ///
+    /// let mut streaminfo = StreamInfo::new();
+    /// streaminfo.sample_rate = 44100;
+    /// streaminfo.num_channels = 2;
+    /// streaminfo.bits_per_sample = 16;
+    /// streaminfo.total_samples = 44100 * 300;
+    ///
+    /// let text = "FILE \"album.wav\" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 00:00:00
+    ///   TRACK 02 AUDIO
+    ///     FLAGS PRE
+    ///     INDEX 00 02:30:00
+    ///     INDEX 01 02:32:00
+    /// ";
+    /// let cuesheet = CueSheet::from_cue(text, &streaminfo).unwrap();
+    ///
+    /// assert!(cuesheet.is_cd);
+    /// assert_eq!(cuesheet.tracks.len(), 3);
+    /// assert_eq!(cuesheet.tracks[1].offset, 44100 * 150);
+    /// assert_eq!(cuesheet.tracks[1].indices[1].offset, 44100 * 2);
+    /// assert_eq!(cuesheet.tracks[2].number, 170);
+    /// assert_eq!(cuesheet.tracks[2].offset, 44100 * 300);
+    /// ```
+    pub fn from_cue(text: &str, streaminfo: &StreamInfo) -> Result<CueSheet> {
+        let invalid = |description| Error::new(ErrorKind::InvalidInput, description);
+
+        let is_cd = is_cdda(streaminfo);
+        let mut cuesheet = CueSheet::new();
+        cuesheet.is_cd = is_cd;
+        // a CD has a lead-in of two seconds
+        cuesheet.num_leadin = if is_cd { 2 * 44100 } else { 0 };
+
+        let mut has_file = false;
+        let mut lead_out = None;
+
+        for line in text.lines() {
+            let words = words(line);
+            let command = match words.first() {
+                Some(command) => command.to_ascii_uppercase(),
+                None => continue,
+            };
+            let arg = |i: usize| {
+                words
+                    .get(i)
+                    .map(String::as_str)
+                    .ok_or_else(|| invalid("missing cue sheet command argument"))
+            };
+
+            match command.as_str() {
+                "CATALOG" => {
+                    let catalog_num = arg(1)?;
+                    if !cuesheet.catalog_num.is_empty() {
+                        return Err(invalid("more than one CATALOG command"));
+                    }
+                    let is_valid = if is_cd {
+                        catalog_num.len() == 13 && catalog_num.bytes().all(|b| b.is_ascii_digit())
+                    } else {
+                        catalog_num.len() <= 128
+                            && catalog_num.bytes().all(|b| (0x20..0x7F).contains(&b))
+                    };
+                    if !is_valid {
+                        return Err(invalid("invalid cue sheet catalog number"));
+                    }
+                    cuesheet.catalog_num = catalog_num.to_owned();
+                }
+                "FILE" => {
+                    arg(1)?;
+                    if has_file {
+                        return Err(invalid("more than one FILE command is not supported"));
+                    }
+                    has_file = true;
+                }
+                "TRACK" => {
+                    if !has_file {
+                        return Err(invalid("TRACK command before FILE command"));
+                    }
+                    let number = arg(1)?
+                        .parse::<u8>()
+                        .map_err(|_| invalid("invalid cue sheet track number"))?;
+                    let max = if is_cd { 99 } else { LEAD_OUT - 1 };
+                    if number == 0 || number > max {
+                        return Err(invalid("invalid cue sheet track number"));
+                    }
+                    if let Some(previous) = cuesheet.tracks.last() {
+                        if previous.indices.is_empty() {
+                            return Err(invalid("cue sheet track has no INDEX command"));
+                        }
+                        if number <= previous.number {
+                            return Err(invalid("cue sheet track numbers do not increase"));
+                        }
+                    }
+                    let mut track = CueSheetTrack::new();
+                    track.number = number;
+                    track.is_audio = arg(2)?.eq_ignore_ascii_case("AUDIO");
+                    cuesheet.tracks.push(track);
+                }
+                "FLAGS" => {
+                    let track = current_track(&mut cuesheet)?;
+                    if words[1..]
+                        .iter()
+                        .any(|flag| flag.eq_ignore_ascii_case("PRE"))
+                    {
+                        track.pre_emphasis = true;
+                    }
+                }
+                "ISRC" => {
+                    let isrc = arg(1)?;
+                    let track = current_track(&mut cuesheet)?;
+                    if isrc.len() != 12 || !isrc.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                        return Err(invalid("invalid cue sheet ISRC"));
+                    }
+                    track.isrc = isrc.to_owned();
+                }
+                "INDEX" => {
+                    let point_num = arg(1)?
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|&point_num| point_num <= 99)
+                        .ok_or_else(|| invalid("invalid cue sheet index number"))?;
+                    let offset = parse_offset(arg(2)?, streaminfo.sample_rate, is_cd)?;
+
+                    let last_offset = cuesheet.tracks.iter().rev().find_map(|track| {
+                        track
+                            .indices
+                            .last()
+                            .map(|index| track.offset + index.offset)
+                    });
+                    let track = current_track(&mut cuesheet)?;
+                    match track.indices.last() {
+                        Some(index) if point_num != index.point_num + 1 => {
+                            return Err(invalid("cue sheet index numbers are not sequential"));
+                        }
+                        None if point_num > 1 => {
+                            return Err(invalid("first cue sheet index number is not 0 or 1"));
+                        }
+                        _ => {}
+                    }
+                    if last_offset.is_some_and(|last_offset| offset <= last_offset) {
+                        return Err(invalid("cue sheet index offsets do not increase"));
+                    }
+
+                    if track.indices.is_empty() {
+                        track.offset = offset;
+                    }
+                    let mut index = CueSheetTrackIndex::new();
+                    index.offset = offset - track.offset;
+                    index.point_num = point_num;
+                    track.indices.push(index);
+                }
+                "PREGAP" | "POSTGAP" => {
+                    return Err(invalid("PREGAP and POSTGAP commands are not supported"));
+                }
+                "REM" => match words.get(1).map(String::as_str) {
+                    // written by `metaflac --export-cuesheet-to`
+                    Some("FLAC__lead-in") => {
+                        cuesheet.num_leadin = arg(2)?
+                            .parse()
+                            .map_err(|_| invalid("invalid cue sheet lead-in"))?;
+                    }
+                    Some("FLAC__lead-out") => {
+                        let number = arg(2)?.parse::<u8>();
+                        let offset = arg(3)?.parse::<u64>();
+                        match (number, offset) {
+                            (Ok(number), Ok(offset)) => lead_out = Some((number, offset)),
+                            _ => return Err(invalid("invalid cue sheet lead-out")),
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        let last_offset = match cuesheet.tracks.last() {
+            Some(track) => match track.indices.last() {
+                Some(index) => track.offset + index.offset,
+                None => return Err(invalid("cue sheet track has no INDEX command")),
+            },
+            None => return Err(invalid("cue sheet has no tracks")),
+        };
+
+        let default_number = if is_cd { CD_LEAD_OUT } else { LEAD_OUT };
+        let (number, offset) = lead_out.unwrap_or((default_number, streaminfo.total_samples));
+        if offset <= last_offset {
+            return Err(invalid("cue sheet index is beyond the end of the stream"));
+        }
+        let mut track = CueSheetTrack::new();
+        track.number = number;
+        track.offset = offset;
+        cuesheet.tracks.push(track);
+
+        if let Some(issue) = cuesheet.validate(streaminfo).first() {
+            return Err(invalid(issue.message));
+        }
+        Ok(cuesheet)
+    }
+
+    /// Checks the cuesheet against the rules of the FLAC format for the stream described by the
+    /// streaminfo block. Returns the problems found, which is empty for a valid cuesheet.
+    ///
+    /// Every cuesheet must end with a lead-out track without index points at the end of the
+    /// stream, the other tracks must have index points numbered 0 or 1 and up, and the track
+    /// numbers and offsets must increase. A CD cuesheet additionally has to follow the CD-DA
+    /// constraints: a 44.1 kHz stream, a lead-in of at least two seconds, track numbers from 1 to
+    /// 99 with lead-out track 170, offsets on CD frame boundaries, and a 13 digit catalog number.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{CueSheet, CueSheetTrack, StreamInfo};
+    ///
+    /// let mut streaminfo = StreamInfo::new();
+    /// streaminfo.sample_rate = 48000;
+    ///
+    /// let mut lead_out = CueSheetTrack::new();
+    /// lead_out.number = 170;
+    /// let mut cuesheet = CueSheet::new();
+    /// cuesheet.tracks.push(lead_out);
+    ///
+    /// let issues = cuesheet.validate(&streaminfo);
+    /// let messages: Vec<_> = issues.iter().map(|issue| issue.message).collect();
+    /// assert_eq!(messages, &[
+    ///     "CD-DA cuesheet requires a sample rate of 44100 Hz",
+    ///     "CD-DA cuesheet has a lead-in of less than two seconds",
+    /// ]);
+    /// ```
+    pub fn validate(&self, streaminfo: &StreamInfo) -> Vec<CueSheetIssue> {
+        let mut issues = Vec::new();
+        let mut issue = |track, message| issues.push(CueSheetIssue { track, message });
+
+        let is_frame_aligned = |offset: u64| !self.is_cd || offset % CD_SAMPLES_PER_FRAME == 0;
+
+        if self.is_cd {
+            if streaminfo.sample_rate != 44100 {
+                issue(None, "CD-DA cuesheet requires a sample rate of 44100 Hz");
+            }
+            if self.num_leadin < 2 * 44100 {
+                issue(
+                    None,
+                    "CD-DA cuesheet has a lead-in of less than two seconds",
+                );
+            } else if !is_frame_aligned(self.num_leadin) {
+                issue(
+                    None,
+                    "CD-DA cuesheet lead-in is not a whole number of CD frames",
+                );
+            }
+            if !self.catalog_num.is_empty()
+                && (self.catalog_num.len() != 13
+                    || !self.catalog_num.bytes().all(|b| b.is_ascii_digit()))
+            {
+                issue(None, "CD-DA cuesheet catalog number is not 13 digits");
+            }
+        }
+        if self.catalog_num.len() > 128 {
+            issue(None, "cuesheet catalog number is longer than 128 bytes");
+        }
+
+        let lead_out_number = if self.is_cd { CD_LEAD_OUT } else { LEAD_OUT };
+        let (lead_out, tracks) = match self.tracks.split_last() {
+            Some((lead_out, tracks)) => (lead_out, tracks),
+            None => {
+                issue(None, "cuesheet has no lead-out track");
+                return issues;
+            }
+        };
+        if tracks.len() > 99 && self.is_cd {
+            issue(None, "CD-DA cuesheet has more than 99 tracks");
+        }
+
+        let mut previous_number = 0;
+        let mut previous_offset = None;
+        for (i, track) in tracks.iter().enumerate() {
+            if track.number == 0 || track.number == lead_out_number {
+                issue(Some(i), "cuesheet track number is reserved");
+            } else if self.is_cd && track.number > 99 {
+                issue(Some(i), "CD-DA cuesheet track number is not from 1 to 99");
+            }
+            if track.number <= previous_number {
+                issue(Some(i), "cuesheet track numbers do not increase");
+            }
+            previous_number = track.number;
+
+            if !track.isrc.is_empty()
+                && (track.isrc.len() != 12
+                    || !track.isrc.bytes().all(|b| b.is_ascii_alphanumeric()))
+            {
+                issue(
+                    Some(i),
+                    "cuesheet track ISRC is not 12 alphanumeric characters",
+                );
+            }
+            if !is_frame_aligned(track.offset) {
+                issue(
+                    Some(i),
+                    "CD-DA cuesheet track offset is not on a CD frame boundary",
+                );
+            }
+
+            match track.indices.first() {
+                Some(index) if index.point_num > 1 => {
+                    issue(
+                        Some(i),
+                        "cuesheet track index numbers do not start at 0 or 1",
+                    );
+                }
+                Some(_) => {}
+                None => issue(Some(i), "cuesheet track has no index points"),
+            }
+            if track
+                .indices
+                .windows(2)
+                .any(|pair| pair[1].point_num != pair[0].point_num.wrapping_add(1))
+            {
+                issue(Some(i), "cuesheet track index numbers are not sequential");
+            }
+            for index in track.indices.iter() {
+                if !is_frame_aligned(index.offset) {
+                    issue(
+                        Some(i),
+                        "CD-DA cuesheet index offset is not on a CD frame boundary",
+                    );
+                }
+                let offset = track.offset + index.offset;
+                if previous_offset.is_some_and(|previous| offset <= previous) {
+                    issue(Some(i), "cuesheet offsets do not increase");
+                }
+                previous_offset = Some(offset);
+            }
+        }
+
+        let i = Some(tracks.len());
+        if lead_out.number != lead_out_number || !lead_out.indices.is_empty() {
+            issue(None, "cuesheet has no lead-out track");
+        } else {
+            if !is_frame_aligned(lead_out.offset) {
+                issue(
+                    i,
+                    "CD-DA cuesheet lead-out offset is not on a CD frame boundary",
+                );
+            }
+            if previous_offset.is_some_and(|previous| lead_out.offset <= previous) {
+                issue(i, "cuesheet offsets do not increase");
+            }
+            if streaminfo.total_samples != 0 && lead_out.offset != streaminfo.total_samples {
+                issue(i, "cuesheet lead-out is not at the end of the stream");
+            }
+        }
+
+        issues
+    }
+
+    /// Renders the cuesheet as the text of a cue sheet file for the audio file with the specified
+    /// name, as `metaflac --export-cuesheet-to` does. This is the inverse of
+    /// `CueSheet::from_cue`.
+    ///
+    /// The index points of a CD are written as `MM:SS:FF` timestamps, rounded down to whole CD
+    /// frames, and those of other media as sample numbers. The lead-in and the lead-out track,
+    /// which have no cue sheet commands, are written as `REM FLAC__lead-in` and
+    /// `REM FLAC__lead-out` comments.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{CueSheet, CueSheetTrack, CueSheetTrackIndex};
+    ///
+    /// let mut track = CueSheetTrack::new();
+    /// track.number = 1;
+    /// track.offset = 588 * 75 * 62;
+    /// let mut index = CueSheetTrackIndex::new();
+    /// index.point_num = 1;
+    /// track.indices.push(index);
+    ///
+    /// let mut lead_out = CueSheetTrack::new();
+    /// lead_out.number = 170;
+    /// lead_out.offset = 44100 * 300;
+    ///
+    /// let mut cuesheet = CueSheet::new();
+    /// cuesheet.num_leadin = 88200;
+    /// cuesheet.tracks = vec!(track, lead_out);
+    ///
+    /// assert_eq!(cuesheet.to_cue("album.flac"), "FILE \"album.flac\" WAVE
+    ///   TRACK 01 AUDIO
+    ///     INDEX 01 01:02:00
+    /// REM FLAC__lead-in 88200
+    /// REM FLAC__lead-out 170 13230000
+    /// ");
+    /// ```
+    pub fn to_cue(&self, file_name: &str) -> String {
+        let mut text = String::new();
+        if !self.catalog_num.is_empty() {
+            text.push_str(&format!("CATALOG {}\n", self.catalog_num));
+        }
+        text.push_str(&format!("FILE \"{}\" WAVE\n", file_name));
+
+        let (tracks, lead_out) = match self.tracks.split_last() {
+            Some((last, tracks)) if last.indices.is_empty() => (tracks, Some(last)),
+            _ => (&self.tracks[..], None),
+        };
+
+        for track in tracks {
+            let track_type = if track.is_audio { "AUDIO" } else { "DATA" };
+            text.push_str(&format!("  TRACK {:02} {}\n", track.number, track_type));
+            if track.pre_emphasis {
+                text.push_str("    FLAGS PRE\n");
+            }
+            if !track.isrc.is_empty() {
+                text.push_str(&format!("    ISRC {}\n", track.isrc));
+            }
+            for index in track.indices.iter() {
+                let offset = track.offset + index.offset;
+                let offset = if self.is_cd {
+                    let frames = offset / CD_SAMPLES_PER_FRAME;
+                    format!(
+                        "{:02}:{:02}:{:02}",
+                        frames / FRAMES_PER_SECOND / 60,
+                        frames / FRAMES_PER_SECOND % 60,
+                        frames % FRAMES_PER_SECOND
+                    )
+                } else {
+                    offset.to_string()
+                };
+                text.push_str(&format!("    INDEX {:02} {}\n", index.point_num, offset));
+            }
+        }
+
+        text.push_str(&format!("REM FLAC__lead-in {}\n", self.num_leadin));
+        if let Some(lead_out) = lead_out {
+            text.push_str(&format!(
+                "REM FLAC__lead-out {} {}\n",
+                lead_out.number, lead_out.offset
+            ));
+        }
+        text
+    }
+}
+
+impl Tag {
+    /// Replaces the cuesheet block with one parsed from the text of a cue sheet file using
+    /// `CueSheet::from_cue`. Returns an `InvalidInput` error if the tag has no streaminfo block.
+    pub fn import_cuesheet(&mut self, text: &str) -> Result<()> {
+        let streaminfo = self
+            .get_streaminfo()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "tag has no streaminfo block"))?;
+        let cuesheet = CueSheet::from_cue(text, streaminfo)?;
+        self.set_cuesheet(cuesheet);
+        Ok(())
+    }
+}
+
+/// Returns true if the stream has the format of CD digital audio.
+fn is_cdda(streaminfo: &StreamInfo) -> bool {
+    streaminfo.sample_rate == 44100
+        && streaminfo.bits_per_sample == 16
+        && (streaminfo.num_channels == 1 || streaminfo.num_channels == 2)
+}
+
+/// Returns the last track, for a command which only appears within a track.
+fn current_track(cuesheet: &mut CueSheet) -> Result<&mut CueSheetTrack> {
+    cuesheet.tracks.last_mut().ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "cue sheet command outside of a track",
+        )
+    })
+}
+
+/// Splits a line of a cue sheet into words. A double quoted string is a single word.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let word: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            words.push(word);
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// Parses the offset of an index point in samples, either as an `MM:SS:FF` timestamp or, unless
+/// the stream is a CD, as a sample number.
+fn parse_offset(text: &str, sample_rate: u32, is_cd: bool) -> Result<u64> {
+    let invalid = || Error::new(ErrorKind::InvalidInput, "invalid cue sheet index offset");
+
+    let parts: Vec<&str> = text.split(':').collect();
+    if parts.len() == 1 && !is_cd {
+        return text.parse().map_err(|_| invalid());
+    }
+    if parts.len() != 3 {
+        return Err(invalid());
+    }
+    let mut values = [0u64; 3];
+    for (value, part) in values.iter_mut().zip(parts) {
+        if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        *value = part.parse().map_err(|_| invalid())?;
+    }
+    let [minutes, seconds, frames] = values;
+    if seconds >= 60 || frames >= FRAMES_PER_SECOND {
+        return Err(invalid());
+    }
+    let frames = (minutes * 60 + seconds) * FRAMES_PER_SECOND + frames;
+    if sample_rate as u64 % FRAMES_PER_SECOND != 0 {
+        return Err(invalid());
+    }
+    Ok(frames * (sample_rate as u64 / FRAMES_PER_SECOND))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_streaminfo;
+
+    fn cdda() -> StreamInfo {
+        let mut streaminfo = test_streaminfo();
+        streaminfo.sample_rate = 44100;
+        streaminfo.total_samples = 44100 * 600;
+        streaminfo
+    }
+
+    #[test]
+    fn cue_sheet_text() {
+        let text = "REM GENRE Rock
+CATALOG 1234567890123
+PERFORMER \"Some Artist\"
+FILE \"Some Album.flac\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"One\"
+    ISRC USRC17607839
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 00 03:00:00
+    INDEX 01 03:02:37
+";
+        let cuesheet = CueSheet::from_cue(text, &cdda()).unwrap();
+        assert_eq!(cuesheet.catalog_num, "1234567890123");
+        assert_eq!(cuesheet.num_leadin, 88200);
+        assert_eq!(cuesheet.tracks[0].isrc, "USRC17607839");
+        assert_eq!(cuesheet.tracks[1].offset, 180 * 44100);
+        assert_eq!(cuesheet.tracks[1].indices[1].offset, 2 * 44100 + 37 * 588);
+        assert_eq!(cuesheet.tracks[2].number, CD_LEAD_OUT);
+
+        // sample numbers are only allowed for other streams
+        let samples = "FILE a.flac WAVE\nTRACK 1 AUDIO\nINDEX 1 1000\n";
+        assert!(CueSheet::from_cue(samples, &cdda()).is_err());
+        let mut streaminfo = cdda();
+        streaminfo.sample_rate = 48000;
+        let cuesheet = CueSheet::from_cue(samples, &streaminfo).unwrap();
+        assert!(!cuesheet.is_cd);
+        assert_eq!(cuesheet.tracks[0].offset, 1000);
+        assert_eq!(cuesheet.tracks[1].number, LEAD_OUT);
+
+        let exported = CueSheet::from_cue(text, &cdda()).unwrap().to_cue("a.flac");
+        assert!(exported.contains("    INDEX 01 03:02:37\n"));
+        assert!(exported.ends_with("REM FLAC__lead-out 170 26460000\n"));
+        let mut streaminfo = cdda();
+        streaminfo.total_samples = 0;
+        assert_eq!(
+            CueSheet::from_cue(&exported, &streaminfo).unwrap(),
+            CueSheet::from_cue(text, &cdda()).unwrap()
+        );
+
+        let mut cuesheet = CueSheet::from_cue(text, &cdda()).unwrap();
+        assert!(cuesheet.validate(&cdda()).is_empty());
+        cuesheet.tracks[0].indices[0].offset = 1;
+        cuesheet.tracks.pop();
+        let issues = cuesheet.validate(&cdda());
+        assert_eq!(
+            issues.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            &[
+                "track[0]: CD-DA cuesheet index offset is not on a CD frame boundary",
+                "cuesheet has no lead-out track",
+            ]
+        );
+
+        for text in [
+            "TRACK 01 AUDIO\nINDEX 01 00:00:00\n",
+            "FILE a WAVE\nTRACK 01 AUDIO\n",
+            "FILE a WAVE\nTRACK 100 AUDIO\nINDEX 01 00:00:00\n",
+            "FILE a WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:75\n",
+            "FILE a WAVE\nTRACK 01 AUDIO\nINDEX 02 00:00:00\n",
+            "FILE a WAVE\nTRACK 01 AUDIO\nINDEX 01 00:10:00\nINDEX 02 00:05:00\n",
+            "FILE a WAVE\nTRACK 01 AUDIO\nINDEX 01 20:00:00\n",
+        ] {
+            assert!(CueSheet::from_cue(text, &cdda()).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn cue_sheet_export() {
+        let index = |point_num, offset| CueSheetTrackIndex { offset, point_num };
+        let mut track = CueSheetTrack::new();
+        track.number = 1;
+        track.is_audio = false;
+        track.pre_emphasis = true;
+        track.isrc = "USRC17607839".to_owned();
+        // an offset between CD frames is rounded down
+        track.offset = 588 * (75 * 61 + 74) + 587;
+        track.indices = vec![index(0, 0), index(1, 588 * 75)];
+        let mut lead_out = CueSheetTrack::new();
+        lead_out.number = CD_LEAD_OUT;
+        lead_out.offset = 44100 * 300;
+
+        let mut cuesheet = CueSheet::new();
+        cuesheet.catalog_num = "1234567890123".to_owned();
+        cuesheet.tracks = vec![track, lead_out];
+        assert_eq!(
+            cuesheet.to_cue("album.flac"),
+            "CATALOG 1234567890123
+FILE \"album.flac\" WAVE
+  TRACK 01 DATA
+    FLAGS PRE
+    ISRC USRC17607839
+    INDEX 00 01:01:74
+    INDEX 01 01:02:74
+REM FLAC__lead-in 0
+REM FLAC__lead-out 170 13230000
+"
+        );
+
+        // other media use sample numbers, and a last track with index points is no lead-out
+        cuesheet.is_cd = false;
+        cuesheet.tracks.pop();
+        cuesheet.tracks[0].is_audio = true;
+        cuesheet.tracks[0].pre_emphasis = false;
+        cuesheet.tracks[0].offset = 1000;
+        assert!(cuesheet.to_cue("a.flac").ends_with(
+            "  TRACK 01 AUDIO
+    ISRC USRC17607839
+    INDEX 00 1000
+    INDEX 01 45100
+REM FLAC__lead-in 0
+"
+        ));
+    }
+
+    #[test]
+    fn cue_sheet_validation() {
+        let messages = |cuesheet: &CueSheet, streaminfo: &StreamInfo| {
+            cuesheet
+                .validate(streaminfo)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        let track = |number, offset, points: &[u8]| {
+            let mut track = CueSheetTrack::new();
+            track.number = number;
+            track.offset = offset;
+            track.indices = points
+                .iter()
+                .map(|&point_num| CueSheetTrackIndex {
+                    offset: point_num as u64 * 588,
+                    point_num,
+                })
+                .collect();
+            track
+        };
+
+        let mut cuesheet = CueSheet::new();
+        cuesheet.is_cd = false;
+        assert_eq!(
+            messages(&cuesheet, &cdda()),
+            &["cuesheet has no lead-out track"]
+        );
+
+        cuesheet.tracks = vec![
+            track(1, 0, &[0, 1]),
+            track(2, 44100, &[1]),
+            track(LEAD_OUT, 44100 * 600, &[]),
+        ];
+        assert!(messages(&cuesheet, &cdda()).is_empty());
+        // the length of the stream is only checked when it is known
+        let mut streaminfo = cdda();
+        streaminfo.total_samples += 1;
+        assert_eq!(
+            messages(&cuesheet, &streaminfo),
+            &["track[2]: cuesheet lead-out is not at the end of the stream"]
+        );
+        streaminfo.total_samples = 0;
+        assert!(messages(&cuesheet, &streaminfo).is_empty());
+
+        cuesheet.tracks = vec![
+            track(0, 0, &[1]),
+            track(LEAD_OUT, 100, &[2, 3]),
+            track(5, 50, &[1, 3]),
+            track(4, 10_000, &[]),
+            track(LEAD_OUT, 44100 * 600, &[1]),
+        ];
+        cuesheet.tracks[0].isrc = "USRC1760783".to_owned();
+        assert_eq!(
+            messages(&cuesheet, &cdda()),
+            &[
+                "track[0]: cuesheet track number is reserved",
+                "track[0]: cuesheet track numbers do not increase",
+                "track[0]: cuesheet track ISRC is not 12 alphanumeric characters",
+                "track[1]: cuesheet track number is reserved",
+                "track[1]: cuesheet track index numbers do not start at 0 or 1",
+                "track[2]: cuesheet track numbers do not increase",
+                "track[2]: cuesheet track index numbers are not sequential",
+                "track[2]: cuesheet offsets do not increase",
+                "track[3]: cuesheet track numbers do not increase",
+                "track[3]: cuesheet track has no index points",
+                "cuesheet has no lead-out track",
+            ]
+        );
+
+        // the CD-DA constraints only apply to CD cuesheets
+        cuesheet.is_cd = true;
+        cuesheet.num_leadin = 88200 + 1;
+        cuesheet.catalog_num = "123456789012X".to_owned();
+        cuesheet.tracks = vec![
+            track(1, 0, &[1]),
+            track(100, 1, &[1]),
+            track(CD_LEAD_OUT, 44100 * 600, &[]),
+        ];
+        let mut streaminfo = cdda();
+        streaminfo.sample_rate = 48000;
+        assert_eq!(
+            messages(&cuesheet, &streaminfo),
+            &[
+                "CD-DA cuesheet requires a sample rate of 44100 Hz",
+                "CD-DA cuesheet lead-in is not a whole number of CD frames",
+                "CD-DA cuesheet catalog number is not 13 digits",
+                "track[1]: CD-DA cuesheet track number is not from 1 to 99",
+                "track[1]: CD-DA cuesheet track offset is not on a CD frame boundary",
+            ]
+        );
+        cuesheet.num_leadin = 88200 - 588;
+        cuesheet.catalog_num = "1".repeat(129);
+        cuesheet.tracks = (1..=100)
+            .map(|number| track(number, number as u64 * 588 * 75, &[1]))
+            .chain(Some(track(LEAD_OUT, 44100 * 600, &[])))
+            .collect();
+        assert_eq!(
+            messages(&cuesheet, &cdda()),
+            &[
+                "CD-DA cuesheet has a lead-in of less than two seconds",
+                "CD-DA cuesheet catalog number is not 13 digits",
+                "cuesheet catalog number is longer than 128 bytes",
+                "CD-DA cuesheet has more than 99 tracks",
+                "track[99]: CD-DA cuesheet track number is not from 1 to 99",
+                "cuesheet has no lead-out track",
+            ]
+        );
+    }
+}
@@ -1,21 +1,45 @@
+use crate::raw::BlockType;
+
+use alloc::string::{self, String};
+use core::fmt;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
+#[cfg(feature = "std")]
 use std::io;
-use std::string;
 
 /// Type alias for the result of tag operations.
-pub type Result<T> = ::std::result::Result<T, Error>;
+pub type Result<T> = ::core::result::Result<T, Error>;
 
 /// Kinds of errors that may occur while performing metadata operations.
 #[derive(Debug)]
 pub enum ErrorKind {
     /// An error kind indicating that an IO error has occurred. Contains the original io::Error.
+    #[cfg(feature = "std")]
     Io(io::Error),
     /// An error kind indicating that a string decoding error has occurred. Contains the invalid
     /// bytes.
     StringDecoding(string::FromUtf8Error),
     /// An error kind indicating that some input was invalid.
     InvalidInput,
+    /// An error kind indicating that a block ended before all of its contents could be read.
+    Truncated,
+    /// An error kind indicating that the length of a block is not valid for its type.
+    InvalidLength,
+    /// An error kind indicating that a tag which was not read from a file was saved without
+    /// specifying a path.
+    NoPath,
+    /// An error kind indicating that the input is not a FLAC stream, such as an empty file, a
+    /// file without the `fLaC` marker or an Ogg stream of another codec.
+    NotFlac,
+}
+
+/// A violation of the FLAC specification which was tolerated while reading a tag.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseWarning {
+    /// The offset from the start of the file of the block in which the violation was found.
+    pub offset: u64,
+    /// A human readable string describing the violation.
+    pub message: String,
 }
 
 /// A structure able to represent any error that may occur while performing metadata operations.
@@ -24,15 +48,56 @@ pub struct Error {
     pub kind: ErrorKind,
     /// A human readable string describing the error.
     pub description: &'static str,
+    /// The type of the block in which the error occurred, if it occurred while reading a block.
+    pub block_type: Option<BlockType>,
+    /// The offset from the start of the file of the block in which the error occurred, if it
+    /// occurred while reading a block.
+    pub offset: Option<u64>,
 }
 
 impl Error {
     /// Creates a new `Error` using the error kind and description.
     pub fn new(kind: ErrorKind, description: &'static str) -> Error {
-        Error { kind, description }
+        Error {
+            kind,
+            description,
+            block_type: None,
+            offset: None,
+        }
+    }
+
+    /// Records the block in which the error occurred, keeping anything already recorded.
+    pub(crate) fn in_block(mut self, block_type: Option<BlockType>, offset: u64) -> Error {
+        self.block_type = self.block_type.or(block_type);
+        self.offset = self.offset.or(Some(offset));
+        self
+    }
+
+    /// Writes the error, followed by the block in which it occurred if known.
+    fn write(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        if !self.description.is_empty() {
+            write!(out, "{:?}: {}", self.kind, self.description)?;
+        } else {
+            match self.kind {
+                #[cfg(feature = "std")]
+                ErrorKind::Io(ref err) => write!(out, "{}", err)?,
+                ErrorKind::StringDecoding(ref err) => write!(out, "{}", err)?,
+                _ => write!(out, "{:?}", self.kind)?,
+            }
+        }
+
+        match (self.block_type, self.offset) {
+            (Some(block_type), Some(offset)) => {
+                write!(out, " in {:?} block at offset {}", block_type, offset)
+            }
+            (None, Some(offset)) => write!(out, " in block at offset {}", offset),
+            (Some(block_type), None) => write!(out, " in {:?} block", block_type),
+            _ => Ok(()),
+        }
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self.kind {
@@ -60,11 +125,14 @@ impl error::Error for Error {
     }
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
         Error {
             kind: ErrorKind::Io(err),
             description: "",
+            block_type: None,
+            offset: None,
         }
     }
 }
@@ -74,30 +142,20 @@ impl From<string::FromUtf8Error> for Error {
         Error {
             kind: ErrorKind::StringDecoding(err),
             description: "",
+            block_type: None,
+            offset: None,
         }
     }
 }
 
 impl fmt::Debug for Error {
     fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
-        if self.description != "" {
-            write!(out, "{:?}: {}", self.kind, self.description)
-        } else if let Some(source) = error::Error::source(self) {
-            write!(out, "{}", source)
-        } else {
-            write!(out, "{:?}", self.kind)
-        }
+        self.write(out)
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
-        if self.description != "" {
-            write!(out, "{:?}: {}", self.kind, self.description)
-        } else if let Some(source) = error::Error::source(self) {
-            write!(out, "{}", source)
-        } else {
-            write!(out, "{:?}", self.kind)
-        }
+        self.write(out)
     }
 }
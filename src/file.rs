@@ -0,0 +1,122 @@
+use crate::block::StreamInfo;
+use crate::error::Result;
+use crate::options::{ReadOptions, WriteOptions};
+use crate::tag::Tag;
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+use std::fs::File;
+use std::io::{self, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// A FLAC file on disk together with its metadata tag.
+///
+/// The file is kept open for the lifetime of the `FlacFile`, so repeated saves reuse the same
+/// handle.
+pub struct FlacFile {
+    /// The path of the file.
+    path: PathBuf,
+    /// The tag read from the file.
+    tag: Tag,
+}
+
+impl FlacFile {
+    /// Attempts to open the FLAC file at the specified path and read its tag.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<FlacFile> {
+        FlacFile::open_with(path, &ReadOptions::new())
+    }
+
+    /// Attempts to open the FLAC file at the specified path and read its tag using the specified
+    /// options.
+    pub fn open_with<P: AsRef<Path>>(path: P, options: &ReadOptions) -> Result<FlacFile> {
+        let mut options = options.clone();
+        options.retain_file = true;
+
+        let tag = Tag::read_from_path_with(&path, &options)?;
+        Ok(FlacFile {
+            path: path.as_ref().to_path_buf(),
+            tag,
+        })
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns a reference to the tag of the file.
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    /// Returns a mutable reference to the tag of the file. Changes are written by `save`.
+    pub fn tag_mut(&mut self) -> &mut Tag {
+        &mut self.tag
+    }
+
+    /// Consumes the `FlacFile`, returning its tag.
+    pub fn into_tag(self) -> Tag {
+        self.tag
+    }
+
+    /// Returns a reference to the streaminfo block of the file.
+    /// Returns `None` if the file has no streaminfo block.
+    pub fn stream_info(&self) -> Option<&StreamInfo> {
+        self.tag.get_streaminfo()
+    }
+
+    /// Attempts to save the tag back to the file using the specified options.
+    pub fn save(&mut self, options: &WriteOptions) -> Result<()> {
+        self.tag.write_to_path_with(&self.path, options)
+    }
+
+    /// Copies the audio frames which follow the metadata to the writer, without any metadata.
+    /// Returns the number of bytes copied.
+    pub fn copy_audio_to(&self, writer: &mut dyn Write) -> Result<u64> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let max_junk_len = self.tag.junk().map_or(0, |junk| junk.end - junk.start);
+        crate::block::read_ident_with(&mut reader, max_junk_len)?;
+
+        loop {
+            let header = reader.read_u32::<BigEndian>()?;
+            let length = header & 0xFF_FF_FF;
+            reader.seek(SeekFrom::Current(length as i64))?;
+
+            if (header >> 24) & 0x80 != 0 {
+                break;
+            }
+        }
+
+        Ok(io::copy(&mut reader, writer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_path, test_stream};
+
+    #[test]
+    fn save_and_copy_audio() {
+        let path = test_path("flac-file.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut file = FlacFile::open(&path).unwrap();
+        assert_eq!(file.stream_info().unwrap().num_channels, 2);
+
+        file.tag_mut().set_vorbis("TITLE", vec!["title"]);
+        file.save(&WriteOptions::new()).unwrap();
+
+        let mut audio = Vec::new();
+        assert_eq!(file.copy_audio_to(&mut audio).unwrap(), 5);
+        assert_eq!(audio, b"audio");
+
+        let file = FlacFile::open(&path).unwrap();
+        assert_eq!(
+            file.tag().get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+use crate::block::{Application, Block};
+use crate::error::{Error, ErrorKind, Result};
+use crate::tag::Tag;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+/// The format of the file from which the foreign metadata was kept.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ForeignFormat {
+    /// A RIFF WAVE file, stored in `riff` application blocks.
+    Riff,
+    /// An AIFF or AIFF-C file, stored in `aiff` application blocks.
+    Aiff,
+}
+
+impl ForeignFormat {
+    /// Returns the ID of the application blocks in which the chunks of the format are stored.
+    pub fn application_id(self) -> &'static [u8; 4] {
+        match self {
+            ForeignFormat::Riff => b"riff",
+            ForeignFormat::Aiff => b"aiff",
+        }
+    }
+
+    /// Returns the format whose chunks are stored in application blocks with the ID.
+    pub fn from_application_id(id: &[u8]) -> Option<ForeignFormat> {
+        match id {
+            b"riff" => Some(ForeignFormat::Riff),
+            b"aiff" => Some(ForeignFormat::Aiff),
+            _ => None,
+        }
+    }
+}
+
+/// A chunk of the original file kept in an application block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ForeignChunk {
+    /// The chunk ID, e.g. `bext` for the broadcast audio extension of a Broadcast WAV file.
+    pub id: [u8; 4],
+    /// The length of the chunk data as stated in the chunk header.
+    pub size: u32,
+    /// The stored chunk data. Only the start of the chunk which held the audio is stored, as the
+    /// audio itself is encoded as FLAC.
+    pub data: Vec<u8>,
+}
+
+/// The chunks of a WAVE or AIFF file kept by `flac --keep-foreign-metadata`, which restores
+/// them when decoding.
+///
+/// The chunks are stored in application blocks, one block for each chunk in the order of the
+/// original file, after a block holding the header of the file. The blocks are written back
+/// untouched when a tag is saved, so the chunks survive retagging.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ForeignMetadata {
+    /// The format of the original file.
+    pub format: ForeignFormat,
+    /// The form type of the original file, e.g. `WAVE` or `AIFC`.
+    pub form_type: [u8; 4],
+    /// The chunks of the original file, in order.
+    pub chunks: Vec<ForeignChunk>,
+}
+
+impl ForeignMetadata {
+    /// Parses the chunks stored in the application blocks. Application blocks of other
+    /// applications are skipped. Returns `None` if there are no foreign metadata blocks.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::Application;
+    /// use metaflac::foreign::{ForeignFormat, ForeignMetadata};
+    ///
+    /// let header = Application { id: b"riff".to_vec(), data: b"RIFF\x24\0\0\0WAVE".to_vec() };
+    /// let bext = Application { id: b"riff".to_vec(), data: b"bext\x02\0\0\0hi".to_vec() };
+    ///
+    /// let foreign = ForeignMetadata::from_applications(vec![&header, &bext]).unwrap().unwrap();
+    /// assert_eq!(foreign.format, ForeignFormat::Riff);
+    /// assert_eq!(&foreign.form_type, b"WAVE");
+    /// assert_eq!(foreign.chunk(b"bext").unwrap().data, b"hi");
+    /// ```
+    pub fn from_applications<'a, I>(applications: I) -> Result<Option<ForeignMetadata>>
+    where
+        I: IntoIterator<Item = &'a Application>,
+    {
+        let invalid = || Error::new(ErrorKind::InvalidInput, "invalid foreign metadata block");
+
+        let mut foreign: Option<ForeignMetadata> = None;
+        for application in applications {
+            let format = match ForeignFormat::from_application_id(&application.id) {
+                Some(format) => format,
+                None => continue,
+            };
+            let data = &application.data[..];
+            if data.len() < 8 {
+                return Err(invalid());
+            }
+
+            let foreign = match foreign {
+                Some(ref mut foreign) if foreign.format == format => foreign,
+                Some(_) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "foreign metadata of more than one format",
+                    ))
+                }
+                // the first block holds the header of the file
+                None => {
+                    let is_header = match format {
+                        ForeignFormat::Riff => &data[..4] == b"RIFF" || &data[..4] == b"RF64",
+                        ForeignFormat::Aiff => &data[..4] == b"FORM",
+                    };
+                    if !is_header || data.len() < 12 {
+                        return Err(invalid());
+                    }
+                    let mut form_type = [0; 4];
+                    form_type.copy_from_slice(&data[8..12]);
+                    foreign = Some(ForeignMetadata {
+                        format,
+                        form_type,
+                        chunks: Vec::new(),
+                    });
+                    continue;
+                }
+            };
+
+            let mut id = [0; 4];
+            id.copy_from_slice(&data[..4]);
+            let size = match format {
+                ForeignFormat::Riff => LittleEndian::read_u32(&data[4..8]),
+                ForeignFormat::Aiff => BigEndian::read_u32(&data[4..8]),
+            };
+            foreign.chunks.push(ForeignChunk {
+                id,
+                size,
+                data: data[8..].to_vec(),
+            });
+        }
+        Ok(foreign)
+    }
+
+    /// Returns the first chunk with the ID.
+    pub fn chunk(&self, id: &[u8; 4]) -> Option<&ForeignChunk> {
+        self.chunks.iter().find(|chunk| &chunk.id == id)
+    }
+}
+
+impl Tag {
+    /// Returns the chunks of the original WAVE or AIFF file kept in the application blocks of the
+    /// tag, or `None` if the file was encoded without `--keep-foreign-metadata`.
+    pub fn foreign_metadata(&self) -> Result<Option<ForeignMetadata>> {
+        ForeignMetadata::from_applications(self.blocks().filter_map(|block| match *block {
+            Block::Application(ref application) => Some(application),
+            _ => None,
+        }))
+    }
+}
@@ -0,0 +1,221 @@
+use crate::error::Result;
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+/// The genres defined by the ID3v1 specification, indexed by genre number.
+const GENRES: [&str; 80] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychadelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+];
+
+/// An ID3v1 tag appended to the end of a file.
+///
+/// ID3v1 tags are not part of the FLAC format, but some taggers append them to FLAC files anyway.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Id3v1 {
+    /// The title of the track.
+    pub title: String,
+    /// The artist of the track.
+    pub artist: String,
+    /// The album of the track.
+    pub album: String,
+    /// The year of the release.
+    pub year: String,
+    /// A comment.
+    pub comment: String,
+    /// The track number, only present in ID3v1.1 tags.
+    pub track: Option<u8>,
+    /// The genre number, or `None` if the genre is unset.
+    pub genre: Option<u8>,
+}
+
+impl Id3v1 {
+    /// The length of an ID3v1 tag in bytes.
+    pub const LEN: u64 = 128;
+
+    /// Returns a new `Id3v1` with every field empty.
+    pub fn new() -> Id3v1 {
+        Id3v1::default()
+    }
+
+    /// Parses an ID3v1 tag. Returns `None` if the bytes are not an ID3v1 tag.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::id3v1::Id3v1;
+    ///
+    /// let mut bytes = b"TAG".to_vec();
+    /// bytes.extend(b"title");
+    /// bytes.resize(125, 0);
+    /// bytes.extend(&[0, 3, 17]);
+    ///
+    /// let id3v1 = Id3v1::from_bytes(&bytes).unwrap();
+    /// assert_eq!(id3v1.title, "title");
+    /// assert_eq!(id3v1.track, Some(3));
+    /// assert_eq!(id3v1.genre_name(), Some("Rock"));
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Option<Id3v1> {
+        if bytes.len() as u64 != Id3v1::LEN || &bytes[0..3] != b"TAG" {
+            return None;
+        }
+
+        // ID3v1.1 stores the track number in the last byte of the comment after a zero byte
+        let (comment, track) = if bytes[125] == 0 && bytes[126] != 0 {
+            (&bytes[97..125], Some(bytes[126]))
+        } else {
+            (&bytes[97..127], None)
+        };
+
+        Some(Id3v1 {
+            title: latin1_field(&bytes[3..33]),
+            artist: latin1_field(&bytes[33..63]),
+            album: latin1_field(&bytes[63..93]),
+            year: latin1_field(&bytes[93..97]),
+            comment: latin1_field(comment),
+            track,
+            genre: if bytes[127] == 0xFF {
+                None
+            } else {
+                Some(bytes[127])
+            },
+        })
+    }
+
+    /// Reads the ID3v1 tag at the end of the reader, if there is one. The reader position is left
+    /// at an unspecified position.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Option<Id3v1>> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        if len < Id3v1::LEN {
+            return Ok(None);
+        }
+
+        let mut bytes = [0; Id3v1::LEN as usize];
+        reader.seek(SeekFrom::Start(len - Id3v1::LEN))?;
+        reader.read_exact(&mut bytes)?;
+        Ok(Id3v1::from_bytes(&bytes))
+    }
+
+    /// Returns the name of the genre, or `None` if the genre is unset or not one of the genres
+    /// defined by the ID3v1 specification.
+    pub fn genre_name(&self) -> Option<&'static str> {
+        self.genre
+            .and_then(|genre| GENRES.get(genre as usize))
+            .cloned()
+    }
+
+    /// Returns the non-empty fields as vorbis comment keys and values.
+    pub(crate) fn comments(&self) -> Vec<(&'static str, String)> {
+        let mut comments = vec![
+            ("TITLE", self.title.clone()),
+            ("ARTIST", self.artist.clone()),
+            ("ALBUM", self.album.clone()),
+            ("DATE", self.year.clone()),
+            ("COMMENT", self.comment.clone()),
+        ];
+        if let Some(track) = self.track {
+            comments.push(("TRACKNUMBER", track.to_string()));
+        }
+        if let Some(genre) = self.genre_name() {
+            comments.push(("GENRE", genre.to_string()));
+        }
+        comments.retain(|(_, value)| !value.is_empty());
+        comments
+    }
+}
+
+/// Decodes a fixed length ISO-8859-1 field, dropping the padding after the value.
+fn latin1_field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let value: String = bytes[..end].iter().map(|&b| b as char).collect();
+    value.trim_end().to_string()
+}
+
+/// Truncates the ID3v1 tag from the end of the file, if there is one. Returns whether a tag was
+/// removed.
+pub(crate) fn strip(mut file: &File) -> Result<bool> {
+    if Id3v1::read_from(&mut file)?.is_none() {
+        return Ok(false);
+    }
+    let len = file.metadata()?.len();
+    file.set_len(len - Id3v1::LEN)?;
+    Ok(true)
+}
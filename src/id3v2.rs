@@ -0,0 +1,270 @@
+use crate::block::{Block, Picture, PictureType, VorbisComment};
+use crate::tag::Tag;
+
+use id3::frame::{
+    Comment, Content, ExtendedText, Frame, Lyrics, Picture as Id3Picture,
+    PictureType as Id3PictureType,
+};
+use id3::TagLike;
+
+/// The ID3v2 text frames and the vorbis comment keys they correspond to.
+const TEXT_FRAMES: [(&str, &str); 22] = [
+    ("TIT2", "TITLE"),
+    ("TIT1", "GROUPING"),
+    ("TIT3", "SUBTITLE"),
+    ("TPE1", "ARTIST"),
+    ("TPE2", "ALBUMARTIST"),
+    ("TPE3", "CONDUCTOR"),
+    ("TPE4", "REMIXER"),
+    ("TALB", "ALBUM"),
+    ("TCOM", "COMPOSER"),
+    ("TEXT", "LYRICIST"),
+    ("TCON", "GENRE"),
+    ("TDRC", "DATE"),
+    ("TSRC", "ISRC"),
+    ("TPUB", "ORGANIZATION"),
+    ("TCOP", "COPYRIGHT"),
+    ("TBPM", "BPM"),
+    ("TENC", "ENCODED-BY"),
+    ("TSSE", "ENCODER"),
+    ("TMED", "MEDIA"),
+    ("TSOA", "ALBUMSORT"),
+    ("TSOP", "ARTISTSORT"),
+    ("TSOT", "TITLESORT"),
+];
+
+/// The vorbis comment keys which are combined into the TRCK and TPOS frames.
+const NUMBER_FRAMES: [(&str, &str, &str); 2] = [
+    ("TRCK", "TRACKNUMBER", "TRACKTOTAL"),
+    ("TPOS", "DISCNUMBER", "DISCTOTAL"),
+];
+
+impl Tag {
+    /// Converts the vorbis comments and pictures to an `id3::Tag`.
+    ///
+    /// Well known keys map to their ID3v2 text frames, e.g. TITLE to TIT2, TRACKNUMBER and
+    /// TRACKTOTAL to TRCK, COMMENT to COMM and LYRICS to USLT. Any other key is written as a TXXX
+    /// frame described by the key. Multiple values are separated by null bytes as in ID3v2.4.
+    /// Pictures become APIC frames.
+    ///
+    /// # Example
+    /// ```
+    /// use id3::TagLike;
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// tag.set_vorbis("TRACKNUMBER", vec!["3"]);
+    /// tag.set_vorbis("TRACKTOTAL", vec!["12"]);
+    ///
+    /// let id3 = tag.to_id3();
+    /// assert_eq!(id3.title(), Some("title"));
+    /// assert_eq!(id3.track(), Some(3));
+    /// assert_eq!(id3.total_tracks(), Some(12));
+    ///
+    /// let tag = Tag::from_id3(&id3);
+    /// assert_eq!(tag.get_vorbis("TRACKTOTAL").unwrap().collect::<Vec<_>>(), &["12"]);
+    /// ```
+    pub fn to_id3(&self) -> id3::Tag {
+        let mut id3 = id3::Tag::new();
+
+        if let Some(comments) = self.vorbis_comments() {
+            let mut entries: Vec<(&str, Vec<&str>)> = Vec::new();
+            for (key, value) in comments.entries() {
+                match entries.iter_mut().find(|(other, _)| *other == key) {
+                    Some((_, values)) => values.push(value),
+                    None => entries.push((key, vec![value])),
+                }
+            }
+            let values = |key: &str| {
+                entries
+                    .iter()
+                    .find(|(other, _)| *other == key)
+                    .map(|(_, values)| values.join("\0"))
+            };
+
+            for &(id, number_key, total_key) in NUMBER_FRAMES.iter() {
+                let text = match (values(number_key), values(total_key)) {
+                    (Some(number), Some(total)) => format!("{}/{}", number, total),
+                    (Some(number), None) => number,
+                    (None, Some(total)) => format!("/{}", total),
+                    (None, None) => continue,
+                };
+                id3.add_frame(Frame::text(id, text));
+            }
+
+            for (key, key_values) in entries.iter() {
+                let key = *key;
+                if NUMBER_FRAMES
+                    .iter()
+                    .any(|&(_, number_key, total_key)| key == number_key || key == total_key)
+                {
+                    continue;
+                }
+
+                if let Some(&(id, _)) = TEXT_FRAMES.iter().find(|&&(_, other)| other == key) {
+                    id3.add_frame(Frame::text(id, key_values.join("\0")));
+                    continue;
+                }
+
+                match key {
+                    "COMMENT" => {
+                        for (i, value) in key_values.iter().enumerate() {
+                            // frames are only distinct by their description
+                            id3.add_frame(Comment {
+                                lang: "eng".to_string(),
+                                description: if i == 0 { String::new() } else { i.to_string() },
+                                text: value.to_string(),
+                            });
+                        }
+                    }
+                    "LYRICS" | "UNSYNCEDLYRICS" => {
+                        id3.add_frame(Lyrics {
+                            lang: "eng".to_string(),
+                            description: String::new(),
+                            text: key_values.join("\n"),
+                        });
+                    }
+                    _ => {
+                        id3.add_frame(ExtendedText {
+                            description: key.to_string(),
+                            value: key_values.join("\0"),
+                        });
+                    }
+                }
+            }
+        }
+
+        for picture in self.pictures() {
+            id3.add_frame(Id3Picture {
+                mime_type: picture.mime_type.clone(),
+                picture_type: to_id3_picture_type(picture.picture_type),
+                description: picture.description.clone(),
+                data: picture.data.clone(),
+            });
+        }
+
+        id3
+    }
+
+    /// Converts the frames of an `id3::Tag` to vorbis comments and pictures. This is the inverse
+    /// of `Tag::to_id3`. Frames which have no vorbis comment equivalent are ignored, as are TXXX
+    /// frames whose description is not a valid vorbis comment key.
+    pub fn from_id3(id3: &id3::Tag) -> Tag {
+        let mut tag = Tag::new();
+
+        for frame in id3.frames() {
+            match *frame.content() {
+                Content::Text(ref text) => {
+                    if let Some(&(_, number_key, total_key)) =
+                        NUMBER_FRAMES.iter().find(|&&(id, _, _)| id == frame.id())
+                    {
+                        let (number, total) = match text.find('/') {
+                            Some(i) => (&text[..i], &text[i + 1..]),
+                            None => (&text[..], ""),
+                        };
+                        add(&mut tag, number_key, number.trim());
+                        add(&mut tag, total_key, total.trim());
+                    } else if let Some(&(_, key)) =
+                        TEXT_FRAMES.iter().find(|&&(id, _)| id == frame.id())
+                    {
+                        for value in text.split('\0') {
+                            add(&mut tag, key, value);
+                        }
+                    }
+                }
+                Content::ExtendedText(ref text)
+                    if VorbisComment::is_valid_key(&text.description) =>
+                {
+                    let key = text.description.to_ascii_uppercase();
+                    for value in text.value.split('\0') {
+                        add(&mut tag, &key, value);
+                    }
+                }
+                Content::Comment(ref comment) => add(&mut tag, "COMMENT", &comment.text),
+                Content::Lyrics(ref lyrics) => add(&mut tag, "LYRICS", &lyrics.text),
+                Content::Picture(ref id3_picture) => {
+                    let mut picture = Picture::new();
+                    picture.mime_type = id3_picture.mime_type.clone();
+                    picture.picture_type =
+                        PictureType::from_u32(u8::from(id3_picture.picture_type) as u32)
+                            .unwrap_or(PictureType::Other);
+                    picture.description = id3_picture.description.clone();
+                    picture.data = id3_picture.data.clone();
+                    tag.push_block(Block::Picture(picture));
+                }
+                _ => {}
+            }
+        }
+
+        tag
+    }
+}
+
+/// Appends a non-empty value to the values of the vorbis comment key.
+fn add(tag: &mut Tag, key: &str, value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    let mut values = tag.get_vorbis_values(key).unwrap_or_default();
+    values.push(value.to_string());
+    tag.set_vorbis(key, values);
+}
+
+/// Returns the ID3v2 picture type with the same number as the FLAC picture type.
+fn to_id3_picture_type(picture_type: PictureType) -> Id3PictureType {
+    match picture_type {
+        PictureType::Other => Id3PictureType::Other,
+        PictureType::Icon => Id3PictureType::Icon,
+        PictureType::OtherIcon => Id3PictureType::OtherIcon,
+        PictureType::CoverFront => Id3PictureType::CoverFront,
+        PictureType::CoverBack => Id3PictureType::CoverBack,
+        PictureType::Leaflet => Id3PictureType::Leaflet,
+        PictureType::Media => Id3PictureType::Media,
+        PictureType::LeadArtist => Id3PictureType::LeadArtist,
+        PictureType::Artist => Id3PictureType::Artist,
+        PictureType::Conductor => Id3PictureType::Conductor,
+        PictureType::Band => Id3PictureType::Band,
+        PictureType::Composer => Id3PictureType::Composer,
+        PictureType::Lyricist => Id3PictureType::Lyricist,
+        PictureType::RecordingLocation => Id3PictureType::RecordingLocation,
+        PictureType::DuringRecording => Id3PictureType::DuringRecording,
+        PictureType::DuringPerformance => Id3PictureType::DuringPerformance,
+        PictureType::ScreenCapture => Id3PictureType::ScreenCapture,
+        PictureType::BrightFish => Id3PictureType::BrightFish,
+        PictureType::Illustration => Id3PictureType::Illustration,
+        PictureType::BandLogo => Id3PictureType::BandLogo,
+        PictureType::PublisherLogo => Id3PictureType::PublisherLogo,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id3_round_trip() {
+        let mut tag = Tag::new();
+        tag.set_vorbis("ARTIST", vec!["a", "b"]);
+        tag.set_vorbis("DISCNUMBER", vec!["2"]);
+        tag.set_vorbis("COMMENT", vec!["one", "two"]);
+        tag.set_vorbis("MOOD", vec!["calm"]);
+        tag.add_picture("image/png", PictureType::CoverBack, vec![0x89, 0x50]);
+
+        let id3 = tag.to_id3();
+        assert_eq!(id3.get("TPE1").unwrap().content().text(), Some("a\0b"));
+        assert_eq!(id3.disc(), Some(2));
+        assert_eq!(id3.comments().count(), 2);
+        let picture = id3.pictures().next().unwrap();
+        assert_eq!(picture.picture_type, Id3PictureType::CoverBack);
+
+        let converted = Tag::from_id3(&id3);
+        for key in ["ARTIST", "DISCNUMBER", "COMMENT", "MOOD"] {
+            assert_eq!(converted.get_vorbis_values(key), tag.get_vorbis_values(key));
+        }
+        assert_eq!(
+            converted.pictures().collect::<Vec<_>>(),
+            tag.pictures().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crate::block::Picture;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+impl Picture {
+    /// Fills in the width, height, color depth and number of indexed colors of the picture by
+    /// parsing the header of its JPEG, PNG, GIF or WebP image data. Returns false and leaves the
+    /// fields unchanged if the data is not recognized.
+    ///
+    /// The values follow the conventions of the reference `metaflac` tool: the depth is in bits
+    /// per pixel and the color count is 0 for images without a palette.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::Picture;
+    ///
+    /// let mut picture = Picture::new();
+    /// picture.data = b"GIF89a\x20\x00\x10\x00\xF7\x00\x00".to_vec();
+    /// assert!(picture.read_dimensions());
+    ///
+    /// assert_eq!((picture.width, picture.height), (32, 16));
+    /// assert_eq!((picture.depth, picture.num_colors), (24, 256));
+    /// ```
+    pub fn read_dimensions(&mut self) -> bool {
+        let data = &self.data[..];
+        let dimensions = match Picture::detect_mime_type(data) {
+            Some("image/png") => png(data),
+            Some("image/jpeg") => jpeg(data),
+            Some("image/gif") => gif(data),
+            Some("image/webp") => webp(data),
+            _ => None,
+        };
+
+        match dimensions {
+            Some((width, height, depth, num_colors)) => {
+                self.width = width;
+                self.height = height;
+                self.depth = depth;
+                self.num_colors = num_colors;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The width, height, depth and number of colors of an image.
+type Dimensions = (u32, u32, u32, u32);
+
+fn png(data: &[u8]) -> Option<Dimensions> {
+    // the IHDR chunk always comes first
+    if data.len() < 26 || &data[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = BigEndian::read_u32(&data[16..20]);
+    let height = BigEndian::read_u32(&data[20..24]);
+    let bit_depth = data[24] as u32;
+    let (depth, num_colors) = match data[25] {
+        // the palette entries are always RGB888, whatever the bit depth of the indices
+        3 => (24, 1u32.checked_shl(bit_depth)?),
+        0 => (bit_depth, 0),
+        2 => (bit_depth * 3, 0),
+        4 => (bit_depth * 2, 0),
+        6 => (bit_depth * 4, 0),
+        _ => return None,
+    };
+    Some((width, height, depth, num_colors))
+}
+
+fn jpeg(data: &[u8]) -> Option<Dimensions> {
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xFF {
+            return None;
+        }
+        let marker = data[i + 1];
+        // fill bytes before a marker
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        // markers without a length
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            i += 2;
+            continue;
+        }
+
+        let len = BigEndian::read_u16(&data[i + 2..i + 4]) as usize;
+        // the start of frame markers, except DHT, JPG and DAC which share the range
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            if i + 10 > data.len() {
+                return None;
+            }
+            let precision = data[i + 4] as u32;
+            let height = BigEndian::read_u16(&data[i + 5..i + 7]) as u32;
+            let width = BigEndian::read_u16(&data[i + 7..i + 9]) as u32;
+            let components = data[i + 9] as u32;
+            return Some((width, height, precision * components, 0));
+        }
+        i += 2 + len;
+    }
+    None
+}
+
+fn gif(data: &[u8]) -> Option<Dimensions> {
+    if data.len() < 11 {
+        return None;
+    }
+    let width = LittleEndian::read_u16(&data[6..8]) as u32;
+    let height = LittleEndian::read_u16(&data[8..10]) as u32;
+    let flags = data[10];
+    // the size of the global color table
+    let num_colors = if flags & 0x80 != 0 {
+        1 << ((flags & 0x07) + 1)
+    } else {
+        0
+    };
+    Some((width, height, 24, num_colors))
+}
+
+fn webp(data: &[u8]) -> Option<Dimensions> {
+    if data.len() < 30 {
+        return None;
+    }
+    match &data[12..16] {
+        b"VP8 " => {
+            // a key frame starts with a frame tag and a start code
+            if data[23..26] != [0x9D, 0x01, 0x2A] {
+                return None;
+            }
+            let width = LittleEndian::read_u16(&data[26..28]) as u32 & 0x3FFF;
+            let height = LittleEndian::read_u16(&data[28..30]) as u32 & 0x3FFF;
+            Some((width, height, 24, 0))
+        }
+        b"VP8L" => {
+            if data[20] != 0x2F {
+                return None;
+            }
+            let bits = LittleEndian::read_u32(&data[21..25]);
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            let depth = if bits & (1 << 28) != 0 { 32 } else { 24 };
+            Some((width, height, depth, 0))
+        }
+        b"VP8X" => {
+            let width = LittleEndian::read_u24(&data[24..27]) + 1;
+            let height = LittleEndian::read_u24(&data[27..30]) + 1;
+            let depth = if data[20] & 0x10 != 0 { 32 } else { 24 };
+            Some((width, height, depth, 0))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_headers() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0DIHDR".to_vec();
+        png.extend(&[0, 0, 1, 0, 0, 0, 0, 0x80, 4, 3]);
+        assert_eq!(super::png(&png), Some((256, 128, 24, 16)));
+        png[25] = 6;
+        assert_eq!(super::png(&png), Some((256, 128, 16, 0)));
+
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00,
+            0x20, 0x00, 0x40, 0x03,
+        ];
+        assert_eq!(super::jpeg(&jpeg), Some((64, 32, 24, 0)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8L\0\0\0\0\x2F".to_vec();
+        webp.extend((9u32 | (4 << 14) | (1 << 28)).to_le_bytes());
+        webp.resize(30, 0);
+        let mut picture = Picture::new();
+        picture.data = webp;
+        assert!(picture.read_dimensions());
+        assert_eq!((picture.width, picture.height, picture.depth), (10, 5, 32));
+
+        picture.data = b"text".to_vec();
+        assert!(!picture.read_dimensions());
+        assert_eq!(picture.width, 10);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::error::{Error, ErrorKind, Result};
+use crate::tag::Tag;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// The indexed metadata of a single file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The modification time of the file in nanoseconds since the unix epoch.
+    pub modified: u64,
+    /// The length of the file in bytes.
+    pub len: u64,
+    /// The vorbis comments of the file.
+    pub comments: BTreeMap<String, Vec<String>>,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Number of channels.
+    pub num_channels: u8,
+    /// Bits per sample.
+    pub bits_per_sample: u8,
+    /// Total samples in stream.
+    pub total_samples: u64,
+    /// Hex encoded MD5 signature of the unencoded audio data.
+    pub md5: String,
+}
+
+/// The outcome of updating an index.
+#[derive(Debug, Default)]
+pub struct IndexUpdate {
+    /// The number of files which were added to the index.
+    pub added: usize,
+    /// The number of files which were read again because they changed.
+    pub updated: usize,
+    /// The number of files which were removed from the index because they no longer exist.
+    pub removed: usize,
+    /// The number of files which were unchanged.
+    pub unchanged: usize,
+    /// Files which could not be read.
+    pub errors: Vec<(PathBuf, Error)>,
+}
+
+/// A queryable index of the metadata of the FLAC files in a directory tree, stored as JSON Lines.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Index {
+    entries: BTreeMap<PathBuf, IndexEntry>,
+}
+
+impl Index {
+    /// Returns a new empty `Index`.
+    pub fn new() -> Index {
+        Index {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the entry for the specified path.
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&IndexEntry> {
+        self.entries.get(path.as_ref())
+    }
+
+    /// Returns an iterator over the entries in the index, ordered by path.
+    pub fn entries(&self) -> impl Iterator<Item = &IndexEntry> {
+        self.entries.values()
+    }
+
+    /// Returns the entries which have the specified vorbis comment key and value.
+    pub fn find<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+    ) -> impl Iterator<Item = &'a IndexEntry> + 'a {
+        let key = key.to_ascii_uppercase();
+        self.entries().filter(move |entry| {
+            entry
+                .comments
+                .get(&key)
+                .is_some_and(|values| values.iter().any(|v| v == value))
+        })
+    }
+
+    /// Scans the directory tree at the specified root and brings the entries for it up to date.
+    /// Files are only read again when their modification time or length has changed.
+    pub fn update<P: AsRef<Path>>(&mut self, root: P) -> Result<IndexUpdate> {
+        let mut update = IndexUpdate::default();
+
+        let mut paths = Vec::new();
+        find_flac_files(root.as_ref(), &mut paths)?;
+
+        let root = root.as_ref();
+        let before = self.entries.len();
+        let found: BTreeSet<_> = paths.iter().cloned().collect();
+        self.entries
+            .retain(|path, _| !path.starts_with(root) || found.contains(path));
+        update.removed = before - self.entries.len();
+
+        for path in paths {
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    update.errors.push((path, err.into()));
+                    continue;
+                }
+            };
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |duration| duration.as_nanos() as u64);
+
+            let existing = self.entries.get(&path);
+            if let Some(entry) = existing {
+                if entry.modified == modified && entry.len == metadata.len() {
+                    update.unchanged += 1;
+                    continue;
+                }
+            }
+            let is_update = existing.is_some();
+
+            let tag = match Tag::read_from_path(&path) {
+                Ok(tag) => tag,
+                Err(err) => {
+                    self.entries.remove(&path);
+                    update.errors.push((path, err));
+                    continue;
+                }
+            };
+
+            let mut entry = IndexEntry {
+                path: path.clone(),
+                modified,
+                len: metadata.len(),
+                comments: BTreeMap::new(),
+                sample_rate: 0,
+                num_channels: 0,
+                bits_per_sample: 0,
+                total_samples: 0,
+                md5: String::new(),
+            };
+            if let Some(comments) = tag.vorbis_comments() {
+                entry.comments = comments
+                    .keys()
+                    .into_iter()
+                    .map(|key| {
+                        let values = comments.get(&key).unwrap_or_default();
+                        let values = values.into_iter().map(String::from).collect();
+                        (key, values)
+                    })
+                    .collect();
+            }
+            if let Some(streaminfo) = tag.get_streaminfo() {
+                entry.sample_rate = streaminfo.sample_rate;
+                entry.num_channels = streaminfo.num_channels;
+                entry.bits_per_sample = streaminfo.bits_per_sample;
+                entry.total_samples = streaminfo.total_samples;
+                entry.md5 = hex::encode(&streaminfo.md5);
+            }
+
+            if is_update {
+                update.updated += 1;
+            } else {
+                update.added += 1;
+            }
+            self.entries.insert(path, entry);
+        }
+
+        Ok(update)
+    }
+
+    /// Attempts to read an index from JSON Lines.
+    pub fn read_from(reader: &mut dyn BufRead) -> Result<Index> {
+        let mut index = Index::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: IndexEntry = serde_json::from_str(&line)
+                .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid index entry"))?;
+            index.entries.insert(entry.path.clone(), entry);
+        }
+        Ok(index)
+    }
+
+    /// Attempts to read an index from the file at the specified path. Returns an empty index if
+    /// the file does not exist.
+    pub fn read_from_path<P: AsRef<Path>>(path: P) -> Result<Index> {
+        match File::open(path) {
+            Ok(file) => Index::read_from(&mut BufReader::new(file)),
+            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Index::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Attempts to write the index as JSON Lines.
+    pub fn write_to(&self, writer: &mut dyn Write) -> Result<()> {
+        for entry in self.entries() {
+            let line = serde_json::to_string(entry)
+                .map_err(|_| Error::new(ErrorKind::InvalidInput, "unable to encode index entry"))?;
+            writer.write_all(line.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Attempts to write the index to the file at the specified path.
+    pub fn write_to_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Recursively collects the paths of the files with a `flac` extension under the path.
+fn find_flac_files(path: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_dir() {
+        let mut children = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        children.sort();
+        for child in children {
+            find_flac_files(&child, paths)?;
+        }
+    } else if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("flac"))
+    {
+        paths.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_path, test_stream};
+    use std::fs::OpenOptions;
+    use std::time::Duration;
+
+    #[test]
+    fn incremental_update() {
+        let root = test_path("index");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("album")).unwrap();
+
+        let path = root.join("album").join("track.flac");
+        fs::write(&path, test_stream()).unwrap();
+        fs::write(root.join("notes.txt"), b"not flac").unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("ARTIST", vec!["artist"]);
+        tag.save().unwrap();
+
+        let mut index = Index::new();
+        let update = index.update(&root).unwrap();
+        assert_eq!(update.added, 1);
+        assert_eq!(index.get(&path).unwrap().bits_per_sample, 16);
+        assert_eq!(index.find("artist", "artist").count(), 1);
+
+        let update = index.update(&root).unwrap();
+        assert_eq!(update.unchanged, 1);
+
+        // a rewrite within the same second with the same length is still noticed
+        let second = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_modified(second + Duration::from_millis(100))
+            .unwrap();
+        assert_eq!(index.update(&root).unwrap().updated, 1);
+        file.set_modified(second + Duration::from_millis(200))
+            .unwrap();
+        let update = index.update(&root).unwrap();
+        assert_eq!((update.updated, update.unchanged), (1, 0));
+        drop(file);
+
+        let mut lines = Vec::new();
+        index.write_to(&mut lines).unwrap();
+        assert_eq!(Index::read_from(&mut &lines[..]).unwrap(), index);
+
+        fs::remove_file(&path).unwrap();
+        let update = index.update(&root).unwrap();
+        assert_eq!(update.removed, 1);
+        assert_eq!(index.entries().count(), 0);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use crate::error::{Error, ErrorKind, Result};
+
+use std::convert::TryInto;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The application id of the APPLICATION block into which a journal is written.
+pub const JOURNAL_APPLICATION_ID: &[u8; 4] = b"mfJl";
+
+/// A single recorded change to the vorbis comments of a tag.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JournalEntry {
+    /// The key which was changed.
+    pub key: String,
+    /// The values of the key before the change, or `None` if the key was not present.
+    pub old: Option<Vec<String>>,
+    /// The values of the key after the change, or `None` if the key was removed.
+    pub new: Option<Vec<String>>,
+    /// The time at which the change was made.
+    pub timestamp: SystemTime,
+}
+
+/// A record of the changes made to the vorbis comments of a tag.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Journal {
+    /// The recorded changes, oldest first.
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Returns a new `Journal` with no entries.
+    pub fn new() -> Journal {
+        Journal {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Attempts to parse the bytes of a journal APPLICATION block, without the application id.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Journal> {
+        let mut journal = Journal::new();
+        let mut reader = JournalReader { bytes, i: 0 };
+
+        let num_entries = reader.u32()?;
+        for _ in 0..num_entries {
+            let secs = reader.u64()?;
+            let nanos = reader.u32()?;
+            let key = reader.string()?;
+            let old = reader.values()?;
+            let new = reader.values()?;
+
+            journal.entries.push(JournalEntry {
+                key,
+                old,
+                new,
+                timestamp: UNIX_EPOCH + Duration::new(secs, nanos),
+            });
+        }
+
+        Ok(journal)
+    }
+
+    /// Returns a vector representation of the journal suitable for the data of an APPLICATION
+    /// block.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend((self.entries.len() as u32).to_be_bytes().iter());
+        for entry in self.entries.iter() {
+            let since_epoch = entry
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            bytes.extend(since_epoch.as_secs().to_be_bytes().iter());
+            bytes.extend(since_epoch.subsec_nanos().to_be_bytes().iter());
+
+            write_string(&mut bytes, &entry.key);
+            write_values(&mut bytes, &entry.old);
+            write_values(&mut bytes, &entry.new);
+        }
+
+        bytes
+    }
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend((s.len() as u32).to_be_bytes().iter());
+    bytes.extend(s.as_bytes());
+}
+
+fn write_values(bytes: &mut Vec<u8>, values: &Option<Vec<String>>) {
+    match *values {
+        Some(ref values) => {
+            bytes.push(1);
+            bytes.extend((values.len() as u32).to_be_bytes().iter());
+            for value in values.iter() {
+                write_string(bytes, value);
+            }
+        }
+        None => bytes.push(0),
+    }
+}
+
+struct JournalReader<'a> {
+    bytes: &'a [u8],
+    i: usize,
+}
+
+impl<'a> JournalReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() - self.i < len {
+            return Err(Error::new(ErrorKind::InvalidInput, "truncated journal"));
+        }
+        let bytes = &self.bytes[self.i..self.i + len];
+        self.i += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+
+    fn values(&mut self) -> Result<Option<Vec<String>>> {
+        if self.take(1)?[0] == 0 {
+            return Ok(None);
+        }
+
+        let num_values = self.u32()?;
+        let mut values = Vec::new();
+        for _ in 0..num_values {
+            values.push(self.string()?);
+        }
+        Ok(Some(values))
+    }
+}
@@ -0,0 +1,230 @@
+use crate::block::Block;
+use crate::error::{Error, ErrorKind, Result};
+use crate::tag::Tag;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+
+impl Tag {
+    /// Serializes every block of the tag to JSON. The JSON is an object with a `blocks` array
+    /// containing an object for each block, in order, whose `type` is the name of the block type
+    /// used by the FLAC specification, e.g. `VORBIS_COMMENT`. Binary data is base64 encoded and
+    /// the MD5 signature is hex encoded. Picture data is only included if `include_picture_data`
+    /// is true.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    ///
+    /// let json = tag.to_json(false).unwrap();
+    /// assert_eq!(
+    ///     json,
+    ///     r#"{"blocks":[{"comments":{"TITLE":["title"]},"type":"VORBIS_COMMENT","vendor":""}]}"#
+    /// );
+    /// ```
+    pub fn to_json(&self, include_picture_data: bool) -> Result<String> {
+        let blocks: Vec<Value> = self
+            .blocks()
+            .map(|block| block_to_json(block, include_picture_data))
+            .collect();
+
+        serde_json::to_string(&json!({ "blocks": blocks }))
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "unable to encode tag as json"))
+    }
+}
+
+/// A tag is serialized as a structure with a `blocks` field containing its blocks in order.
+impl Serialize for Tag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let blocks: Vec<&Block> = self.blocks().collect();
+        let mut state = serializer.serialize_struct("Tag", 1)?;
+        state.serialize_field("blocks", &blocks)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Tag, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct TagData {
+            blocks: Vec<Block>,
+        }
+
+        let data = TagData::deserialize(deserializer)?;
+        let mut tag = Tag::new();
+        for block in data.blocks {
+            tag.push_block(block);
+        }
+        tag.load_legacy_coverart();
+        Ok(tag)
+    }
+}
+
+/// Serializes bytes as a base64 string in human readable formats such as JSON, and as plain bytes
+/// otherwise.
+pub(crate) mod base64_bytes {
+    use super::BASE64;
+
+    use base64::Engine;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&BASE64.encode(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            let encoded = String::deserialize(deserializer)?;
+            BASE64.decode(encoded).map_err(D::Error::custom)
+        } else {
+            Vec::deserialize(deserializer)
+        }
+    }
+}
+
+fn block_to_json(block: &Block, include_picture_data: bool) -> Value {
+    match *block {
+        Block::StreamInfo(ref streaminfo) => json!({
+            "type": "STREAMINFO",
+            "min_block_size": streaminfo.min_block_size,
+            "max_block_size": streaminfo.max_block_size,
+            "min_frame_size": streaminfo.min_frame_size,
+            "max_frame_size": streaminfo.max_frame_size,
+            "sample_rate": streaminfo.sample_rate,
+            "num_channels": streaminfo.num_channels,
+            "bits_per_sample": streaminfo.bits_per_sample,
+            "total_samples": streaminfo.total_samples,
+            "md5": hex::encode(&streaminfo.md5),
+        }),
+        Block::Padding(length) => json!({
+            "type": "PADDING",
+            "length": length,
+        }),
+        Block::Application(ref application) => json!({
+            "type": "APPLICATION",
+            "id": hex::encode(&application.id),
+            "data": BASE64.encode(&application.data),
+        }),
+        Block::SeekTable(ref seektable) => json!({
+            "type": "SEEKTABLE",
+            "seekpoints": seektable.seekpoints.iter().map(|point| json!({
+                "sample_number": point.sample_number,
+                "offset": point.offset,
+                "num_samples": point.num_samples,
+            })).collect::<Vec<_>>(),
+        }),
+        Block::VorbisComment(ref vorbis) => {
+            // sort the keys so that the output is stable
+            let comments: BTreeMap<_, _> = vorbis
+                .keys()
+                .into_iter()
+                .map(|key| {
+                    let values = vorbis.get(&key).unwrap_or_default();
+                    (key, values)
+                })
+                .collect();
+            json!({
+                "type": "VORBIS_COMMENT",
+                "vendor": vorbis.vendor_string,
+                "comments": comments,
+            })
+        }
+        Block::CueSheet(ref cuesheet) => json!({
+            "type": "CUESHEET",
+            "catalog_num": cuesheet.catalog_num,
+            "num_leadin": cuesheet.num_leadin,
+            "is_cd": cuesheet.is_cd,
+            "tracks": cuesheet.tracks.iter().map(|track| json!({
+                "offset": track.offset,
+                "number": track.number,
+                "isrc": track.isrc,
+                "is_audio": track.is_audio,
+                "pre_emphasis": track.pre_emphasis,
+                "indices": track.indices.iter().map(|index| json!({
+                    "offset": index.offset,
+                    "point_num": index.point_num,
+                })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        }),
+        Block::Picture(ref picture) => {
+            let len = match picture.deferred {
+                Some(deferred) => deferred.len as usize,
+                None => picture.data.len(),
+            };
+            let mut value = json!({
+                "type": "PICTURE",
+                "picture_type": picture.picture_type as u32,
+                "mime_type": picture.mime_type,
+                "description": picture.description,
+                "width": picture.width,
+                "height": picture.height,
+                "depth": picture.depth,
+                "num_colors": picture.num_colors,
+                "data_len": len,
+            });
+            if include_picture_data && picture.is_loaded() {
+                value["data"] = json!(BASE64.encode(&picture.data));
+            }
+            value
+        }
+        Block::Unknown((block_type, ref data)) => json!({
+            "type": "UNKNOWN",
+            "block_type": block_type,
+            "data": BASE64.encode(data),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{PictureType, StreamInfo};
+
+    #[test]
+    fn picture_data_is_optional() {
+        let mut tag = Tag::new();
+        tag.set_streaminfo(StreamInfo::new());
+        tag.add_picture("image/png", PictureType::CoverFront, vec![1, 2, 3]);
+
+        let value: Value = serde_json::from_str(&tag.to_json(false).unwrap()).unwrap();
+        assert_eq!(value["blocks"][0]["type"], "STREAMINFO");
+        assert_eq!(value["blocks"][1]["picture_type"], 3);
+        assert_eq!(value["blocks"][1]["data_len"], 3);
+        assert!(value["blocks"][1].get("data").is_none());
+
+        let value: Value = serde_json::from_str(&tag.to_json(true).unwrap()).unwrap();
+        assert_eq!(value["blocks"][1]["data"], "AQID");
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let mut tag = Tag::new();
+        tag.set_streaminfo(StreamInfo::new());
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.add_picture("image/png", PictureType::CoverFront, vec![1, 2, 3]);
+
+        let json = serde_json::to_string(&tag).unwrap();
+        assert!(json.contains(r#""data":"AQID""#));
+
+        let tag: Tag = serde_json::from_str(&json).unwrap();
+        assert_eq!(tag.blocks().count(), 3);
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+        assert_eq!(tag.pictures().next().unwrap().data, vec![1, 2, 3]);
+    }
+}
@@ -1,16 +1,105 @@
 //! A library to read and write FLAC metadata tags.
+//!
+//! Everything except the `raw` module needs the default `std` feature. Without it, the crate is
+//! `no_std` and only needs `alloc`. The `raw` module holds the parser and serializer for block
+//! headers and the streaminfo, vorbis comment and picture blocks, and the owned types of the
+//! `block` module are read and written through it.
+//!
+//! The crate has no unsafe code, except with the `mmap` feature, which maps files into memory
+//! when reading them. That feature's single unsafe call is confined to a private module, and
+//! unsafe code stays denied everywhere else.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(not(feature = "mmap"), forbid(unsafe_code))]
+#![cfg_attr(feature = "mmap", deny(unsafe_code))]
 #![warn(missing_docs)]
 
+extern crate alloc;
 #[macro_use]
 extern crate log;
 
-pub use block::{Block, BlockType};
-pub use error::{Error, ErrorKind, Result};
+#[cfg(feature = "std")]
+pub use block::Block;
+#[cfg(feature = "std")]
+pub use builder::TagBuilder;
+pub use error::{Error, ErrorKind, ParseWarning, Result};
+#[cfg(feature = "std")]
+pub use file::FlacFile;
+#[cfg(feature = "std")]
+pub use options::{CopyOptions, PaddingPolicy, ParseMode, ReadOptions, WriteOptions};
+#[cfg(feature = "std")]
+pub use plan::{SaveMethod, SavePlan};
+pub use raw::BlockType;
+#[cfg(feature = "std")]
 pub use tag::Tag;
+#[cfg(feature = "std")]
+pub use validate::{Violation, ViolationKind};
+#[cfg(feature = "notify")]
+pub use watch::{TagWatcher, WatchEvent};
 
+/// APEv2 tags appended to FLAC files.
+#[cfg(feature = "std")]
+pub mod ape;
 /// Includes various types of metadata blocks.
+#[cfg(feature = "std")]
 pub mod block;
+/// Chunks of WAVE and AIFF files kept by `flac --keep-foreign-metadata`.
+#[cfg(feature = "std")]
+pub mod foreign;
+/// ID3v1 tags appended to FLAC files.
+#[cfg(feature = "std")]
+pub mod id3v1;
+/// An incrementally updated index of the metadata of a directory tree.
+#[cfg(feature = "index")]
+pub mod index;
+/// A record of the changes made to a tag.
+#[cfg(feature = "std")]
+pub mod journal;
+/// Reading and writing the metadata blocks of a FLAC stream held in a byte slice, using only
+/// `alloc`.
+pub mod raw;
+/// ReplayGain analysis.
+#[cfg(feature = "std")]
+pub mod replaygain;
+/// Reports summarizing the metadata of many files.
+#[cfg(feature = "std")]
+pub mod report;
+/// Reading the tags of every file in a directory tree.
+#[cfg(feature = "std")]
+pub mod scan;
 
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "std")]
+mod cue;
 mod error;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "id3")]
+mod id3v2;
+#[cfg(feature = "image")]
+mod image;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "std")]
+mod list;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "std")]
+mod ogg;
+#[cfg(feature = "std")]
+mod options;
+#[cfg(feature = "std")]
+mod plan;
+#[cfg(feature = "std")]
 mod tag;
+#[cfg(all(test, feature = "std"))]
+mod test_util;
+#[cfg(feature = "std")]
+mod validate;
+#[cfg(feature = "md5")]
+mod verify;
+#[cfg(feature = "notify")]
+mod watch;
@@ -0,0 +1,217 @@
+use crate::block::{Block, PictureType};
+use crate::tag::Tag;
+
+use std::fmt;
+
+/// Lists every block in the same layout as `metaflac --list`.
+///
+/// # Example
+/// ```
+/// use metaflac::Tag;
+///
+/// let mut tag = Tag::new();
+/// tag.set_vorbis("TITLE", vec!["title"]);
+///
+/// assert_eq!(
+///     tag.to_string(),
+///     "METADATA block #0
+///   type: 4 (VORBIS_COMMENT)
+///   is last: true
+///   length: 23
+///   vendor string: \n  comments: 1
+///     comment[0]: TITLE=title
+/// "
+/// );
+/// ```
+impl fmt::Display for Tag {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        let nblocks = self.blocks().count();
+        for (i, block) in self.blocks().enumerate() {
+            let (type_num, type_name) = match *block {
+                Block::StreamInfo(_) => (0, "STREAMINFO"),
+                Block::Padding(_) => (1, "PADDING"),
+                Block::Application(_) => (2, "APPLICATION"),
+                Block::SeekTable(_) => (3, "SEEKTABLE"),
+                Block::VorbisComment(_) => (4, "VORBIS_COMMENT"),
+                Block::CueSheet(_) => (5, "CUESHEET"),
+                Block::Picture(_) => (6, "PICTURE"),
+                Block::Unknown((n, _)) => (n, "UNKNOWN"),
+            };
+
+            writeln!(out, "METADATA block #{}", i)?;
+            writeln!(out, "  type: {} ({})", type_num, type_name)?;
+            writeln!(out, "  is last: {}", i == nblocks - 1)?;
+            writeln!(out, "  length: {}", block.content_len())?;
+
+            match *block {
+                Block::StreamInfo(ref streaminfo) => {
+                    writeln!(
+                        out,
+                        "  minimum blocksize: {} samples",
+                        streaminfo.min_block_size
+                    )?;
+                    writeln!(
+                        out,
+                        "  maximum blocksize: {} samples",
+                        streaminfo.max_block_size
+                    )?;
+                    writeln!(
+                        out,
+                        "  minimum framesize: {} bytes",
+                        streaminfo.min_frame_size
+                    )?;
+                    writeln!(
+                        out,
+                        "  maximum framesize: {} bytes",
+                        streaminfo.max_frame_size
+                    )?;
+                    writeln!(out, "  sample_rate: {} Hz", streaminfo.sample_rate)?;
+                    writeln!(out, "  channels: {}", streaminfo.num_channels)?;
+                    writeln!(out, "  bits-per-sample: {}", streaminfo.bits_per_sample)?;
+                    writeln!(out, "  total samples: {}", streaminfo.total_samples)?;
+                    writeln!(out, "  MD5 signature: {}", hex::encode(&streaminfo.md5))?;
+                }
+                Block::Application(ref application) => {
+                    writeln!(out, "  application ID: {}", hex::encode(&application.id))?;
+                    writeln!(out, "  data contents:")?;
+                    hexdump(out, &application.data)?;
+                }
+                Block::SeekTable(ref seektable) => {
+                    writeln!(out, "  seek points: {}", seektable.seekpoints.len())?;
+                    for (j, point) in seektable.seekpoints.iter().enumerate() {
+                        if point.is_placeholder() {
+                            writeln!(out, "    point {}: PLACEHOLDER", j)?;
+                        } else {
+                            writeln!(
+                                out,
+                                "    point {}: sample_number={}, stream_offset={}, frame_samples={}",
+                                j, point.sample_number, point.offset, point.num_samples
+                            )?;
+                        }
+                    }
+                }
+                Block::VorbisComment(ref vorbis) => {
+                    let entries = vorbis.entries();
+                    writeln!(out, "  vendor string: {}", vorbis.vendor_string)?;
+                    writeln!(out, "  comments: {}", entries.len())?;
+                    for (j, (key, value)) in entries.into_iter().enumerate() {
+                        writeln!(out, "    comment[{}]: {}={}", j, key, value)?;
+                    }
+                }
+                Block::CueSheet(ref cuesheet) => {
+                    writeln!(out, "  media catalog number: {}", cuesheet.catalog_num)?;
+                    writeln!(out, "  lead-in: {}", cuesheet.num_leadin)?;
+                    writeln!(out, "  is CD: {}", cuesheet.is_cd)?;
+                    writeln!(out, "  number of tracks: {}", cuesheet.tracks.len())?;
+                    let ntracks = cuesheet.tracks.len();
+                    for (j, track) in cuesheet.tracks.iter().enumerate() {
+                        let is_last = j == ntracks - 1;
+                        let is_leadout = is_last && track.indices.is_empty();
+                        writeln!(out, "    track[{}]", j)?;
+                        writeln!(out, "      offset: {}", track.offset)?;
+                        if is_last {
+                            let note = if is_leadout { "LEAD-OUT" } else { "INVALID" };
+                            writeln!(out, "      number: {} ({})", track.number, note)?;
+                        } else {
+                            writeln!(out, "      number: {}", track.number)?;
+                        }
+                        if is_leadout {
+                            continue;
+                        }
+                        writeln!(out, "      ISRC: {}", track.isrc)?;
+                        let track_type = if track.is_audio { "AUDIO" } else { "DATA" };
+                        writeln!(out, "      type: {}", track_type)?;
+                        writeln!(out, "      pre-emphasis: {}", track.pre_emphasis)?;
+                        writeln!(out, "      number of index points: {}", track.indices.len())?;
+                        for (k, index) in track.indices.iter().enumerate() {
+                            writeln!(out, "        index[{}]", k)?;
+                            writeln!(out, "          offset: {}", index.offset)?;
+                            writeln!(out, "          number: {}", index.point_num)?;
+                        }
+                    }
+                }
+                Block::Picture(ref picture) => {
+                    writeln!(
+                        out,
+                        "  type: {} ({})",
+                        picture.picture_type as u32,
+                        picture_type_name(picture.picture_type)
+                    )?;
+                    writeln!(out, "  MIME type: {}", picture.mime_type)?;
+                    writeln!(out, "  description: {}", picture.description)?;
+                    writeln!(out, "  width: {}", picture.width)?;
+                    writeln!(out, "  height: {}", picture.height)?;
+                    writeln!(out, "  depth: {}", picture.depth)?;
+                    let unindexed = if picture.num_colors == 0 {
+                        " (unindexed)"
+                    } else {
+                        ""
+                    };
+                    writeln!(out, "  colors: {}{}", picture.num_colors, unindexed)?;
+                    let data_len = match picture.deferred {
+                        Some(deferred) => deferred.len as usize,
+                        None => picture.data.len(),
+                    };
+                    writeln!(out, "  data length: {}", data_len)?;
+                    writeln!(out, "  data:")?;
+                    hexdump(out, &picture.data)?;
+                }
+                Block::Unknown((_, ref data)) => {
+                    writeln!(out, "  data contents:")?;
+                    hexdump(out, data)?;
+                }
+                Block::Padding(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the name libFLAC uses for the picture type.
+fn picture_type_name(picture_type: PictureType) -> &'static str {
+    match picture_type {
+        PictureType::Other => "Other",
+        PictureType::Icon => "32x32 pixels 'file icon' (PNG only)",
+        PictureType::OtherIcon => "Other file icon",
+        PictureType::CoverFront => "Cover (front)",
+        PictureType::CoverBack => "Cover (back)",
+        PictureType::Leaflet => "Leaflet page",
+        PictureType::Media => "Media (e.g. label side of CD)",
+        PictureType::LeadArtist => "Lead artist/lead performer/soloist",
+        PictureType::Artist => "Artist/performer",
+        PictureType::Conductor => "Conductor",
+        PictureType::Band => "Band/Orchestra",
+        PictureType::Composer => "Composer",
+        PictureType::Lyricist => "Lyricist/text writer",
+        PictureType::RecordingLocation => "Recording Location",
+        PictureType::DuringRecording => "During recording",
+        PictureType::DuringPerformance => "During performance",
+        PictureType::ScreenCapture => "Movie/video screen capture",
+        PictureType::BrightFish => "A bright coloured fish",
+        PictureType::Illustration => "Illustration",
+        PictureType::BandLogo => "Band/artist logotype",
+        PictureType::PublisherLogo => "Publisher/Studio logotype",
+    }
+}
+
+/// Writes the bytes as rows of 16 hex encoded bytes followed by their printable characters, like
+/// metaflac does.
+fn hexdump(out: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for (i, row) in bytes.chunks(16).enumerate() {
+        write!(out, "    {:08X}: ", i * 16)?;
+        for j in 0..16 {
+            write!(out, "{:02X} ", row.get(j).cloned().unwrap_or(0))?;
+        }
+        for j in 0..16 {
+            let c = match row.get(j) {
+                Some(&b) if (0x20..0x7F).contains(&b) => b as char,
+                Some(_) => '.',
+                None => ' ',
+            };
+            write!(out, "{}", c)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
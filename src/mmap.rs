@@ -0,0 +1,19 @@
+// The only unsafe code in the crate. Mapping a file is unsafe because the contents of the map
+// change if another process writes to or truncates the file while it is mapped. The map never
+// leaves `Tag::read_from_path_with`, which only reads it while the tag is parsed.
+#![allow(unsafe_code)]
+
+use crate::error::Result;
+
+use memmap2::Mmap;
+
+use std::fs::File;
+
+/// Maps the file into memory, so that the blocks are parsed from the page cache rather than
+/// copied through a read buffer. Only the pages which are parsed are read from disk.
+pub(crate) fn map_file(file: &File) -> Result<Mmap> {
+    // SAFETY: the map is only read while the tag is parsed and is dropped before returning.
+    // Another process truncating the file meanwhile raises SIGBUS, which is the documented
+    // trade-off of the mmap feature.
+    Ok(unsafe { Mmap::map(file)? })
+}
@@ -0,0 +1,288 @@
+use crate::error::{Error, ErrorKind, Result};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use std::io::{self, Read, Write};
+
+/// The page header flag of a page which continues a packet from the previous page.
+const CONTINUED: u8 = 0x01;
+/// The page header flag of the first page of a logical stream.
+const BEGINNING_OF_STREAM: u8 = 0x02;
+
+/// The granule position of a page on which no packet ends.
+const NO_GRANULE: u64 = u64::MAX;
+
+/// The maximum number of lacing values on a page.
+const MAX_SEGMENTS: usize = 255;
+
+/// The lookup table of the CRC-32 used by Ogg, with polynomial 0x04c11db7 and no reflection.
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Continues the checksum over the bytes.
+fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, &b| {
+        (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ b) as usize]
+    })
+}
+
+/// A page of an Ogg bitstream.
+pub(crate) struct Page {
+    pub header_type: u8,
+    pub granule: u64,
+    pub serial: u32,
+    pub sequence: u32,
+    /// The lacing values, each giving the length of a segment of the data.
+    pub segments: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+impl Page {
+    /// Reads the next page. Returns `None` at the end of the reader.
+    pub fn read_from<R: Read + ?Sized>(reader: &mut R) -> Result<Option<Page>> {
+        let mut header = [0; 27];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        if &header[0..4] != b"OggS" || header[4] != 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid ogg page"));
+        }
+
+        let mut segments = vec![0; header[26] as usize];
+        reader.read_exact(&mut segments)?;
+        let mut data = vec![0; segments.iter().map(|&len| len as usize).sum()];
+        reader.read_exact(&mut data)?;
+
+        let page = Page {
+            header_type: header[5],
+            granule: LittleEndian::read_u64(&header[6..14]),
+            serial: LittleEndian::read_u32(&header[14..18]),
+            sequence: LittleEndian::read_u32(&header[18..22]),
+            segments,
+            data,
+        };
+        if page.crc() != LittleEndian::read_u32(&header[22..26]) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "ogg page checksum mismatch",
+            ));
+        }
+        Ok(Some(page))
+    }
+
+    /// Writes the page with a freshly computed checksum.
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<()> {
+        let mut bytes = self.header_bytes();
+        LittleEndian::write_u32(&mut bytes[22..26], self.crc());
+        writer.write_all(&bytes)?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
+    fn header_bytes(&self) -> Vec<u8> {
+        let mut bytes = b"OggS\0".to_vec();
+        bytes.push(self.header_type);
+        bytes.extend(self.granule.to_le_bytes());
+        bytes.extend(self.serial.to_le_bytes());
+        bytes.extend(self.sequence.to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.push(self.segments.len() as u8);
+        bytes.extend(&self.segments);
+        bytes
+    }
+
+    fn crc(&self) -> u32 {
+        crc32(crc32(0, &self.header_bytes()), &self.data)
+    }
+
+    /// Returns the packets on the page. The first packet continues the packet of the previous
+    /// page if the page is marked as continued. The second value tells whether the last packet
+    /// is complete.
+    fn packets(&self) -> (Vec<&[u8]>, bool) {
+        let mut packets = Vec::new();
+        let mut start = 0;
+        let mut end = 0;
+        for &len in self.segments.iter() {
+            end += len as usize;
+            if len < 255 {
+                packets.push(&self.data[start..end]);
+                start = end;
+            }
+        }
+        let complete = self.segments.last().map_or(true, |&len| len < 255);
+        if !complete {
+            packets.push(&self.data[start..end]);
+        }
+        (packets, complete)
+    }
+}
+
+/// The FLAC header packets at the start of an Ogg FLAC stream.
+pub(crate) struct Headers {
+    /// The serial number of the FLAC logical stream.
+    pub serial: u32,
+    /// The metadata blocks, starting with the streaminfo block, including their block headers.
+    pub blocks: Vec<Vec<u8>>,
+    /// The pages of other logical streams found between the header pages.
+    pub other_pages: Vec<Page>,
+    /// The sequence number of the first page after the header pages.
+    pub next_sequence: u32,
+}
+
+/// Reads the header packets of an Ogg FLAC stream, leaving the reader at the first page after
+/// them. Returns an error once a packet grows past a block of `max_block_len` bytes, so that a
+/// packet continued over many pages is not buffered whole.
+pub(crate) fn read_headers<R: Read + ?Sized>(
+    reader: &mut R,
+    max_block_len: Option<u32>,
+) -> Result<Headers> {
+    let not_flac = || Error::new(ErrorKind::NotFlac, "ogg stream does not contain flac");
+
+    let mut headers = Headers {
+        serial: 0,
+        blocks: Vec::new(),
+        other_pages: Vec::new(),
+        next_sequence: 0,
+    };
+    let mut packet = Vec::new();
+    let mut found = false;
+    let mut expected = None;
+
+    loop {
+        let page = Page::read_from(reader)?.ok_or_else(not_flac)?;
+        if !found {
+            if page.header_type & BEGINNING_OF_STREAM == 0 {
+                return Err(not_flac());
+            }
+            if !page.data.starts_with(b"\x7FFLAC") {
+                // the beginning of another logical stream
+                headers.other_pages.push(page);
+                continue;
+            }
+            found = true;
+            headers.serial = page.serial;
+        } else if page.serial != headers.serial {
+            headers.other_pages.push(page);
+            continue;
+        }
+        headers.next_sequence = page.sequence.wrapping_add(1);
+
+        let (packets, complete) = page.packets();
+        let npackets = packets.len();
+        for (i, data) in packets.into_iter().enumerate() {
+            packet.extend(data);
+            // the first packet also holds the mapping header
+            let overhead = if headers.blocks.is_empty() { 13 + 4 } else { 4 };
+            if max_block_len.is_some_and(|max| packet.len() > max as usize + overhead) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "block exceeds the maximum block length",
+                ));
+            }
+            if i == npackets - 1 && !complete {
+                break;
+            }
+
+            let block = if headers.blocks.is_empty() {
+                // the mapping header: 0x7F, "FLAC", the version, the number of header packets
+                // and the native "fLaC" marker, followed by the streaminfo block
+                if packet.len() < 13 || &packet[9..13] != b"fLaC" {
+                    return Err(not_flac());
+                }
+                if packet[5] != 1 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "unsupported ogg flac mapping version",
+                    ));
+                }
+                // a count of 0 means unknown, leaving only the last block flag
+                let count = u16::from_be_bytes([packet[7], packet[8]]);
+                expected = if count > 0 {
+                    Some(count as usize + 1)
+                } else {
+                    None
+                };
+                packet.split_off(13)
+            } else {
+                std::mem::take(&mut packet)
+            };
+            packet.clear();
+
+            if block.is_empty() {
+                return Err(not_flac());
+            }
+            let is_last = block[0] & 0x80 != 0;
+            headers.blocks.push(block);
+            if expected.map_or(is_last, |expected| headers.blocks.len() >= expected) {
+                return Ok(headers);
+            }
+        }
+    }
+}
+
+/// Writes the header packets for the blocks of an Ogg FLAC stream. Every packet starts on a new
+/// page. Returns the sequence number of the page following the header pages.
+pub(crate) fn write_headers<W: Write + ?Sized>(
+    writer: &mut W,
+    serial: u32,
+    blocks: &[Vec<u8>],
+) -> Result<u32> {
+    let mut sequence = 0;
+    for (i, block) in blocks.iter().enumerate() {
+        let packet = if i == 0 {
+            let mut packet = b"\x7FFLAC\x01\x00".to_vec();
+            packet.extend((blocks.len() as u16 - 1).to_be_bytes());
+            packet.extend(b"fLaC");
+            packet.extend(block);
+            packet
+        } else {
+            block.clone()
+        };
+
+        let mut lacing = vec![255; packet.len() / 255];
+        lacing.push((packet.len() % 255) as u8);
+
+        let mut offset = 0;
+        let pages = lacing.chunks(MAX_SEGMENTS).collect::<Vec<_>>();
+        for (j, segments) in pages.iter().enumerate() {
+            let len = segments.iter().map(|&len| len as usize).sum::<usize>();
+            let mut header_type = if j > 0 { CONTINUED } else { 0 };
+            if sequence == 0 {
+                header_type |= BEGINNING_OF_STREAM;
+            }
+            Page {
+                header_type,
+                granule: if j == pages.len() - 1 { 0 } else { NO_GRANULE },
+                serial,
+                sequence,
+                segments: segments.to_vec(),
+                data: packet[offset..offset + len].to_vec(),
+            }
+            .write_to(writer)?;
+            offset += len;
+            sequence += 1;
+        }
+    }
+    Ok(sequence)
+}
@@ -0,0 +1,313 @@
+use crate::block::BlockType;
+use crate::error::{Error, ErrorKind, ParseWarning, Result};
+
+/// How strictly a tag is checked against the FLAC specification while it is read.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ParseMode {
+    /// Returns an error for any violation of the specification, such as a block with an invalid
+    /// length or a streaminfo block which is not the first block.
+    Strict,
+    /// Reads whatever can be read, recording each violation of the specification in
+    /// `Tag::warnings`. A block which is truncated or too short to be parsed is kept as an
+    /// `Unknown` block, see `Block::is_damaged`, and must be removed before the tag is written.
+    #[default]
+    Lenient,
+    /// Like `Lenient`, but a block which cannot be parsed or has a nonsensical length does not
+    /// fail the read. Instead the damaged bytes are skipped up to the next plausible block header,
+    /// and recorded in `Tag::skipped_regions`. The rest of the stream is read into memory to
+    /// search for block headers.
+    Recover,
+}
+
+impl ParseMode {
+    /// Handles a violation of the specification found in the block at the offset. Returns an
+    /// error in strict mode and records a warning otherwise.
+    pub(crate) fn violation(
+        self,
+        offset: u64,
+        kind: ErrorKind,
+        message: &'static str,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<()> {
+        match self {
+            ParseMode::Strict => Err(Error::new(kind, message).in_block(None, offset)),
+            ParseMode::Lenient | ParseMode::Recover => {
+                warnings.push(ParseWarning {
+                    offset,
+                    message: message.to_string(),
+                });
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Options controlling how a tag is read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReadOptions {
+    /// The maximum number of junk bytes to scan past while looking for the `fLaC` marker. Junk is
+    /// only searched for after any ID3v2 tag has been skipped. A value of 0 requires the marker to
+    /// be found immediately.
+    pub max_junk_len: u64,
+    /// Keeps the file open for reading and writing after the tag has been read, so that saves
+    /// reuse the same handle instead of reopening the file. Clones of the tag share the handle.
+    pub retain_file: bool,
+    /// Skips the data of picture blocks while reading, recording only where the data is stored.
+    /// The data can be loaded later with `Tag::load_picture_data`.
+    pub skip_picture_data: bool,
+    /// How strictly the tag is checked against the FLAC specification.
+    pub parse_mode: ParseMode,
+    /// Replaces invalid UTF-8 in vorbis comments with the replacement character instead of
+    /// failing to read the tag. Each affected comment is recorded in `Tag::warnings`, and is
+    /// written back with the replacement characters when the tag is saved.
+    pub lossy_utf8: bool,
+    /// The maximum length of a single block in bytes, not counting the block header. Reading
+    /// fails before the contents of a longer block are read.
+    pub max_block_len: Option<u32>,
+    /// The maximum total length of the metadata in bytes, including the block headers. Reading
+    /// fails once the blocks read so far exceed it.
+    pub max_metadata_len: Option<u64>,
+    /// The maximum number of comments in a vorbis comment block.
+    pub max_comments: Option<u32>,
+    /// Merges the comments of every vorbis comment block into the first one, as
+    /// `Tag::normalize` does. The specification allows only one vorbis comment block, but some
+    /// tools write more.
+    pub merge_vorbis_comments: bool,
+    /// The types of the blocks to read, or `None` to read every block. The contents of the other
+    /// blocks are skipped without being parsed, seeking past them when reading from a path, and
+    /// the blocks are left out of the tag. Such a tag cannot be written, see `Tag::is_partial`.
+    pub block_types: Option<Vec<BlockType>>,
+}
+
+impl ReadOptions {
+    /// Returns a new `ReadOptions` which requires the `fLaC` marker at the start of the stream and
+    /// tolerates violations of the specification in the blocks.
+    pub fn new() -> ReadOptions {
+        ReadOptions {
+            max_junk_len: 0,
+            retain_file: false,
+            skip_picture_data: false,
+            parse_mode: ParseMode::Lenient,
+            lossy_utf8: false,
+            max_block_len: None,
+            max_metadata_len: None,
+            max_comments: None,
+            merge_vorbis_comments: false,
+            block_types: None,
+        }
+    }
+
+    /// Returns a new `ReadOptions` which reads only the blocks of the specified types, such as
+    /// the streaminfo and vorbis comment blocks when the pictures are not needed. See
+    /// `ReadOptions::block_types`.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{BlockType, PictureType};
+    /// use metaflac::{ReadOptions, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// tag.add_picture("image/png", PictureType::CoverFront, vec![0; 1024]);
+    /// let bytes = tag.write_to_vec().unwrap();
+    ///
+    /// let options = ReadOptions::only(&[BlockType::StreamInfo, BlockType::VorbisComment]);
+    /// let tag = Tag::read_from_with(&mut &bytes[..], &options).unwrap();
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+    /// assert_eq!(tag.pictures().count(), 0);
+    /// assert!(tag.is_partial());
+    /// ```
+    pub fn only(block_types: &[BlockType]) -> ReadOptions {
+        ReadOptions {
+            block_types: Some(block_types.to_vec()),
+            ..ReadOptions::new()
+        }
+    }
+
+    /// Returns true if blocks of the type are read.
+    pub(crate) fn reads(&self, block_type: BlockType) -> bool {
+        self.block_types
+            .as_ref()
+            .map_or(true, |block_types| block_types.contains(&block_type))
+    }
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Options controlling how a tag is written to a file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WriteOptions {
+    /// Removes any junk bytes found before the `fLaC` marker when the tag was read.
+    pub remove_junk: bool,
+    /// Removes any ID3v2 tag found before the `fLaC` marker. Such tags are not part of the FLAC
+    /// format and are prepended by some broken taggers.
+    pub remove_id3: bool,
+    /// Removes any ID3v1 tag from the end of the file.
+    pub remove_id3v1: bool,
+    /// Removes any APEv2 tag from the end of the file, keeping an ID3v1 tag which follows it
+    /// unless `remove_id3v1` is also set.
+    pub remove_ape: bool,
+    /// Writes the metadata into the existing metadata region when it fits. When disabled the file
+    /// is always rewritten.
+    pub use_padding: bool,
+    /// Restores the access and modification times of the file after it has been written.
+    pub preserve_modtime: bool,
+    /// How much padding to write after the metadata.
+    pub padding: PaddingPolicy,
+    /// Keeps the blocks in the order they are in, including padding blocks between other blocks.
+    /// Only padding at the end of the metadata is replaced. When disabled every padding block is
+    /// merged into a single padding block after the other blocks. The streaminfo block is always
+    /// written first.
+    pub preserve_order: bool,
+    /// Removes pictures which are too large for the 24 bit length of a block header instead of
+    /// returning an `InvalidLength` error.
+    pub drop_oversized_pictures: bool,
+    /// Block types which are left out of the written file, such as the seektable and padding of a
+    /// file destined for streaming. The tag itself keeps the blocks. Excluding padding writes the
+    /// file without any padding, regardless of `padding`.
+    pub exclude_blocks: Vec<BlockType>,
+    /// Writes the same bytes for the same logical content, regardless of the order in which
+    /// blocks and comments were read or added. The vorbis comments are sorted by key, the blocks
+    /// are sorted by type keeping the order of blocks of the same type, every padding block is
+    /// replaced as if `preserve_order` was disabled, and `PaddingPolicy::Preserve` writes exactly
+    /// 1024 bytes of padding. The tag itself is sorted as well.
+    pub deterministic: bool,
+}
+
+impl WriteOptions {
+    /// Returns a new `WriteOptions` which leaves the file layout untouched.
+    pub fn new() -> WriteOptions {
+        WriteOptions {
+            remove_junk: false,
+            remove_id3: false,
+            remove_id3v1: false,
+            remove_ape: false,
+            use_padding: true,
+            preserve_modtime: false,
+            padding: PaddingPolicy::Preserve,
+            preserve_order: false,
+            drop_oversized_pictures: false,
+            exclude_blocks: Vec::new(),
+            deterministic: false,
+        }
+    }
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Determines the padding written after the metadata when a tag is written to a file.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PaddingPolicy {
+    /// Writes no padding. The metadata is only written in place if it fills the existing metadata
+    /// region exactly.
+    None,
+    /// Writes a padding block of exactly this many bytes, not counting the block header.
+    Fixed(u32),
+    /// Pads the metadata, including the `fLaC` marker, to a multiple of this many bytes, so that
+    /// the region grows in steps of this size. The metadata is written in place whenever the
+    /// existing region is such a multiple and still has room.
+    Multiple(u32),
+    /// Reuses the existing padding when the metadata fits into the existing metadata region, and
+    /// adds 1024 bytes of padding if the file has to be rewritten.
+    #[default]
+    Preserve,
+}
+
+impl PaddingPolicy {
+    /// Returns the padding to write when metadata of `new_length` bytes replaces a metadata
+    /// region of `old_length` bytes in place, or `None` if the policy does not allow writing in
+    /// place. A padding of `Some(None)` means no padding block is written at all. Both lengths
+    /// include block headers but not the `fLaC` marker.
+    pub(crate) fn in_place(&self, old_length: u32, new_length: u32) -> Option<Option<u32>> {
+        // one block header is needed to turn the leftover space into padding
+        let leftover = old_length.checked_sub(new_length + 4);
+        // blocks which exactly fill the old metadata region need no padding at all
+        let exact_fit = new_length == old_length;
+        match *self {
+            PaddingPolicy::None if exact_fit => Some(None),
+            PaddingPolicy::None => None,
+            PaddingPolicy::Fixed(size) => leftover.filter(|&leftover| leftover == size).map(Some),
+            PaddingPolicy::Multiple(multiple) if (old_length + 4) % multiple.max(1) != 0 => None,
+            PaddingPolicy::Multiple(_) | PaddingPolicy::Preserve if exact_fit => Some(None),
+            PaddingPolicy::Multiple(_) | PaddingPolicy::Preserve => leftover.map(Some),
+        }
+    }
+
+    /// Returns the padding to write when the file is rewritten with metadata of `new_length`
+    /// bytes, or `None` if no padding block is written.
+    pub(crate) fn rewrite(&self, new_length: u32) -> Option<u32> {
+        match *self {
+            PaddingPolicy::None => None,
+            PaddingPolicy::Fixed(size) => Some(size),
+            PaddingPolicy::Multiple(multiple) => {
+                let multiple = multiple.max(1);
+                // the marker and the padding block header
+                let unpadded = new_length + 8;
+                Some((multiple - unpadded % multiple) % multiple)
+            }
+            PaddingPolicy::Preserve => Some(1024),
+        }
+    }
+}
+
+/// Options controlling which blocks are copied from one file to another.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CopyOptions {
+    /// Copies the streaminfo block. This describes the audio of a particular file, so it is not
+    /// copied by default.
+    pub copy_streaminfo: bool,
+    /// Copies the seektable block. Seek points refer to frame offsets of a particular file, so it
+    /// is not copied by default.
+    pub copy_seektable: bool,
+    /// Copies the vorbis comment block.
+    pub copy_vorbis_comment: bool,
+    /// Copies the picture blocks.
+    pub copy_pictures: bool,
+    /// Copies the cuesheet block.
+    pub copy_cuesheet: bool,
+    /// Copies the application blocks.
+    pub copy_applications: bool,
+}
+
+impl CopyOptions {
+    /// Returns a new `CopyOptions` which copies every block except the streaminfo, seektable and
+    /// padding blocks.
+    pub fn new() -> CopyOptions {
+        CopyOptions {
+            copy_streaminfo: false,
+            copy_seektable: false,
+            copy_vorbis_comment: true,
+            copy_pictures: true,
+            copy_cuesheet: true,
+            copy_applications: true,
+        }
+    }
+
+    /// Returns true if blocks of the specified type are copied.
+    pub fn copies(&self, block_type: BlockType) -> bool {
+        match block_type {
+            BlockType::StreamInfo => self.copy_streaminfo,
+            BlockType::SeekTable => self.copy_seektable,
+            BlockType::VorbisComment => self.copy_vorbis_comment,
+            BlockType::Picture => self.copy_pictures,
+            BlockType::CueSheet => self.copy_cuesheet,
+            BlockType::Application => self.copy_applications,
+            BlockType::Padding => false,
+            _ => true,
+        }
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
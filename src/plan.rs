@@ -0,0 +1,292 @@
+use crate::block::{Block, BlockType};
+use crate::error::{Error, ErrorKind, Result};
+use crate::options::{PaddingPolicy, WriteOptions};
+use crate::tag::{check_excluded, check_unique_blocks, Tag};
+
+/// How a save writes the file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SaveMethod {
+    /// Nothing is written, since the tag is unmodified. See `Tag::is_dirty`.
+    Skip,
+    /// The metadata is written over the existing metadata region, leaving the audio in place.
+    InPlace,
+    /// The whole file is rewritten through a temporary file, copying the audio.
+    Rewrite,
+}
+
+/// A description of what saving a tag would do to its file, returned by `Tag::plan_save`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SavePlan {
+    /// How the file is written.
+    pub method: SaveMethod,
+    /// The types of the blocks which are written, in order, including padding.
+    pub blocks: Vec<BlockType>,
+    /// Whether blocks other than padding are written in a different order than they have in the
+    /// tag, such as a streaminfo block which is moved to the front.
+    pub reordered: bool,
+    /// The length of the metadata in the file before the save, including block headers but not
+    /// the `fLaC` marker.
+    pub old_metadata_len: u64,
+    /// The length of the metadata in the file after the save, including block headers and
+    /// padding but not the `fLaC` marker.
+    pub metadata_len: u64,
+    /// The number of padding bytes in the file before the save, not counting block headers.
+    pub old_padding: u64,
+    /// The number of padding bytes in the file after the save, not counting block headers.
+    pub padding: u64,
+}
+
+impl SavePlan {
+    /// Returns the number of padding bytes taken up by the new metadata.
+    pub fn padding_consumed(&self) -> u64 {
+        self.old_padding.saturating_sub(self.padding)
+    }
+
+    /// Returns the number of padding bytes added by the save.
+    pub fn padding_created(&self) -> u64 {
+        self.padding.saturating_sub(self.old_padding)
+    }
+}
+
+impl Tag {
+    /// Describes what `Tag::save` would do to the file, without touching it. See
+    /// `Tag::plan_save_with`.
+    pub fn plan_save(&self) -> Result<SavePlan> {
+        self.plan_save_with(&WriteOptions::new())
+    }
+
+    /// Describes what `Tag::save_with` would do to the file with the specified options, without
+    /// touching it: whether the metadata is written in place or the whole file is rewritten, the
+    /// blocks which are written and the resulting metadata and padding lengths. Returns the
+    /// error the save would fail with for a tag which cannot be written, such as one without a
+    /// path.
+    ///
+    /// The plan assumes the file has not changed since the tag was read or last written.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{Block, StreamInfo};
+    /// use metaflac::{SaveMethod, Tag};
+    ///
+    /// let name = format!("metaflac-doc-plan-save-{}.flac", std::process::id());
+    /// let path = std::env::temp_dir().join(name);
+    /// let mut streaminfo = StreamInfo::new();
+    /// streaminfo.num_channels = 2;
+    /// streaminfo.bits_per_sample = 16;
+    /// streaminfo.md5 = vec![0; 16];
+    /// let mut tag = Tag::new();
+    /// tag.push_block(Block::StreamInfo(streaminfo));
+    /// tag.save_to(&path).unwrap();
+    ///
+    /// let mut tag = Tag::read_from_path(&path).unwrap();
+    /// assert_eq!(tag.plan_save().unwrap().method, SaveMethod::Skip);
+    ///
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// let plan = tag.plan_save().unwrap();
+    /// assert_eq!(plan.method, SaveMethod::InPlace);
+    /// assert_eq!(plan.metadata_len, plan.old_metadata_len);
+    /// assert_eq!(plan.padding_consumed(), 27);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn plan_save_with(&self, options: &WriteOptions) -> Result<SavePlan> {
+        if self.saved_path().is_none() {
+            return Err(Error::new(
+                ErrorKind::NoPath,
+                "attempted to save file which was not read from a path",
+            ));
+        }
+
+        let old_metadata_len = self.region_len() as u64;
+        let old_padding = self
+            .block_layout()
+            .iter()
+            .filter(|location| location.block_type == BlockType::Padding)
+            .map(|location| location.length as u64 - 4)
+            .sum();
+
+        if !self.is_dirty() && !self.options_change_file(options) {
+            return Ok(SavePlan {
+                method: SaveMethod::Skip,
+                blocks: self
+                    .block_layout()
+                    .iter()
+                    .map(|location| location.block_type)
+                    .collect(),
+                reordered: false,
+                old_metadata_len,
+                metadata_len: old_metadata_len,
+                old_padding,
+                padding: old_padding,
+            });
+        }
+
+        self.check_complete()?;
+
+        // the same adjustments of the options as made by `Tag::write_to_path_with`
+        let mut options = options.clone();
+        if !options.exclude_blocks.is_empty() {
+            check_excluded(&options)?;
+            if options.exclude_blocks.contains(&BlockType::Padding) {
+                options.padding = PaddingPolicy::None;
+                options.use_padding = false;
+            }
+        }
+        if options.deterministic {
+            options.preserve_order = false;
+            if options.padding == PaddingPolicy::Preserve {
+                options.padding = PaddingPolicy::Fixed(1024);
+            }
+        }
+
+        let mut blocks: Vec<(usize, &Block)> = self
+            .blocks()
+            .enumerate()
+            .filter(|(_, block)| !options.exclude_blocks.contains(&block.block_type()))
+            .filter(|(_, block)| match **block {
+                Block::Picture(ref picture) if options.drop_oversized_pictures => {
+                    picture.fits_block_header()
+                }
+                _ => true,
+            })
+            .collect();
+        check_unique_blocks(blocks.iter().map(|(_, block)| *block))?;
+        if let Some((_, block)) = blocks.iter().find(|(_, block)| !block.fits_block_header()) {
+            return Err(block.too_long_error());
+        }
+
+        if options.deterministic {
+            blocks.sort_by_key(|(_, block)| block.block_type().to_u8());
+        }
+        // the streaminfo block is always written first
+        if let Some(i) = blocks
+            .iter()
+            .position(|(_, block)| block.block_type() == BlockType::StreamInfo)
+        {
+            let streaminfo = blocks.remove(i);
+            blocks.insert(0, streaminfo);
+        }
+
+        let method;
+        let mut padding_size = None;
+        if self.is_ogg() {
+            // ogg flac streams are rewritten with the blocks as they are
+            method = SaveMethod::Rewrite;
+        } else {
+            if options.preserve_order {
+                while let Some((_, Block::Padding(_))) = blocks.last() {
+                    blocks.pop();
+                }
+            } else {
+                blocks.retain(|(_, block)| block.block_type() != BlockType::Padding);
+            }
+
+            let new_length = metadata_len(&blocks);
+            let remove_junk = options.remove_junk && self.junk().is_some();
+            let remove_id3 = options.remove_id3 && self.id3v2().is_some();
+            let in_place = options
+                .padding
+                .in_place(self.region_len(), new_length as u32)
+                .filter(|padding| padding.is_some() || !blocks.is_empty())
+                .filter(|_| options.use_padding && !remove_junk && !remove_id3);
+            match in_place {
+                Some(padding) => {
+                    method = SaveMethod::InPlace;
+                    padding_size = padding;
+                }
+                None => {
+                    method = SaveMethod::Rewrite;
+                    padding_size = match options.padding.rewrite(new_length as u32) {
+                        None if !blocks.is_empty() => None,
+                        padding => Some(padding.unwrap_or(0)),
+                    };
+                }
+            }
+        }
+
+        let reordered = blocks
+            .iter()
+            .filter(|(_, block)| block.block_type() != BlockType::Padding)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .any(|pair| pair[0] > pair[1]);
+        let padding_block = padding_size.map(Block::Padding);
+        if let Some(ref padding) = padding_block {
+            blocks.push((self.blocks().count(), padding));
+        }
+
+        Ok(SavePlan {
+            method,
+            blocks: blocks.iter().map(|(_, block)| block.block_type()).collect(),
+            reordered,
+            old_metadata_len,
+            metadata_len: metadata_len(&blocks),
+            old_padding,
+            padding: blocks
+                .iter()
+                .filter(|(_, block)| block.block_type() == BlockType::Padding)
+                .map(|(_, block)| block.content_len())
+                .sum(),
+        })
+    }
+}
+
+/// Returns the length of the blocks including their headers.
+fn metadata_len(blocks: &[(usize, &Block)]) -> u64 {
+    blocks
+        .iter()
+        .map(|(_, block)| block.content_len() + 4)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::VorbisComment;
+    use crate::test_util::{test_path, test_streaminfo};
+
+    #[test]
+    fn plan_matches_save() {
+        let path = test_path("plan-save.flac");
+        let mut tag = Tag::new();
+        tag.push_block(Block::Padding(100));
+        tag.push_block(Block::StreamInfo(test_streaminfo()));
+        tag.save_to(&path).unwrap();
+        assert!(tag.plan_save().is_ok());
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["a".repeat(2000)]);
+        let plan = tag.plan_save().unwrap();
+        assert_eq!(plan.method, SaveMethod::Rewrite);
+        assert_eq!(
+            plan.blocks,
+            &[
+                BlockType::StreamInfo,
+                BlockType::VorbisComment,
+                BlockType::Padding
+            ]
+        );
+        assert_eq!(plan.padding_consumed(), 0);
+        tag.save().unwrap();
+        assert_eq!(plan.metadata_len, tag.region_len() as u64);
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.remove_vorbis("TITLE");
+        tag.insert_block_at(0, Block::VorbisComment(VorbisComment::new()))
+            .unwrap();
+        tag.remove_block_at(2).unwrap();
+        let plan = tag.plan_save().unwrap();
+        assert!(plan.reordered);
+        assert_eq!(plan.method, SaveMethod::InPlace);
+        tag.save().unwrap();
+        assert_eq!(plan.metadata_len, tag.region_len() as u64);
+        let padding = Tag::read_from_path(&path)
+            .unwrap()
+            .get_blocks(BlockType::Padding)
+            .map(Block::content_len)
+            .sum::<u64>();
+        assert_eq!(plan.padding, padding);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
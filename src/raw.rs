@@ -0,0 +1,783 @@
+use crate::error::{Error, ErrorKind, Result};
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::str;
+
+/// The largest length of the contents of a block which fits in the 24 bit length of a block
+/// header.
+pub(crate) const MAX_CONTENT_LEN: u32 = (1 << 24) - 1;
+
+// BlockType {{{
+/// Types of blocks. Used primarily to map blocks to block identifiers when reading and writing.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockType {
+    StreamInfo,
+    Padding,
+    Application,
+    SeekTable,
+    VorbisComment,
+    CueSheet,
+    Picture,
+    Unknown(u8),
+}
+
+#[allow(missing_docs)]
+impl BlockType {
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            BlockType::StreamInfo => 0,
+            BlockType::Padding => 1,
+            BlockType::Application => 2,
+            BlockType::SeekTable => 3,
+            BlockType::VorbisComment => 4,
+            BlockType::CueSheet => 5,
+            BlockType::Picture => 6,
+            BlockType::Unknown(n) => n,
+        }
+    }
+
+    pub(crate) fn from_u8(n: u8) -> BlockType {
+        match n {
+            0 => BlockType::StreamInfo,
+            1 => BlockType::Padding,
+            2 => BlockType::Application,
+            3 => BlockType::SeekTable,
+            4 => BlockType::VorbisComment,
+            5 => BlockType::CueSheet,
+            6 => BlockType::Picture,
+            n => BlockType::Unknown(n),
+        }
+    }
+}
+// }}}
+
+// Block {{{
+/// A metadata block borrowed from a byte slice. The contents of the block are not parsed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Block<'a> {
+    /// The type of the block.
+    pub block_type: BlockType,
+    /// True if the block is the last metadata block of the stream.
+    pub is_last: bool,
+    /// The offset of the block header from the start of the bytes it was read from.
+    pub offset: usize,
+    /// The contents of the block, without the block header.
+    pub data: &'a [u8],
+}
+
+impl<'a> Block<'a> {
+    /// Returns a new `Block` of the type with the contents, which is not the last block.
+    pub fn new(block_type: BlockType, data: &'a [u8]) -> Block<'a> {
+        Block {
+            block_type,
+            is_last: false,
+            offset: 0,
+            data,
+        }
+    }
+
+    /// Attempts to append the block header followed by the contents of the block to the vector.
+    ///
+    /// Returns an `InvalidLength` error without writing anything if the contents of the block do
+    /// not fit in the 24 bit length of the block header.
+    pub fn write_to(&self, out: &mut Vec<u8>) -> Result<()> {
+        let header = header(self.block_type, self.is_last, self.data.len())?;
+        out.extend_from_slice(&header);
+        out.extend_from_slice(self.data);
+        Ok(())
+    }
+}
+
+/// Returns the header of a block with contents of the length. Returns an `InvalidLength` error if
+/// the length does not fit in the 24 bit length of a block header.
+pub(crate) fn header(block_type: BlockType, is_last: bool, len: usize) -> Result<[u8; 4]> {
+    if len > MAX_CONTENT_LEN as usize {
+        return Err(too_long_error(block_type));
+    }
+
+    let mut header = (len as u32).to_be_bytes();
+    header[0] = block_type.to_u8() & 0x7F;
+    if is_last {
+        header[0] |= 0x80;
+    }
+    Ok(header)
+}
+
+/// Parses a block header, returning a tuple containing a boolean indicating if the block is the
+/// last block, the block type byte and the length of the contents.
+pub(crate) fn parse_header(header: [u8; 4]) -> (bool, u8, u32) {
+    let is_last = header[0] & 0x80 != 0;
+    let length = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+    (is_last, header[0] & 0x7F, length)
+}
+
+/// Returns the error for a block which does not fit in the 24 bit length of a block header.
+pub(crate) fn too_long_error(block_type: BlockType) -> Error {
+    let mut err = Error::new(
+        ErrorKind::InvalidLength,
+        "block is too long for the 24 bit length of a block header",
+    );
+    err.block_type = Some(block_type);
+    err
+}
+// }}}
+
+// Blocks {{{
+/// An iterator over the metadata blocks of a FLAC stream held in a byte slice.
+#[derive(Clone, Debug)]
+pub struct Blocks<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    finished: bool,
+}
+
+impl<'a> Blocks<'a> {
+    /// Attempts to find the `fLaC` marker at the start of the bytes, after any id3v2 tag. Returns
+    /// an iterator over the metadata blocks which follow the marker.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::raw::{self, Block, Blocks, VorbisComment};
+    /// use metaflac::BlockType;
+    ///
+    /// let vorbis = VorbisComment {
+    ///     vendor_string: "vendor",
+    ///     comments: vec![("TITLE", "title")],
+    ///     ..VorbisComment::default()
+    /// };
+    /// let contents = vorbis.to_bytes();
+    /// let mut bytes = Vec::new();
+    /// raw::write_metadata(&[Block::new(BlockType::VorbisComment, &contents)], &mut bytes).unwrap();
+    /// bytes.extend(b"audio");
+    ///
+    /// let mut blocks = Blocks::new(&bytes).unwrap();
+    /// for block in &mut blocks {
+    ///     let block = block.unwrap();
+    ///     if block.block_type == BlockType::VorbisComment {
+    ///         let vorbis = VorbisComment::from_bytes(block.data).unwrap();
+    ///         assert_eq!(vorbis.get("title").collect::<Vec<_>>(), &["title"]);
+    ///     }
+    /// }
+    /// assert_eq!(&bytes[blocks.offset()..], b"audio");
+    /// ```
+    pub fn new(bytes: &'a [u8]) -> Result<Blocks<'a>> {
+        let mut offset = 0;
+        if let Some(header) = bytes.get(..10) {
+            if let Some(id3_len) = id3v2_len(header.try_into().unwrap()) {
+                offset = id3_len.try_into().unwrap_or(usize::MAX);
+            }
+        }
+
+        match offset.checked_add(4).and_then(|end| bytes.get(offset..end)) {
+            Some(ident) if ident == b"fLaC" => Ok(Blocks {
+                bytes,
+                offset: offset + 4,
+                finished: false,
+            }),
+            _ => Err(not_flac_error()),
+        }
+    }
+
+    /// Returns the offset of the next block from the start of the bytes. Once the last block has
+    /// been returned, this is the offset of the first audio frame.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Reads the block whose header starts at the offset.
+    fn read_block(&mut self, offset: usize) -> Result<Block<'a>> {
+        let header = self.bytes.get(offset..offset + 4).ok_or_else(|| {
+            Error::new(ErrorKind::Truncated, "block header is truncated")
+                .in_block(None, offset as u64)
+        })?;
+        let (is_last, blocktype_byte, length) = parse_header(header.try_into().unwrap());
+        let block_type = BlockType::from_u8(blocktype_byte);
+        let length = length as usize;
+
+        let end = offset + 4 + length;
+        let data = self.bytes.get(offset + 4..end).ok_or_else(|| {
+            Error::new(ErrorKind::Truncated, "block is truncated")
+                .in_block(Some(block_type), offset as u64)
+        })?;
+        self.offset = end;
+
+        Ok(Block {
+            block_type,
+            is_last,
+            offset,
+            data,
+        })
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Result<Block<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let block = self.read_block(self.offset);
+        self.finished = block.as_ref().map_or(true, |block| block.is_last);
+        Some(block)
+    }
+}
+
+/// Attempts to append the `fLaC` marker followed by the blocks to the vector. Only the final block
+/// is marked as the last block, whatever the blocks say.
+///
+/// Returns an `InvalidLength` error without writing anything if the contents of a block do not fit
+/// in the 24 bit length of a block header.
+pub fn write_metadata(blocks: &[Block], out: &mut Vec<u8>) -> Result<()> {
+    let start = out.len();
+    out.extend_from_slice(b"fLaC");
+    for (i, block) in blocks.iter().enumerate() {
+        let block = Block {
+            is_last: i + 1 == blocks.len(),
+            ..*block
+        };
+        if let Err(err) = block.write_to(out) {
+            out.truncate(start);
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+// }}}
+
+// StreamInfo {{{
+/// The fields of a STREAMINFO block.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StreamInfo {
+    /// The minimum block size (in samples) used in the stream.
+    pub min_block_size: u16,
+    /// The maximum block size (in samples) used in the stream.
+    pub max_block_size: u16,
+    /// The minimum frame size (in bytes) used in the stream.
+    pub min_frame_size: u32,
+    /// The maximum frame size (in bytes) used in the stream.
+    pub max_frame_size: u32,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+    /// Number of channels. FLAC supports from 1 to 8 channels.
+    pub num_channels: u8,
+    /// Bits per sample. FLAC supports from 4 to 32 bits per sample.
+    pub bits_per_sample: u8,
+    /// Total samples in stream.
+    pub total_samples: u64,
+    /// MD5 signature of the unencoded audio data.
+    pub md5: [u8; 16],
+}
+
+impl StreamInfo {
+    /// Attempts to parse the bytes as a streaminfo block. Returns an `InvalidLength` error if
+    /// there are fewer than 34 bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<StreamInfo> {
+        if bytes.len() < 34 {
+            return Err(Error::new(
+                ErrorKind::InvalidLength,
+                "streaminfo block is shorter than 34 bytes",
+            ));
+        }
+
+        let be = |start: usize, end: usize| {
+            bytes[start..end]
+                .iter()
+                .fold(0, |n, &byte| n << 8 | byte as u64)
+        };
+
+        // first 16 bits of sample rate
+        let sample_first = be(10, 12) as u32;
+        // last 4 bits of sample rate, 3 bits of channel, first bit of bits/sample
+        let sample_channel_bps = bytes[12];
+        // last 4 bits of bits/sample, 36 bits of total samples
+        let bps_total = be(13, 18);
+
+        Ok(StreamInfo {
+            min_block_size: be(0, 2) as u16,
+            max_block_size: be(2, 4) as u16,
+            min_frame_size: be(4, 7) as u32,
+            max_frame_size: be(7, 10) as u32,
+            sample_rate: sample_first << 4 | (sample_channel_bps as u32) >> 4,
+            num_channels: ((sample_channel_bps >> 1) & 0x7) + 1,
+            bits_per_sample: ((sample_channel_bps & 0x1) << 4 | (bps_total >> 36) as u8) + 1,
+            total_samples: bps_total & 0xF_FF_FF_FF_FF,
+            md5: bytes[18..34].try_into().unwrap(),
+        })
+    }
+
+    /// Attempts to return a vector representation of the streaminfo block suitable for writing to
+    /// a file. Returns an `InvalidInput` error if a field does not fit in the block, such as a
+    /// number of channels other than 1 to 8 or a number of bits per sample other than 1 to 32.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let valid = (1..=8).contains(&self.num_channels)
+            && (1..=32).contains(&self.bits_per_sample)
+            && self.sample_rate < 1 << 20
+            && self.total_samples < 1 << 36
+            && self.min_frame_size < 1 << 24
+            && self.max_frame_size < 1 << 24;
+        if !valid {
+            let mut err = Error::new(
+                ErrorKind::InvalidInput,
+                "streaminfo field does not fit in a streaminfo block",
+            );
+            err.block_type = Some(BlockType::StreamInfo);
+            return Err(err);
+        }
+
+        let mut bytes = Vec::with_capacity(34);
+
+        bytes.extend_from_slice(&self.min_block_size.to_be_bytes());
+        bytes.extend_from_slice(&self.max_block_size.to_be_bytes());
+        bytes.extend_from_slice(&self.min_frame_size.to_be_bytes()[1..]);
+        bytes.extend_from_slice(&self.max_frame_size.to_be_bytes()[1..]);
+
+        // first 16 bits of sample rate
+        bytes.extend_from_slice(&((self.sample_rate >> 4) as u16).to_be_bytes());
+
+        // last 4 bits of sample rate, 3 bits of channel, first bit of bits/sample
+        let byte = ((self.sample_rate & 0xF) << 4) as u8
+            | (((self.num_channels - 1) & 0x7) << 1)
+            | (((self.bits_per_sample - 1) >> 4) & 0x1);
+        bytes.push(byte);
+
+        // last 4 bits of bits/sample, first 4 bits of sample count
+        let byte =
+            (((self.bits_per_sample - 1) & 0xF) << 4) | ((self.total_samples >> 32) & 0xF) as u8;
+        bytes.push(byte);
+
+        // last 32 bits of sample count
+        bytes.extend_from_slice(&((self.total_samples & 0xFF_FF_FF_FF) as u32).to_be_bytes());
+
+        bytes.extend_from_slice(&self.md5);
+
+        Ok(bytes)
+    }
+}
+// }}}
+
+// Picture {{{
+/// The fields of a PICTURE block, borrowed from the bytes of the block.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Picture<'a> {
+    /// The picture type, numbered as in the ID3v2 APIC frame.
+    pub picture_type: u32,
+    /// The MIME type of the picture.
+    pub mime_type: &'a str,
+    /// The description of the picture.
+    pub description: &'a str,
+    /// The width of the picture in pixels.
+    pub width: u32,
+    /// The height of the picture in pixels.
+    pub height: u32,
+    /// The color depth of the picture in bits per pixel.
+    pub depth: u32,
+    /// The number of colors used for indexed pictures, or 0 for other pictures.
+    pub num_colors: u32,
+    /// The binary picture data.
+    pub data: &'a [u8],
+}
+
+impl<'a> Picture<'a> {
+    /// Attempts to parse the bytes as a picture block.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Picture<'a>> {
+        let (mut picture, data_length, i) = Picture::read_fields(bytes)?;
+        let data = &bytes[i..];
+        if data.len() < data_length as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "picture data is shorter than its length",
+            ));
+        }
+        picture.data = &data[..data_length as usize];
+        Ok(picture)
+    }
+
+    /// Parses the fields of a picture block which precede the picture data. Returns the picture
+    /// without its data, the length of the data and the offset of the data within the bytes.
+    pub(crate) fn read_fields(bytes: &'a [u8]) -> Result<(Picture<'a>, u32, usize)> {
+        const TRUNCATED: &str = "truncated picture block";
+
+        let mut i = 0;
+        let be_u32 = |i: &mut usize| -> Result<u32> {
+            Ok(u32::from_be_bytes(
+                field(bytes, i, 4, TRUNCATED)?.try_into().unwrap(),
+            ))
+        };
+
+        let picture_type = be_u32(&mut i)?;
+        let mime_length = be_u32(&mut i)? as usize;
+        let mime_type = utf8(field(bytes, &mut i, mime_length, TRUNCATED)?)?;
+        let description_length = be_u32(&mut i)? as usize;
+        let description = utf8(field(bytes, &mut i, description_length, TRUNCATED)?)?;
+        let width = be_u32(&mut i)?;
+        let height = be_u32(&mut i)?;
+        let depth = be_u32(&mut i)?;
+        let num_colors = be_u32(&mut i)?;
+        let data_length = be_u32(&mut i)?;
+
+        let picture = Picture {
+            picture_type,
+            mime_type,
+            description,
+            width,
+            height,
+            depth,
+            num_colors,
+            data: &[],
+        };
+        Ok((picture, data_length, i))
+    }
+
+    /// Returns a vector representation of the picture block suitable for writing to a file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&self.picture_type.to_be_bytes());
+
+        bytes.extend_from_slice(&(self.mime_type.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.mime_type.as_bytes());
+
+        bytes.extend_from_slice(&(self.description.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.description.as_bytes());
+
+        bytes.extend_from_slice(&self.width.to_be_bytes());
+        bytes.extend_from_slice(&self.height.to_be_bytes());
+        bytes.extend_from_slice(&self.depth.to_be_bytes());
+        bytes.extend_from_slice(&self.num_colors.to_be_bytes());
+
+        bytes.extend_from_slice(&(self.data.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.data);
+
+        bytes
+    }
+}
+// }}}
+
+// VorbisComment {{{
+/// The comments of a VORBIS_COMMENT block, borrowed from the bytes of the block.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VorbisComment<'a> {
+    /// The vendor string.
+    pub vendor_string: &'a str,
+    /// The key and value of each comment, in the order in which they appear in the block.
+    pub comments: Vec<(&'a str, &'a str)>,
+    /// The comments without a `=` separator, which have no key or value, each with the number of
+    /// comments in `comments` which come before it in the block.
+    pub unseparated: Vec<(usize, &'a str)>,
+}
+
+impl<'a> VorbisComment<'a> {
+    /// Attempts to parse the bytes as a vorbis comment block. Comments without a `=` separator
+    /// are kept in `unseparated`.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<VorbisComment<'a>> {
+        let (vendor_string, comments) = read_comments(bytes, None, |bytes, _| utf8(bytes))?;
+        Ok(VorbisComment::from_comments(vendor_string, comments))
+    }
+
+    /// Returns the vorbis comment with the vendor string and comments, splitting each comment
+    /// into its key and value.
+    pub(crate) fn from_comments<I: IntoIterator<Item = &'a str>>(
+        vendor_string: &'a str,
+        comments: I,
+    ) -> VorbisComment<'a> {
+        let mut vorbis = VorbisComment {
+            vendor_string,
+            ..VorbisComment::default()
+        };
+        for comment in comments {
+            match comment.find('=') {
+                Some(sep) => vorbis.comments.push((&comment[..sep], &comment[sep + 1..])),
+                None => {
+                    debug!("Keeping comment without a separator: {}", comment);
+                    vorbis.unseparated.push((vorbis.comments.len(), comment));
+                }
+            }
+        }
+        vorbis
+    }
+
+    /// Returns an iterator over the values of the comments with the key, which is compared
+    /// ignoring ASCII case.
+    pub fn get<'s>(&'s self, key: &'s str) -> impl Iterator<Item = &'a str> + 's {
+        self.comments
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|&(_, value)| value)
+    }
+
+    /// Returns a vector representation of the vorbis comment block suitable for writing to a
+    /// file. Each comment in `unseparated` is written after the number of comments given with
+    /// it, or after the last comment if there are fewer.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&(self.vendor_string.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.vendor_string.as_bytes());
+
+        let num_comments = self.comments.len() + self.unseparated.len();
+        bytes.extend_from_slice(&(num_comments as u32).to_le_bytes());
+        let write_unseparated = |bytes: &mut Vec<u8>, position: usize| {
+            for &(_, comment) in self
+                .unseparated
+                .iter()
+                .filter(|&&(i, _)| i.min(self.comments.len()) == position)
+            {
+                debug!("Writing comment without a separator: {}", comment);
+                bytes.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(comment.as_bytes());
+            }
+        };
+        for (i, (key, value)) in self.comments.iter().enumerate() {
+            write_unseparated(&mut bytes, i);
+            debug!("Writing comment: {}={}", key, value);
+            bytes.extend_from_slice(&((key.len() + 1 + value.len()) as u32).to_le_bytes());
+            bytes.extend_from_slice(key.as_bytes());
+            bytes.push(b'=');
+            bytes.extend_from_slice(value.as_bytes());
+        }
+        write_unseparated(&mut bytes, self.comments.len());
+
+        bytes
+    }
+}
+
+/// Splits the bytes of a vorbis comment block into the vendor string and the comments, decoding
+/// each with `decode`, which is told whether it decodes the vendor string. Returns an error if the
+/// block contains more than `max_comments` comments.
+pub(crate) fn read_comments<'b, S, F>(
+    bytes: &'b [u8],
+    max_comments: Option<u32>,
+    mut decode: F,
+) -> Result<(S, Vec<S>)>
+where
+    F: FnMut(&'b [u8], bool) -> Result<S>,
+{
+    const TRUNCATED: &str = "truncated vorbis comment block";
+
+    let mut i = 0;
+    let le_u32 = |i: &mut usize| -> Result<u32> {
+        Ok(u32::from_le_bytes(
+            field(bytes, i, 4, TRUNCATED)?.try_into().unwrap(),
+        ))
+    };
+
+    let vendor_length = le_u32(&mut i)? as usize;
+    let vendor_string = decode(field(bytes, &mut i, vendor_length, TRUNCATED)?, true)?;
+
+    let num_comments = le_u32(&mut i)?;
+    if max_comments.is_some_and(|max_comments| num_comments > max_comments) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "vorbis comment block exceeds the maximum number of comments",
+        ));
+    }
+
+    let mut comments = Vec::new();
+    for _ in 0..num_comments {
+        let comment_length = le_u32(&mut i)? as usize;
+        comments.push(decode(
+            field(bytes, &mut i, comment_length, TRUNCATED)?,
+            false,
+        )?);
+    }
+
+    Ok((vendor_string, comments))
+}
+
+// }}}
+
+/// Returns the next `len` bytes of a block starting at `i`, advancing `i` past them, or a
+/// `Truncated` error with the description if the block ends first.
+fn field<'b>(
+    bytes: &'b [u8],
+    i: &mut usize,
+    len: usize,
+    description: &'static str,
+) -> Result<&'b [u8]> {
+    let field = i
+        .checked_add(len)
+        .and_then(|end| bytes.get(*i..end))
+        .ok_or_else(|| Error::new(ErrorKind::Truncated, description))?;
+    *i += len;
+    Ok(field)
+}
+
+/// Borrows the bytes as a string. Invalid UTF-8 gives the same `StringDecoding` error as
+/// converting the bytes to an owned string.
+fn utf8(bytes: &[u8]) -> Result<&str> {
+    str::from_utf8(bytes).map_err(|_| String::from_utf8(bytes.to_vec()).unwrap_err().into())
+}
+
+/// Returns the total length of the id3v2 tag starting with the header, including the header and
+/// any footer, or `None` if the header is not an id3 v2.2, v2.3 or v2.4 header.
+pub(crate) fn id3v2_len(header: &[u8; 10]) -> Option<u64> {
+    if &header[0..3] != b"ID3" || ![0x02, 0x03, 0x04].contains(&header[3]) {
+        return None;
+    }
+    // Header layout from the id3v2 tag spec:
+    // 3 Bytes: "ID3"
+    // 2 Bytes: Maj/Min version
+    // 1 Byte: Flags, bit 0x10 indicates a 10-Byte footer
+    // 4 Bytes: size of the Tag, excluding header and footer, taking 7 bits per byte.
+    let has_footer = header[5] & 0x10 > 0;
+    let size = (header[6] as u64 & 0b_0111_1111) << 21
+        | (header[7] as u64 & 0b_0111_1111) << 14
+        | (header[8] as u64 & 0b_0111_1111) << 7
+        | (header[9] as u64 & 0b_0111_1111);
+    Some(if has_footer { size + 20 } else { size + 10 })
+}
+
+/// Returns the error for a stream without the `fLaC` marker.
+pub(crate) fn not_flac_error() -> Error {
+    Error::new(ErrorKind::NotFlac, "reader does not contain flac metadata")
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::block::{self, PictureType};
+    use crate::tag::Tag;
+    use crate::test_util::test_streaminfo;
+
+    fn test_tag() -> Tag {
+        let mut streaminfo = test_streaminfo();
+        streaminfo.sample_rate = 44100;
+        streaminfo.total_samples = 44100 * 90;
+        streaminfo.md5 = (0..16).collect();
+
+        let mut tag = Tag::new();
+        tag.push_block(block::Block::StreamInfo(streaminfo));
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.set_vorbis("ARTIST", vec!["a", "b"]);
+        tag.add_picture("image/png", PictureType::CoverFront, vec![1, 2, 3]);
+        tag.push_block(block::Block::Padding(10));
+        tag
+    }
+
+    #[test]
+    fn read_blocks() {
+        let mut tag = test_tag();
+        let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x02\x00\x00".to_vec();
+        bytes.extend(tag.write_to_vec().unwrap());
+        bytes.extend(b"audio");
+
+        let mut blocks = Blocks::new(&bytes).unwrap();
+        let raw_blocks = (&mut blocks).collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(&bytes[blocks.offset()..], b"audio");
+        assert!(blocks.next().is_none());
+        assert_eq!(
+            raw_blocks
+                .iter()
+                .map(|block| block.block_type)
+                .collect::<Vec<_>>(),
+            tag.blocks()
+                .map(|block| block.block_type())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(raw_blocks[0].offset, 16);
+        assert!(raw_blocks.last().unwrap().is_last);
+
+        let streaminfo = StreamInfo::from_bytes(raw_blocks[0].data).unwrap();
+        assert_eq!(
+            block::StreamInfo::from(streaminfo),
+            *tag.get_streaminfo().unwrap()
+        );
+
+        let vorbis = VorbisComment::from_bytes(raw_blocks[1].data).unwrap();
+        assert_eq!(
+            vorbis.vendor_string,
+            tag.vorbis_comments().unwrap().vendor_string
+        );
+        assert_eq!(vorbis.get("title").collect::<Vec<_>>(), &["title"]);
+        assert_eq!(vorbis.get("Artist").collect::<Vec<_>>(), &["a", "b"]);
+        assert_eq!(
+            block::VorbisComment::from_bytes(&vorbis.to_bytes()).unwrap(),
+            *tag.vorbis_comments().unwrap()
+        );
+
+        let picture = Picture::from_bytes(raw_blocks[2].data).unwrap();
+        assert_eq!(picture.picture_type, PictureType::CoverFront as u32);
+        assert_eq!(picture.mime_type, "image/png");
+        assert_eq!(picture.data, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn write_blocks() {
+        let mut bytes = test_tag().write_to_vec().unwrap();
+        let blocks = Blocks::new(&bytes)
+            .unwrap()
+            .filter(|block| {
+                block
+                    .as_ref()
+                    .map_or(true, |block| block.block_type != BlockType::Picture)
+            })
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        let mut out = Vec::new();
+        write_metadata(&blocks, &mut out).unwrap();
+        let tag = Tag::read_from_bytes(&out).unwrap();
+        assert_eq!(tag.pictures().count(), 0);
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+        assert!(matches!(
+            tag.blocks().last(),
+            Some(block::Block::Padding(10))
+        ));
+
+        let long = vec![0; MAX_CONTENT_LEN as usize + 1];
+        let blocks = [Block::new(BlockType::Padding, &long)];
+        let err = write_metadata(&blocks, &mut out).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidLength));
+        assert_eq!(Tag::read_from_bytes(&out).unwrap(), tag);
+
+        // a truncated block ends the iteration with an error at its offset
+        bytes.truncate(50);
+        let mut blocks = Blocks::new(&bytes).unwrap();
+        assert!(blocks.next().unwrap().is_ok());
+        let err = blocks.next().unwrap().unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::Truncated));
+        assert_eq!(err.offset, Some(42));
+        assert!(blocks.next().is_none());
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        for bytes in [&b""[..], b"fLa", b"ID3\x04", b"not flac"].iter() {
+            let err = Blocks::new(bytes).unwrap_err();
+            assert!(matches!(err.kind, ErrorKind::NotFlac));
+        }
+
+        let err = StreamInfo::from_bytes(&[0; 33]).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidLength));
+
+        let vorbis = VorbisComment {
+            vendor_string: "vendor",
+            comments: vec![("TITLE", "title")],
+            unseparated: vec![(0, "unseparated"), (1, "end")],
+        };
+        let bytes = vorbis.to_bytes();
+        assert_eq!(VorbisComment::from_bytes(&bytes).unwrap(), vorbis);
+        let err = VorbisComment::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::Truncated));
+
+        let mut bytes = bytes;
+        let len = bytes.len();
+        bytes[len - 1] = 0xFF;
+        let err = VorbisComment::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::StringDecoding(_)));
+
+        let mut picture = block::Picture::new();
+        picture.data = vec![1, 2, 3];
+        let bytes = picture.to_bytes();
+        let err = Picture::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidInput));
+    }
+}
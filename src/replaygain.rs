@@ -0,0 +1,47 @@
+/// The gain and peak computed for a track or an album.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GainResult {
+    /// The gain in dB which brings the audio to the reference loudness.
+    pub gain: f64,
+    /// The peak sample amplitude, where 1.0 is full scale.
+    pub peak: f64,
+}
+
+/// An implementation of a ReplayGain loudness analysis.
+///
+/// The crate does not decode audio, so the caller feeds the analyzer decoded samples, for example
+/// from a decoder crate, and writes the results with `Tag::apply_gain_results`.
+pub trait GainAnalyzer {
+    /// Analyzes a chunk of decoded audio. Each slice holds the samples of one channel, normalized
+    /// to the range -1.0 to 1.0. Every slice has the same length.
+    fn analyze(&mut self, channels: &[&[f64]], sample_rate: u32);
+
+    /// Finishes the analysis of the current track and returns its result. Samples fed afterwards
+    /// belong to the next track.
+    fn finish_track(&mut self) -> GainResult;
+
+    /// Returns the result for all tracks analyzed so far taken together as an album.
+    fn finish_album(&mut self) -> GainResult;
+}
+
+/// Formats a gain as the value of a REPLAYGAIN_*_GAIN comment, e.g. `-7.50 dB`.
+pub(crate) fn format_gain(gain: f64) -> String {
+    format!("{:+.2} dB", gain)
+}
+
+/// Formats a peak as the value of a REPLAYGAIN_*_PEAK comment.
+pub(crate) fn format_peak(peak: f64) -> String {
+    format!("{:.8}", peak)
+}
+
+/// Parses the value of a REPLAYGAIN_* comment, ignoring any `dB` unit.
+pub(crate) fn parse_value(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = match value.len().checked_sub(2) {
+        Some(i) if value.is_char_boundary(i) && value[i..].eq_ignore_ascii_case("db") => {
+            &value[..i]
+        }
+        _ => value,
+    };
+    value.trim().parse().ok()
+}
@@ -0,0 +1,168 @@
+use crate::block::PictureType;
+use crate::error::Error;
+use crate::tag::Tag;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// The location of a picture within a set of files.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PictureLocation {
+    /// The path of the file containing the picture.
+    pub path: PathBuf,
+    /// The index of the picture among the pictures of the file.
+    pub index: usize,
+    /// The picture type.
+    pub picture_type: PictureType,
+    /// The length of the picture data in bytes.
+    pub len: usize,
+}
+
+/// The embedded artwork of a single file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileArtwork {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The number of pictures in the file.
+    pub num_pictures: usize,
+    /// The total length of the picture data in the file in bytes.
+    pub len: u64,
+}
+
+/// A picture which is embedded more than once.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuplicatePicture {
+    /// The hash of the picture data.
+    pub hash: u64,
+    /// The length of the picture data in bytes.
+    pub len: usize,
+    /// Every location at which the picture is embedded.
+    pub locations: Vec<PictureLocation>,
+}
+
+/// A summary of the embedded artwork of a set of files.
+#[derive(Debug)]
+pub struct ArtworkReport {
+    /// The artwork of each file which could be read.
+    pub files: Vec<FileArtwork>,
+    /// The total length of the picture data in all files in bytes.
+    pub total_len: u64,
+    /// Pictures with identical data which are embedded more than once.
+    pub duplicates: Vec<DuplicatePicture>,
+    /// Pictures whose data is larger than the requested limit.
+    pub oversized: Vec<PictureLocation>,
+    /// Files which could not be read.
+    pub errors: Vec<(PathBuf, Error)>,
+}
+
+/// Reads the tags of the files at the specified paths and summarizes their embedded artwork.
+/// Pictures with more than `max_picture_len` bytes of data are reported as oversized.
+pub fn artwork<I, P>(paths: I, max_picture_len: usize) -> ArtworkReport
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut report = ArtworkReport {
+        files: Vec::new(),
+        total_len: 0,
+        duplicates: Vec::new(),
+        oversized: Vec::new(),
+        errors: Vec::new(),
+    };
+    let mut by_hash: HashMap<u64, Vec<PictureLocation>> = HashMap::new();
+    let mut hashes = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref().to_path_buf();
+        let tag = match Tag::read_from_path(&path) {
+            Ok(tag) => tag,
+            Err(err) => {
+                report.errors.push((path, err));
+                continue;
+            }
+        };
+
+        let mut file = FileArtwork {
+            path: path.clone(),
+            num_pictures: 0,
+            len: 0,
+        };
+
+        for (index, picture) in tag.pictures().enumerate() {
+            let location = PictureLocation {
+                path: path.clone(),
+                index,
+                picture_type: picture.picture_type,
+                len: picture.data.len(),
+            };
+
+            file.num_pictures += 1;
+            file.len += picture.data.len() as u64;
+
+            if picture.data.len() > max_picture_len {
+                report.oversized.push(location.clone());
+            }
+
+            let mut hasher = DefaultHasher::new();
+            picture.data.hash(&mut hasher);
+            let hash = hasher.finish();
+            if !by_hash.contains_key(&hash) {
+                hashes.push(hash);
+            }
+            by_hash.entry(hash).or_default().push(location);
+        }
+
+        report.total_len += file.len;
+        report.files.push(file);
+    }
+
+    // report duplicates in the order they were first seen
+    for hash in hashes {
+        let locations = by_hash.remove(&hash).unwrap();
+        if locations.len() > 1 {
+            report.duplicates.push(DuplicatePicture {
+                hash,
+                len: locations[0].len,
+                locations,
+            });
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_path, test_stream};
+
+    #[test]
+    fn duplicate_and_oversized_artwork() {
+        let mut paths = Vec::new();
+        for (i, cover) in [vec![1; 10], vec![1; 10], vec![2; 100]].iter().enumerate() {
+            let path = test_path(&format!("report-{}.flac", i));
+            std::fs::write(&path, test_stream()).unwrap();
+
+            let mut tag = Tag::read_from_path(&path).unwrap();
+            tag.add_picture("image/png", PictureType::CoverFront, cover.clone());
+            tag.save().unwrap();
+            paths.push(path);
+        }
+        paths.push(test_path("report-missing.flac"));
+
+        let report = artwork(&paths, 50);
+        assert_eq!(report.files.len(), 3);
+        assert_eq!(report.total_len, 120);
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].locations.len(), 2);
+        assert_eq!(report.oversized.len(), 1);
+        assert_eq!(report.oversized[0].path, paths[2]);
+        assert_eq!(report.errors.len(), 1);
+
+        for path in &paths[..3] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
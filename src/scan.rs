@@ -0,0 +1,197 @@
+use crate::error::{Error, Result};
+use crate::options::ReadOptions;
+use crate::tag::{is_temp_path, Tag};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Options controlling which files are read by a scan.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScanOptions {
+    /// Descends into subdirectories. When disabled only the files directly inside the scanned
+    /// directory are read.
+    pub recursive: bool,
+    /// The extensions of the files to read, compared ignoring ASCII case. Every file is read if
+    /// the list is empty. The temporary files of rewrites in progress are never read.
+    pub extensions: Vec<String>,
+    /// The options each tag is read with.
+    pub read_options: ReadOptions,
+}
+
+impl ScanOptions {
+    /// Returns a new `ScanOptions` which recursively reads the files with a `flac` extension.
+    pub fn new() -> ScanOptions {
+        ScanOptions {
+            recursive: true,
+            extensions: vec!["flac".to_string()],
+            read_options: ReadOptions::new(),
+        }
+    }
+
+    /// Returns true if the file at the path is read by the scan.
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        if is_temp_path(path) {
+            return false;
+        }
+        self.extensions.is_empty()
+            || path.extension().is_some_and(|ext| {
+                self.extensions
+                    .iter()
+                    .any(|wanted| ext.eq_ignore_ascii_case(wanted))
+            })
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Recursively reads the tags of the FLAC files under the directory at the specified path. See
+/// `scan_dir_with`.
+pub fn scan_dir<P: AsRef<Path>>(path: P) -> impl Iterator<Item = (PathBuf, Result<Tag>)> {
+    scan_dir_with(path, &ScanOptions::new())
+}
+
+/// Reads the tags of the files selected by the options under the directory at the specified
+/// path. Files are yielded in path order, each with the result of reading its tag. A directory
+/// which cannot be listed is yielded with the error, before any of the files.
+///
+/// The directory tree is listed up front, and the tags are read as the iterator advances. With
+/// the `rayon` feature the tags are read in parallel, in batches on the rayon thread pool.
+///
+/// # Example
+/// ```no_run
+/// use metaflac::scan::{self, ScanOptions};
+///
+/// let mut options = ScanOptions::new();
+/// options.extensions.push("oga".to_string());
+/// for (path, tag) in scan::scan_dir_with("music", &options) {
+///     match tag {
+///         Ok(tag) => println!("{}: {} pictures", path.display(), tag.pictures().count()),
+///         Err(err) => eprintln!("{}: {}", path.display(), err),
+///     }
+/// }
+/// ```
+pub fn scan_dir_with<P: AsRef<Path>>(
+    path: P,
+    options: &ScanOptions,
+) -> impl Iterator<Item = (PathBuf, Result<Tag>)> {
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+    find_files(path.as_ref(), options, true, &mut paths, &mut errors);
+
+    let errors = errors.into_iter().map(|(path, err)| (path, Err(err)));
+    let read_options = options.read_options.clone();
+    errors.chain(read_tags(paths, read_options))
+}
+
+/// Reads the tags of the files at the paths in order.
+#[cfg(not(feature = "rayon"))]
+fn read_tags(
+    paths: Vec<PathBuf>,
+    options: ReadOptions,
+) -> impl Iterator<Item = (PathBuf, Result<Tag>)> {
+    paths.into_iter().map(move |path| {
+        let tag = Tag::read_from_path_with(&path, &options);
+        (path, tag)
+    })
+}
+
+/// Reads the tags of the files at the paths in order, reading each batch in parallel.
+#[cfg(feature = "rayon")]
+fn read_tags(
+    paths: Vec<PathBuf>,
+    options: ReadOptions,
+) -> impl Iterator<Item = (PathBuf, Result<Tag>)> {
+    // enough files to keep every thread busy, without holding the tags of the whole tree
+    let batch_len = rayon::current_num_threads() * 8;
+    let batches: Vec<Vec<PathBuf>> = paths.chunks(batch_len).map(<[_]>::to_vec).collect();
+    batches.into_iter().flat_map(move |batch| {
+        batch
+            .into_par_iter()
+            .map(|path| {
+                let tag = Tag::read_from_path_with(&path, &options);
+                (path, tag)
+            })
+            .collect::<Vec<_>>()
+    })
+}
+
+/// Collects the paths of the files selected by the options under the path, in sorted order.
+fn find_files(
+    path: &Path,
+    options: &ScanOptions,
+    is_root: bool,
+    paths: &mut Vec<PathBuf>,
+    errors: &mut Vec<(PathBuf, Error)>,
+) {
+    if path.is_dir() {
+        if !is_root && !options.recursive {
+            return;
+        }
+        let children = fs::read_dir(path).and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()
+        });
+        match children {
+            Ok(mut children) => {
+                children.sort();
+                for child in children {
+                    find_files(&child, options, false, paths, errors);
+                }
+            }
+            Err(err) => errors.push((path.to_path_buf(), err.into())),
+        }
+    } else if options.matches(path) {
+        paths.push(path.to_path_buf());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_path, test_stream};
+
+    #[test]
+    fn scan_directory_tree() {
+        let root = test_path("scan");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("album")).unwrap();
+
+        let bytes = test_stream();
+        fs::write(root.join("a.flac"), &bytes).unwrap();
+        fs::write(root.join("album").join("b.FLAC"), &bytes).unwrap();
+        fs::write(root.join("album").join("c.flac"), b"not flac").unwrap();
+        fs::write(root.join("notes.txt"), b"notes").unwrap();
+        fs::write(root.join(".a.flac.123.tmp"), &bytes).unwrap();
+
+        let results: Vec<_> = scan_dir(&root).collect();
+        let paths: Vec<_> = results.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            paths,
+            &[
+                root.join("a.flac"),
+                root.join("album").join("b.FLAC"),
+                root.join("album").join("c.flac"),
+            ]
+        );
+        assert!(results[0].1.is_ok());
+        assert!(results[1].1.is_ok());
+        assert!(results[2].1.is_err());
+
+        let mut options = ScanOptions::new();
+        options.recursive = false;
+        assert_eq!(scan_dir_with(&root, &options).count(), 1);
+
+        options.extensions.clear();
+        assert_eq!(scan_dir_with(&root, &options).count(), 2);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
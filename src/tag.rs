@@ -1,14 +1,31 @@
-use crate::block::{Block, BlockType, Blocks, Picture, PictureType, StreamInfo, VorbisComment};
-use crate::error::{Error, ErrorKind, Result};
+use crate::ape::ApeTag;
+use crate::block::{
+    chapter_comments, Application, Block, BlockLocation, BlockType, Chapter, CueSheet, Picture,
+    PictureType, SeekTable, StreamInfo, VorbisComment,
+};
+use crate::error::{Error, ErrorKind, ParseWarning, Result};
+use crate::id3v1::Id3v1;
+use crate::journal::{Journal, JournalEntry, JOURNAL_APPLICATION_ID};
+use crate::options::{CopyOptions, PaddingPolicy, ParseMode, ReadOptions, WriteOptions};
+use crate::replaygain::{format_gain, format_peak, GainResult};
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use byteorder::{BigEndian, ReadBytesExt};
 
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsString;
+use std::fs::{File, FileTimes, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 /// A structure representing a flac metadata tag.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Tag {
     /// The path from which the blocks were loaded.
     path: Option<PathBuf>,
@@ -16,6 +33,37 @@ pub struct Tag {
     blocks: Vec<Block>,
     /// The size of the metadata when the file was read.
     length: u32,
+    /// The byte range of any ID3v2 tag found before the `fLaC` marker when the file was read.
+    id3v2: Option<Range<u64>>,
+    /// The byte range of any junk found before the `fLaC` marker when the file was read.
+    junk: Option<Range<u64>>,
+    /// The ID3v1 tag found at the end of the file when it was read.
+    id3v1: Option<Id3v1>,
+    /// The APEv2 tag found at the end of the file when it was read.
+    ape: Option<ApeTag>,
+    /// The locations of the blocks when the file was last read or written.
+    layout: Vec<BlockLocation>,
+    /// The violations of the specification tolerated while reading.
+    warnings: Vec<ParseWarning>,
+    /// The byte ranges of damaged metadata skipped while reading in recovery mode.
+    skipped: Vec<Range<u64>>,
+    /// The handle retained for writing back to the file which was read.
+    file: Option<Arc<File>>,
+    /// The changes made to the vorbis comments, if journaling is enabled.
+    journal: Option<Journal>,
+    /// The pictures decoded from legacy `COVERART` vorbis comments, with the index of the value
+    /// each was decoded from.
+    legacy_pictures: Vec<(usize, Picture)>,
+    /// Whether the tag was read from a FLAC stream encapsulated in Ogg.
+    ogg: bool,
+    /// Whether the blocks were modified since the tag was read or last written to a file.
+    dirty: bool,
+    /// Whether blocks were left out while reading because of `ReadOptions::block_types`.
+    partial: bool,
+    /// The `WriteOptions::preserve_order` setting with which the blocks were arranged by the last
+    /// write to a file, which left them in write order followed by its padding block. Only
+    /// holds while the tag is not dirty.
+    arranged: Option<bool>,
 }
 
 impl<'a> Tag {
@@ -25,6 +73,20 @@ impl<'a> Tag {
             path: None,
             blocks: Vec::new(),
             length: 0,
+            id3v2: None,
+            junk: None,
+            id3v1: None,
+            ape: None,
+            layout: Vec::new(),
+            warnings: Vec::new(),
+            skipped: Vec::new(),
+            file: None,
+            journal: None,
+            legacy_pictures: Vec::new(),
+            ogg: false,
+            dirty: false,
+            partial: false,
+            arranged: None,
         }
     }
 
@@ -36,6 +98,166 @@ impl<'a> Tag {
         }
 
         self.blocks.push(block);
+        self.dirty = true;
+    }
+
+    /// Returns the location of every block when the file was last read or written, in file order.
+    /// The locations are not updated when the blocks are changed, only when the tag is written.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{BlockType, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// assert!(tag.block_layout().is_empty());
+    ///
+    /// let mut bytes = Vec::new();
+    /// tag.write_to(&mut bytes).unwrap();
+    ///
+    /// let tag = Tag::read_from(&mut &bytes[..]).unwrap();
+    /// let layout = tag.block_layout();
+    /// assert_eq!(layout[0].block_type, BlockType::VorbisComment);
+    /// assert_eq!(layout[0].offset, 4);
+    /// assert_eq!(layout[0].length as usize, bytes.len() - 4);
+    /// ```
+    pub fn block_layout(&self) -> &[BlockLocation] {
+        &self.layout[..]
+    }
+
+    /// Records the location of every block, given the offset of the first block header and the
+    /// length of each block.
+    fn set_layout(&mut self, mut offset: u64, lengths: &[u32]) {
+        self.layout = self
+            .blocks
+            .iter()
+            .zip(lengths)
+            .map(|(block, &length)| {
+                let location = BlockLocation {
+                    block_type: block.block_type(),
+                    offset,
+                    length,
+                };
+                offset += length as u64;
+                location
+            })
+            .collect();
+    }
+
+    /// Returns the violations of the FLAC specification which were tolerated while reading the
+    /// tag with `ParseMode::Lenient`.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{ParseMode, ReadOptions, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// let bytes = tag.write_to_vec().unwrap();
+    ///
+    /// let tag = Tag::read_from_bytes(&bytes).unwrap();
+    /// assert_eq!(tag.warnings()[0].message, "first block is not a streaminfo block");
+    ///
+    /// let mut options = ReadOptions::new();
+    /// options.parse_mode = ParseMode::Strict;
+    /// assert!(Tag::read_from_with(&mut &bytes[..], &options).is_err());
+    /// ```
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings[..]
+    }
+
+    /// Returns the byte ranges of the damaged metadata which was skipped while reading the tag
+    /// with `ParseMode::Recover`.
+    pub fn skipped_regions(&self) -> &[Range<u64>] {
+        &self.skipped[..]
+    }
+
+    /// Returns the size in bytes of the blocks as they would be written, including the block
+    /// headers but not the `fLaC` marker. Comparing this against the total length of the blocks in
+    /// `Tag::block_layout` tells whether a save can write the blocks in place.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    ///
+    /// let mut bytes = Vec::new();
+    /// tag.write_to(&mut bytes).unwrap();
+    /// assert_eq!(tag.metadata_len(), bytes.len() as u64 - 4);
+    /// ```
+    pub fn metadata_len(&self) -> u64 {
+        self.blocks
+            .iter()
+            .map(|block| block.content_len() + 4)
+            .sum()
+    }
+
+    /// Returns the byte range of the ID3v2 tag which was skipped before the `fLaC` marker. Returns
+    /// `None` if the stream did not start with an ID3v2 tag.
+    pub fn id3v2(&self) -> Option<Range<u64>> {
+        self.id3v2.clone()
+    }
+
+    /// Returns the ID3v1 tag found at the end of the file when the tag was read from a path.
+    pub fn id3v1(&self) -> Option<&Id3v1> {
+        self.id3v1.as_ref()
+    }
+
+    /// Copies the fields of the ID3v1 tag found when the tag was read into vorbis comments. Only
+    /// keys without any values are set, so existing comments take precedence. Returns the number
+    /// of comments which were set.
+    pub fn import_id3v1(&mut self) -> usize {
+        let comments = match self.id3v1 {
+            Some(ref id3v1) => id3v1.comments(),
+            None => return 0,
+        };
+        self.import_missing_comments(comments)
+    }
+
+    /// Returns the APEv2 tag found at the end of the file when the tag was read from a path.
+    pub fn ape(&self) -> Option<&ApeTag> {
+        self.ape.as_ref()
+    }
+
+    /// Copies the text items of the APEv2 tag found when the tag was read into vorbis comments.
+    /// Only keys without any values are set, so existing comments take precedence. Returns the
+    /// number of values which were set.
+    pub fn import_ape(&mut self) -> usize {
+        let comments = match self.ape {
+            Some(ref ape) => ape.comments(),
+            None => return 0,
+        };
+        self.import_missing_comments(comments)
+    }
+
+    /// Appends the values to the vorbis comments whose keys had no values beforehand. Returns the
+    /// number of values which were appended.
+    fn import_missing_comments<K: AsRef<str>>(&mut self, comments: Vec<(K, String)>) -> usize {
+        let existing: Vec<String> = self
+            .vorbis_comments()
+            .map(VorbisComment::keys)
+            .unwrap_or_default();
+
+        let mut count = 0;
+        for (key, value) in comments {
+            let key = key.as_ref().to_ascii_uppercase();
+            if existing.contains(&key) {
+                continue;
+            }
+            let mut values = self.get_vorbis_values(&key).unwrap_or_default();
+            values.push(value);
+            self.set_vorbis(key, values);
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns the byte range of the junk which was skipped while searching for the `fLaC` marker.
+    /// Returns `None` if the marker was found where it was expected.
+    pub fn junk(&self) -> Option<Range<u64>> {
+        self.junk.clone()
     }
 
     /// Returns a reference to the blocks in the tag.
@@ -64,7 +286,230 @@ impl<'a> Tag {
     /// assert_eq!(tag.blocks().count(), 1);
     /// ```
     pub fn remove_blocks(&mut self, block_type: BlockType) {
+        let count = self.blocks.len();
         self.blocks.retain(|b| b.block_type() != block_type);
+        self.dirty |= self.blocks.len() != count;
+        if block_type == BlockType::VorbisComment {
+            self.load_legacy_coverart();
+        }
+    }
+
+    /// Merges the comments of every vorbis comment block into the first vorbis comment block, in
+    /// block order, and removes the other vorbis comment blocks. Returns whether any blocks were
+    /// merged.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::VorbisComment;
+    /// use metaflac::{Block, BlockType, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// let mut other = VorbisComment::new();
+    /// other.set("ARTIST", vec!["artist"]);
+    /// tag.push_block(Block::VorbisComment(other));
+    ///
+    /// assert!(tag.normalize());
+    /// assert_eq!(tag.get_blocks(BlockType::VorbisComment).count(), 1);
+    /// assert_eq!(tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(), &["artist"]);
+    /// assert!(!tag.normalize());
+    /// ```
+    pub fn normalize(&mut self) -> bool {
+        let first = match self
+            .blocks
+            .iter()
+            .position(|block| block.block_type() == BlockType::VorbisComment)
+        {
+            Some(first) => first,
+            None => return false,
+        };
+
+        let mut others = Vec::new();
+        let mut i = first + 1;
+        while i < self.blocks.len() {
+            if self.blocks[i].block_type() != BlockType::VorbisComment {
+                i += 1;
+            } else if let Block::VorbisComment(other) = self.blocks.remove(i) {
+                others.push(other);
+            }
+        }
+        if others.is_empty() {
+            return false;
+        }
+
+        if let Block::VorbisComment(ref mut vorbis) = self.blocks[first] {
+            for other in others.iter() {
+                vorbis.merge(other);
+            }
+        }
+        self.dirty = true;
+        self.load_legacy_coverart();
+        true
+    }
+
+    /// Cleans up the comments of every vorbis comment block, removing the empty keys, blank
+    /// values and NUL characters often left by imported metadata. If `dedupe` is set, repeated
+    /// key/value pairs are removed as well. See `VorbisComment::sanitize`. Returns whether any
+    /// comments changed.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title\0"]);
+    /// tag.set_vorbis("ARTIST", vec!["artist", "artist", "  "]);
+    ///
+    /// assert!(tag.sanitize(true));
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+    /// assert_eq!(tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(), &["artist"]);
+    /// ```
+    pub fn sanitize(&mut self, dedupe: bool) -> bool {
+        let old: Vec<_> = match self.journal {
+            Some(_) => {
+                let mut keys = self
+                    .vorbis_comments()
+                    .map(VorbisComment::keys)
+                    .unwrap_or_default();
+                keys.sort();
+                keys.into_iter()
+                    .map(|key| {
+                        let values = self.values(&key);
+                        (key, values)
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        let mut changed = false;
+        for block in self.blocks.iter_mut() {
+            if let Block::VorbisComment(ref mut vorbis) = *block {
+                changed |= vorbis.sanitize(dedupe);
+            }
+        }
+        if !changed {
+            return false;
+        }
+        self.dirty = true;
+
+        // sanitizing only removes comments, so the old keys cover every change
+        for (key, values) in old {
+            self.record(key, values);
+        }
+        self.load_legacy_coverart();
+        true
+    }
+
+    /// Inserts a block at the specified position among the blocks of the tag, shifting the blocks
+    /// after it. Returns an `InvalidInput` error if the index is greater than the number of
+    /// blocks.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{Block, BlockType, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.push_block(Block::Padding(10));
+    /// tag.insert_block_at(0, Block::Unknown((20, Vec::new()))).unwrap();
+    ///
+    /// let types: Vec<_> = tag.blocks().map(|block| block.block_type()).collect();
+    /// assert_eq!(types, &[BlockType::Unknown(20), BlockType::Padding]);
+    /// assert!(tag.insert_block_at(3, Block::Padding(0)).is_err());
+    /// ```
+    pub fn insert_block_at(&mut self, index: usize, block: Block) -> Result<()> {
+        if index > self.blocks.len() {
+            return Err(block_index_error());
+        }
+        let is_vorbis = block.block_type() == BlockType::VorbisComment;
+        self.blocks.insert(index, block);
+        self.dirty = true;
+        if is_vorbis {
+            self.load_legacy_coverart();
+        }
+        Ok(())
+    }
+
+    /// Replaces the block at the specified position, returning the block which was replaced.
+    /// Returns an `InvalidInput` error if there is no block at the index.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{Block, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.push_block(Block::Padding(10));
+    ///
+    /// let old = tag.replace_block(0, Block::Padding(20)).unwrap();
+    /// assert_eq!(old, Block::Padding(10));
+    /// assert_eq!(tag.blocks().next(), Some(&Block::Padding(20)));
+    /// ```
+    pub fn replace_block(&mut self, index: usize, block: Block) -> Result<Block> {
+        let slot = self.blocks.get_mut(index).ok_or_else(block_index_error)?;
+        let old = std::mem::replace(slot, block);
+        self.dirty = true;
+        if old.block_type() == BlockType::VorbisComment
+            || self.blocks[index].block_type() == BlockType::VorbisComment
+        {
+            self.load_legacy_coverart();
+        }
+        Ok(old)
+    }
+
+    /// Removes the block at the specified position, returning it. Returns an `InvalidInput`
+    /// error if there is no block at the index.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{Block, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.push_block(Block::Padding(10));
+    /// tag.push_block(Block::Padding(20));
+    ///
+    /// assert_eq!(tag.remove_block_at(0).unwrap(), Block::Padding(10));
+    /// assert_eq!(tag.blocks().count(), 1);
+    /// assert!(tag.remove_block_at(1).is_err());
+    /// ```
+    pub fn remove_block_at(&mut self, index: usize) -> Result<Block> {
+        if index >= self.blocks.len() {
+            return Err(block_index_error());
+        }
+        let block = self.blocks.remove(index);
+        self.dirty = true;
+        if block.block_type() == BlockType::VorbisComment {
+            self.load_legacy_coverart();
+        }
+        Ok(block)
+    }
+
+    /// Removes every block except the streaminfo block and blocks of the specified types, like
+    /// `metaflac --remove-all`. When the tag is saved in place the space of the removed blocks is
+    /// turned into padding.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{Block, BlockType, Tag};
+    /// use metaflac::block::{PictureType, StreamInfo};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_streaminfo(StreamInfo::new());
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// tag.add_picture("image/png", PictureType::CoverFront, vec![1, 2, 3]);
+    ///
+    /// tag.strip(&[BlockType::Picture]);
+    /// assert!(tag.vorbis_comments().is_none());
+    /// assert_eq!(tag.pictures().count(), 1);
+    /// assert_eq!(tag.blocks().count(), 2);
+    /// ```
+    pub fn strip(&mut self, keep: &[BlockType]) {
+        let count = self.blocks.len();
+        self.blocks.retain(|block| {
+            let block_type = block.block_type();
+            block_type == BlockType::StreamInfo || keep.contains(&block_type)
+        });
+        self.dirty |= self.blocks.len() != count;
+        self.load_legacy_coverart();
     }
 
     /// Returns a reference to the first vorbis comment block.
@@ -104,23 +549,67 @@ impl<'a> Tag {
     /// let value1 = "value1".to_owned();
     /// let value2 = "value2".to_owned();
     ///
-    /// tag.vorbis_comments_mut().comments.insert(key.clone(), vec!(value1.clone(),
-    ///     value2.clone()));
+    /// tag.vorbis_comments_mut().comments.push((key.clone(), value1.clone()));
+    /// tag.vorbis_comments_mut().comments.push((key.clone(), value2.clone()));
     ///
     /// assert!(tag.vorbis_comments().is_some());
-    /// assert!(tag.vorbis_comments().unwrap().comments.get(&key).is_some());
+    /// assert_eq!(tag.vorbis_comments().unwrap().get(&key).unwrap(), &[value1, value2]);
     /// ```
     pub fn vorbis_comments_mut(&mut self) -> &mut VorbisComment {
-        for i in 0..self.blocks.len() {
-            unsafe {
-                if let Block::VorbisComment(ref mut comm) = *self.blocks.as_mut_ptr().add(i) {
-                    return comm;
-                }
+        self.dirty = true;
+        self.comments_mut()
+    }
+
+    /// Returns a mutable reference to the first vorbis comment block, adding a vorbis comment
+    /// block if there is none, without marking the tag as modified unless the block is added.
+    fn comments_mut(&mut self) -> &mut VorbisComment {
+        let index = match self
+            .blocks
+            .iter()
+            .position(|block| block.block_type() == BlockType::VorbisComment)
+        {
+            Some(index) => index,
+            None => {
+                self.push_block(Block::VorbisComment(VorbisComment::new()));
+                self.blocks.len() - 1
             }
+        };
+
+        match self.blocks[index] {
+            Block::VorbisComment(ref mut comm) => comm,
+            _ => unreachable!(),
         }
+    }
+
+    /// Returns the vendor string of the vorbis comment block, which identifies the encoder.
+    /// Returns `None` if the tag does not contain a vorbis comment block.
+    ///
+    /// The vendor string read from a file is kept as it is when the comments are changed and the
+    /// tag is saved.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// assert!(tag.vendor_string().is_none());
+    ///
+    /// tag.set_vendor_string("reference libFLAC 1.4.3 20230623");
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// assert_eq!(tag.vendor_string(), Some("reference libFLAC 1.4.3 20230623"));
+    /// ```
+    pub fn vendor_string(&self) -> Option<&str> {
+        self.vorbis_comments()
+            .map(|comments| comments.vendor_string.as_str())
+    }
 
-        self.push_block(Block::VorbisComment(VorbisComment::new()));
-        self.vorbis_comments_mut()
+    /// Sets the vendor string of the vorbis comment block, adding a vorbis comment block if there
+    /// is none.
+    pub fn set_vendor_string<T: Into<String>>(&mut self, vendor_string: T) {
+        let vendor_string = vendor_string.into();
+        if self.vendor_string() != Some(&vendor_string) {
+            self.vorbis_comments_mut().vendor_string = vendor_string;
+        }
     }
 
     /// Returns a vector of strings values for the specified vorbis comment key.
@@ -144,10 +633,33 @@ impl<'a> Tag {
     pub fn get_vorbis(&'a self, key: &str) -> Option<impl Iterator<Item = &'a str> + 'a> {
         self.vorbis_comments()
             .and_then(|c| c.get(&key.to_ascii_uppercase()))
-            .map(|l| l.iter().map(|s| s.as_ref()))
+            .map(|values| values.into_iter())
+    }
+
+    /// Returns a copy of the values for the specified vorbis comment key, in order.
+    /// Returns `None` if the tag does not contain a vorbis comment or if the vorbis comment does
+    /// not contain a comment with the specified key.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("ARTIST", vec!["artist 1", "artist 2"]);
+    ///
+    /// assert_eq!(
+    ///     tag.get_vorbis_values("artist"),
+    ///     Some(vec!["artist 1".to_owned(), "artist 2".to_owned()])
+    /// );
+    /// assert_eq!(tag.get_vorbis_values("GENRE"), None);
+    /// ```
+    pub fn get_vorbis_values(&self, key: &str) -> Option<Vec<String>> {
+        self.get_vorbis(key)
+            .map(|values| values.map(String::from).collect())
     }
 
-    /// Sets the values for the specified vorbis comment key.
+    /// Sets the values for the specified vorbis comment key. Characters which are not allowed in
+    /// field names are removed from the key; use `try_set_vorbis` to detect invalid keys instead.
     ///
     /// # Example
     /// ```
@@ -164,8 +676,38 @@ impl<'a> Tag {
     /// assert_eq!(tag.get_vorbis(&key).unwrap().collect::<Vec<_>>(), &[&value1, &value2]);
     /// ```
     pub fn set_vorbis<K: Into<String>, V: Into<String>>(&mut self, key: K, values: Vec<V>) {
-        self.vorbis_comments_mut()
-            .set(key.into().to_ascii_uppercase(), values);
+        let key = VorbisComment::sanitize_key(&key.into()).to_ascii_uppercase();
+        let old = self.values(&key);
+        self.comments_mut().set(key.clone(), values);
+        self.comment_changed(key, old);
+    }
+
+    /// Sets the values for the specified vorbis comment key. Returns an error without changing the
+    /// tag if the key is not a valid field name.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    ///
+    /// assert!(tag.try_set_vorbis("TITLE", vec!["title"]).is_ok());
+    /// assert!(tag.try_set_vorbis("TITLE=", vec!["title"]).is_err());
+    /// ```
+    pub fn try_set_vorbis<K: Into<String>, V: Into<String>>(
+        &mut self,
+        key: K,
+        values: Vec<V>,
+    ) -> Result<()> {
+        let key = key.into();
+        if !VorbisComment::is_valid_key(&key) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid vorbis comment key",
+            ));
+        }
+        self.set_vorbis(key, values);
+        Ok(())
     }
 
     /// Removes the values for the specified vorbis comment key.
@@ -187,9 +729,10 @@ impl<'a> Tag {
     /// assert!(tag.get_vorbis(&key).is_none());
     /// ```
     pub fn remove_vorbis(&mut self, key: &str) {
-        self.vorbis_comments_mut()
-            .comments
-            .remove(&key.to_ascii_uppercase());
+        let key = key.to_ascii_uppercase();
+        let old = self.values(&key);
+        self.comments_mut().remove(&key);
+        self.comment_changed(key, old);
     }
 
     /// Removes the vorbis comments with the specified key and value.
@@ -211,341 +754,4153 @@ impl<'a> Tag {
     /// assert_eq!(tag.get_vorbis(&key).unwrap().collect::<Vec<_>>(), &[&value2]);
     /// ```
     pub fn remove_vorbis_pair(&mut self, key: &str, value: &str) {
-        self.vorbis_comments_mut()
-            .remove_pair(&key.to_ascii_uppercase(), value);
+        let key = key.to_ascii_uppercase();
+        let old = self.values(&key);
+        self.comments_mut().remove_pair(&key, value);
+        self.comment_changed(key, old);
     }
 
-    /// Returns an iterator of references to the pictures in the tag.
+    /// Imports vorbis comments from text in the `KEY=value` line format used by
+    /// `metaflac --import-tags-from`. Each value is added to any existing values of its key, so
+    /// existing comments are preserved and a key may be repeated to give it multiple values.
+    /// Empty lines are ignored. Returns an error without changing the tag if any line is not of
+    /// the form `KEY=value`.
     ///
     /// # Example
     /// ```
     /// use metaflac::Tag;
-    /// use metaflac::block::PictureType::CoverFront;
     ///
     /// let mut tag = Tag::new();
-    /// assert_eq!(tag.pictures().count(), 0);
-    /// tag.add_picture("image/jpeg", CoverFront, vec!(0xFF));
-    /// assert_eq!(tag.pictures().count(), 1);
+    /// tag.set_vorbis("ARTIST", vec!["artist 1"]);
+    ///
+    /// tag.import_comments_from_str("ARTIST=artist 2\nTITLE=title\n").unwrap();
+    /// assert_eq!(
+    ///     tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(),
+    ///     &["artist 1", "artist 2"]
+    /// );
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+    ///
+    /// assert!(tag.import_comments_from_str("no separator").is_err());
     /// ```
-    pub fn pictures(&'a self) -> impl Iterator<Item = &'a Picture> + 'a {
-        self.blocks.iter().filter_map(|block| match *block {
-            Block::Picture(ref picture) => Some(picture),
-            _ => None,
-        })
+    pub fn import_comments_from_str(&mut self, s: &str) -> Result<()> {
+        let mut imported: Vec<(String, Vec<&str>)> = Vec::new();
+        for line in s.lines() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(i) if VorbisComment::is_valid_key(&line[..i]) => {
+                    (line[..i].to_ascii_uppercase(), &line[i + 1..])
+                }
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "malformed comment line, expected KEY=value",
+                    ))
+                }
+            };
+
+            match imported.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, values)) => values.push(value),
+                None => imported.push((key, vec![value])),
+            }
+        }
+
+        for (key, values) in imported {
+            let mut all = self.get_vorbis_values(&key).unwrap_or_default();
+            all.extend(values.into_iter().map(String::from));
+            self.set_vorbis(key, all);
+        }
+
+        Ok(())
     }
 
-    /// Adds a picture block.
+    /// Starts recording the changes made to the vorbis comments through the methods of the tag.
+    /// Any journal previously written to the tag with `write_journal_block` is continued.
+    /// Changes made directly through `vorbis_comments_mut` are not recorded.
     ///
     /// # Example
     /// ```
     /// use metaflac::Tag;
-    /// use metaflac::block::PictureType::CoverFront;
     ///
     /// let mut tag = Tag::new();
-    /// assert_eq!(tag.pictures().count(), 0);
+    /// tag.set_vorbis("TITLE", vec!["old"]);
     ///
-    /// tag.add_picture("image/jpeg", CoverFront, vec!(0xFF));
+    /// tag.enable_journal().unwrap();
+    /// tag.set_vorbis("TITLE", vec!["new"]);
     ///
-    /// let picture = tag.pictures().next().unwrap();
-    /// assert_eq!(&picture.mime_type, "image/jpeg");
-    /// assert_eq!(picture.picture_type, CoverFront);
-    /// assert_eq!(&picture.data, &vec!(0xFF));
+    /// let entry = &tag.journal().unwrap().entries[0];
+    /// assert_eq!(entry.key, "TITLE");
+    /// assert_eq!(entry.old, Some(vec!["old".to_owned()]));
+    /// assert_eq!(entry.new, Some(vec!["new".to_owned()]));
     /// ```
-    pub fn add_picture<T: Into<String>>(
-        &mut self,
-        mime_type: T,
-        picture_type: PictureType,
-        data: Vec<u8>,
-    ) {
-        self.remove_picture_type(picture_type);
+    pub fn enable_journal(&mut self) -> Result<()> {
+        if self.journal.is_some() {
+            return Ok(());
+        }
 
-        let mut picture = Picture::new();
-        picture.mime_type = mime_type.into();
-        picture.picture_type = picture_type;
-        picture.data = data;
+        let journal = match self.application_blocks(JOURNAL_APPLICATION_ID).last() {
+            Some(application) => Journal::from_bytes(&application.data)?,
+            None => Journal::new(),
+        };
 
-        self.push_block(Block::Picture(picture));
+        self.journal = Some(journal);
+        Ok(())
     }
 
-    /// Removes the picture with the specified picture type.
+    /// Returns the journal of changes made to the vorbis comments.
+    /// Returns `None` if journaling is not enabled.
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    /// Writes the journal into a private APPLICATION block, replacing any previous journal block,
+    /// so that it is saved along with the tag. Does nothing if journaling is not enabled.
+    pub fn write_journal_block(&mut self) {
+        let data = match self.journal {
+            Some(ref journal) => journal.to_bytes(),
+            None => return,
+        };
+
+        self.blocks.retain(|block| match *block {
+            Block::Application(ref application) => application.id != JOURNAL_APPLICATION_ID,
+            _ => true,
+        });
+
+        self.add_application_block(JOURNAL_APPLICATION_ID, data);
+    }
+
+    /// Returns the current values of the key.
+    fn values(&self, key: &str) -> Option<Vec<String>> {
+        self.get_vorbis_values(key)
+    }
+
+    /// Updates the state which depends on the vorbis comments after the values of the key changed.
+    fn comment_changed(&mut self, key: String, old: Option<Vec<String>>) {
+        if old != self.values(&key) {
+            self.dirty = true;
+        }
+        if key.starts_with("COVERART") {
+            self.load_legacy_coverart();
+        }
+        self.record(key, old);
+    }
+
+    /// Records a change of the key in the journal if journaling is enabled and the values changed.
+    fn record(&mut self, key: String, old: Option<Vec<String>>) {
+        if self.journal.is_none() {
+            return;
+        }
+
+        let new = self.get_vorbis_values(&key);
+        if old != new {
+            self.journal.as_mut().unwrap().entries.push(JournalEntry {
+                key,
+                old,
+                new,
+                timestamp: SystemTime::now(),
+            });
+        }
+    }
+
+    /// Writes the results of a ReplayGain analysis to the REPLAYGAIN_TRACK_GAIN and
+    /// REPLAYGAIN_TRACK_PEAK comments, and to the REPLAYGAIN_ALBUM_GAIN and
+    /// REPLAYGAIN_ALBUM_PEAK comments if an album result is given.
     ///
     /// # Example
     /// ```
     /// use metaflac::Tag;
-    /// use metaflac::block::PictureType::{CoverFront, Other};
+    /// use metaflac::replaygain::GainResult;
     ///
     /// let mut tag = Tag::new();
-    /// assert_eq!(tag.pictures().count(), 0);
-    ///
-    /// tag.add_picture("image/jpeg", CoverFront, vec!(0xFF));
-    /// tag.add_picture("image/png", Other, vec!(0xAB));
-    /// assert_eq!(tag.pictures().count(), 2);
-    ///
-    /// tag.remove_picture_type(CoverFront);
-    /// assert_eq!(tag.pictures().count(), 1);
+    /// tag.apply_gain_results(GainResult { gain: -7.5, peak: 0.98 }, None);
     ///
-    /// let picture = tag.pictures().next().unwrap();
-    /// assert_eq!(&picture.mime_type, "image/png");
-    /// assert_eq!(picture.picture_type, Other);
-    /// assert_eq!(&picture.data, &vec!(0xAB));
+    /// let gain = tag.get_vorbis("REPLAYGAIN_TRACK_GAIN").unwrap();
+    /// assert_eq!(gain.collect::<Vec<_>>(), &["-7.50 dB"]);
+    /// let peak = tag.get_vorbis("REPLAYGAIN_TRACK_PEAK").unwrap();
+    /// assert_eq!(peak.collect::<Vec<_>>(), &["0.98000000"]);
+    /// assert!(tag.get_vorbis("REPLAYGAIN_ALBUM_GAIN").is_none());
     /// ```
-    pub fn remove_picture_type(&mut self, picture_type: PictureType) {
-        self.blocks.retain(|block: &Block| match *block {
-            Block::Picture(ref picture) => picture.picture_type != picture_type,
-            _ => true,
-        });
+    pub fn apply_gain_results(&mut self, track: GainResult, album: Option<GainResult>) {
+        self.set_vorbis("REPLAYGAIN_TRACK_GAIN", vec![format_gain(track.gain)]);
+        self.set_vorbis("REPLAYGAIN_TRACK_PEAK", vec![format_peak(track.peak)]);
+
+        if let Some(album) = album {
+            self.set_vorbis("REPLAYGAIN_ALBUM_GAIN", vec![format_gain(album.gain)]);
+            self.set_vorbis("REPLAYGAIN_ALBUM_PEAK", vec![format_peak(album.peak)]);
+        }
     }
 
-    /// Returns a reference to the first streaminfo block.
-    /// Returns `None` if no streaminfo blocks are found.
+    /// Returns an iterator of references to the pictures in the tag. Pictures stored in legacy
+    /// `COVERART` vorbis comments follow the picture blocks.
     ///
     /// # Example
     /// ```
     /// use metaflac::Tag;
-    /// use metaflac::block::StreamInfo;
+    /// use metaflac::block::PictureType::CoverFront;
     ///
     /// let mut tag = Tag::new();
-    /// assert!(tag.get_streaminfo().is_none());
-    /// tag.set_streaminfo(StreamInfo::new());
-    /// assert!(tag.get_streaminfo().is_some());
+    /// assert_eq!(tag.pictures().count(), 0);
+    /// tag.add_picture("image/jpeg", CoverFront, vec!(0xFF));
+    /// assert_eq!(tag.pictures().count(), 1);
     /// ```
-    pub fn get_streaminfo(&self) -> Option<&StreamInfo> {
-        for block in self.blocks() {
-            if let Block::StreamInfo(ref info) = *block {
-                return Some(info);
-            }
-        }
-
-        None
+    pub fn pictures(&'a self) -> impl Iterator<Item = &'a Picture> + 'a {
+        self.blocks
+            .iter()
+            .filter_map(|block| match *block {
+                Block::Picture(ref picture) => Some(picture),
+                _ => None,
+            })
+            .chain(self.legacy_pictures.iter().map(|(_, picture)| picture))
     }
 
-    /// Sets the streaminfo block. If there is already a streaminfo block then it will be replaced.
+    /// Converts pictures stored in legacy base64 `COVERART` vorbis comments, with their MIME
+    /// types in `COVERARTMIME`, into picture blocks and removes the comments. Returns the number
+    /// of pictures which were converted. Returns an error without changing the tag if a
+    /// `COVERART` value is not valid base64.
     ///
     /// # Example
     /// ```
     /// use metaflac::Tag;
-    /// use metaflac::block::StreamInfo;
+    /// use metaflac::block::PictureType::CoverFront;
     ///
     /// let mut tag = Tag::new();
-    /// tag.set_streaminfo(StreamInfo::new());
-    /// assert!(tag.get_streaminfo().is_some());
+    /// tag.set_vorbis("COVERART", vec!["AQID"]);
+    /// tag.set_vorbis("COVERARTMIME", vec!["image/png"]);
+    /// assert_eq!(tag.pictures().next().unwrap().data, &[1, 2, 3]);
+    ///
+    /// assert_eq!(tag.migrate_legacy_coverart().unwrap(), 1);
+    /// assert!(tag.get_vorbis("COVERART").is_none());
+    ///
+    /// let picture = tag.pictures().next().unwrap();
+    /// assert_eq!(picture.picture_type, CoverFront);
+    /// assert_eq!(&picture.mime_type, "image/png");
+    /// assert_eq!(picture.data, &[1, 2, 3]);
     /// ```
-    pub fn set_streaminfo(&mut self, block: StreamInfo) {
-        self.remove_blocks(BlockType::StreamInfo);
-        self.blocks.insert(0, Block::StreamInfo(block));
-    }
-
-    /// Attempts to save the tag back to the file which it was read from. An `Error::InvalidInput`
-    /// will be returned if this is called on a tag which was not read from a file.
-    pub fn save(&mut self) -> Result<()> {
-        if self.path.is_none() {
+    pub fn migrate_legacy_coverart(&mut self) -> Result<usize> {
+        let num_values = self
+            .get_vorbis("COVERART")
+            .map_or(0, |values| values.count());
+        if self.legacy_pictures.len() != num_values {
             return Err(Error::new(
                 ErrorKind::InvalidInput,
-                "attempted to save file which was not read from a path",
+                "COVERART comment is not valid base64",
             ));
         }
 
-        let path = self.path.clone().unwrap();
-        self.write_to_path(&path)
-    }
-
-    /// Returns the contents of the reader without any FLAC metadata.
-    pub fn skip_metadata<R: Read + Seek>(reader: &mut R) -> Vec<u8> {
-        macro_rules! try_io {
-            ($reader:ident, $action:expr) => {
-                match $action {
-                    Ok(bytes) => bytes,
-                    Err(_) => match $reader.seek(SeekFrom::Start(0)) {
-                        Ok(_) => {
-                            let mut data = Vec::new();
-                            match $reader.read_to_end(&mut data) {
-                                Ok(_) => return data,
-                                Err(_) => return Vec::new(),
-                            }
-                        }
-                        Err(_) => return Vec::new(),
-                    },
-                }
-            };
+        let pictures = self.legacy_pictures.clone();
+        for (_, picture) in pictures.iter() {
+            self.push_block(Block::Picture(picture.clone()));
         }
+        self.remove_vorbis("COVERART");
+        self.remove_vorbis("COVERARTMIME");
 
-        let mut ident = [0; 4];
-        try_io!(reader, reader.read_exact(&mut ident));
-        if &ident[..] == b"fLaC" {
-            let mut more = true;
-            while more {
-                let header = try_io!(reader, reader.read_u32::<BigEndian>());
+        Ok(pictures.len())
+    }
 
-                more = ((header >> 24) & 0x80) == 0;
-                let length = header & 0xFF_FF_FF;
+    /// Decodes the pictures stored in legacy `COVERART` vorbis comments, keeping the index of the
+    /// value each picture was decoded from. Values which are not valid base64 are skipped.
+    pub(crate) fn load_legacy_coverart(&mut self) {
+        self.legacy_pictures.clear();
 
-                debug!("Skipping {} bytes", length);
-                try_io!(reader, reader.seek(SeekFrom::Current(length as i64)));
-            }
-        } else {
-            try_io!(reader, reader.seek(SeekFrom::Start(0)));
-        }
+        let comments = match self.vorbis_comments() {
+            Some(comments) => comments,
+            None => return,
+        };
+        let mime_types = comments.get("COVERARTMIME");
+        let pictures = comments
+            .get("COVERART")
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(i, value)| {
+                let data = match BASE64.decode(value.trim()) {
+                    Ok(data) => data,
+                    Err(_) => {
+                        debug!("Skipping COVERART comment which is not valid base64");
+                        return None;
+                    }
+                };
+                let mut picture = Picture::new();
+                picture.picture_type = PictureType::CoverFront;
+                picture.mime_type = mime_types
+                    .as_ref()
+                    .and_then(|mime_types| mime_types.get(i))
+                    .map(|mime_type| String::from(*mime_type))
+                    .unwrap_or_default();
+                picture.data = data;
+                Some((i, picture))
+            })
+            .collect();
 
-        let mut data = Vec::new();
-        try_io!(reader, reader.read_to_end(&mut data));
-        data
+        self.legacy_pictures = pictures;
     }
 
-    /// Will return true if the reader is a candidate for FLAC metadata. The reader position will be
-    /// reset back to the previous position before returning.
-    pub fn is_candidate<R: Read + Seek>(reader: &mut R) -> bool {
-        macro_rules! try_or_false {
-            ($action:expr) => {
-                match $action {
-                    Ok(result) => result,
-                    Err(_) => return false,
-                }
-            };
+    /// Attempts to load the data of any pictures which was skipped while reading the tag with
+    /// `ReadOptions::skip_picture_data`. The data is read from the file the tag was read from.
+    pub fn load_picture_data(&mut self) -> Result<()> {
+        if self.pictures().all(Picture::is_loaded) {
+            return Ok(());
         }
 
-        let mut ident = [0; 4];
-        try_or_false!(reader.read_exact(&mut ident));
-        let _ = reader.seek(SeekFrom::Current(-4));
-        &ident[..] == b"fLaC"
+        let mut source = self.picture_source()?;
+        for block in self.blocks.iter_mut() {
+            if let Block::Picture(ref mut picture) = *block {
+                picture.load_data(&mut source)?;
+            }
+        }
+        Ok(())
     }
 
-    /// Attempts to read a FLAC tag from the reader.
-    pub fn read_from(reader: &mut dyn Read) -> Result<Tag> {
-        let mut tag = Tag::new();
+    /// Replaces the URLs of pictures which link to their image with the images themselves, using
+    /// the fetch function as described for `Picture::resolve_url`. Returns the number of pictures
+    /// which were resolved. Stops at the first error returned by the fetch function.
+    pub fn resolve_picture_urls<F>(&mut self, mut fetch: F) -> Result<usize>
+    where
+        F: FnMut(&str) -> Result<(String, Vec<u8>)>,
+    {
+        self.load_picture_data()?;
 
-        for result in Blocks::new(reader) {
-            let (length, block) = result?;
-            tag.length += length;
-            tag.blocks.push(block);
+        let mut count = 0;
+        for block in self.blocks.iter_mut() {
+            if let Block::Picture(ref mut picture) = *block {
+                if picture.resolve_url(&mut fetch)? {
+                    count += 1;
+                }
+            }
         }
-
-        Ok(tag)
+        self.dirty |= count > 0;
+        Ok(count)
     }
 
-    /// Attempts to write the FLAC tag to the writer.
-    pub fn write_to(&mut self, writer: &mut dyn Write) -> Result<()> {
-        writer.write_all(b"fLaC")?;
-
-        let nblocks = self.blocks.len();
-        self.length = 0;
-        for i in 0..nblocks {
-            let block = &self.blocks[i];
-            self.length += block.write_to(i == nblocks - 1, writer)?;
+    /// Returns a copy of the block with any deferred picture data loaded.
+    fn loaded_block(&self, block: &Block) -> Result<Block> {
+        let mut block = block.clone();
+        if let Block::Picture(ref mut picture) = block {
+            if !picture.is_loaded() {
+                picture.load_data(&mut self.picture_source()?)?;
+            }
         }
-
-        Ok(())
+        Ok(block)
     }
 
-    /// Attempts to write the FLAC tag to a file at the indicated path. If the specified path is
-    /// the same path which the tag was read from, then the tag will be written to the padding if
-    /// possible.
-    pub fn write_to_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        self.remove_blocks(BlockType::Padding);
-
-        let mut block_bytes = Vec::new();
-        let nblocks = self.blocks.len();
-        let mut new_length = 0;
-        for i in 0..nblocks {
-            let block = &self.blocks[i];
-            let mut writer = Vec::<u8>::new();
-            new_length += block.write_to(false, &mut writer)?;
-            block_bytes.push(writer);
+    /// Opens the file from which deferred picture data is loaded.
+    fn picture_source(&self) -> Result<File> {
+        match (&self.file, &self.path) {
+            (Some(file), _) => Ok(file.try_clone()?),
+            (None, Some(path)) => Ok(File::open(path)?),
+            (None, None) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "picture data can only be loaded from the file the tag was read from",
+            )),
         }
+    }
 
-        // write using padding
-        if self.path.is_some()
-            && path.as_ref() == self.path.as_ref().unwrap().as_path()
-            && new_length + 4 <= self.length
-        {
-            debug!("Writing using padding");
-            let mut file = OpenOptions::new()
-                .write(true)
-                .read(true)
-                .open(self.path.as_ref().unwrap())?;
-            crate::block::read_ident(&mut file)?;
+    /// Adds a picture block. With the `image` feature enabled, the dimensions of the picture are
+    /// read from the image data.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::PictureType::CoverFront;
+    ///
+    /// let mut tag = Tag::new();
+    /// assert_eq!(tag.pictures().count(), 0);
+    ///
+    /// tag.add_picture("image/jpeg", CoverFront, vec!(0xFF));
+    ///
+    /// let picture = tag.pictures().next().unwrap();
+    /// assert_eq!(&picture.mime_type, "image/jpeg");
+    /// assert_eq!(picture.picture_type, CoverFront);
+    /// assert_eq!(&picture.data, &vec!(0xFF));
+    /// ```
+    pub fn add_picture<T: Into<String>>(
+        &mut self,
+        mime_type: T,
+        picture_type: PictureType,
+        data: Vec<u8>,
+    ) {
+        self.remove_picture_type(picture_type);
 
-            for bytes in block_bytes.iter() {
-                file.write_all(&bytes[..])?;
-            }
+        let mut picture = Picture::new();
+        picture.mime_type = mime_type.into();
+        picture.picture_type = picture_type;
+        picture.data = data;
+        #[cfg(feature = "image")]
+        picture.read_dimensions();
 
-            let padding = Block::Padding(self.length - new_length - 4);
-            padding.write_to(true, &mut file)?;
-            self.push_block(padding);
-        } else {
-            // write by copying file data
-            debug!("Writing to new file");
+        self.push_block(Block::Picture(picture));
+    }
 
-            let data_opt = {
-                match File::open(&path) {
-                    Ok(mut file) => Some(Tag::skip_metadata(&mut file)),
-                    Err(_) => None,
-                }
-            };
+    /// Reads the image file at the specified path and adds it as a picture of the specified type,
+    /// replacing any picture of that type. The MIME type is detected from the image data, and an
+    /// error is returned if the image is not a JPEG, PNG, GIF or WebP image.
+    pub fn add_picture_from_path<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        picture_type: PictureType,
+    ) -> Result<()> {
+        let data = std::fs::read(path)?;
+        let mime_type = Picture::detect_mime_type(&data)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "unrecognized image format"))?;
+        self.add_picture(mime_type, picture_type, data);
+        Ok(())
+    }
 
-            let mut file = OpenOptions::new()
-                .write(true)
-                .truncate(true)
-                .create(true)
-                .open(&path)?;
+    /// Removes the picture with the specified picture type.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::PictureType::{CoverFront, Other};
+    ///
+    /// let mut tag = Tag::new();
+    /// assert_eq!(tag.pictures().count(), 0);
+    ///
+    /// tag.add_picture("image/jpeg", CoverFront, vec!(0xFF));
+    /// tag.add_picture("image/png", Other, vec!(0xAB));
+    /// assert_eq!(tag.pictures().count(), 2);
+    ///
+    /// tag.remove_picture_type(CoverFront);
+    /// assert_eq!(tag.pictures().count(), 1);
+    ///
+    /// let picture = tag.pictures().next().unwrap();
+    /// assert_eq!(&picture.mime_type, "image/png");
+    /// assert_eq!(picture.picture_type, Other);
+    /// assert_eq!(&picture.data, &vec!(0xAB));
+    /// ```
+    pub fn remove_picture_type(&mut self, picture_type: PictureType) {
+        self.remove_pictures_where(|picture| picture.picture_type == picture_type);
+    }
+
+    /// Removes the pictures for which the predicate returns true, including those stored in legacy
+    /// `COVERART` comments. Returns the number of pictures which were removed. The data of
+    /// pictures skipped with `ReadOptions::skip_picture_data` is not loaded, but its length is in
+    /// `Picture::deferred`.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::PictureType::{CoverBack, CoverFront};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.add_picture("image/jpeg", CoverFront, vec!(0xFF; 16));
+    /// tag.add_picture("image/jpeg", CoverBack, vec!(0xFF; 1024));
+    ///
+    /// assert_eq!(tag.remove_pictures_where(|picture| picture.data.len() > 256), 1);
+    /// assert_eq!(tag.pictures().next().unwrap().picture_type, CoverFront);
+    /// ```
+    pub fn remove_pictures_where<F: FnMut(&Picture) -> bool>(&mut self, mut predicate: F) -> usize {
+        let count = self.blocks.len();
+        self.blocks.retain(|block: &Block| match *block {
+            Block::Picture(ref picture) => !predicate(picture),
+            _ => true,
+        });
+        self.dirty |= self.blocks.len() != count;
 
-            file.write_all(b"fLaC")?;
+        let legacy: Vec<usize> = self
+            .legacy_pictures
+            .iter()
+            .filter(|&(_, picture)| predicate(picture))
+            .map(|&(i, _)| i)
+            .collect();
+        self.remove_legacy_coverart(&legacy);
 
-            for bytes in block_bytes.iter() {
-                file.write_all(&bytes[..])?;
-            }
+        count - self.blocks.len() + legacy.len()
+    }
 
-            let padding_size = 1024;
-            debug!("Adding {} bytes of padding", padding_size);
-            let padding = Block::Padding(padding_size);
-            new_length += padding.write_to(true, &mut file)?;
-            self.push_block(padding);
+    /// Removes the `COVERART` values with the specified indices along with their `COVERARTMIME`
+    /// values.
+    fn remove_legacy_coverart(&mut self, indices: &[usize]) {
+        if indices.is_empty() {
+            return;
+        }
 
-            if let Some(data) = data_opt {
-                file.write_all(&data[..])?;
+        for key in ["COVERART", "COVERARTMIME"].iter() {
+            let values: Vec<String> = match self.get_vorbis_values(key) {
+                Some(values) => values
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| !indices.contains(i))
+                    .map(|(_, value)| value)
+                    .collect(),
+                None => continue,
+            };
+            if values.is_empty() {
+                self.remove_vorbis(key);
+            } else {
+                self.set_vorbis(*key, values);
             }
         }
+    }
 
-        self.length = new_length;
-        self.path = Some(path.as_ref().to_path_buf());
-        Ok(())
+    /// Removes the pictures with the specified description. Returns the number of pictures which
+    /// were removed.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::{Block, Picture};
+    ///
+    /// let mut tag = Tag::new();
+    /// let mut picture = Picture::new();
+    /// picture.description = "scan".to_owned();
+    /// tag.push_block(Block::Picture(picture));
+    ///
+    /// assert_eq!(tag.remove_picture_by_description("scan"), 1);
+    /// assert_eq!(tag.pictures().count(), 0);
+    /// ```
+    pub fn remove_picture_by_description(&mut self, description: &str) -> usize {
+        self.remove_pictures_where(|picture| picture.description == description)
     }
 
-    /// Attempts to read a FLAC tag from the file at the specified path.
-    pub fn read_from_path<P: AsRef<Path>>(path: P) -> Result<Tag> {
-        let file = File::open(&path)?;
-        let mut reader = BufReader::new(file);
-        let mut tag = Tag::read_from(&mut reader)?;
-        tag.path = Some(path.as_ref().to_path_buf());
-        Ok(tag)
+    /// Returns the first front cover picture, including one stored in a legacy `COVERART`
+    /// comment. Returns `None` if there is no front cover.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// assert!(tag.front_cover().is_none());
+    ///
+    /// tag.set_front_cover("image/jpeg", vec!(0xFF));
+    /// assert_eq!(&tag.front_cover().unwrap().data, &vec!(0xFF));
+    ///
+    /// tag.remove_front_cover();
+    /// assert!(tag.front_cover().is_none());
+    ///
+    /// tag.set_vorbis("COVERART", vec!["AQID"]);
+    /// assert_eq!(&tag.front_cover().unwrap().data, &[1, 2, 3]);
+    ///
+    /// tag.remove_front_cover();
+    /// assert!(tag.front_cover().is_none());
+    /// ```
+    pub fn front_cover(&self) -> Option<&Picture> {
+        self.pictures()
+            .find(|picture| picture.picture_type == PictureType::CoverFront)
     }
-}
 
-impl Default for Tag {
-    fn default() -> Self {
-        Tag::new()
+    /// Sets the front cover picture, replacing any existing front cover.
+    pub fn set_front_cover<T: Into<String>>(&mut self, mime_type: T, data: Vec<u8>) {
+        self.remove_front_cover();
+        self.add_picture(mime_type, PictureType::CoverFront, data);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Removes the front cover pictures, including those stored in legacy `COVERART` comments.
+    pub fn remove_front_cover(&mut self) {
+        self.remove_picture_type(PictureType::CoverFront);
+    }
+
+    /// Removes pictures whose image data is identical to that of an earlier picture, keeping the
+    /// first of each. Pictures stored in legacy `COVERART` comments follow the picture blocks, as
+    /// in `pictures`. Any deferred picture data is loaded first so that it can be compared.
+    /// Returns the number of pictures which were removed.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::PictureType::{CoverBack, CoverFront, Other};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.add_picture("image/jpeg", CoverFront, vec!(0xFF));
+    /// tag.add_picture("image/jpeg", Other, vec!(0xFF));
+    /// tag.add_picture("image/jpeg", CoverBack, vec!(0xFE));
+    ///
+    /// assert_eq!(tag.dedup_pictures().unwrap(), 1);
+    /// let types: Vec<_> = tag.pictures().map(|picture| picture.picture_type).collect();
+    /// assert_eq!(types, &[CoverFront, CoverBack]);
+    /// ```
+    pub fn dedup_pictures(&mut self) -> Result<usize> {
+        self.load_picture_data()?;
+
+        // hash the data so that only pictures with equal hashes need a full comparison
+        let hash = |data: &[u8]| {
+            let mut hasher = DefaultHasher::new();
+            data.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let mut seen: Vec<(u64, &Picture)> = Vec::new();
+        let mut duplicate = Vec::with_capacity(self.blocks.len());
+        for block in self.blocks.iter() {
+            let is_duplicate = match *block {
+                Block::Picture(ref picture) => {
+                    let picture_hash = hash(&picture.data);
+                    let is_duplicate = seen.iter().any(|&(other_hash, other)| {
+                        other_hash == picture_hash && other.data == picture.data
+                    });
+                    if !is_duplicate {
+                        seen.push((picture_hash, picture));
+                    }
+                    is_duplicate
+                }
+                _ => false,
+            };
+            duplicate.push(is_duplicate);
+        }
+
+        let mut legacy = Vec::new();
+        for &(i, ref picture) in self.legacy_pictures.iter() {
+            let picture_hash = hash(&picture.data);
+            if seen.iter().any(|&(other_hash, other)| {
+                other_hash == picture_hash && other.data == picture.data
+            }) {
+                legacy.push(i);
+            } else {
+                seen.push((picture_hash, picture));
+            }
+        }
+
+        let removed = duplicate
+            .iter()
+            .filter(|&&is_duplicate| is_duplicate)
+            .count();
+        let mut duplicate = duplicate.into_iter();
+        self.blocks.retain(|_| !duplicate.next().unwrap_or(false));
+        self.dirty |= removed > 0;
+        self.remove_legacy_coverart(&legacy);
+        Ok(removed + legacy.len())
+    }
+
+    /// Returns a reference to the first streaminfo block.
+    /// Returns `None` if no streaminfo blocks are found.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::StreamInfo;
+    ///
+    /// let mut tag = Tag::new();
+    /// assert!(tag.get_streaminfo().is_none());
+    /// tag.set_streaminfo(StreamInfo::new());
+    /// assert!(tag.get_streaminfo().is_some());
+    /// ```
+    pub fn get_streaminfo(&self) -> Option<&StreamInfo> {
+        for block in self.blocks() {
+            if let Block::StreamInfo(ref info) = *block {
+                return Some(info);
+            }
+        }
+
+        None
+    }
+
+    /// Sets the streaminfo block. If there is already a streaminfo block then it will be replaced.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::StreamInfo;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_streaminfo(StreamInfo::new());
+    /// assert!(tag.get_streaminfo().is_some());
+    /// ```
+    pub fn set_streaminfo(&mut self, block: StreamInfo) {
+        self.remove_blocks(BlockType::StreamInfo);
+        self.blocks.insert(0, Block::StreamInfo(block));
+        self.dirty = true;
+    }
+
+    /// Returns the duration of the stream computed from the streaminfo block.
+    /// Returns `None` if there is no streaminfo block or if the duration is unknown.
+    pub fn duration(&self) -> Option<Duration> {
+        self.get_streaminfo()
+            .and_then(|streaminfo| streaminfo.duration())
+    }
+
+    /// Returns a reference to the first seektable block.
+    /// Returns `None` if no seektable blocks are found.
+    pub fn seektable(&self) -> Option<&SeekTable> {
+        self.blocks().find_map(|block| match *block {
+            Block::SeekTable(ref seektable) => Some(seektable),
+            _ => None,
+        })
+    }
+
+    /// Returns a mutable reference to the first seektable block.
+    /// If no block is found, a new seektable block is added to the tag and a reference to the
+    /// newly added block is returned.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::SeekPoint;
+    ///
+    /// let mut tag = Tag::new();
+    /// assert!(tag.seektable().is_none());
+    ///
+    /// tag.seektable_mut().insert(SeekPoint::placeholder());
+    /// assert_eq!(tag.seektable().unwrap().seekpoints.len(), 1);
+    /// ```
+    pub fn seektable_mut(&mut self) -> &mut SeekTable {
+        self.dirty = true;
+        let i = match self
+            .blocks
+            .iter()
+            .position(|block| block.block_type() == BlockType::SeekTable)
+        {
+            Some(i) => i,
+            None => {
+                self.push_block(Block::SeekTable(SeekTable::new()));
+                self.blocks.len() - 1
+            }
+        };
+
+        match self.blocks[i] {
+            Block::SeekTable(ref mut seektable) => seektable,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns an iterator of references to the application blocks with the specified
+    /// application id.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.add_application_block(b"test", vec![1, 2, 3]);
+    /// tag.add_application_block(b"othr", vec![4]);
+    ///
+    /// let blocks: Vec<_> = tag.application_blocks(b"test").collect();
+    /// assert_eq!(blocks.len(), 1);
+    /// assert_eq!(blocks[0].data, &[1, 2, 3]);
+    /// ```
+    pub fn application_blocks(
+        &'a self,
+        id: &'a [u8; 4],
+    ) -> impl Iterator<Item = &'a Application> + 'a {
+        self.blocks().filter_map(move |block| match *block {
+            Block::Application(ref application) if application.id == id => Some(application),
+            _ => None,
+        })
+    }
+
+    /// Adds an application block with the specified application id and data.
+    pub fn add_application_block(&mut self, id: &[u8; 4], data: Vec<u8>) {
+        let mut application = Application::new();
+        application.id = id.to_vec();
+        application.data = data;
+        self.push_block(Block::Application(application));
+    }
+
+    /// Returns a reference to the first cuesheet block.
+    /// Returns `None` if no cuesheet blocks are found.
+    pub fn cuesheet(&self) -> Option<&CueSheet> {
+        self.blocks().find_map(|block| match *block {
+            Block::CueSheet(ref cuesheet) => Some(cuesheet),
+            _ => None,
+        })
+    }
+
+    /// Sets the cuesheet block. If there is already a cuesheet block then it will be replaced.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::CueSheet;
+    ///
+    /// let mut tag = Tag::new();
+    /// assert!(tag.cuesheet().is_none());
+    /// tag.set_cuesheet(CueSheet::new());
+    /// assert!(tag.cuesheet().is_some());
+    /// ```
+    pub fn set_cuesheet(&mut self, cuesheet: CueSheet) {
+        self.remove_blocks(BlockType::CueSheet);
+        self.push_block(Block::CueSheet(cuesheet));
+    }
+
+    /// Returns the chapters stored in the `CHAPTERxxx` vorbis comments as described for
+    /// `VorbisComment::chapters`. If there are no chapter comments, the chapters are taken from
+    /// the tracks of the cuesheet block instead. Those chapters start at the first index point
+    /// of the track numbered 1, or at the start of the track, and have no title.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::Chapter;
+    /// use std::time::Duration;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_chapters(&[
+    ///     Chapter { start: Duration::from_secs(0), title: "Intro".to_owned(), url: None },
+    ///     Chapter { start: Duration::from_secs(95), title: "Part 1".to_owned(), url: None },
+    /// ]);
+    ///
+    /// assert_eq!(tag.get_vorbis("CHAPTER001").unwrap().collect::<Vec<_>>(), &["00:01:35.000"]);
+    /// assert_eq!(tag.chapters()[1].title, "Part 1");
+    /// ```
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let chapters = self
+            .vorbis_comments()
+            .map(VorbisComment::chapters)
+            .unwrap_or_default();
+        if !chapters.is_empty() {
+            return chapters;
+        }
+
+        let sample_rate = self
+            .get_streaminfo()
+            .map_or(0, |streaminfo| streaminfo.sample_rate);
+        let cuesheet = match self.cuesheet() {
+            Some(cuesheet) if sample_rate > 0 => cuesheet,
+            _ => return Vec::new(),
+        };
+        cuesheet
+            .tracks
+            .iter()
+            .filter(|track| !track.indices.is_empty())
+            .map(|track| {
+                let index = track.indices.iter().find(|index| index.point_num == 1);
+                let offset = track.offset + index.map_or(0, |index| index.offset);
+                Chapter {
+                    start: Duration::from_nanos(
+                        (offset as u128 * 1_000_000_000 / sample_rate as u128) as u64,
+                    ),
+                    title: String::new(),
+                    url: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Replaces the chapter vorbis comments with comments for the chapters as described for
+    /// `VorbisComment::set_chapters`. The cuesheet block is left unchanged.
+    pub fn set_chapters(&mut self, chapters: &[Chapter]) {
+        self.remove_chapters();
+        for (key, value) in chapter_comments(chapters) {
+            self.set_vorbis(key, vec![value]);
+        }
+    }
+
+    /// Removes the chapter vorbis comments.
+    pub fn remove_chapters(&mut self) {
+        let keys = self
+            .vorbis_comments()
+            .map(VorbisComment::chapter_keys)
+            .unwrap_or_default();
+        for key in keys {
+            self.remove_vorbis(&key);
+        }
+    }
+
+    /// Returns true if the blocks were modified since the tag was read or last written to a
+    /// file. Setting comments to the values they already have does not modify the tag, while
+    /// taking a mutable reference to a block through `vorbis_comments_mut` or `seektable_mut`
+    /// always does.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// let bytes = tag.write_to_vec().unwrap();
+    ///
+    /// let mut tag = Tag::read_from_bytes(&bytes).unwrap();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// assert!(!tag.is_dirty());
+    /// tag.set_vorbis("TITLE", vec!["other"]);
+    /// assert!(tag.is_dirty());
+    /// ```
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Returns true if blocks were left out while the tag was read, since their types are not in
+    /// `ReadOptions::block_types`. Such a tag cannot be written, which would lose the blocks
+    /// which were left out.
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Attempts to save the tag back to the file which it was read from. An error of kind
+    /// `ErrorKind::NoPath` will be returned if this is called on a tag which was not read from or
+    /// saved to a file.
+    ///
+    /// Nothing is written if the tag has not been modified since it was read or last written, see
+    /// `Tag::is_dirty`, so the file and its modification time are left untouched.
+    pub fn save(&mut self) -> Result<()> {
+        self.save_with(&WriteOptions::new())
+    }
+
+    /// Attempts to save the tag back to the file which it was read from using the specified
+    /// options. An error of kind `ErrorKind::NoPath` will be returned if this is called on a tag
+    /// which was not read from or saved to a file.
+    ///
+    /// Nothing is written if the tag has not been modified and the options do not change the
+    /// file otherwise, such as by removing an ID3v2 tag which the file has. Use
+    /// `Tag::write_to_path_with` to write the file regardless.
+    pub fn save_with(&mut self, options: &WriteOptions) -> Result<()> {
+        self.save_with_progress(options, |_, _| {})
+    }
+
+    /// Attempts to save the tag back to the file which it was read from using the specified
+    /// options, reporting the progress of copying the audio when the file is rewritten. See
+    /// `Tag::write_to_path_with_progress`.
+    pub fn save_with_progress<F: FnMut(u64, u64)>(
+        &mut self,
+        options: &WriteOptions,
+        mut progress: F,
+    ) -> Result<()> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::NoPath,
+                    "attempted to save file which was not read from a path",
+                ))
+            }
+        };
+
+        if !self.dirty && !self.options_change_file(options) {
+            debug!("Skipping save of unmodified tag");
+            return Ok(());
+        }
+
+        self.write_to_path_reporting(&path, options, &mut progress)
+    }
+
+    /// Attempts to save the tag to the file at the specified path, which later calls to
+    /// `Tag::save` write to. The audio of an existing file at the path is kept, while a new file
+    /// only receives the metadata.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{ErrorKind, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// assert!(matches!(tag.save().unwrap_err().kind, ErrorKind::NoPath));
+    ///
+    /// let name = format!("metaflac-save-to-{}.flac", std::process::id());
+    /// let path = std::env::temp_dir().join(name);
+    /// # let _ = std::fs::remove_file(&path);
+    /// tag.save_to(&path).unwrap();
+    /// tag.set_vorbis("ARTIST", vec!["artist"]);
+    /// tag.save().unwrap();
+    ///
+    /// let tag = Tag::read_from_path(&path).unwrap();
+    /// assert_eq!(tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(), &["artist"]);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn save_to<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.write_to_path_with(path, &WriteOptions::new())
+    }
+
+    /// Attempts to save the tag as a new file at the specified path, which later calls to
+    /// `Tag::save` write to. The new file receives the blocks of the tag followed by a copy of
+    /// the audio of the file the tag was read from, which is left untouched. Any existing file
+    /// at the path is replaced. A tag which was not read from a file is saved as a file with
+    /// only the metadata.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::{Block, StreamInfo};
+    /// use metaflac::Tag;
+    ///
+    /// let dir = std::env::temp_dir();
+    /// let original = dir.join(format!("metaflac-doc-original-{}.flac", std::process::id()));
+    /// let copy = dir.join(format!("metaflac-doc-copy-{}.flac", std::process::id()));
+    /// let mut streaminfo = StreamInfo::new();
+    /// streaminfo.num_channels = 2;
+    /// streaminfo.bits_per_sample = 16;
+    /// streaminfo.md5 = vec![0; 16];
+    /// let mut tag = Tag::new();
+    /// tag.push_block(Block::StreamInfo(streaminfo));
+    /// let mut bytes = tag.write_to_vec().unwrap();
+    /// bytes.extend(b"audio");
+    /// std::fs::write(&original, &bytes).unwrap();
+    ///
+    /// let mut tag = Tag::read_from_path(&original).unwrap();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// tag.save_as(&copy).unwrap();
+    ///
+    /// assert_eq!(std::fs::read(&original).unwrap(), bytes);
+    /// assert!(std::fs::read(&copy).unwrap().ends_with(b"audio"));
+    /// assert!(Tag::read_from_path(&copy).unwrap().get_vorbis("TITLE").is_some());
+    /// # std::fs::remove_file(&original).unwrap();
+    /// # std::fs::remove_file(&copy).unwrap();
+    /// ```
+    pub fn save_as<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.save_as_with(path, &WriteOptions::new())
+    }
+
+    /// Attempts to save the tag as a new file at the specified path using the specified options.
+    /// See `Tag::save_as`.
+    pub fn save_as_with<P: AsRef<Path>>(&mut self, path: P, options: &WriteOptions) -> Result<()> {
+        let path = path.as_ref();
+        if self.path.as_deref() == Some(path) {
+            return self.write_to_path_with(path, options);
+        }
+        self.load_picture_data()?;
+
+        // the copy is written under a temporary name, so that a file at the path is only replaced
+        // once the copy is complete
+        let temp_path = temp_path(path);
+        let source = (self.path.clone(), self.file.clone());
+        let result = self
+            .save_as_temp(&temp_path, options)
+            .and_then(|_| Ok(std::fs::rename(&temp_path, path)?));
+        match result {
+            Ok(()) => {
+                // the retained handle follows the renamed file
+                self.path = Some(path.to_path_buf());
+                Ok(())
+            }
+            Err(err) => {
+                let _ = std::fs::remove_file(&temp_path);
+                self.path = source.0;
+                self.file = source.1;
+                Err(err)
+            }
+        }
+    }
+
+    /// Copies the file the tag was read from to the temporary path, or creates an empty file if
+    /// there is none, and writes the tag to it.
+    fn save_as_temp(&mut self, temp_path: &Path, options: &WriteOptions) -> Result<()> {
+        match self.path {
+            Some(ref source) => {
+                std::fs::copy(source, temp_path)?;
+            }
+            None => {
+                File::create(temp_path)?;
+            }
+        }
+        if self.file.is_some() {
+            self.file = Some(Arc::new(
+                OpenOptions::new().read(true).write(true).open(temp_path)?,
+            ));
+        }
+        self.path = Some(temp_path.to_path_buf());
+        self.write_to_path_with(temp_path, options)
+    }
+
+    /// Sets the path of the file which `Tag::save` writes to. The tag is no longer associated
+    /// with the file it was read from, so the next save writes the metadata over the audio of an
+    /// existing file at the path, as `Tag::save_to` does, or creates a file with only the
+    /// metadata. Pictures whose data was skipped while reading are loaded first.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let name = format!("metaflac-doc-set-path-{}.flac", std::process::id());
+    /// let path = std::env::temp_dir().join(name);
+    /// # let _ = std::fs::remove_file(&path);
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// tag.set_path(&path).unwrap();
+    /// tag.save().unwrap();
+    ///
+    /// assert!(Tag::read_from_path(&path).unwrap().get_vorbis("TITLE").is_some());
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn set_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.load_picture_data()?;
+
+        self.path = Some(path.as_ref().to_path_buf());
+        self.file = None;
+        self.length = 0;
+        self.layout.clear();
+        self.id3v2 = None;
+        self.junk = None;
+        self.id3v1 = None;
+        self.ape = None;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Attempts to copy the blocks of this tag onto the FLAC file at the specified path. Blocks
+    /// in the destination file which are copied by the options are replaced, while the others
+    /// are kept.
+    pub fn copy_tags_to<P: AsRef<Path>>(&self, path: P, options: &CopyOptions) -> Result<()> {
+        let mut dest = Tag::read_from_path(&path)?;
+
+        let count = dest.blocks.len();
+        dest.blocks
+            .retain(|block| !options.copies(block.block_type()));
+        dest.dirty |= dest.blocks.len() != count;
+        for block in self.blocks() {
+            if options.copies(block.block_type()) {
+                dest.push_block(self.loaded_block(block)?);
+            }
+        }
+
+        dest.save()
+    }
+
+    /// Attempts to copy the blocks selected by the options from the FLAC file at `src` onto the
+    /// FLAC file at `dst`, replacing the blocks of the same types in `dst`. By default the
+    /// streaminfo and seektable of `dst` are kept, so tags can be carried over to a file whose
+    /// audio was encoded again.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use metaflac::{CopyOptions, Tag};
+    ///
+    /// let mut options = CopyOptions::new();
+    /// options.copy_pictures = false;
+    /// Tag::copy_to_path("original.flac", "reencoded.flac", &options).unwrap();
+    /// ```
+    pub fn copy_to_path<P: AsRef<Path>, Q: AsRef<Path>>(
+        src: P,
+        dst: Q,
+        options: &CopyOptions,
+    ) -> Result<()> {
+        Tag::read_from_path(src)?.copy_tags_to(dst, options)
+    }
+
+    /// Serializes the blocks of this tag which are copied by the options into a standalone patch.
+    /// The patch can be applied to a FLAC file later, or on another machine, using
+    /// `Tag::apply_patch`.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{CopyOptions, Tag};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    ///
+    /// let patch = tag.export_patch(&CopyOptions::new()).unwrap();
+    /// let tag = Tag::read_from(&mut &patch[..]).unwrap();
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+    /// ```
+    pub fn export_patch(&self, options: &CopyOptions) -> Result<Vec<u8>> {
+        let mut patch = b"fLaC".to_vec();
+        for block in self.blocks() {
+            if options.copies(block.block_type()) {
+                self.loaded_block(block)?.write_to(false, &mut patch)?;
+            }
+        }
+        // an empty padding block terminates the patch, even if no blocks were copied
+        Block::Padding(0).write_to(true, &mut patch)?;
+
+        Ok(patch)
+    }
+
+    /// Attempts to apply a patch created by `Tag::export_patch` to the FLAC file at the specified
+    /// path. Blocks in the file which are copied by the options are replaced by the blocks in
+    /// the patch.
+    pub fn apply_patch<P: AsRef<Path>>(path: P, patch: &[u8], options: &CopyOptions) -> Result<()> {
+        let mut reader = patch;
+        Tag::read_from(&mut reader)?.copy_tags_to(path, options)
+    }
+
+    /// Removes the ID3v2 tag preceding the `fLaC` marker of the file at the specified path,
+    /// leaving the rest of the file untouched. Returns whether a tag was removed. Use
+    /// `WriteOptions::remove_id3` to remove the tag while saving instead.
+    pub fn remove_id3<P: AsRef<Path>>(path: P) -> Result<bool> {
+        let path = path.as_ref();
+        let source = File::open(path)?;
+        let mut reader = BufReader::new(&source);
+        let (id3_len, _) = crate::block::read_ident_with(&mut reader, 0)?;
+        if id3_len == 0 {
+            return Ok(false);
+        }
+        reader.seek(SeekFrom::Start(id3_len))?;
+
+        let temp_path = temp_path(path);
+        let result = File::create(&temp_path)
+            .map_err(Error::from)
+            .and_then(|temp| {
+                let mut writer = BufWriter::new(&temp);
+                io::copy(&mut reader, &mut writer)?;
+                writer.flush()?;
+                drop(writer);
+
+                copy_attributes(&source, &temp)?;
+                temp.sync_all()?;
+                drop(temp);
+
+                std::fs::rename(&temp_path, path)?;
+                Ok(true)
+            });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    /// Returns the contents of the reader without any FLAC metadata.
+    pub fn skip_metadata<R: Read + Seek>(reader: &mut R) -> Vec<u8> {
+        macro_rules! try_io {
+            ($reader:ident, $action:expr) => {
+                match $action {
+                    Ok(bytes) => bytes,
+                    Err(_) => match $reader.seek(SeekFrom::Start(0)) {
+                        Ok(_) => {
+                            let mut data = Vec::new();
+                            match $reader.read_to_end(&mut data) {
+                                Ok(_) => return data,
+                                Err(_) => return Vec::new(),
+                            }
+                        }
+                        Err(_) => return Vec::new(),
+                    },
+                }
+            };
+        }
+
+        let mut ident = [0; 4];
+        try_io!(reader, reader.read_exact(&mut ident));
+        if &ident[..] == b"fLaC" {
+            let mut more = true;
+            while more {
+                let header = try_io!(reader, reader.read_u32::<BigEndian>());
+
+                more = ((header >> 24) & 0x80) == 0;
+                let length = header & 0xFF_FF_FF;
+
+                debug!("Skipping {} bytes", length);
+                try_io!(reader, reader.seek(SeekFrom::Current(length as i64)));
+            }
+        } else {
+            try_io!(reader, reader.seek(SeekFrom::Start(0)));
+        }
+
+        let mut data = Vec::new();
+        try_io!(reader, reader.read_to_end(&mut data));
+        data
+    }
+
+    /// Will return true if the reader is a candidate for FLAC metadata. The reader position will be
+    /// reset back to the previous position before returning.
+    pub fn is_candidate<R: Read + Seek>(reader: &mut R) -> bool {
+        macro_rules! try_or_false {
+            ($action:expr) => {
+                match $action {
+                    Ok(result) => result,
+                    Err(_) => return false,
+                }
+            };
+        }
+
+        let start = try_or_false!(reader.stream_position());
+        let mut header = [0; 10];
+        let mut ident = [0; 4];
+        let result = reader.read_exact(&mut ident).and_then(|_| {
+            // look past any ID3v2 tag prepended by a broken tagger
+            if &ident[0..3] == b"ID3" {
+                header[..4].copy_from_slice(&ident);
+                reader.read_exact(&mut header[4..])?;
+                if let Some(id3_len) = crate::raw::id3v2_len(&header) {
+                    reader.seek(SeekFrom::Start(start + id3_len))?;
+                    reader.read_exact(&mut ident)?;
+                }
+            }
+            Ok(())
+        });
+        let _ = reader.seek(SeekFrom::Start(start));
+        result.is_ok() && &ident[..] == b"fLaC"
+    }
+
+    /// Attempts to read a FLAC tag from the reader.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use std::io::Cursor;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    ///
+    /// let mut cursor = Cursor::new(Vec::new());
+    /// tag.write_to(&mut cursor).unwrap();
+    /// cursor.set_position(0);
+    ///
+    /// let tag = Tag::read_from(&mut cursor).unwrap();
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+    /// ```
+    pub fn read_from<R: Read + ?Sized>(reader: &mut R) -> Result<Tag> {
+        Tag::read_from_with(reader, &ReadOptions::new())
+    }
+
+    /// Attempts to read a FLAC tag from a byte slice, such as a file which has been loaded into
+    /// memory. Anything following the metadata is ignored.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// let mut bytes = tag.write_to_vec().unwrap();
+    /// bytes.extend(b"audio");
+    ///
+    /// let tag = Tag::read_from_bytes(&bytes).unwrap();
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["title"]);
+    /// ```
+    pub fn read_from_bytes(bytes: &[u8]) -> Result<Tag> {
+        Tag::read_from(&mut &bytes[..])
+    }
+
+    /// Attempts to read a FLAC tag from the reader using the specified options.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::{ReadOptions, Tag};
+    ///
+    /// let mut bytes = b"junk".to_vec();
+    /// bytes.extend(b"fLaC");
+    /// bytes.extend(&[0x81, 0, 0, 0]);
+    ///
+    /// assert!(Tag::read_from(&mut &bytes[..]).is_err());
+    ///
+    /// let mut options = ReadOptions::new();
+    /// options.max_junk_len = 16;
+    /// let tag = Tag::read_from_with(&mut &bytes[..], &options).unwrap();
+    /// assert_eq!(tag.junk(), Some(0..4));
+    /// ```
+    pub fn read_from_with<R: Read + ?Sized>(reader: &mut R, options: &ReadOptions) -> Result<Tag> {
+        Tag::read_from_skipping(reader, options, skip_by_reading)
+    }
+
+    /// Reads a FLAC tag like `Tag::read_from_with`, passing over the contents of the blocks which
+    /// the options leave out with `skip`, which returns the number of bytes skipped.
+    fn read_from_skipping<R: Read + ?Sized>(
+        reader: &mut R,
+        options: &ReadOptions,
+        skip: fn(&mut R, u64) -> io::Result<u64>,
+    ) -> Result<Tag> {
+        let (id3_len, junk_len) =
+            crate::block::read_ident_with(&mut *reader, options.max_junk_len)?;
+        Tag::read_blocks(reader, id3_len, junk_len, options, skip)
+    }
+
+    /// Reads the blocks following the `fLaC` marker, which is preceded by an ID3v2 tag and junk
+    /// of the specified lengths. See `Tag::read_from_skipping`.
+    pub(crate) fn read_blocks<R: Read + ?Sized>(
+        reader: &mut R,
+        id3_len: u64,
+        junk_len: u64,
+        options: &ReadOptions,
+        skip: fn(&mut R, u64) -> io::Result<u64>,
+    ) -> Result<Tag> {
+        let mut tag = Tag::new();
+        if id3_len > 0 {
+            tag.id3v2 = Some(0..id3_len);
+        }
+        if junk_len > 0 {
+            tag.junk = Some(id3_len..id3_len + junk_len);
+        }
+
+        let mut offset = id3_len + junk_len + 4;
+        if options.parse_mode == ParseMode::Recover {
+            tag.read_recovering(reader, offset, options)?;
+        } else {
+            loop {
+                let header = Block::read_header(reader, offset)?;
+                let block_type = BlockType::from_u8(header.1);
+                let (is_last, length) = if options.reads(block_type) {
+                    let (is_last, length, block) =
+                        Block::read_contents(reader, offset, options, &mut tag.warnings, header)?;
+                    tag.push_read_block(block, offset, length, options)?;
+                    (is_last, length)
+                } else {
+                    debug!("Skipping {:?} block with {} bytes", block_type, header.2);
+                    let skipped = skip(reader, header.2 as u64)
+                        .map_err(|err| Error::from(err).in_block(Some(block_type), offset))?;
+                    tag.skip_read_block(skipped as u32 + 4, options)?;
+                    // a truncated block ends the metadata, as when it is read
+                    (header.0 || skipped < header.2 as u64, skipped as u32 + 4)
+                };
+                offset += length as u64;
+                if is_last {
+                    break;
+                }
+            }
+        }
+
+        if options.merge_vorbis_comments {
+            tag.normalize();
+        }
+        tag.load_legacy_coverart();
+        Ok(tag)
+    }
+
+    /// Adds a block which was read from the offset, checking that the streaminfo block comes
+    /// first and that the metadata does not exceed the maximum length.
+    fn push_read_block(
+        &mut self,
+        block: Block,
+        offset: u64,
+        length: u32,
+        options: &ReadOptions,
+    ) -> Result<()> {
+        if block.block_type() == BlockType::SeekTable && self.seektable().is_some() {
+            options
+                .parse_mode
+                .violation(
+                    offset,
+                    ErrorKind::InvalidInput,
+                    "duplicate seektable block",
+                    &mut self.warnings,
+                )
+                .map_err(|err| err.in_block(Some(block.block_type()), offset))?;
+        }
+        if block.block_type() == BlockType::StreamInfo && self.length > 0 {
+            let message = if self.get_streaminfo().is_some() {
+                "duplicate streaminfo block"
+            } else {
+                "streaminfo block is not the first block"
+            };
+            options
+                .parse_mode
+                .violation(offset, ErrorKind::InvalidInput, message, &mut self.warnings)
+                .map_err(|err| err.in_block(Some(block.block_type()), offset))?;
+        } else if block.block_type() != BlockType::StreamInfo && self.length == 0 {
+            options
+                .parse_mode
+                .violation(
+                    offset,
+                    ErrorKind::InvalidInput,
+                    "first block is not a streaminfo block",
+                    &mut self.warnings,
+                )
+                .map_err(|err| err.in_block(Some(block.block_type()), offset))?;
+        }
+
+        self.layout.push(BlockLocation {
+            block_type: block.block_type(),
+            offset,
+            length,
+        });
+        self.length += length;
+        self.blocks.push(block);
+        self.check_metadata_len(options)
+    }
+
+    /// Accounts for a block of the length which was skipped while reading, since the options
+    /// leave out its type.
+    fn skip_read_block(&mut self, length: u32, options: &ReadOptions) -> Result<()> {
+        self.length += length;
+        self.partial = true;
+        self.check_metadata_len(options)
+    }
+
+    /// Checks that the blocks read so far do not exceed the maximum metadata length.
+    fn check_metadata_len(&self, options: &ReadOptions) -> Result<()> {
+        if options
+            .max_metadata_len
+            .is_some_and(|max_metadata_len| self.length as u64 > max_metadata_len)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "metadata exceeds the maximum metadata length",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads the blocks following the `fLaC` marker, skipping over damaged parts of the metadata
+    /// by searching for the next plausible block header. The search stops at the first frame
+    /// sync code, which marks the start of the audio. `start` is the offset of the first block
+    /// header.
+    fn read_recovering<R: Read + ?Sized>(
+        &mut self,
+        reader: &mut R,
+        start: u64,
+        options: &ReadOptions,
+    ) -> Result<()> {
+        // the metadata cannot extend past the maximum length, so neither can the search
+        let mut data = Vec::new();
+        match options.max_metadata_len {
+            Some(max_metadata_len) => reader.take(max_metadata_len).read_to_end(&mut data)?,
+            None => reader.read_to_end(&mut data)?,
+        };
+
+        let mut pos = 0;
+        while pos + 4 <= data.len() {
+            let offset = start + pos as u64;
+            let mut warnings = Vec::new();
+            if let Some((is_last, length, block)) =
+                Tag::recover_block(&data[pos..], offset, options, false, &mut warnings)
+            {
+                self.warnings.extend(warnings);
+                if options.reads(block.block_type()) {
+                    self.push_read_block(block, offset, length, options)?;
+                } else {
+                    self.skip_read_block(length, options)?;
+                }
+                pos += length as usize;
+                if is_last {
+                    break;
+                }
+                continue;
+            }
+
+            // the audio starts with a frame sync code
+            let end = data[pos + 1..]
+                .windows(2)
+                .position(|sync| sync[0] == 0xFF && sync[1] & 0xFE == 0xF8)
+                .map_or(data.len(), |sync| pos + 1 + sync);
+            let next = (pos + 1..end).find(|&next| {
+                let offset = start + next as u64;
+                Tag::recover_block(&data[next..end], offset, options, true, &mut Vec::new())
+                    .is_some()
+            });
+
+            debug!("Skipping damaged metadata at offset {}", offset);
+            self.skipped
+                .push(offset..start + next.unwrap_or(end) as u64);
+            match next {
+                Some(next) => pos = next,
+                None => {
+                    pos = end;
+                    break;
+                }
+            }
+        }
+
+        // a save in place replaces the damaged parts along with the blocks
+        self.length = pos as u32;
+        Ok(())
+    }
+
+    /// Attempts to read a plausible block from the start of the bytes. A block is plausible if it
+    /// has a known type, fits within the bytes and satisfies the specification. When searching
+    /// for a block after damaged bytes, padding must also consist of zero bytes, and the block
+    /// must be followed by another plausible block header unless it is the last block.
+    fn recover_block(
+        bytes: &[u8],
+        offset: u64,
+        options: &ReadOptions,
+        searching: bool,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Option<(bool, u32, Block)> {
+        let plausible_header = |header: &[u8]| {
+            header.len() >= 4 && {
+                let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+                header[0] & 0x7F <= 6 && length + 4 <= header.len()
+            }
+        };
+        if !plausible_header(bytes) {
+            return None;
+        }
+
+        let mut strict = options.clone();
+        strict.parse_mode = ParseMode::Strict;
+        let (is_last, length, block) =
+            Block::read_with(&mut &bytes[..], offset, &strict, warnings).ok()?;
+
+        let end = length as usize;
+        if searching {
+            if let Block::Padding(_) = block {
+                if bytes[4..end].iter().any(|&b| b != 0) {
+                    return None;
+                }
+            }
+            if !is_last && !plausible_header(&bytes[end..]) {
+                return None;
+            }
+        }
+
+        Some((is_last, length, block))
+    }
+
+    /// Attempts to write the FLAC tag to the writer.
+    ///
+    /// Returns an `InvalidLength` error before writing anything if a block does not fit in the 24
+    /// bit length of a block header, e.g. a picture of 16 MiB or more. Returns an `InvalidInput`
+    /// error if the tag was read without some of its blocks, see `Tag::is_partial`, or still has a
+    /// damaged block, see `Block::is_damaged`.
+    pub fn write_to<W: Write + ?Sized>(&mut self, writer: &mut W) -> Result<()> {
+        self.check_complete()?;
+        check_unique_blocks(&self.blocks)?;
+        if let Some(block) = self.blocks.iter().find(|block| !block.fits_block_header()) {
+            return Err(block.too_long_error());
+        }
+
+        writer.write_all(b"fLaC")?;
+
+        let nblocks = self.blocks.len();
+        let mut lengths = Vec::with_capacity(nblocks);
+        for i in 0..nblocks {
+            let block = &self.blocks[i];
+            lengths.push(block.write_to(i == nblocks - 1, writer)?);
+        }
+        self.length = lengths.iter().sum();
+        self.set_layout(4, &lengths);
+
+        Ok(())
+    }
+
+    /// Attempts to write the FLAC tag to a new vector, which starts with the `fLaC` marker and
+    /// ends after the last block.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    ///
+    /// let bytes = tag.write_to_vec().unwrap();
+    /// assert_eq!(&bytes[..4], b"fLaC");
+    /// assert_eq!(bytes.len() as u64, tag.metadata_len() + 4);
+    /// ```
+    pub fn write_to_vec(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Returns a copy of the FLAC file held in `file` with its metadata replaced by the blocks of
+    /// this tag. The audio, and anything stored before the `fLaC` marker or after the audio, is
+    /// copied unchanged. This edits a file which has been loaded into memory without any file
+    /// system access, as in a browser.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// let mut file = tag.write_to_vec().unwrap();
+    /// file.extend(b"audio");
+    ///
+    /// let mut tag = Tag::read_from_bytes(&file).unwrap();
+    /// tag.set_vorbis("TITLE", vec!["a longer title"]);
+    /// let file = tag.rewrite_bytes(&file).unwrap();
+    ///
+    /// assert!(file.ends_with(b"audio"));
+    /// let tag = Tag::read_from_bytes(&file).unwrap();
+    /// assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), &["a longer title"]);
+    /// ```
+    pub fn rewrite_bytes(&mut self, file: &[u8]) -> Result<Vec<u8>> {
+        let mut audio = file;
+        let original = Tag::read_from(&mut audio)?;
+        let prefix_len = original
+            .junk
+            .or(original.id3v2)
+            .map_or(0, |prefix| prefix.end as usize);
+
+        let mut bytes = file[..prefix_len].to_vec();
+        self.write_to(&mut bytes)?;
+        bytes.extend_from_slice(audio);
+        Ok(bytes)
+    }
+
+    /// Attempts to write the FLAC tag to a file at the indicated path. If the specified path is
+    /// the same path which the tag was read from, then the tag will be written to the padding if
+    /// possible.
+    pub fn write_to_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.write_to_path_with(path, &WriteOptions::new())
+    }
+
+    /// Attempts to write the FLAC tag to a file at the indicated path using the specified options.
+    /// If the specified path is the same path which the tag was read from, then the tag will be
+    /// written to the padding if possible.
+    pub fn write_to_path_with<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        self.write_to_path_reporting(path, options, &mut |_, _| {})
+    }
+
+    /// Attempts to write the FLAC tag to a file at the indicated path using the specified options,
+    /// like `Tag::write_to_path_with`. When the file has to be rewritten, the progress function
+    /// is called with the number of bytes of audio copied so far and the total number of bytes
+    /// of audio after each chunk is copied, so that the copy of a large file can be shown to
+    /// the user. It is not called when the tag is written into the existing metadata region.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use metaflac::block::PictureType;
+    /// use metaflac::{Tag, WriteOptions};
+    ///
+    /// let mut tag = Tag::read_from_path("music.flac").unwrap();
+    /// tag.add_picture_from_path("cover.jpg", PictureType::CoverFront)
+    ///     .unwrap();
+    /// tag.write_to_path_with_progress("music.flac", &WriteOptions::new(), |copied, total| {
+    ///     println!("{}%", copied * 100 / total.max(1));
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn write_to_path_with_progress<P: AsRef<Path>, F: FnMut(u64, u64)>(
+        &mut self,
+        path: P,
+        options: &WriteOptions,
+        mut progress: F,
+    ) -> Result<()> {
+        self.write_to_path_reporting(path, options, &mut progress)
+    }
+
+    /// Writes the tag to the file at the path, reporting the progress of copying the audio.
+    fn write_to_path_reporting<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &WriteOptions,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<()> {
+        self.check_complete()?;
+        self.load_picture_data()?;
+
+        if options.drop_oversized_pictures {
+            self.remove_pictures_where(|picture| !picture.fits_block_header());
+        }
+
+        if !options.exclude_blocks.is_empty() {
+            return self.write_excluding(path.as_ref(), options, progress);
+        }
+        check_unique_blocks(&self.blocks)?;
+
+        let deterministic_options;
+        let options = if options.deterministic {
+            self.sort_deterministic();
+            deterministic_options = WriteOptions {
+                preserve_order: false,
+                padding: match options.padding {
+                    PaddingPolicy::Preserve => PaddingPolicy::Fixed(1024),
+                    padding => padding,
+                },
+                ..options.clone()
+            };
+            &deterministic_options
+        } else {
+            options
+        };
+
+        if self.ogg {
+            return self.write_ogg_to_path(path.as_ref(), options, progress);
+        }
+
+        self.arrange_blocks(options.preserve_order);
+
+        let mut block_bytes = Vec::new();
+        let nblocks = self.blocks.len();
+        let mut new_length = 0;
+        for i in 0..nblocks {
+            let block = &self.blocks[i];
+            let mut writer = Vec::<u8>::new();
+            new_length += block.write_to(false, &mut writer)?;
+            block_bytes.push(writer);
+        }
+        let mut lengths: Vec<u32> = block_bytes.iter().map(|bytes| bytes.len() as u32).collect();
+        // the offset of the first block header once written
+        let start;
+
+        let same_path =
+            self.path.is_some() && path.as_ref() == self.path.as_ref().unwrap().as_path();
+        // junk from the file which was read only applies when writing back to that same file
+        let junk = if same_path { self.junk.clone() } else { None };
+        let remove_junk = options.remove_junk && junk.is_some();
+        let remove_id3 = options.remove_id3 && same_path && self.id3v2.is_some();
+
+        // the in-place write has to fill the old metadata region exactly
+        let in_place = options
+            .padding
+            .in_place(self.length, new_length)
+            .filter(|padding| padding.is_some() || nblocks > 0);
+
+        let times = if options.preserve_modtime {
+            file_times(path.as_ref())
+        } else {
+            None
+        };
+
+        // reuse the handle retained from reading when writing back to the same file
+        let retained = match self.file {
+            Some(ref file) if same_path => Some(file.clone()),
+            _ => None,
+        };
+
+        // write using padding
+        let in_place =
+            in_place.filter(|_| same_path && options.use_padding && !remove_junk && !remove_id3);
+        if let Some(padding_size) = in_place {
+            debug!("Writing using padding");
+            let handle = match retained {
+                Some(handle) => handle,
+                None => Arc::new(OpenOptions::new().write(true).read(true).open(&path)?),
+            };
+            let mut file = &*handle;
+            file.seek(SeekFrom::Start(0))?;
+
+            let max_junk_len = junk.as_ref().map_or(0, |junk| junk.end - junk.start);
+            let (id3_len, junk_len) = crate::block::read_ident_with(&mut file, max_junk_len)?;
+            start = id3_len + junk_len + 4;
+
+            if padding_size.is_none() {
+                // mark the last block as the last metadata block
+                block_bytes[nblocks - 1][0] |= 0x80;
+            }
+
+            for bytes in block_bytes.iter() {
+                file.write_all(&bytes[..])?;
+            }
+
+            if let Some(padding_size) = padding_size {
+                let padding = Block::Padding(padding_size);
+                let padding_length = padding.write_to(true, &mut file)?;
+                new_length += padding_length;
+                lengths.push(padding_length);
+                self.push_block(padding);
+            }
+
+            if options.remove_ape {
+                crate::ape::strip(file)?;
+            }
+            if options.remove_id3v1 {
+                crate::id3v1::strip(file)?;
+            }
+
+            if let Some(times) = times {
+                debug!("Restoring file times");
+                file.set_times(times)?;
+            }
+        } else {
+            // write by copying file data into a temporary file which then replaces the file, so
+            // that the file is never left partially written
+            debug!("Writing to new file");
+
+            let source = match retained {
+                Some(handle) => Some(handle),
+                None => File::open(&path).ok().map(Arc::new),
+            };
+
+            // locate the marker in the file being replaced, which may be preceded by an ID3v2 tag
+            // and by junk when it is the file which was read. An empty file has nothing to keep.
+            let max_junk_len = junk.as_ref().map_or(0, |junk| junk.end - junk.start);
+            let (id3_len, junk_len) = match source {
+                Some(ref source) if source.metadata()?.len() > 0 => {
+                    let mut reader = BufReader::new(&**source);
+                    reader.seek(SeekFrom::Start(0))?;
+                    crate::block::read_ident_with(&mut reader, max_junk_len)?
+                }
+                _ => (0, 0),
+            };
+            let stream_offset = id3_len + junk_len;
+
+            // keep the ID3v2 tag and the junk unless asked to remove them
+            let prefix_start = if remove_id3 { id3_len } else { 0 };
+            let prefix_end = if remove_junk { id3_len } else { stream_offset };
+            let prefix = prefix_start..prefix_end.max(prefix_start);
+
+            // a stream needs at least one block to mark as the last one
+            let padding = match options.padding.rewrite(new_length) {
+                None if nblocks > 0 => {
+                    block_bytes[nblocks - 1][0] |= 0x80;
+                    None
+                }
+                padding_size => {
+                    let padding_size = padding_size.unwrap_or(0);
+                    debug!("Adding {} bytes of padding", padding_size);
+                    Some(Block::Padding(padding_size))
+                }
+            };
+
+            let temp_path = temp_path(path.as_ref());
+            let result = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&temp_path)
+                .map_err(Error::from)
+                .and_then(|temp| {
+                    let mut writer = BufWriter::new(&temp);
+                    let padding_length = Tag::write_file(
+                        &mut writer,
+                        source.as_deref(),
+                        prefix.clone(),
+                        stream_offset,
+                        &block_bytes,
+                        padding.as_ref(),
+                        progress,
+                    )?;
+                    writer.flush()?;
+                    drop(writer);
+
+                    if options.remove_ape {
+                        crate::ape::strip(&temp)?;
+                    }
+                    if options.remove_id3v1 {
+                        crate::id3v1::strip(&temp)?;
+                    }
+
+                    if let Some(ref source) = source {
+                        copy_attributes(source, &temp)?;
+                    }
+                    if let Some(times) = times {
+                        debug!("Restoring file times");
+                        temp.set_times(times)?;
+                    }
+                    temp.sync_all()?;
+                    drop(temp);
+
+                    std::fs::rename(&temp_path, &path)?;
+                    Ok(padding_length)
+                });
+
+            let padding_length = match result {
+                Ok(padding_length) => padding_length,
+                Err(err) => {
+                    let _ = std::fs::remove_file(&temp_path);
+                    return Err(err);
+                }
+            };
+            new_length += padding_length;
+            start = prefix.end - prefix.start + 4;
+            if let Some(padding) = padding {
+                lengths.push(padding_length);
+                self.push_block(padding);
+            }
+
+            if remove_junk {
+                self.junk = None;
+            }
+            if remove_id3 && id3_len > 0 {
+                self.junk = self
+                    .junk
+                    .take()
+                    .map(|junk| junk.start - id3_len..junk.end - id3_len);
+            }
+            self.id3v2 = if prefix.start == 0 && id3_len > 0 {
+                Some(0..id3_len)
+            } else {
+                None
+            };
+
+            // the retained handle refers to the replaced file
+            if self.file.is_some() {
+                self.file = Some(Arc::new(
+                    OpenOptions::new().read(true).write(true).open(&path)?,
+                ));
+            }
+        }
+
+        if options.remove_ape {
+            self.ape = None;
+        }
+        if options.remove_id3v1 {
+            self.id3v1 = None;
+        }
+
+        self.length = new_length;
+        self.set_layout(start, &lengths);
+        self.path = Some(path.as_ref().to_path_buf());
+        self.dirty = false;
+        self.arranged = Some(options.preserve_order);
+        Ok(())
+    }
+
+    /// Puts the blocks in the order in which they are written to a file: the streaminfo block
+    /// first, and without the padding which the write replaces. Unless `preserve_order` is set,
+    /// every padding block is dropped, otherwise only trailing padding is. The blocks are left
+    /// as they are if they have not been modified since the last write arranged them the same
+    /// way, apart from the padding block that write added.
+    fn arrange_blocks(&mut self, preserve_order: bool) {
+        if !self.dirty && self.arranged == Some(preserve_order) {
+            if let Some(Block::Padding(_)) = self.blocks.last() {
+                self.blocks.pop();
+            }
+            return;
+        }
+
+        // the streaminfo block must always be the first block
+        if let Some(i) = self
+            .blocks
+            .iter()
+            .position(|block| block.block_type() == BlockType::StreamInfo)
+            .filter(|&i| i > 0)
+        {
+            self.blocks[..=i].rotate_right(1);
+        }
+
+        if preserve_order {
+            // only trailing padding is replaced, padding between other blocks stays in place
+            while let Some(Block::Padding(_)) = self.blocks.last() {
+                self.blocks.pop();
+            }
+        } else {
+            self.remove_blocks(BlockType::Padding);
+        }
+    }
+
+    /// Sorts the blocks by type, keeping the order of blocks of the same type, and sorts the
+    /// comments of every vorbis comment block, for `WriteOptions::deterministic`.
+    fn sort_deterministic(&mut self) {
+        self.blocks.sort_by_key(|block| block.block_type().to_u8());
+        self.arranged = None;
+        for block in self.blocks.iter_mut() {
+            if let Block::VorbisComment(ref mut vorbis) = *block {
+                vorbis.sort_comments();
+            }
+        }
+    }
+
+    /// Returns true if writing the tag with the options changes the file it was read from even
+    /// though the blocks are unmodified.
+    pub(crate) fn options_change_file(&self, options: &WriteOptions) -> bool {
+        (options.remove_junk && self.junk.is_some())
+            || (options.remove_id3 && self.id3v2.is_some())
+            || (options.remove_id3v1 && self.id3v1.is_some())
+            || (options.remove_ape && self.ape.is_some())
+            || !options.use_padding
+            || options.padding != PaddingPolicy::Preserve
+            || (!options.preserve_order && !self.padding_merged())
+            || options.deterministic
+            || !options.exclude_blocks.is_empty()
+    }
+
+    /// Returns true if the tag has at most one padding block, after every other block, as
+    /// written unless `WriteOptions::preserve_order` is set.
+    fn padding_merged(&self) -> bool {
+        match self
+            .blocks
+            .iter()
+            .position(|block| block.block_type() == BlockType::Padding)
+        {
+            Some(i) => i == self.blocks.len() - 1,
+            None => true,
+        }
+    }
+
+    /// Writes a copy of the tag without the blocks excluded by the options, then takes over the
+    /// layout of the written file so that later saves find the metadata region.
+    fn write_excluding(
+        &mut self,
+        path: &Path,
+        options: &WriteOptions,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<()> {
+        check_excluded(options)?;
+
+        let mut written = self.clone();
+        written
+            .blocks
+            .retain(|block| !options.exclude_blocks.contains(&block.block_type()));
+        let mut written_options = options.clone();
+        written_options.exclude_blocks.clear();
+        if options.exclude_blocks.contains(&BlockType::Padding) {
+            written_options.padding = PaddingPolicy::None;
+            written_options.use_padding = false;
+        }
+        written.write_to_path_reporting(path, &written_options, progress)?;
+
+        self.path = written.path;
+        self.length = written.length;
+        self.layout = written.layout;
+        self.id3v2 = written.id3v2;
+        self.junk = written.junk;
+        self.id3v1 = written.id3v1;
+        self.ape = written.ape;
+        self.file = written.file;
+        // the tag keeps the excluded blocks which are no longer in the file
+        self.dirty = self
+            .blocks
+            .iter()
+            .any(|block| options.exclude_blocks.contains(&block.block_type()));
+        Ok(())
+    }
+
+    /// Writes a complete file: the prefix range of the source, the `fLaC` marker, the
+    /// serialized blocks followed by the padding if any, and the audio of the source. Returns the
+    /// length of the padding block in bytes.
+    fn write_file(
+        writer: &mut dyn Write,
+        source: Option<&File>,
+        prefix: Range<u64>,
+        stream_offset: u64,
+        block_bytes: &[Vec<u8>],
+        padding: Option<&Block>,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<u32> {
+        if let Some(mut source) = source {
+            source.seek(SeekFrom::Start(prefix.start))?;
+            io::copy(&mut source.take(prefix.end - prefix.start), writer)?;
+        }
+
+        writer.write_all(b"fLaC")?;
+        for bytes in block_bytes.iter() {
+            writer.write_all(&bytes[..])?;
+        }
+        let padding_length = match padding {
+            Some(padding) => padding.write_to(true, writer)?,
+            None => 0,
+        };
+
+        if let Some(mut source) = source {
+            source.seek(SeekFrom::Start(stream_offset))?;
+            Tag::copy_without_metadata(&mut BufReader::new(source), writer, progress)?;
+        }
+
+        Ok(padding_length)
+    }
+
+    /// Copies the contents of the reader without any FLAC metadata to the writer. If the reader
+    /// does not contain FLAC metadata at its current position, everything from that position is
+    /// copied.
+    fn copy_without_metadata<R: Read + Seek>(
+        reader: &mut R,
+        writer: &mut dyn Write,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<u64> {
+        let start = reader.stream_position()?;
+
+        let mut ident = [0; 4];
+        if reader.read_exact(&mut ident).is_ok() && &ident[..] == b"fLaC" {
+            loop {
+                let header = reader.read_u32::<BigEndian>()?;
+                let length = header & 0xFF_FF_FF;
+
+                debug!("Skipping {} bytes", length);
+                reader.seek(SeekFrom::Current(length as i64))?;
+
+                if (header >> 24) & 0x80 != 0 {
+                    break;
+                }
+            }
+        } else {
+            reader.seek(SeekFrom::Start(start))?;
+        }
+
+        let audio_start = reader.stream_position()?;
+        // the metadata of a truncated file ends past the end of the file
+        let end = reader.seek(SeekFrom::End(0))?;
+        let audio_start = audio_start.min(end);
+        let total = end - audio_start;
+        reader.seek(SeekFrom::Start(audio_start))?;
+        copy_reporting(reader, writer, total, progress)
+    }
+
+    /// Attempts to read only the streaminfo block from the file at the specified path. Reading
+    /// stops after the first block, so the rest of the metadata is never parsed. Returns an error
+    /// if the first block is not a streaminfo block.
+    pub fn read_streaminfo<P: AsRef<Path>>(path: P) -> Result<StreamInfo> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let (id3_len, junk_len) = crate::block::read_ident_with(&mut reader, 0)?;
+
+        let offset = id3_len + junk_len + 4;
+        let header = Block::read_header(&mut reader, offset)?;
+        if BlockType::from_u8(header.1) != BlockType::StreamInfo {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "first metadata block is not a streaminfo block",
+            ));
+        }
+        if header.2 != 34 {
+            return Err(Error::new(
+                ErrorKind::InvalidLength,
+                "streaminfo block has an invalid length",
+            )
+            .in_block(Some(BlockType::StreamInfo), offset));
+        }
+
+        let mut options = ReadOptions::new();
+        options.parse_mode = ParseMode::Strict;
+        match Block::read_contents(&mut reader, offset, &options, &mut Vec::new(), header)? {
+            (_, _, Block::StreamInfo(streaminfo)) => Ok(streaminfo),
+            _ => Err(
+                Error::new(ErrorKind::Truncated, "streaminfo block is truncated")
+                    .in_block(Some(BlockType::StreamInfo), offset),
+            ),
+        }
+    }
+
+    /// Attempts to read a FLAC tag from the file at the specified path.
+    pub fn read_from_path<P: AsRef<Path>>(path: P) -> Result<Tag> {
+        Tag::read_from_path_with(path, &ReadOptions::new())
+    }
+
+    /// Attempts to read a FLAC tag from the file at the specified path using the specified
+    /// options.
+    ///
+    /// With the `mmap` feature the file is mapped into memory instead of being read through a
+    /// buffer, unless `ReadOptions::retain_file` is set. The file must not be truncated by another
+    /// process while the tag is read.
+    pub fn read_from_path_with<P: AsRef<Path>>(path: P, options: &ReadOptions) -> Result<Tag> {
+        if options.retain_file {
+            let file = Arc::new(OpenOptions::new().read(true).write(true).open(&path)?);
+            let mut reader = BufReader::new(&*file);
+            if reader.fill_buf()?.starts_with(b"OggS") {
+                let mut tag = Tag::read_from_ogg_with(&mut reader, options)?;
+                tag.path = Some(path.as_ref().to_path_buf());
+                tag.file = Some(file.clone());
+                return Ok(tag);
+            }
+            let mut tag = Tag::read_from_skipping(&mut reader, options, skip_buffered)?;
+            tag.id3v1 = Id3v1::read_from(&mut &*file)?;
+            tag.ape = Tag::read_ape(&mut &*file);
+            tag.path = Some(path.as_ref().to_path_buf());
+            tag.file = Some(file);
+            return Ok(tag);
+        }
+
+        let file = File::open(&path)?;
+        #[cfg(feature = "mmap")]
+        let mut tag = {
+            let map = crate::mmap::map_file(&file)?;
+            let mut reader = io::Cursor::new(&map[..]);
+            if map.starts_with(b"OggS") {
+                Tag::read_from_ogg_with(&mut reader, options)?
+            } else {
+                let mut tag = Tag::read_from_skipping(&mut reader, options, skip_by_seeking)?;
+                tag.id3v1 = Id3v1::read_from(&mut reader)?;
+                tag.ape = Tag::read_ape(&mut reader);
+                tag
+            }
+        };
+        #[cfg(not(feature = "mmap"))]
+        let mut tag = {
+            let mut reader = BufReader::new(file);
+            if reader.fill_buf()?.starts_with(b"OggS") {
+                Tag::read_from_ogg_with(&mut reader, options)?
+            } else {
+                let mut tag = Tag::read_from_skipping(&mut reader, options, skip_buffered)?;
+                tag.id3v1 = Id3v1::read_from(reader.get_mut())?;
+                tag.ape = Tag::read_ape(&mut reader.get_ref());
+                tag
+            }
+        };
+        tag.path = Some(path.as_ref().to_path_buf());
+        Ok(tag)
+    }
+
+    /// Attempts to read a FLAC tag from a FLAC stream encapsulated in Ogg. The reader is left after
+    /// the header pages. `Tag::read_from_path` detects Ogg files by itself.
+    pub fn read_from_ogg<R: Read + ?Sized>(reader: &mut R) -> Result<Tag> {
+        Tag::read_from_ogg_with(reader, &ReadOptions::new())
+    }
+
+    /// Attempts to read a FLAC tag from a FLAC stream encapsulated in Ogg using the specified
+    /// options. See `Tag::read_from_with`.
+    ///
+    /// The header packets are read into memory before they are parsed, so the data of pictures is
+    /// always read and `ReadOptions::skip_picture_data` has no effect. The offsets in warnings
+    /// and errors are those the blocks would have in a native FLAC stream.
+    pub fn read_from_ogg_with<R: Read + ?Sized>(
+        reader: &mut R,
+        options: &ReadOptions,
+    ) -> Result<Tag> {
+        let headers = crate::ogg::read_headers(reader, options.max_block_len)?;
+
+        let mut options = options.clone();
+        options.skip_picture_data = false;
+
+        let mut tag = Tag::new();
+        let mut offset = 4;
+        for bytes in headers.blocks.iter() {
+            let mut reader = &bytes[..];
+            let header = Block::read_header(&mut reader, offset)?;
+            let block_type = BlockType::from_u8(header.1);
+            if reader.len() > header.2 as usize {
+                options
+                    .parse_mode
+                    .violation(
+                        offset,
+                        ErrorKind::InvalidLength,
+                        "ogg packet is longer than its block",
+                        &mut tag.warnings,
+                    )
+                    .map_err(|err| err.in_block(Some(block_type), offset))?;
+            }
+
+            if options.reads(block_type) {
+                let (_, length, block) =
+                    Block::read_contents(&mut reader, offset, &options, &mut tag.warnings, header)?;
+                tag.push_read_block(block, offset, length, &options)?;
+            } else {
+                tag.skip_read_block(bytes.len() as u32, &options)?;
+            }
+            offset += bytes.len() as u64;
+        }
+
+        // the blocks have no location in the file, which is rewritten whenever it is saved
+        tag.layout.clear();
+        tag.length = 0;
+        tag.ogg = true;
+        if options.merge_vorbis_comments {
+            tag.normalize();
+        }
+        tag.load_legacy_coverart();
+        Ok(tag)
+    }
+
+    /// Returns an `InvalidInput` error if the tag was read without some of its blocks, which
+    /// writing it would remove.
+    pub(crate) fn check_complete(&self) -> Result<()> {
+        if self.partial {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "tag was read without some of its blocks",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the path of the file which the tag was read from or last written to.
+    pub(crate) fn saved_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the size of the metadata region of the file when the tag was read or last
+    /// written, including block headers but not the `fLaC` marker.
+    pub(crate) fn region_len(&self) -> u32 {
+        self.length
+    }
+
+    /// Returns true if the tag was read from a FLAC stream encapsulated in Ogg. Saving such a tag
+    /// rewrites the header packets of the Ogg stream instead of native FLAC metadata.
+    pub fn is_ogg(&self) -> bool {
+        self.ogg
+    }
+
+    /// Rewrites the Ogg FLAC file at the path with the blocks as its header packets. The pages
+    /// following the header pages are renumbered when the number of header pages changes. Of the
+    /// write options only `preserve_modtime` applies, since Ogg FLAC streams are not padded.
+    fn write_ogg_to_path(
+        &mut self,
+        path: &Path,
+        options: &WriteOptions,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<()> {
+        let streaminfo = match self
+            .blocks
+            .iter()
+            .position(|block| block.block_type() == BlockType::StreamInfo)
+        {
+            Some(i) => self.blocks.remove(i),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "an ogg flac stream requires a streaminfo block",
+                ))
+            }
+        };
+        self.blocks.insert(0, streaminfo);
+
+        let nblocks = self.blocks.len();
+        let block_bytes = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| block.to_bytes(i == nblocks - 1))
+            .collect::<Result<Vec<_>>>()?;
+
+        let times = if options.preserve_modtime {
+            file_times(path)
+        } else {
+            None
+        };
+
+        let source = File::open(path)?;
+        let mut reader = BufReader::new(&source);
+        let headers = crate::ogg::read_headers(&mut reader, None)?;
+
+        let temp_path = temp_path(path);
+        let result = File::create(&temp_path)
+            .map_err(Error::from)
+            .and_then(|temp| {
+                let mut writer = BufWriter::new(&temp);
+                let mut sequence =
+                    crate::ogg::write_headers(&mut writer, headers.serial, &block_bytes)?;
+                for page in headers.other_pages.iter() {
+                    page.write_to(&mut writer)?;
+                }
+                let audio_start = reader.stream_position()?;
+                let total = source.metadata()?.len().saturating_sub(audio_start);
+                while let Some(mut page) = crate::ogg::Page::read_from(&mut reader)? {
+                    if page.serial == headers.serial {
+                        page.sequence = sequence;
+                        sequence = sequence.wrapping_add(1);
+                    }
+                    page.write_to(&mut writer)?;
+                    progress(reader.stream_position()? - audio_start, total);
+                }
+                writer.flush()?;
+                drop(writer);
+
+                copy_attributes(&source, &temp)?;
+                if let Some(times) = times {
+                    temp.set_times(times)?;
+                }
+                temp.sync_all()?;
+                drop(temp);
+
+                std::fs::rename(&temp_path, path)?;
+                Ok(())
+            });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result?;
+
+        // the retained handle refers to the replaced file
+        if self.file.is_some() {
+            self.file = Some(Arc::new(
+                OpenOptions::new().read(true).write(true).open(path)?,
+            ));
+        }
+
+        self.path = Some(path.to_path_buf());
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Reads the APEv2 tag at the end of the file. A damaged APEv2 tag does not prevent the FLAC
+    /// metadata from being read, so it is ignored.
+    fn read_ape<R: Read + Seek>(file: &mut R) -> Option<ApeTag> {
+        match ApeTag::read_from(file) {
+            Ok(ape) => ape.map(|(ape, _)| ape),
+            Err(err) => {
+                debug!("Ignoring APEv2 tag: {}", err);
+                None
+            }
+        }
+    }
+}
+
+impl Default for Tag {
+    fn default() -> Self {
+        Tag::new()
+    }
+}
+
+/// Tags are equal if they contain the same blocks in the same order, regardless of where they were
+/// read from.
+impl PartialEq for Tag {
+    fn eq(&self, other: &Tag) -> bool {
+        self.blocks == other.blocks
+    }
+}
+
+impl Eq for Tag {}
+
+/// Returns the access and modification times of the file at the path, for restoring them after
+/// the file has been written.
+fn file_times(path: &Path) -> Option<FileTimes> {
+    std::fs::metadata(path).ok().map(|metadata| {
+        let mut times = FileTimes::new();
+        if let Ok(accessed) = metadata.accessed() {
+            times = times.set_accessed(accessed);
+        }
+        if let Ok(modified) = metadata.modified() {
+            times = times.set_modified(modified);
+        }
+        times
+    })
+}
+
+/// Gives the temporary file which replaces the source file the permissions of the source file,
+/// and on Unix its owner and group. Changing the owner requires privileges, so the owner is kept
+/// only where the process is allowed to, such as when run by root on the file of another user,
+/// and otherwise just the group is kept where allowed.
+fn copy_attributes(source: &File, temp: &File) -> Result<()> {
+    let metadata = source.metadata()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{fchown, MetadataExt};
+
+        if fchown(temp, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+            let _ = fchown(temp, None, Some(metadata.gid()));
+        }
+    }
+    // after changing the owner, which clears the setuid and setgid bits
+    temp.set_permissions(metadata.permissions())?;
+    Ok(())
+}
+
+/// Returns an `InvalidInput` error if the blocks include more than one streaminfo or seektable
+/// block, which the specification forbids, or a damaged block, which would be written as it was
+/// read and could not be read back.
+pub(crate) fn check_unique_blocks<'b, I: IntoIterator<Item = &'b Block>>(blocks: I) -> Result<()> {
+    let (mut num_streaminfo, mut num_seektables) = (0, 0);
+    for block in blocks {
+        if block.is_damaged() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "tag has a damaged block which must be removed before writing",
+            ));
+        }
+        match block.block_type() {
+            BlockType::StreamInfo => num_streaminfo += 1,
+            BlockType::SeekTable => num_seektables += 1,
+            _ => {}
+        }
+    }
+    if num_streaminfo > 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "tag has more than one streaminfo block",
+        ));
+    }
+    if num_seektables > 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "tag has more than one seektable block",
+        ));
+    }
+    Ok(())
+}
+
+/// Reads and discards the specified number of bytes, returning the number of bytes skipped.
+pub(crate) fn skip_by_reading<R: Read + ?Sized>(reader: &mut R, len: u64) -> io::Result<u64> {
+    io::copy(&mut reader.take(len), &mut io::sink())
+}
+
+/// Skips the specified number of bytes by seeking within the buffer of the reader, or within the
+/// underlying reader past the end of the buffer.
+fn skip_buffered<R: Read + Seek>(reader: &mut BufReader<R>, len: u64) -> io::Result<u64> {
+    reader.seek_relative(len as i64)?;
+    Ok(len)
+}
+
+/// Skips the specified number of bytes by seeking, returning the number of bytes skipped before
+/// the end of the stream.
+#[cfg(feature = "mmap")]
+fn skip_by_seeking<R: Seek>(reader: &mut R, len: u64) -> io::Result<u64> {
+    let pos = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(end.min(pos + len)))?;
+    Ok(end.min(pos + len) - pos)
+}
+
+/// Returns an `InvalidInput` error if the options exclude the streaminfo block, which every
+/// stream requires.
+pub(crate) fn check_excluded(options: &WriteOptions) -> Result<()> {
+    if options.exclude_blocks.contains(&BlockType::StreamInfo) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "streaminfo block cannot be excluded",
+        ));
+    }
+    Ok(())
+}
+
+/// Copies the contents of the reader to the writer in chunks, calling the progress function with
+/// the number of bytes copied so far and the total after each chunk. Returns the number of bytes
+/// copied.
+fn copy_reporting<R: Read + ?Sized>(
+    reader: &mut R,
+    writer: &mut dyn Write,
+    total: u64,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<u64> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut copied = 0;
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(len) => len,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        writer.write_all(&buf[..len])?;
+        copied += len as u64;
+        progress(copied, total.max(copied));
+    }
+}
+
+/// Returns the error for a block index which is out of range.
+fn block_index_error() -> Error {
+    Error::new(ErrorKind::InvalidInput, "block index out of range")
+}
+
+/// Returns the path of the temporary file used while rewriting the file at the path.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", process::id()));
+    path.with_file_name(name)
+}
+
+/// Returns true if the path has the form of the temporary file used while rewriting a file, in
+/// this or any other process.
+pub(crate) fn is_temp_path(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix('.'))
+        .and_then(|name| name.strip_suffix(".tmp"))
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|(_, pid)| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{CueSheetTrack, CueSheetTrackIndex};
+    use crate::test_util::{test_path, test_stream};
+
+    #[test]
+    fn vorbis_case_sensitivity() {
+        let mut tag = Tag::new();
+
+        tag.set_vorbis("KEY", vec!["value"]);
+
+        assert_eq!(
+            tag.get_vorbis("KEY").unwrap().collect::<Vec<_>>(),
+            &["value"]
+        );
+        assert_eq!(
+            tag.get_vorbis("key").unwrap().collect::<Vec<_>>(),
+            &["value"]
+        );
+
+        tag.remove_vorbis("key");
+        assert!(tag.get_vorbis("KEY").is_none());
+    }
+
+    #[test]
+    fn preserve_order_on_save() {
+        let path = test_path("preserve-order.flac");
+        let stream = |title: &str| {
+            let mut bytes = test_stream();
+            bytes.truncate(bytes.len() - b"audio".len());
+            bytes[4] &= 0x7F;
+            Block::Padding(16).write_to(false, &mut bytes).unwrap();
+            let mut vorbis = VorbisComment::new();
+            vorbis.set_title(vec![title]);
+            Block::VorbisComment(vorbis)
+                .write_to(false, &mut bytes)
+                .unwrap();
+            Block::Padding(32).write_to(true, &mut bytes).unwrap();
+            bytes.extend(b"audio");
+            bytes
+        };
+        std::fs::write(&path, stream("title")).unwrap();
+
+        // the padding between the blocks stays in place
+        let mut options = WriteOptions::new();
+        options.preserve_order = true;
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["other"]);
+        tag.save_with(&options).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), stream("other"));
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.save().unwrap();
+        assert_eq!(
+            tag.block_layout(),
+            Tag::read_from_path(&path).unwrap().block_layout()
+        );
+        let types: Vec<_> = Tag::read_from_path(&path)
+            .unwrap()
+            .blocks()
+            .map(|block| block.block_type())
+            .collect();
+        assert_eq!(
+            types,
+            &[
+                BlockType::StreamInfo,
+                BlockType::VorbisComment,
+                BlockType::Padding
+            ]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_streaminfo_is_not_saved() {
+        let path = test_path("invalid-streaminfo.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_streaminfo(StreamInfo::new());
+        tag.set_vorbis("TITLE", vec!["title"]);
+        let err = tag.save().unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidInput));
+        assert_eq!(std::fs::read(&path).unwrap(), test_stream());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn repeated_saves_keep_arrangement() {
+        let path = test_path("repeated-saves.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+        let types = |tag: &Tag| tag.blocks().map(Block::block_type).collect::<Vec<_>>();
+
+        let mut options = WriteOptions::new();
+        options.use_padding = false;
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.save_with(&options).unwrap();
+        let blocks = types(&tag);
+        tag.save_with(&options).unwrap();
+        assert_eq!(types(&tag), blocks);
+        assert_eq!(types(&Tag::read_from_path(&path).unwrap()), blocks);
+
+        // a modification arranges the blocks again
+        tag.insert_block_at(0, Block::Padding(8)).unwrap();
+        tag.save_with(&options).unwrap();
+        assert_eq!(types(&tag), blocks);
+        assert_eq!(types(&Tag::read_from_path(&path).unwrap()), blocks);
+
+        // padding between blocks stays when preserving the order
+        options.preserve_order = true;
+        tag.insert_block_at(1, Block::Padding(8)).unwrap();
+        tag.save_with(&options).unwrap();
+        tag.save_with(&options).unwrap();
+        assert_eq!(
+            types(&Tag::read_from_path(&path).unwrap()),
+            &[
+                BlockType::StreamInfo,
+                BlockType::Padding,
+                BlockType::VorbisComment,
+                BlockType::Padding
+            ]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn padding_policy() {
+        let path = test_path("padding-policy.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut options = WriteOptions::new();
+        options.padding = PaddingPolicy::Multiple(4096);
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.save_with(&options).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap().len(), 4096 + b"audio".len());
+
+        // the region is already a multiple, so the padding shrinks instead
+        tag.set_vorbis("ARTIST", vec!["artist"]);
+        tag.save_with(&options).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap().len(), 4096 + b"audio".len());
+
+        options.padding = PaddingPolicy::None;
+        tag.save_with(&options).unwrap();
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(
+            tag.blocks()
+                .filter(|b| b.block_type() == BlockType::Padding)
+                .count(),
+            0
+        );
+        assert_eq!(
+            Tag::skip_metadata(&mut File::open(&path).unwrap()),
+            b"audio"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        let mut bytes = test_stream();
+        bytes.truncate(bytes.len() - b"audio".len());
+        bytes[4] &= 0x7F;
+        let streaminfo = bytes[4..].to_vec();
+        bytes.extend(streaminfo);
+        Block::Unknown((3, vec![0; 20]))
+            .write_to(false, &mut bytes)
+            .unwrap();
+        // a padding block which claims more bytes than there are
+        bytes.extend(&[0x81, 0, 1, 0]);
+        bytes.extend(&[0; 16]);
+
+        let tag = Tag::read_from_bytes(&bytes).unwrap();
+        let messages: Vec<_> = tag.warnings().iter().map(|w| &w.message[..]).collect();
+        assert_eq!(
+            messages,
+            &[
+                "duplicate streaminfo block",
+                "block has an invalid length",
+                "block is truncated"
+            ]
+        );
+        assert_eq!(tag.warnings()[1].offset, 4 + 38 + 38);
+        assert_eq!(tag.blocks().count(), 4);
+
+        let mut options = ReadOptions::new();
+        options.parse_mode = ParseMode::Strict;
+        let err = Tag::read_from_with(&mut &bytes[..], &options)
+            .err()
+            .unwrap();
+        assert_eq!(err.block_type, Some(BlockType::StreamInfo));
+        assert_eq!(err.offset, Some(42));
+        assert_eq!(
+            err.to_string(),
+            "InvalidInput: duplicate streaminfo block in StreamInfo block at offset 42"
+        );
+
+        // without the duplicate the seektable is the first violation
+        bytes.drain(42..80);
+        let err = Tag::read_from_with(&mut &bytes[..], &options)
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind, ErrorKind::InvalidLength));
+        assert_eq!(err.block_type, Some(BlockType::SeekTable));
+    }
+
+    #[test]
+    fn lossy_utf8_comments() {
+        let mut comments = vec![0, 0, 0, 0, 1, 0, 0, 0, 9, 0, 0, 0];
+        comments.extend(b"ARTIST=\xff\xfe");
+        let mut bytes = test_stream();
+        bytes.truncate(bytes.len() - b"audio".len());
+        bytes[4] &= 0x7F;
+        Block::Unknown((4, comments))
+            .write_to(true, &mut bytes)
+            .unwrap();
+
+        assert!(Tag::read_from_bytes(&bytes).is_err());
+
+        let mut options = ReadOptions::new();
+        options.lossy_utf8 = true;
+        let tag = Tag::read_from_with(&mut &bytes[..], &options).unwrap();
+        assert_eq!(
+            tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(),
+            &["\u{fffd}\u{fffd}"]
+        );
+        assert_eq!(tag.warnings()[0].message, "invalid UTF-8 in ARTIST");
+        assert_eq!(tag.warnings()[0].offset, 42);
+    }
+
+    #[test]
+    fn recover_damaged_blocks() {
+        let mut bytes = test_stream();
+        bytes.truncate(bytes.len() - b"audio".len());
+        bytes[4] &= 0x7F;
+        // a vorbis comment block claiming far more bytes than there are, followed by garbage
+        bytes.extend(&[0x04, 0xFF, 0xFF, 0xFF]);
+        bytes.extend(&[0x7F; 6]);
+        let mut vorbis = VorbisComment::new();
+        vorbis.set_title(vec!["title"]);
+        Block::VorbisComment(vorbis)
+            .write_to(true, &mut bytes)
+            .unwrap();
+        bytes.extend(b"\xFF\xF8audio");
+
+        assert!(Tag::read_from_bytes(&bytes)
+            .unwrap()
+            .get_vorbis("TITLE")
+            .is_none());
+
+        let mut options = ReadOptions::new();
+        options.parse_mode = ParseMode::Recover;
+        let tag = Tag::read_from_with(&mut &bytes[..], &options).unwrap();
+        assert_eq!(tag.skipped_regions().len(), 1);
+        assert_eq!(tag.skipped_regions()[0], 42..52);
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+        assert_eq!(tag.block_layout()[1].offset, 52);
+    }
+
+    #[test]
+    fn damaged_blocks_not_written() {
+        let mut bytes = test_stream();
+        bytes.truncate(bytes.len() - b"audio".len());
+        bytes[4] &= 0x7F;
+        // a vorbis comment block which claims more bytes than there are
+        bytes.extend(&[0x84, 0, 0, 100]);
+        bytes.extend(&[0; 10]);
+        let path = test_path("damaged-blocks.flac");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.warnings()[0].message, "block is truncated");
+        assert!(tag.blocks().nth(1).unwrap().is_damaged());
+        assert!(tag.vorbis_comments().is_none());
+        tag.set_vorbis("TITLE", vec!["title"]);
+        let err = tag.save().err().unwrap();
+        assert!(matches!(err.kind, ErrorKind::InvalidInput));
+        assert!(tag.write_to_vec().is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+        tag.remove_blocks(BlockType::Unknown(4));
+        tag.save().unwrap();
+        let mut options = ReadOptions::new();
+        options.parse_mode = ParseMode::Strict;
+        let tag = Tag::read_from_with(&mut File::open(&path).unwrap(), &options).unwrap();
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+        assert_eq!(tag.blocks().filter(|block| block.is_damaged()).count(), 0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parsing_limits() {
+        let mut tag = Tag::new();
+        tag.set_streaminfo(StreamInfo {
+            num_channels: 2,
+            bits_per_sample: 16,
+            md5: vec![0; 16],
+            ..StreamInfo::new()
+        });
+        tag.set_vorbis("ARTIST", vec!["a", "b", "c"]);
+        tag.push_block(Block::Padding(100));
+        let bytes = tag.write_to_vec().unwrap();
+        assert!(Tag::read_from_bytes(&bytes).is_ok());
+
+        let mut options = ReadOptions::new();
+        options.max_block_len = Some(99);
+        assert!(Tag::read_from_with(&mut &bytes[..], &options).is_err());
+
+        let mut options = ReadOptions::new();
+        options.max_metadata_len = Some(bytes.len() as u64 - 5);
+        assert!(Tag::read_from_with(&mut &bytes[..], &options).is_err());
+
+        let mut options = ReadOptions::new();
+        options.max_comments = Some(2);
+        assert!(Tag::read_from_with(&mut &bytes[..], &options).is_err());
+    }
+
+    #[test]
+    fn tag_equality() {
+        let mut tag = Tag::read_from_bytes(&test_stream()).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.add_picture("image/png", PictureType::CoverFront, vec![0x89, 0x50]);
+
+        let bytes = tag.clone().write_to_vec().unwrap();
+        assert_eq!(Tag::read_from_bytes(&bytes).unwrap(), tag);
+
+        let mut other = tag.clone();
+        other.set_vorbis("TITLE", vec!["other"]);
+        assert_ne!(other, tag);
+    }
+
+    #[test]
+    fn id3v2_prefix() {
+        let path = test_path("id3v2-prefix.flac");
+        // an id3v2.3 tag with 4 bytes of frame data
+        let mut bytes = b"ID3\x03\x00\x00\x00\x00\x00\x04TEST".to_vec();
+        bytes.extend(test_stream());
+        std::fs::write(&path, &bytes).unwrap();
+
+        assert!(Tag::is_candidate(&mut io::Cursor::new(&bytes)));
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.id3v2(), Some(0..14));
+
+        // rewriting keeps the id3v2 tag in front of the marker
+        tag.set_vorbis("TITLE", vec!["title"]);
+        let mut options = WriteOptions::new();
+        options.use_padding = false;
+        tag.save_with(&options).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(&written[..18], &bytes[..18]);
+        assert!(written.ends_with(b"audio"));
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+
+        options.remove_id3 = true;
+        tag.save_with(&options).unwrap();
+        assert!(tag.id3v2().is_none());
+        let written = std::fs::read(&path).unwrap();
+        assert!(written.starts_with(b"fLaC"));
+        assert!(written.ends_with(b"audio"));
+
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(Tag::remove_id3(&path).unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), test_stream());
+        assert!(!Tag::remove_id3(&path).unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn prefixed_file_to_other_path() {
+        let path = test_path("prefixed-source.flac");
+        let other = test_path("prefixed-other.flac");
+        let mut junk = b"junk".to_vec();
+        junk.extend(test_stream());
+        std::fs::write(&path, &junk).unwrap();
+        std::fs::write(&other, &junk).unwrap();
+
+        let mut read_options = ReadOptions::new();
+        read_options.max_junk_len = 8;
+        let mut tag = Tag::read_from_path_with(&path, &read_options).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+
+        // the junk of the file which was read says nothing about the marker of another file
+        let err = tag.write_to_path(&other).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::NotFlac));
+        assert_eq!(std::fs::read(&other).unwrap(), junk);
+
+        std::fs::remove_file(&other).unwrap();
+        tag.write_to_path(&other).unwrap();
+        let tag = Tag::read_from_path(&other).unwrap();
+        assert!(tag.junk().is_none());
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+
+        // only the id3v2 tag of the file which was read is removed
+        let mut id3 = b"ID3\x03\x00\x00\x00\x00\x00\x04TEST".to_vec();
+        id3.extend(test_stream());
+        std::fs::write(&path, &id3).unwrap();
+        std::fs::write(&other, &id3).unwrap();
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        let mut options = WriteOptions::new();
+        options.remove_id3 = true;
+        tag.write_to_path_with(&other, &options).unwrap();
+        let written = std::fs::read(&other).unwrap();
+        assert_eq!(&written[..18], &id3[..18]);
+        assert!(written.ends_with(b"audio"));
+        assert_eq!(tag.id3v2(), Some(0..14));
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&other).unwrap();
+    }
+
+    #[test]
+    fn trailing_id3v1() {
+        let path = test_path("trailing-id3v1.flac");
+        let mut id3v1 = b"TAGtitle".to_vec();
+        id3v1.resize(33, 0);
+        id3v1.extend(b"artist");
+        id3v1.resize(126, 0);
+        id3v1.extend(&[7, 0xFF]);
+        let mut bytes = test_stream();
+        bytes.extend(&id3v1);
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        let id3v1 = tag.id3v1().unwrap();
+        assert_eq!(id3v1.artist, "artist");
+        assert_eq!(id3v1.track, Some(7));
+        assert_eq!(id3v1.genre, None);
+
+        tag.set_vorbis("TITLE", vec!["other"]);
+        assert_eq!(tag.import_id3v1(), 2);
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["other"]
+        );
+        assert_eq!(
+            tag.get_vorbis("TRACKNUMBER").unwrap().collect::<Vec<_>>(),
+            &["7"]
+        );
+
+        let mut options = WriteOptions::new();
+        options.remove_id3v1 = true;
+        options.use_padding = false;
+        tag.save_with(&options).unwrap();
+        assert!(tag.id3v1().is_none());
+        assert!(std::fs::read(&path).unwrap().ends_with(b"audio"));
+
+        // in place
+        std::fs::write(&path, &bytes).unwrap();
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        options.use_padding = true;
+        tag.save_with(&options).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), test_stream());
+        assert!(Tag::read_from_path(&path).unwrap().id3v1().is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn trailing_ape() {
+        let path = test_path("trailing-ape.flac");
+        let mut items = Vec::new();
+        for (key, value) in [
+            ("Artist", &b"a\0b"[..]),
+            ("Year", b"1999"),
+            ("Debut Album", b"debut"),
+        ] {
+            items.extend((value.len() as u32).to_le_bytes());
+            items.extend(0u32.to_le_bytes());
+            items.extend(key.as_bytes());
+            items.push(0);
+            items.extend(value);
+        }
+        let footer = |flags: u32| {
+            let mut footer = b"APETAGEX".to_vec();
+            footer.extend(2000u32.to_le_bytes());
+            footer.extend((items.len() as u32 + 32).to_le_bytes());
+            footer.extend(3u32.to_le_bytes());
+            footer.extend(flags.to_le_bytes());
+            footer.extend([0; 8]);
+            footer
+        };
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+
+        let mut bytes = test_stream();
+        bytes.extend(footer(0xA000_0000));
+        bytes.extend(&items);
+        bytes.extend(footer(0x8000_0000));
+        bytes.extend(&id3v1);
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.ape().unwrap().items.len(), 3);
+        assert!(tag.id3v1().is_some());
+        assert_eq!(tag.import_ape(), 4);
+        assert_eq!(
+            tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(),
+            &["a", "b"]
+        );
+        assert_eq!(
+            tag.get_vorbis("DATE").unwrap().collect::<Vec<_>>(),
+            &["1999"]
+        );
+        // the debut album of the artist is not the album of the track
+        assert!(tag.get_vorbis("ALBUM").is_none());
+        assert_eq!(
+            tag.get_vorbis("DEBUT ALBUM").unwrap().collect::<Vec<_>>(),
+            &["debut"]
+        );
+        assert_eq!(tag.import_ape(), 0);
+
+        let mut options = WriteOptions::new();
+        options.remove_ape = true;
+        tag.save_with(&options).unwrap();
+        assert!(tag.ape().is_none());
+        let written = std::fs::read(&path).unwrap();
+        assert!(written.ends_with(&id3v1));
+        assert_eq!(&written[written.len() - 133..written.len() - 128], b"audio");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn short_blocks_from_bytes() {
+        let streaminfo = Block::from_bytes(&[0, 0, 0, 0]).unwrap_err();
+        assert!(matches!(streaminfo.kind, ErrorKind::InvalidLength));
+        let application = Block::from_bytes(&[2, 0, 0, 1, 0]).unwrap_err();
+        assert!(matches!(application.kind, ErrorKind::InvalidLength));
+        let cuesheet = Block::from_bytes(&[5, 0, 0, 1, 0]).unwrap_err();
+        assert!(matches!(cuesheet.kind, ErrorKind::Truncated));
+
+        let mut cuesheet = CueSheet::new();
+        cuesheet.tracks.push(CueSheetTrack::new());
+        let bytes = Block::CueSheet(cuesheet).to_bytes(true).unwrap();
+        assert!(Block::from_bytes(&bytes).is_ok());
+        assert!(CueSheet::from_bytes(&bytes[4..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn not_flac_errors() {
+        for bytes in [&b""[..], b"fLa", b"ID3\x04", b"not flac"] {
+            let err = Tag::read_from_bytes(bytes).unwrap_err();
+            assert!(matches!(err.kind, ErrorKind::NotFlac));
+        }
+
+        let opus = crate::ogg::Page {
+            header_type: 0x02,
+            granule: 0,
+            serial: 1,
+            sequence: 0,
+            segments: vec![8],
+            data: b"OpusHead".to_vec(),
+        };
+        let mut bytes = Vec::new();
+        opus.write_to(&mut bytes).unwrap();
+        let err = Tag::read_from_ogg(&mut &bytes[..]).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::NotFlac));
+    }
+
+    #[test]
+    fn ogg_flac() {
+        let path = test_path("ogg-flac.oga");
+        let mut vorbis = VorbisComment::new();
+        vorbis.set_title(vec!["title"]);
+        let blocks = [
+            Block::from_bytes(&test_stream()[4..42])
+                .unwrap()
+                .to_bytes(false)
+                .unwrap(),
+            Block::VorbisComment(vorbis).to_bytes(true).unwrap(),
+        ];
+        let mut bytes = Vec::new();
+        let sequence = crate::ogg::write_headers(&mut bytes, 7, &blocks).unwrap();
+        let audio = crate::ogg::Page {
+            header_type: 0x04,
+            granule: 4096,
+            serial: 7,
+            sequence,
+            segments: vec![5],
+            data: b"audio".to_vec(),
+        };
+        audio.write_to(&mut bytes).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        assert!(tag.is_ogg());
+        assert!(!tag.is_dirty());
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+        assert_eq!(tag.get_streaminfo().unwrap().num_channels, 2);
+
+        // a comment spanning several pages
+        let long = "x".repeat(100_000);
+        tag.set_vorbis("LONG", vec![long.as_str()]);
+        tag.save().unwrap();
+
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        let tag = Tag::read_from_ogg(&mut reader).unwrap();
+        assert_eq!(
+            tag.get_vorbis("LONG").unwrap().collect::<Vec<_>>(),
+            &[&long]
+        );
+        let page = crate::ogg::Page::read_from(&mut reader).unwrap().unwrap();
+        assert_eq!(page.data, b"audio");
+        assert!(page.sequence > sequence);
+        assert!(crate::ogg::Page::read_from(&mut reader).unwrap().is_none());
+
+        // the read options apply to ogg streams too
+        let mut options = ReadOptions::new();
+        options.max_block_len = Some(1000);
+        assert!(Tag::read_from_path_with(&path, &options).is_err());
+        let options = ReadOptions::only(&[BlockType::StreamInfo]);
+        let mut tag = Tag::read_from_path_with(&path, &options).unwrap();
+        assert!(tag.is_partial());
+        assert!(tag.get_vorbis("LONG").is_none());
+        tag.set_vorbis("TITLE", vec!["title"]);
+        assert!(tag.save().is_err());
+
+        // a short streaminfo packet is a warning rather than a panic
+        let blocks = [vec![0x80, 0, 0, 2, 0, 0]];
+        let mut bytes = Vec::new();
+        crate::ogg::write_headers(&mut bytes, 7, &blocks).unwrap();
+        let tag = Tag::read_from_ogg(&mut &bytes[..]).unwrap();
+        assert!(tag.get_streaminfo().is_none());
+        assert!(!tag.warnings().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn picture_from_path() {
+        let path = test_path("picture-from-path.gif");
+        std::fs::write(&path, b"GIF89a\x01\x00\x01\x00").unwrap();
+
+        let mut tag = Tag::new();
+        tag.add_picture_from_path(&path, PictureType::CoverFront)
+            .unwrap();
+        let picture = tag.pictures().next().unwrap();
+        assert_eq!(picture.mime_type, "image/gif");
+        assert_eq!(picture.picture_type, PictureType::CoverFront);
+
+        std::fs::write(&path, b"not an image").unwrap();
+        assert!(tag
+            .add_picture_from_path(&path, PictureType::CoverBack)
+            .is_err());
+        assert_eq!(tag.pictures().count(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
 
     #[test]
-    fn vorbis_case_sensitivity() {
+    fn remove_junk_on_save() {
+        let path = test_path("remove-junk.flac");
+        let mut bytes = b"junk".to_vec();
+        bytes.extend(test_stream());
+        std::fs::write(&path, &bytes).unwrap();
+
+        assert!(Tag::read_from_path(&path).is_err());
+
+        let mut read_options = ReadOptions::new();
+        read_options.max_junk_len = 8;
+        let mut tag = Tag::read_from_path_with(&path, &read_options).unwrap();
+        assert_eq!(tag.junk(), Some(0..4));
+
+        let mut write_options = WriteOptions::new();
+        write_options.remove_junk = true;
+        tag.save_with(&write_options).unwrap();
+        assert!(tag.junk().is_none());
+
+        let mut file = File::open(&path).unwrap();
+        assert!(Tag::read_from(&mut file).is_ok());
+        assert_eq!(
+            Tag::skip_metadata(&mut File::open(&path).unwrap()),
+            b"audio"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn padding_replaced_on_save() {
+        let path = test_path("replace-padding.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+        let types = |tag: &Tag| tag.blocks().map(Block::block_type).collect::<Vec<_>>();
+        let blocks = [
+            BlockType::StreamInfo,
+            BlockType::VorbisComment,
+            BlockType::Padding,
+        ];
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.push_block(Block::Padding(10));
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.save().unwrap();
+        assert_eq!(types(&tag), blocks);
+
+        // the padding block added by the last save is replaced
+        tag.set_vorbis("TITLE", vec!["other"]);
+        tag.save().unwrap();
+        assert_eq!(types(&tag), blocks);
+        assert_eq!(types(&Tag::read_from_path(&path).unwrap()), blocks);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn padding_and_modtime_options() {
+        let path = test_path("write-options.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1 << 30);
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let mut options = WriteOptions::new();
+        options.preserve_modtime = true;
+        options.padding = PaddingPolicy::Fixed(100);
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.save_with(&options).unwrap();
+
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(
+            tag.get_blocks(BlockType::Padding)
+                .map(|block| match *block {
+                    Block::Padding(size) => size,
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>(),
+            &[100]
+        );
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.modified().unwrap(), modified);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn retained_file_saves() {
+        let path = test_path("retain-file.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut options = ReadOptions::new();
+        options.retain_file = true;
+        let mut tag = Tag::read_from_path_with(&path, &options).unwrap();
+
+        tag.set_vorbis("TITLE", vec!["first"]);
+        tag.save().unwrap();
+        tag.set_vorbis("TITLE", vec!["second"]);
+        tag.save().unwrap();
+        tag.set_vorbis("TITLE", vec!["x".repeat(2048)]);
+        tag.save().unwrap();
+
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["x".repeat(2048)]
+        );
+        assert_eq!(
+            Tag::skip_metadata(&mut File::open(&path).unwrap()),
+            b"audio"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn copy_tags_between_files() {
+        let src_path = test_path("copy-tags-src.flac");
+        let dest_path = test_path("copy-tags-dest.flac");
+        std::fs::write(&src_path, test_stream()).unwrap();
+        std::fs::write(&dest_path, test_stream()).unwrap();
+
+        let mut src = Tag::read_from_path(&src_path).unwrap();
+        src.set_vorbis("TITLE", vec!["title"]);
+        src.add_picture("image/png", PictureType::CoverFront, vec![0xAB]);
+        let mut streaminfo = src.get_streaminfo().unwrap().clone();
+        streaminfo.sample_rate = 44100;
+        src.set_streaminfo(streaminfo);
+
+        let mut dest = Tag::read_from_path(&dest_path).unwrap();
+        dest.set_vorbis("ARTIST", vec!["artist"]);
+        dest.save().unwrap();
+
+        src.copy_tags_to(&dest_path, &CopyOptions::new()).unwrap();
+
+        let dest = Tag::read_from_path(&dest_path).unwrap();
+        assert_eq!(dest.get_streaminfo().unwrap().sample_rate, 0);
+        assert!(dest.get_vorbis("ARTIST").is_none());
+        assert_eq!(
+            dest.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+        assert_eq!(dest.pictures().count(), 1);
+
+        // copying from an untagged source removes the tags of the destination
+        let src = Tag::read_from_bytes(&test_stream()).unwrap();
+        src.copy_tags_to(&dest_path, &CopyOptions::new()).unwrap();
+        let dest = Tag::read_from_path(&dest_path).unwrap();
+        assert!(dest.get_vorbis("TITLE").is_none());
+        assert_eq!(dest.pictures().count(), 0);
+
+        std::fs::remove_file(&src_path).unwrap();
+        std::fs::remove_file(&dest_path).unwrap();
+    }
+
+    #[test]
+    fn copy_selected_blocks_to_path() {
+        let src_path = test_path("copy-to-path-src.flac");
+        let dest_path = test_path("copy-to-path-dest.flac");
+
+        let mut src = Tag::read_from_bytes(&test_stream()).unwrap();
+        src.set_vorbis("TITLE", vec!["title"]);
+        src.add_picture("image/png", PictureType::CoverFront, vec![0xAB]);
+        src.add_application_block(b"riff", b"RIFF\0\0\0\0WAVE".to_vec());
+        let mut bytes = src.write_to_vec().unwrap();
+        bytes.extend(b"audio");
+        std::fs::write(&src_path, &bytes).unwrap();
+
+        let mut dest = Tag::read_from_bytes(&test_stream()).unwrap();
+        let mut streaminfo = dest.get_streaminfo().unwrap().clone();
+        streaminfo.sample_rate = 48000;
+        dest.set_streaminfo(streaminfo);
+        dest.add_picture("image/jpeg", PictureType::CoverBack, vec![0xCD]);
+        let mut bytes = dest.write_to_vec().unwrap();
+        bytes.extend(b"reencoded");
+        std::fs::write(&dest_path, &bytes).unwrap();
+
+        let mut options = CopyOptions::new();
+        options.copy_pictures = false;
+        Tag::copy_to_path(&src_path, &dest_path, &options).unwrap();
+
+        let dest = Tag::read_from_path(&dest_path).unwrap();
+        assert_eq!(dest.get_streaminfo().unwrap().sample_rate, 48000);
+        assert_eq!(
+            dest.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+        assert_eq!(dest.application_blocks(b"riff").count(), 1);
+        let pictures: Vec<_> = dest.pictures().collect();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(pictures[0].data, &[0xCD]);
+        assert!(std::fs::read(&dest_path).unwrap().ends_with(b"reencoded"));
+
+        std::fs::remove_file(&src_path).unwrap();
+        std::fs::remove_file(&dest_path).unwrap();
+    }
+
+    #[test]
+    fn exclude_blocks_from_written_file() {
+        let path = test_path("exclude-blocks.flac");
+        let mut tag = Tag::read_from_bytes(&test_stream()).unwrap();
+        tag.push_block(Block::SeekTable(SeekTable::template_n_points(2)));
+        tag.set_vorbis("TITLE", vec!["title"]);
+        let mut bytes = tag.write_to_vec().unwrap();
+        bytes.extend(b"audio");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        let mut options = WriteOptions::new();
+        options.exclude_blocks = vec![BlockType::SeekTable, BlockType::Padding];
+        tag.save_with(&options).unwrap();
+        assert_eq!(tag.get_blocks(BlockType::SeekTable).count(), 1);
+
+        let written = Tag::read_from_path(&path).unwrap();
+        assert_eq!(written.get_blocks(BlockType::SeekTable).count(), 0);
+        assert_eq!(written.get_blocks(BlockType::Padding).count(), 0);
+        assert_eq!(written.block_layout(), tag.block_layout());
+        assert!(std::fs::read(&path).unwrap().ends_with(b"audio"));
+
+        // a later save finds the metadata region which was written
+        tag.set_vorbis("TITLE", vec!["other"]);
+        tag.save().unwrap();
+        let written = Tag::read_from_path(&path).unwrap();
+        assert_eq!(written.get_blocks(BlockType::SeekTable).count(), 1);
+        assert_eq!(
+            written.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["other"]
+        );
+        assert!(std::fs::read(&path).unwrap().ends_with(b"audio"));
+
+        options.exclude_blocks = vec![BlockType::StreamInfo];
+        assert!(tag.save_with(&options).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rewrite_progress() {
+        let path = test_path("rewrite-progress.flac");
+        let mut bytes = test_stream();
+        bytes.extend(vec![0xFF; 200_000]);
+        std::fs::write(&path, &bytes).unwrap();
+        let audio_len = 200_000 + b"audio".len() as u64;
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        let mut calls = Vec::new();
+        tag.save_with_progress(&WriteOptions::new(), |copied, total| {
+            calls.push((copied, total))
+        })
+        .unwrap();
+        assert!(calls.len() > 1);
+        assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(calls.last(), Some(&(audio_len, audio_len)));
+
+        // written into the padding without copying the audio
+        tag.set_vorbis("TITLE", vec!["other"]);
+        calls.clear();
+        tag.save_with_progress(&WriteOptions::new(), |copied, total| {
+            calls.push((copied, total))
+        })
+        .unwrap();
+        assert!(calls.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn skip_unmodified_save() {
+        let path = test_path("skip-unmodified-save.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        assert!(!tag.is_dirty());
+        tag.set_vorbis("TITLE", vec!["title"]);
+        assert!(tag.is_dirty());
+        tag.save().unwrap();
+        assert!(!tag.is_dirty());
+
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.remove_vorbis("ARTIST");
+        tag.set_vendor_string(tag.vendor_string().unwrap().to_owned());
+        assert!(!tag.is_dirty());
+
+        // a skipped save leaves the file alone
+        std::fs::write(&path, test_stream()).unwrap();
+        tag.save().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), test_stream());
+
+        let mut options = WriteOptions::new();
+        options.padding = PaddingPolicy::None;
+        tag.save_with(&options).unwrap();
+        assert_ne!(std::fs::read(&path).unwrap(), test_stream());
+
+        tag.remove_blocks(BlockType::Picture);
+        assert!(!tag.is_dirty());
+        tag.remove_blocks(BlockType::VorbisComment);
+        assert!(tag.is_dirty());
+        tag.save().unwrap();
+
+        let mut vorbis = VorbisComment::new();
+        vorbis.set_title(vec!["replaced"]);
+        tag.push_block(Block::Padding(10));
+        tag.save().unwrap();
+        tag.replace_block(1, Block::VorbisComment(vorbis)).unwrap();
+        assert!(tag.is_dirty());
+        tag.save().unwrap();
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["replaced"]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn deterministic_write() {
+        let paths = [
+            test_path("deterministic-a.flac"),
+            test_path("deterministic-b.flac"),
+        ];
+        let mut options = WriteOptions::new();
+        options.deterministic = true;
+
+        let mut tag = Tag::read_from_bytes(&test_stream()).unwrap();
+        tag.push_block(Block::Padding(10));
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.set_vorbis("ARTIST", vec!["a", "b"]);
+        tag.add_picture("image/png", PictureType::CoverFront, vec![1]);
+        std::fs::write(&paths[0], test_stream()).unwrap();
+        tag.write_to_path_with(&paths[0], &options).unwrap();
+
+        let mut tag = Tag::read_from_bytes(&test_stream()).unwrap();
+        tag.add_picture("image/png", PictureType::CoverFront, vec![1]);
+        tag.set_vorbis("ARTIST", vec!["a", "b"]);
+        tag.set_vorbis("TITLE", vec!["title"]);
+        std::fs::write(&paths[1], test_stream()).unwrap();
+        tag.write_to_path_with(&paths[1], &options).unwrap();
+
+        let bytes = std::fs::read(&paths[0]).unwrap();
+        assert_eq!(bytes, std::fs::read(&paths[1]).unwrap());
+        let tag = Tag::read_from_path(&paths[0]).unwrap();
+        assert_eq!(
+            tag.get_blocks(BlockType::Padding).collect::<Vec<_>>(),
+            &[&Block::Padding(1024)]
+        );
+        for path in paths.iter() {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn sanitize_comments() {
+        let mut tag = Tag::read_from_bytes(&test_stream()).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.set_vorbis("ARTIST", vec!["a", "\t", "a\0"]);
+        tag.enable_journal().unwrap();
+
+        assert!(tag.sanitize(false));
+        assert_eq!(
+            tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(),
+            &["a", "a"]
+        );
+        assert!(tag.sanitize(true));
+        assert_eq!(
+            tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(),
+            &["a"]
+        );
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+        assert!(!tag.sanitize(true));
+
+        let keys: Vec<_> = tag
+            .journal()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|entry| entry.key.as_str())
+            .collect();
+        assert_eq!(keys, &["ARTIST", "ARTIST"]);
+    }
+
+    #[test]
+    fn merge_vorbis_comments_on_read() {
+        let mut tag = Tag::read_from_bytes(&test_stream()).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.push_block(Block::Padding(8));
+        let mut other = VorbisComment::new();
+        other.vendor_string = "other".to_string();
+        other.set("ARTIST", vec!["b"]);
+        other.set("title", vec!["second"]);
+        tag.push_block(Block::VorbisComment(other));
+        let bytes = tag.write_to_vec().unwrap();
+
+        let tag = Tag::read_from_bytes(&bytes).unwrap();
+        assert_eq!(tag.get_blocks(BlockType::VorbisComment).count(), 2);
+
+        let mut options = ReadOptions::new();
+        options.merge_vorbis_comments = true;
+        let tag = Tag::read_from_with(&mut &bytes[..], &options).unwrap();
+        let types: Vec<_> = tag.blocks().map(Block::block_type).collect();
+        assert_eq!(
+            types,
+            &[
+                BlockType::StreamInfo,
+                BlockType::VorbisComment,
+                BlockType::Padding
+            ]
+        );
+        let vorbis = tag.vorbis_comments().unwrap();
+        assert_eq!(vorbis.vendor_string, "other");
+        assert_eq!(
+            vorbis.entries(),
+            &[("TITLE", "title"), ("ARTIST", "b"), ("title", "second")]
+        );
+    }
+
+    #[test]
+    fn duplicate_seektable() {
+        let mut tag = Tag::read_from_bytes(&test_stream()).unwrap();
+        tag.push_block(Block::SeekTable(SeekTable::new()));
+        let mut bytes = tag.write_to_vec().unwrap();
+        tag.push_block(Block::SeekTable(SeekTable::new()));
+        assert!(tag.write_to_vec().is_err());
+        let path = test_path("duplicate-seektable.flac");
+        assert!(tag.write_to_path(&path).is_err());
+        assert!(!path.exists());
+
+        // a second seektable block written by another tool
+        let last = bytes.len() - 4;
+        bytes[last] &= 0x7F;
+        bytes.extend(&[0x83, 0, 0, 0]);
+
+        let tag = Tag::read_from_bytes(&bytes).unwrap();
+        assert_eq!(tag.warnings()[0].message, "duplicate seektable block");
+        let mut options = ReadOptions::new();
+        options.parse_mode = ParseMode::Strict;
+        assert!(Tag::read_from_with(&mut &bytes[..], &options).is_err());
+    }
+
+    #[test]
+    fn apply_exported_patch() {
+        let path = test_path("apply-patch.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
         let mut tag = Tag::new();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        let patch = tag.export_patch(&CopyOptions::new()).unwrap();
 
-        tag.set_vorbis("KEY", vec!["value"]);
+        Tag::apply_patch(&path, &patch, &CopyOptions::new()).unwrap();
 
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert!(tag.get_streaminfo().is_some());
         assert_eq!(
-            tag.get_vorbis("KEY").unwrap().collect::<Vec<_>>(),
-            &["value"]
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn journal_block_round_trip() {
+        let mut tag = Tag::new();
+        tag.enable_journal().unwrap();
+        tag.set_vorbis("ARTIST", vec!["a", "b"]);
+        tag.remove_vorbis_pair("ARTIST", "a");
+        tag.remove_vorbis("ARTIST");
+        tag.remove_vorbis("ARTIST");
+        assert_eq!(tag.journal().unwrap().entries.len(), 3);
+
+        tag.write_journal_block();
+        let mut bytes = Vec::new();
+        tag.write_to(&mut bytes).unwrap();
+
+        let mut tag = Tag::read_from(&mut &bytes[..]).unwrap();
+        assert!(tag.journal().is_none());
+        tag.enable_journal().unwrap();
+
+        let journal = tag.journal().unwrap();
+        assert_eq!(journal.entries.len(), 3);
+        assert_eq!(journal.entries[0].old, None);
+        assert_eq!(journal.entries[1].new, Some(vec!["b".to_owned()]));
+        assert_eq!(journal.entries[2].new, None);
+    }
+
+    #[test]
+    fn cuesheet_round_trip() {
+        let mut cuesheet = CueSheet::new();
+        cuesheet.catalog_num = "1234567890123".to_owned();
+        cuesheet.num_leadin = 88200;
+
+        let mut track = CueSheetTrack::new();
+        track.offset = 588;
+        track.number = 1;
+        track.isrc = "USRC17607839".to_owned();
+        track.pre_emphasis = true;
+        let mut index = CueSheetTrackIndex::new();
+        index.point_num = 1;
+        track.indices.push(index);
+        cuesheet.tracks.push(track);
+
+        let mut lead_out = CueSheetTrack::new();
+        lead_out.offset = 44100 * 60;
+        lead_out.number = 170;
+        cuesheet.tracks.push(lead_out);
+
+        let mut tag = Tag::new();
+        tag.set_cuesheet(cuesheet.clone());
+        let mut bytes = Vec::new();
+        tag.write_to(&mut bytes).unwrap();
+
+        let tag = Tag::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(tag.cuesheet(), Some(&cuesheet));
+    }
+
+    #[test]
+    fn foreign_metadata_round_trip() {
+        let path = test_path("foreign-metadata.flac");
+        let mut tag = Tag::read_from_bytes(&test_stream()).unwrap();
+        tag.add_application_block(b"riff", b"RIFF\x40\0\0\0WAVE".to_vec());
+        tag.add_application_block(b"riff", b"bext\x04\0\0\0orig".to_vec());
+        tag.add_application_block(b"riff", b"fmt \x02\0\0\0\x01\0".to_vec());
+        tag.add_application_block(b"riff", b"data\x10\0\0\0".to_vec());
+        let mut bytes = tag.write_to_vec().unwrap();
+        bytes.extend(b"audio");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let applications = |tag: &Tag| tag.application_blocks(b"riff").cloned().collect::<Vec<_>>();
+        let original = applications(&tag);
+
+        let mut options = WriteOptions::new();
+        for use_padding in [false, true] {
+            let mut tag = Tag::read_from_path(&path).unwrap();
+            tag.set_vorbis("TITLE", vec!["title"]);
+            tag.add_picture("image/png", PictureType::CoverFront, vec![0x89, 0x50]);
+            options.use_padding = use_padding;
+            tag.save_with(&options).unwrap();
+
+            let tag = Tag::read_from_path(&path).unwrap();
+            assert_eq!(applications(&tag), original);
+            let foreign = tag.foreign_metadata().unwrap().unwrap();
+            assert_eq!(&foreign.form_type, b"WAVE");
+            let ids: Vec<_> = foreign.chunks.iter().map(|chunk| &chunk.id).collect();
+            assert_eq!(ids, &[b"bext", b"fmt ", b"data"]);
+            assert_eq!(foreign.chunk(b"data").unwrap().size, 16);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn chapters_from_cuesheet() {
+        let mut streaminfo = StreamInfo::new();
+        streaminfo.sample_rate = 44100;
+        let mut tag = Tag::new();
+        tag.push_block(Block::StreamInfo(streaminfo));
+
+        let mut cuesheet = CueSheet::new();
+        for (number, offset) in [(1, 0), (2, 44100 * 60)] {
+            let mut track = CueSheetTrack::new();
+            track.number = number;
+            track.offset = offset;
+            for (point_num, offset) in [(0, 0), (1, 44100)] {
+                let mut index = CueSheetTrackIndex::new();
+                index.point_num = point_num;
+                index.offset = offset;
+                track.indices.push(index);
+            }
+            cuesheet.tracks.push(track);
+        }
+        let mut lead_out = CueSheetTrack::new();
+        lead_out.number = 170;
+        cuesheet.tracks.push(lead_out);
+        tag.set_cuesheet(cuesheet);
+
+        let starts: Vec<_> = tag.chapters().iter().map(|chapter| chapter.start).collect();
+        assert_eq!(starts, &[Duration::from_secs(1), Duration::from_secs(61)]);
+
+        // chapter comments take precedence
+        let mut chapters = tag.chapters();
+        chapters[0].title = "first".to_owned();
+        tag.set_chapters(&chapters[..1]);
+        assert_eq!(tag.chapters(), &chapters[..1]);
+        tag.remove_chapters();
+        assert_eq!(tag.chapters().len(), 2);
+    }
+
+    #[test]
+    fn in_place_save_without_padding() {
+        let path = test_path("exact-fit.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["aaaa"]);
+        tag.save().unwrap();
+        let len = std::fs::metadata(&path).unwrap().len();
+
+        // fill the padding exactly with a longer value
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["a".repeat(4 + 1024 + 4)]);
+        tag.save().unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.get_blocks(BlockType::Padding).count(), 0);
+        assert_eq!(
+            Tag::skip_metadata(&mut File::open(&path).unwrap()),
+            b"audio"
         );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rewrite_keeps_owner() {
+        use std::os::unix::fs::{chown, MetadataExt};
+
+        let path = test_path("rewrite-owner.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+        // only a privileged process can give the file to another user
+        if chown(&path, Some(12345), Some(12345)).is_err() {
+            std::fs::remove_file(&path).unwrap();
+            return;
+        }
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.save().unwrap();
+
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!((metadata.uid(), metadata.gid()), (12345, 12345));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn save_as_copies_audio() {
+        let original = test_path("save-as-original.flac");
+        let copy = test_path("save-as-copy.flac");
+        let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+        bytes.extend(test_stream());
+        std::fs::write(&original, &bytes).unwrap();
+        std::fs::write(&copy, b"replaced").unwrap();
+
+        let mut options = ReadOptions::new();
+        options.retain_file = true;
+        let mut tag = Tag::read_from_path_with(&original, &options).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.save_as(&copy).unwrap();
+        assert_eq!(std::fs::read(&original).unwrap(), bytes);
+        assert!(!temp_path(&copy).exists());
+
+        // later saves go to the copy, through the retained handle
+        tag.set_vorbis("TITLE", vec!["other"]);
+        tag.save().unwrap();
+        assert_eq!(std::fs::read(&original).unwrap(), bytes);
+        let saved = Tag::read_from_path(&copy).unwrap();
         assert_eq!(
-            tag.get_vorbis("key").unwrap().collect::<Vec<_>>(),
-            &["value"]
+            saved.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["other"]
         );
+        assert_eq!(saved.id3v2(), Some(0..10));
+        assert!(std::fs::read(&copy).unwrap().ends_with(b"audio"));
 
-        tag.remove_vorbis("key");
-        assert!(tag.get_vorbis("KEY").is_none());
+        // a tag retargeted at a new path forgets the layout of the original file
+        let mut tag = Tag::read_from_path(&original).unwrap();
+        std::fs::remove_file(&copy).unwrap();
+        tag.set_path(&copy).unwrap();
+        tag.save().unwrap();
+        assert!(Tag::read_from_path(&copy).unwrap().id3v2().is_none());
+        assert_eq!(std::fs::read(&original).unwrap(), bytes);
+
+        std::fs::remove_file(&original).unwrap();
+        std::fs::remove_file(&copy).unwrap();
+    }
+
+    #[test]
+    fn rewrite_replaces_file_atomically() {
+        let path = test_path("atomic-save.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions.clone()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.save().unwrap();
+
+        assert!(!temp_path(&path).exists());
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions(), permissions);
+        assert_eq!(
+            Tag::skip_metadata(&mut File::open(&path).unwrap()),
+            b"audio"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_streaminfo_only() {
+        let path = test_path("read-streaminfo.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.save().unwrap();
+
+        let streaminfo = Tag::read_streaminfo(&path).unwrap();
+        assert_eq!(&streaminfo, tag.get_streaminfo().unwrap());
+
+        // a short streaminfo block is an error rather than a panic
+        let mut bytes = b"fLaC".to_vec();
+        bytes.extend(&[0x80, 0, 0, 2, 0, 0]);
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(Tag::read_from_path(&path).is_ok());
+        let err = Tag::read_streaminfo(&path).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidLength));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn legacy_coverart_on_read() {
+        let path = test_path("legacy-coverart.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.vorbis_comments_mut()
+            .set("COVERART", vec!["AQID", "not base64!"]);
+        tag.save().unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.pictures().count(), 1);
+        assert_eq!(tag.pictures().next().unwrap().data, &[1, 2, 3]);
+        assert!(tag.migrate_legacy_coverart().is_err());
+
+        tag.remove_vorbis_pair("COVERART", "not base64!");
+        assert_eq!(tag.migrate_legacy_coverart().unwrap(), 1);
+        assert_eq!(tag.pictures().count(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn legacy_coverart_removal() {
+        let path = test_path("legacy-coverart-removal.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.add_picture("image/png", PictureType::Other, vec![1, 2, 3]);
+        tag.set_vorbis("COVERART", vec!["not base64!", "AQID", "BAUG"]);
+        tag.set_vorbis("COVERARTMIME", vec!["", "image/png", "image/jpeg"]);
+        assert_eq!(tag.pictures().count(), 3);
+
+        // the legacy picture with the same data as the picture block is a duplicate
+        assert_eq!(tag.dedup_pictures().unwrap(), 1);
+        assert_eq!(
+            tag.get_vorbis_values("COVERART").unwrap(),
+            &["not base64!", "BAUG"]
+        );
+        assert_eq!(
+            tag.get_vorbis_values("COVERARTMIME").unwrap(),
+            &["", "image/jpeg"]
+        );
+        assert_eq!(tag.pictures().count(), 2);
+
+        assert_eq!(
+            tag.remove_pictures_where(|picture| picture.mime_type == "image/jpeg"),
+            1
+        );
+        tag.save().unwrap();
+
+        let tag = Tag::read_from_path(&path).unwrap();
+        let types: Vec<_> = tag.pictures().map(|picture| picture.picture_type).collect();
+        assert_eq!(types, &[PictureType::Other]);
+        assert_eq!(tag.get_vorbis_values("COVERART").unwrap(), &["not base64!"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn vendor_string_survives_save() {
+        let path = test_path("vendor-string.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vendor_string("encoder 1.0");
+        tag.save().unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.remove_vorbis("TITLE");
+        tag.save().unwrap();
+
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.vendor_string(), Some("encoder 1.0"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn deferred_picture_data() {
+        let path = test_path("deferred-picture.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.add_picture("image/png", PictureType::CoverFront, vec![0xAB; 100]);
+        tag.save().unwrap();
+
+        let mut options = ReadOptions::new();
+        options.skip_picture_data = true;
+        let mut tag = Tag::read_from_path_with(&path, &options).unwrap();
+        let picture = tag.pictures().next().unwrap();
+        assert_eq!(&picture.mime_type, "image/png");
+        assert!(picture.data.is_empty());
+        assert_eq!(picture.deferred.unwrap().len, 100);
+
+        let mut unloaded = tag.clone();
+        let len = tag.metadata_len();
+        tag.load_picture_data().unwrap();
+        assert_eq!(tag.metadata_len(), len);
+        assert_eq!(tag.pictures().next().unwrap().data, vec![0xAB; 100]);
+
+        unloaded.set_vorbis("TITLE", vec!["title"]);
+        unloaded.save().unwrap();
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.pictures().next().unwrap().data, vec![0xAB; 100]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn oversized_picture() {
+        let path = test_path("oversized-picture.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.add_picture("image/png", PictureType::CoverFront, vec![0; 1 << 24]);
+
+        let mut bytes = Vec::new();
+        let err = tag.write_to(&mut bytes).err().unwrap();
+        assert!(matches!(err.kind, ErrorKind::InvalidLength));
+        assert_eq!(err.block_type, Some(BlockType::Picture));
+        assert!(bytes.is_empty());
+        assert!(tag.save().is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), test_stream());
+
+        let mut options = WriteOptions::new();
+        options.drop_oversized_pictures = true;
+        tag.save_with(&options).unwrap();
+        let tag = Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.pictures().count(), 0);
+        assert!(tag.get_vorbis("TITLE").is_some());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_only_block_types() {
+        let path = test_path("read-only.flac");
+        std::fs::write(&path, test_stream()).unwrap();
+        let mut tag = Tag::read_from_path(&path).unwrap();
+        tag.add_picture("image/png", PictureType::CoverFront, vec![0; 4096]);
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.save().unwrap();
+        let region_len = tag.region_len();
+
+        let options = ReadOptions::only(&[BlockType::StreamInfo, BlockType::VorbisComment]);
+        let mut tag = Tag::read_from_path_with(&path, &options).unwrap();
+        assert!(tag.is_partial());
+        assert!(tag.warnings().is_empty());
+        assert!(tag.get_streaminfo().is_some());
+        assert_eq!(tag.pictures().count(), 0);
+        assert_eq!(tag.region_len(), region_len);
+        // an unmodified partial tag has nothing to write
+        assert!(tag.save().is_ok());
+
+        tag.set_vorbis("TITLE", vec!["other"]);
+        assert!(tag.plan_save().is_err());
+        assert!(tag.save().is_err());
+        assert!(tag.write_to_vec().is_err());
+        assert!(tag.rewrite_bytes(&std::fs::read(&path).unwrap()).is_err());
+        assert_eq!(Tag::read_from_path(&path).unwrap().pictures().count(), 1);
+
+        // blocks which are not read are not parsed either
+        let mut bytes = std::fs::read(&path).unwrap();
+        let options = ReadOptions::only(&[BlockType::VorbisComment]);
+        bytes[8..42].iter_mut().for_each(|b| *b = 0xFF);
+        let tag = Tag::read_from_with(&mut &bytes[..], &options).unwrap();
+        assert_eq!(tag.blocks().count(), 1);
+        assert_eq!(
+            tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(),
+            &["title"]
+        );
+
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,30 @@
+//! Fixtures shared by the unit tests.
+
+use crate::block::{Block, StreamInfo};
+
+use std::path::PathBuf;
+
+/// Returns the streaminfo of a 16 bit stereo stream with an unset MD5 signature.
+pub(crate) fn test_streaminfo() -> StreamInfo {
+    let mut streaminfo = StreamInfo::new();
+    streaminfo.num_channels = 2;
+    streaminfo.bits_per_sample = 16;
+    streaminfo.md5 = vec![0; 16];
+    streaminfo
+}
+
+/// Returns a flac stream with only a streaminfo block, followed by a few bytes of audio.
+pub(crate) fn test_stream() -> Vec<u8> {
+    let mut bytes = b"fLaC".to_vec();
+    Block::StreamInfo(test_streaminfo())
+        .write_to(true, &mut bytes)
+        .unwrap();
+    bytes.extend(b"audio");
+    bytes
+}
+
+/// Returns a path in the temporary directory which includes the process id, so that concurrent
+/// test runs do not use each other's files.
+pub(crate) fn test_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("metaflac-{}-{}", std::process::id(), name))
+}
@@ -0,0 +1,236 @@
+use crate::block::{Block, BlockType};
+use crate::tag::Tag;
+
+use std::fmt;
+
+/// The kinds of violations of the FLAC specification found by `Tag::validate`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ViolationKind {
+    /// The tag has no streaminfo block.
+    MissingStreamInfo,
+    /// The streaminfo block is not the first block.
+    StreamInfoNotFirst,
+    /// The tag has more than one streaminfo block.
+    DuplicateStreamInfo,
+    /// The tag has more than one vorbis comment block.
+    DuplicateVorbisComment,
+    /// The tag has more than one seektable block.
+    DuplicateSeekTable,
+    /// A padding block is followed by a block which is not padding.
+    PaddingNotLast,
+    /// The contents of a block are too long for the 24 bit length of a block header.
+    BlockTooLong,
+    /// The seek points of a seektable are not in ascending order of sample number, are not
+    /// unique, or are followed by a seek point after a placeholder.
+    UnsortedSeekPoints,
+    /// The MIME type of a picture contains characters other than printable ASCII.
+    InvalidMimeType,
+}
+
+/// A violation of the FLAC specification found by `Tag::validate`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Violation {
+    /// The kind of violation.
+    pub kind: ViolationKind,
+    /// The position of the block among the blocks of the tag to which the violation applies, or
+    /// `None` if it applies to the tag as a whole.
+    pub block: Option<usize>,
+    /// A human readable string describing the violation.
+    pub message: &'static str,
+}
+
+impl Violation {
+    fn new(kind: ViolationKind, block: Option<usize>, message: &'static str) -> Violation {
+        Violation {
+            kind,
+            block,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self.block {
+            Some(block) => write!(out, "block[{}]: {}", block, self.message),
+            None => write!(out, "{}", self.message),
+        }
+    }
+}
+
+impl Tag {
+    /// Checks the blocks of the tag against the FLAC specification, returning every violation
+    /// found in block order. An empty vector means the tag is valid.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::block::StreamInfo;
+    /// use metaflac::{Block, Tag, ViolationKind};
+    ///
+    /// let mut tag = Tag::new();
+    /// tag.set_vorbis("TITLE", vec!["title"]);
+    /// let kinds: Vec<_> = tag.validate().iter().map(|violation| violation.kind).collect();
+    /// assert_eq!(kinds, &[ViolationKind::MissingStreamInfo]);
+    ///
+    /// tag.push_block(Block::StreamInfo(StreamInfo::new()));
+    /// assert!(tag.validate().is_empty());
+    /// ```
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        let mut num_streaminfo = 0;
+        let mut num_vorbis_comments = 0;
+        let mut num_seektables = 0;
+        let mut after_padding = false;
+        for (i, block) in self.blocks().enumerate() {
+            let block_type = block.block_type();
+            if after_padding && block_type != BlockType::Padding {
+                violations.push(Violation::new(
+                    ViolationKind::PaddingNotLast,
+                    Some(i - 1),
+                    "padding block is followed by a block which is not padding",
+                ));
+            }
+            after_padding = block_type == BlockType::Padding;
+
+            if !block.fits_block_header() {
+                violations.push(Violation::new(
+                    ViolationKind::BlockTooLong,
+                    Some(i),
+                    "block is too long for the 24 bit length of a block header",
+                ));
+            }
+
+            match *block {
+                Block::StreamInfo(_) => {
+                    num_streaminfo += 1;
+                    if num_streaminfo > 1 {
+                        violations.push(Violation::new(
+                            ViolationKind::DuplicateStreamInfo,
+                            Some(i),
+                            "tag has more than one streaminfo block",
+                        ));
+                    } else if i > 0 {
+                        violations.push(Violation::new(
+                            ViolationKind::StreamInfoNotFirst,
+                            Some(i),
+                            "streaminfo block is not the first block",
+                        ));
+                    }
+                }
+                Block::VorbisComment(_) => {
+                    num_vorbis_comments += 1;
+                    if num_vorbis_comments > 1 {
+                        violations.push(Violation::new(
+                            ViolationKind::DuplicateVorbisComment,
+                            Some(i),
+                            "tag has more than one vorbis comment block",
+                        ));
+                    }
+                }
+                Block::SeekTable(ref seektable) => {
+                    num_seektables += 1;
+                    if num_seektables > 1 {
+                        violations.push(Violation::new(
+                            ViolationKind::DuplicateSeekTable,
+                            Some(i),
+                            "tag has more than one seektable block",
+                        ));
+                    }
+                    // placeholders sort after every seek point, and may repeat
+                    let sorted = seektable.seekpoints.windows(2).all(|pair| {
+                        pair[1].is_placeholder()
+                            || (!pair[0].is_placeholder()
+                                && pair[0].sample_number < pair[1].sample_number)
+                    });
+                    if !sorted {
+                        violations.push(Violation::new(
+                            ViolationKind::UnsortedSeekPoints,
+                            Some(i),
+                            "seek points are not unique and in ascending order",
+                        ));
+                    }
+                }
+                Block::Picture(ref picture)
+                    if !picture
+                        .mime_type
+                        .bytes()
+                        .all(|b| (0x20..=0x7E).contains(&b)) =>
+                {
+                    violations.push(Violation::new(
+                        ViolationKind::InvalidMimeType,
+                        Some(i),
+                        "picture mime type is not printable ascii",
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if num_streaminfo == 0 {
+            violations.insert(
+                0,
+                Violation::new(
+                    ViolationKind::MissingStreamInfo,
+                    None,
+                    "tag has no streaminfo block",
+                ),
+            );
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{PictureType, SeekPoint, SeekTable, StreamInfo};
+
+    #[test]
+    fn violations() {
+        let mut tag = Tag::new();
+        tag.set_vorbis("TITLE", vec!["title"]);
+        tag.push_block(Block::Padding(10));
+        tag.push_block(Block::VorbisComment(Default::default()));
+        tag.insert_block_at(1, Block::StreamInfo(StreamInfo::new()))
+            .unwrap();
+        tag.insert_block_at(4, Block::StreamInfo(StreamInfo::new()))
+            .unwrap();
+
+        let mut seektable = SeekTable::new();
+        seektable.seekpoints.push(SeekPoint::placeholder());
+        seektable.seekpoints.push(SeekPoint {
+            sample_number: 0,
+            offset: 0,
+            num_samples: 4096,
+        });
+        tag.push_block(Block::SeekTable(seektable));
+        tag.push_block(Block::SeekTable(SeekTable::template_n_points(2)));
+        tag.add_picture("image/png\n", PictureType::CoverFront, vec![]);
+
+        let violations: Vec<_> = tag
+            .validate()
+            .into_iter()
+            .map(|violation| (violation.kind, violation.block))
+            .collect();
+        assert_eq!(
+            violations,
+            &[
+                (ViolationKind::StreamInfoNotFirst, Some(1)),
+                (ViolationKind::PaddingNotLast, Some(2)),
+                (ViolationKind::DuplicateVorbisComment, Some(3)),
+                (ViolationKind::DuplicateStreamInfo, Some(4)),
+                (ViolationKind::UnsortedSeekPoints, Some(5)),
+                (ViolationKind::DuplicateSeekTable, Some(6)),
+                (ViolationKind::InvalidMimeType, Some(7)),
+            ]
+        );
+        assert_eq!(
+            tag.validate()[0].to_string(),
+            "block[1]: streaminfo block is not the first block"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use crate::error::{Error, ErrorKind, Result};
+use crate::tag::Tag;
+
+use std::io::Read;
+#[cfg(feature = "decode")]
+use std::path::Path;
+
+impl Tag {
+    /// Checks the unencoded audio against the MD5 signature stored in the streaminfo block.
+    /// Returns whether the signature matches.
+    ///
+    /// The reader supplies the decoded samples in the form the signature is computed over:
+    /// interleaved by channel, each sample as a signed little endian integer of the smallest whole
+    /// number of bytes which holds the bits per sample of the stream.
+    ///
+    /// Returns an `InvalidInput` error if the tag has no streaminfo block, or if the encoder left
+    /// the signature unset.
+    ///
+    /// # Example
+    /// ```
+    /// use metaflac::Tag;
+    /// use metaflac::block::{Block, StreamInfo};
+    ///
+    /// let samples: &[u8] = &[0x01, 0x00, 0xFF, 0xFF];
+    ///
+    /// let mut streaminfo = StreamInfo::new();
+    /// streaminfo.md5 = md5::compute(samples).0.to_vec();
+    /// let mut tag = Tag::new();
+    /// tag.push_block(Block::StreamInfo(streaminfo));
+    ///
+    /// assert!(tag.verify_audio_md5(samples).unwrap());
+    /// assert!(!tag.verify_audio_md5(&samples[..2]).unwrap());
+    /// ```
+    pub fn verify_audio_md5<R: Read>(&self, mut audio: R) -> Result<bool> {
+        let expected = self.expected_md5()?;
+
+        let mut context = md5::Context::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let len = audio.read(&mut buffer)?;
+            if len == 0 {
+                break;
+            }
+            context.consume(&buffer[..len]);
+        }
+        Ok(context.finalize().0[..] == expected[..])
+    }
+
+    /// Decodes the audio of the FLAC file at the path and checks it against the MD5 signature
+    /// stored in the streaminfo block of the file. Returns whether the signature matches.
+    ///
+    /// Returns an `InvalidInput` error if the audio cannot be decoded, or if the encoder left the
+    /// signature unset.
+    #[cfg(feature = "decode")]
+    pub fn verify_audio_md5_path<P: AsRef<Path>>(path: P) -> Result<bool> {
+        let tag = Tag::read_from_path(path.as_ref())?;
+        let expected = tag.expected_md5()?;
+
+        let mut reader = claxon::FlacReader::open(path.as_ref()).map_err(decode_error)?;
+        let width = reader.streaminfo().bits_per_sample.div_ceil(8) as usize;
+
+        let mut context = md5::Context::new();
+        let mut buffer = Vec::with_capacity(64 * 1024);
+        for sample in reader.samples() {
+            let sample = sample.map_err(decode_error)?;
+            buffer.extend(&sample.to_le_bytes()[..width]);
+            if buffer.len() >= 64 * 1024 {
+                context.consume(&buffer);
+                buffer.clear();
+            }
+        }
+        context.consume(&buffer);
+        Ok(context.finalize().0[..] == expected[..])
+    }
+
+    /// Returns the MD5 signature of the streaminfo block.
+    fn expected_md5(&self) -> Result<&[u8]> {
+        let streaminfo = self
+            .get_streaminfo()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "tag has no streaminfo block"))?;
+        // an encoder which did not compute the signature leaves it zeroed
+        if streaminfo.md5.iter().all(|&b| b == 0) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "streaminfo block has no audio md5 signature",
+            ));
+        }
+        Ok(&streaminfo.md5)
+    }
+}
+
+/// Converts an error of the decoder.
+#[cfg(feature = "decode")]
+fn decode_error(err: claxon::Error) -> Error {
+    match err {
+        claxon::Error::IoError(err) => err.into(),
+        claxon::Error::FormatError(description) | claxon::Error::Unsupported(description) => {
+            Error::new(ErrorKind::InvalidInput, description)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{Block, BlockType, StreamInfo};
+
+    #[test]
+    fn audio_md5() {
+        let mut tag = Tag::new();
+        assert!(tag.verify_audio_md5(&b""[..]).is_err());
+
+        tag.push_block(Block::StreamInfo(StreamInfo::new()));
+        assert!(tag.verify_audio_md5(&b""[..]).is_err());
+
+        // longer than the read buffer
+        let audio = vec![0x5A; 100_000];
+        let mut streaminfo = StreamInfo::new();
+        streaminfo.md5 = md5::compute(&audio).0.to_vec();
+        tag.remove_blocks(BlockType::StreamInfo);
+        tag.push_block(Block::StreamInfo(streaminfo));
+        assert!(tag.verify_audio_md5(&audio[..]).unwrap());
+        assert!(!tag.verify_audio_md5(&audio[1..]).unwrap());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::error::{Error, ErrorKind, Result};
+use crate::scan::ScanOptions;
+use crate::tag::Tag;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+
+/// An event emitted by a `TagWatcher`.
+#[allow(clippy::large_enum_variant)]
+pub enum WatchEvent {
+    /// A watched file was created or modified. Contains the path and the result of reading the
+    /// tag from the file again.
+    Changed(PathBuf, Result<Tag>),
+    /// A watched file was removed.
+    Removed(PathBuf),
+}
+
+/// Monitors a set of paths and emits events with freshly read tags when they change on disk.
+pub struct TagWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    options: ScanOptions,
+}
+
+impl TagWatcher {
+    /// Attempts to create a new `TagWatcher` which watches no paths and reports the files with a
+    /// `flac` extension, as `ScanOptions::new` selects them.
+    pub fn new() -> Result<TagWatcher> {
+        TagWatcher::with_options(ScanOptions::new())
+    }
+
+    /// Attempts to create a new `TagWatcher` which watches no paths. The options select the files
+    /// which are reported, whether directories are watched recursively, and how tags are read.
+    /// The temporary files of rewrites in progress are never reported.
+    pub fn with_options(options: ScanOptions) -> Result<TagWatcher> {
+        let (sender, events) = channel();
+        let watcher = notify::recommended_watcher(sender).map_err(from_notify)?;
+        Ok(TagWatcher {
+            watcher,
+            events,
+            options,
+        })
+    }
+
+    /// Starts watching the file or directory at the specified path. Directories are watched
+    /// recursively unless the options disable it.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mode = if self.options.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        self.watcher.watch(path.as_ref(), mode).map_err(from_notify)
+    }
+
+    /// Stops watching the file or directory at the specified path.
+    pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.watcher.unwatch(path.as_ref()).map_err(from_notify)
+    }
+
+    /// Blocks until the next batch of events is available. Returns `None` if the watcher has
+    /// stopped.
+    pub fn recv(&self) -> Option<Result<Vec<WatchEvent>>> {
+        self.events
+            .recv()
+            .ok()
+            .map(|event| to_watch_events(event, &self.options))
+    }
+
+    /// Returns the next batch of events if one is available without blocking.
+    pub fn try_recv(&self) -> Option<Result<Vec<WatchEvent>>> {
+        match self.events.try_recv() {
+            Ok(event) => Some(to_watch_events(event, &self.options)),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        }
+    }
+}
+
+fn to_watch_events(
+    event: notify::Result<notify::Event>,
+    options: &ScanOptions,
+) -> Result<Vec<WatchEvent>> {
+    let event = event.map_err(from_notify)?;
+    debug!("Received watch event {:?}", event);
+
+    let paths = event.paths.into_iter().filter(|path| options.matches(path));
+    let events = match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) => paths
+            .filter(|path| path.is_file())
+            .map(|path| {
+                let tag = Tag::read_from_path_with(&path, &options.read_options);
+                WatchEvent::Changed(path, tag)
+            })
+            .collect(),
+        EventKind::Remove(_) => paths.map(WatchEvent::Removed).collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(events)
+}
+
+fn from_notify(err: notify::Error) -> Error {
+    match err.kind {
+        notify::ErrorKind::Io(err) => Error::from(err),
+        _ => Error::new(ErrorKind::InvalidInput, "unable to watch path"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{test_path, test_stream};
+
+    use notify::event::{CreateKind, RemoveKind};
+
+    #[test]
+    fn filtered_events() {
+        let root = test_path("watch-events");
+        std::fs::create_dir_all(&root).unwrap();
+        let flac = root.join("a.flac");
+        let temp = root.join(".a.flac.123.tmp");
+        let notes = root.join("notes.txt");
+        for path in [&flac, &temp, &notes] {
+            std::fs::write(path, test_stream()).unwrap();
+        }
+        let event = |kind| {
+            Ok(notify::Event::new(kind)
+                .add_path(flac.clone())
+                .add_path(temp.clone())
+                .add_path(notes.clone()))
+        };
+
+        let options = ScanOptions::new();
+        let events = to_watch_events(event(EventKind::Create(CreateKind::File)), &options).unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], WatchEvent::Changed(ref path, Ok(_)) if *path == flac));
+        let events = to_watch_events(event(EventKind::Remove(RemoveKind::File)), &options).unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], WatchEvent::Removed(ref path) if *path == flac));
+
+        // the temporary files of rewrites are skipped even when every file is reported
+        let mut options = ScanOptions::new();
+        options.extensions.clear();
+        let events = to_watch_events(event(EventKind::Create(CreateKind::File)), &options).unwrap();
+        let paths: Vec<_> = events
+            .iter()
+            .map(|event| match *event {
+                WatchEvent::Changed(ref path, _) | WatchEvent::Removed(ref path) => path,
+            })
+            .collect();
+        assert_eq!(paths, &[&flac, &notes]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,308 @@
+//! Tests of the `metaflac-rs` command line tool.
+
+#![cfg(feature = "std")]
+
+use metaflac::block::{Block, StreamInfo};
+use metaflac::Tag;
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Returns a path in the temporary directory which includes the process id.
+fn test_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("metaflac-cli-{}-{}", std::process::id(), name))
+}
+
+/// Writes a FLAC file with only a streaminfo block followed by a few bytes of audio.
+fn write_flac(name: &str) -> PathBuf {
+    let mut streaminfo = StreamInfo::new();
+    streaminfo.num_channels = 2;
+    streaminfo.bits_per_sample = 16;
+    streaminfo.md5 = vec![0; 16];
+    let mut tag = Tag::new();
+    tag.push_block(Block::StreamInfo(streaminfo));
+    let mut bytes = tag.write_to_vec().unwrap();
+    bytes.extend(b"audio");
+
+    let path = test_path(name);
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_metaflac-rs"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn exit_codes() {
+    let flac = write_flac("exit-codes.flac");
+    let flac = flac.to_str().unwrap();
+    let not_flac = test_path("exit-codes.txt");
+    std::fs::write(&not_flac, b"not flac").unwrap();
+    let not_flac = not_flac.to_str().unwrap();
+    // a comment which is not valid UTF-8
+    let damaged = test_path("exit-codes-damaged.flac");
+    let mut bytes = Tag::read_from_path(flac).unwrap().write_to_vec().unwrap();
+    bytes[4] &= 0x7F;
+    let mut comment = vec![0, 0, 0, 0, 1, 0, 0, 0, 9, 0, 0, 0];
+    comment.extend(b"ARTIST=\xff\xfe");
+    Block::Unknown((4, comment))
+        .write_to(true, &mut bytes)
+        .unwrap();
+    std::fs::write(&damaged, bytes).unwrap();
+    let damaged = damaged.to_str().unwrap();
+    let missing = test_path("exit-codes-missing.flac");
+    let missing = missing.to_str().unwrap();
+
+    assert_eq!(run(&["--show-tag=TITLE", flac]).status.code(), Some(0));
+    let help = String::from_utf8(run(&["--help"]).stdout).unwrap();
+    assert!(help.contains("  6  a file could not be opened or read"));
+    assert_eq!(run(&["--list"]).status.code(), Some(1));
+    assert_eq!(run(&["--unknown", flac]).status.code(), Some(1));
+    assert_eq!(run(&["--list", not_flac]).status.code(), Some(2));
+    assert_eq!(run(&["--list", damaged]).status.code(), Some(3));
+    assert_eq!(run(&["--export-picture-to=-", flac]).status.code(), Some(5));
+    assert_eq!(run(&["--set-tag=TITLE", flac]).status.code(), Some(5));
+    let output = run(&["--list", missing]);
+    assert_eq!(output.status.code(), Some(6));
+    assert!(stderr(&output).starts_with(&format!("{}: unable to read file", missing)));
+    let cover = test_path("exit-codes-missing.png");
+    let import = format!("--import-picture-from={}", cover.to_str().unwrap());
+    assert_eq!(run(&[&import, flac]).status.code(), Some(6));
+
+    std::fs::write(&cover, b"\x89PNG\r\n\x1a\n").unwrap();
+    assert_eq!(run(&[&import, flac]).status.code(), Some(0));
+    // a directory cannot be written as a file
+    let dir = test_path("exit-codes-dir");
+    std::fs::create_dir_all(&dir).unwrap();
+    let export = format!("--export-picture-to={}", dir.to_str().unwrap());
+    assert_eq!(run(&[&export, flac]).status.code(), Some(4));
+
+    // the last failure decides the exit code
+    assert_eq!(run(&["--list", not_flac, missing]).status.code(), Some(6));
+    assert_eq!(run(&["--list", missing, flac]).status.code(), Some(6));
+
+    std::fs::remove_dir(&dir).unwrap();
+    std::fs::remove_file(&cover).unwrap();
+    for path in [flac, not_flac, damaged] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn json_errors() {
+    let not_flac = test_path("json-errors.txt");
+    std::fs::write(&not_flac, b"not flac").unwrap();
+    let not_flac = not_flac.to_str().unwrap();
+    let missing = test_path("json \"errors\"\n\t\\\u{1}.flac");
+    let missing = missing.to_str().unwrap();
+
+    let output = run(&["--errors=json", "--list", not_flac, missing]);
+    assert_eq!(output.status.code(), Some(6));
+    let lines: Vec<_> = stderr(&output).lines().map(str::to_owned).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        format!(
+            "{{\"file\":\"{}\",\"error\":\"not_flac\",\"exit_code\":2,\"message\":\"not a FLAC file\"}}",
+            not_flac
+        )
+    );
+    let file = missing
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\u{1}', "\\u0001");
+    assert!(lines[1].starts_with(&format!(
+        "{{\"file\":\"{}\",\"error\":\"io_error\",\"exit_code\":6,\"message\":\"unable to read file: ",
+        file
+    )));
+    assert!(lines[1].ends_with("\"}"));
+
+    std::fs::remove_file(not_flac).unwrap();
+}
+
+#[test]
+fn tag_operations() {
+    let flac = write_flac("tag-operations.flac");
+    let flac = flac.to_str().unwrap();
+
+    let output = run(&[
+        "--set-tag=ARTIST=a",
+        "--set-tag=ARTIST=b",
+        "--set-tag=TITLE=t",
+        flac,
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    let output = run(&["--show-tag=ARTIST", "--show-tag=TITLE", flac]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "ARTIST=a\nARTIST=b\nTITLE=t\n"
+    );
+
+    run(&["--remove-tag=ARTIST", flac]);
+    let tag = Tag::read_from_path(flac).unwrap();
+    assert!(tag.get_vorbis("ARTIST").is_none());
+    assert!(tag.get_vorbis("TITLE").is_some());
+
+    let output = run(&["--list", flac, flac]);
+    let listing = String::from_utf8(output.stdout).unwrap();
+    assert!(listing.contains(&format!("{}:METADATA block #1\n", flac)));
+    assert!(listing.contains(&format!("{}:    comment[0]: TITLE=t\n", flac)));
+
+    run(&["--remove-all-tags", flac]);
+    let tag = Tag::read_from_path(flac).unwrap();
+    assert!(tag.get_vorbis("TITLE").is_none());
+    assert!(tag.get_streaminfo().is_some());
+
+    std::fs::remove_file(flac).unwrap();
+}
+
+#[test]
+fn picture_operations() {
+    let flac = write_flac("picture-operations.flac");
+    let flac = flac.to_str().unwrap();
+    let image = test_path("picture-operations.png");
+    std::fs::write(&image, b"\x89PNG\r\n\x1a\nimage").unwrap();
+    let exported = test_path("picture-operations-exported.png");
+
+    let spec = format!("4||back|10x20x24/3|{}", image.to_str().unwrap());
+    let import = format!("--import-picture-from={}", spec);
+    assert_eq!(run(&[&import, flac]).status.code(), Some(0));
+    let tag = Tag::read_from_path(flac).unwrap();
+    let picture = tag.pictures().next().unwrap();
+    assert_eq!(picture.mime_type, "image/png");
+    assert_eq!(picture.description, "back");
+    assert_eq!(
+        (
+            picture.width,
+            picture.height,
+            picture.depth,
+            picture.num_colors
+        ),
+        (10, 20, 24, 3)
+    );
+
+    let export = format!("--export-picture-to={}", exported.to_str().unwrap());
+    assert_eq!(run(&[&export, flac]).status.code(), Some(0));
+    assert_eq!(std::fs::read(&exported).unwrap(), b"\x89PNG\r\n\x1a\nimage");
+    let output = run(&["--export-picture-to=-", flac]);
+    assert_eq!(output.stdout, b"\x89PNG\r\n\x1a\nimage");
+
+    let import = format!("--import-picture-from=99||||{}", image.to_str().unwrap());
+    assert_eq!(run(&[&import, flac]).status.code(), Some(5));
+    assert_eq!(Tag::read_from_path(flac).unwrap().pictures().count(), 1);
+
+    for path in [flac, image.to_str().unwrap(), exported.to_str().unwrap()] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn file_handling_flags() {
+    let flac = write_flac("file-handling-flags.flac");
+    let flac = flac.to_str().unwrap();
+    let paddings = |path: &str| {
+        Tag::read_from_path(path)
+            .unwrap()
+            .blocks()
+            .filter_map(|block| match *block {
+                Block::Padding(size) => Some(size),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        run(&["--set-tag=TITLE=t", "--add-padding=100", flac])
+            .status
+            .code(),
+        Some(0)
+    );
+    assert_eq!(paddings(flac), &[100]);
+    assert!(std::fs::read(flac).unwrap().ends_with(b"audio"));
+
+    // the edit fits in the padding, but the file is rewritten without it
+    run(&["--set-tag=ARTIST=a", "--dont-use-padding", flac]);
+    assert_eq!(paddings(flac), &[1024]);
+
+    let modified = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1 << 30);
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(flac)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    run(&["--set-tag=ARTIST=b", "--preserve-modtime", flac]);
+    let metadata = std::fs::metadata(flac).unwrap();
+    assert_eq!(metadata.modified().unwrap(), modified);
+    assert_eq!(
+        Tag::read_from_path(flac)
+            .unwrap()
+            .get_vorbis("ARTIST")
+            .unwrap()
+            .collect::<Vec<_>>(),
+        &["a", "b"]
+    );
+
+    assert_eq!(run(&["--add-padding=x", flac]).status.code(), Some(1));
+    assert_eq!(
+        run(&["--add-padding=16777216", flac]).status.code(),
+        Some(1)
+    );
+
+    std::fs::remove_file(flac).unwrap();
+}
+
+#[test]
+fn remove_blocks_by_number() {
+    let flac = write_flac("remove-blocks.flac");
+    let flac = flac.to_str().unwrap();
+    let mut tag = Tag::read_from_path(flac).unwrap();
+    tag.set_vorbis("TITLE", vec!["t"]);
+    tag.add_application_block(b"test", vec![1]);
+    tag.add_application_block(b"test", vec![2]);
+    tag.save().unwrap();
+    let types = |path: &str| {
+        Tag::read_from_path(path)
+            .unwrap()
+            .blocks()
+            .map(Block::block_type)
+            .collect::<Vec<_>>()
+    };
+    let before = types(flac);
+
+    assert_eq!(run(&["--remove", flac]).status.code(), Some(1));
+    assert_eq!(
+        run(&["--remove", "--block-number=1,x", flac]).status.code(),
+        Some(1)
+    );
+    // nothing is removed unless every block number is valid
+    assert_eq!(
+        run(&["--remove", "--block-number=2,9", flac]).status.code(),
+        Some(5)
+    );
+    assert_eq!(
+        run(&["--remove", "--block-number=0", flac]).status.code(),
+        Some(5)
+    );
+    assert_eq!(types(flac), before);
+
+    let output = run(&["--remove", "--block-number=3, 1,3", flac]);
+    assert_eq!(output.status.code(), Some(0));
+    let tag = Tag::read_from_path(flac).unwrap();
+    assert!(tag.get_vorbis("TITLE").is_none());
+    let applications: Vec<_> = tag.application_blocks(b"test").collect();
+    assert_eq!(applications.len(), 1);
+    assert_eq!(applications[0].data, &[1]);
+
+    std::fs::remove_file(flac).unwrap();
+}